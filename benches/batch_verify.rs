@@ -0,0 +1,56 @@
+//! Compares the lane-by-lane `ZKP::verify_batch_simd` with the scalar `ZKP::verify_batch`.
+//!
+//! ```text
+//! cargo bench --bench batch_verify --features experimental [-- BATCH_SIZE]
+//! ```
+
+use std::time::{Duration, Instant};
+use zkp_chaum_pedersen::{Group, Proof, ZKP};
+
+const DEFAULT_BATCH_SIZE: usize = 256;
+
+/// Returns the time of one run of `f`, and its result.
+fn time<T>(f: impl FnOnce() -> T) -> (Duration, T) {
+    let start = Instant::now();
+    let result = f();
+    (start.elapsed(), result)
+}
+
+fn main() {
+    let batch_size = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_BATCH_SIZE);
+
+    println!("batches of {} proofs, time per proof", batch_size);
+    println!();
+    println!("{:<8} {:>12} {:>12} {:>8}", "group", "scalar", "lanes", "speedup");
+
+    for (name, group) in [("rfc1024", Group::Rfc1024), ("rfc2048", Group::Rfc2048)] {
+        let zkp = ZKP::from_group(group);
+        let proofs: Vec<_> = (0..batch_size)
+            .map(|_| {
+                let x = ZKP::generate_random_number_below(&zkp.q);
+                let k = ZKP::generate_random_number_below(&zkp.q);
+                let (y1, y2) = zkp.compute_pair(&x);
+                let (r1, r2) = zkp.compute_pair(&k);
+                let c = zkp.issue_challenge();
+                let s = zkp.solve(&k, &c, &x);
+                (y1, y2, Proof { r1, r2, c, s })
+            })
+            .collect();
+
+        let (scalar, expected) = time(|| zkp.verify_batch(&proofs));
+        let (lanes, results) = time(|| zkp.verify_batch_simd(&proofs));
+        assert_eq!(results, expected, "the batched path must match the scalar one");
+
+        let per_proof = |total: Duration| total / batch_size.max(1) as u32;
+        println!(
+            "{:<8} {:>12.2?} {:>12.2?} {:>7.2}x",
+            name,
+            per_proof(scalar),
+            per_proof(lanes),
+            scalar.as_secs_f64() / lanes.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Compares verifying with a `ZKP` built from shared parameters with verifying with a `ZkpRef`
+//! borrowing them.
+//!
+//! A server holding one parameter set for all its verifications either clones it into a `ZKP`
+//! per verification (`ZKP::new` clones the four `BigUint`s), or borrows it. A counting allocator
+//! reports the heap allocations and bytes of each verification next to its time. The borrowed
+//! parameters save the four clones, and their bytes: the exponentiations of the verification
+//! allocate much more, so the time hardly changes.
+//!
+//! ```text
+//! cargo bench --bench borrowed_params [-- ITERATIONS]
+//! ```
+
+use num_bigint::BigUint;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use zkp_chaum_pedersen::{Group, ZkpRef, ZKP};
+
+const DEFAULT_ITERATIONS: u32 = 50;
+
+/// The system allocator, counting the allocations and the bytes allocated.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// The mean time, allocations and bytes allocated of `iterations` runs of `f`.
+struct Measurement {
+    time: Duration,
+    allocations: usize,
+    bytes: usize,
+}
+
+fn measure(iterations: u32, mut f: impl FnMut() -> bool) -> Measurement {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        assert!(f());
+    }
+    let time = start.elapsed() / iterations;
+    Measurement {
+        time,
+        allocations: (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / iterations as usize,
+        bytes: (BYTES.load(Ordering::Relaxed) - bytes) / iterations as usize,
+    }
+}
+
+fn main() {
+    let iterations = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS);
+
+    println!("{} iterations per measurement, mean per verification", iterations);
+    println!();
+    println!(
+        "{:<8} {:<18} {:>12} {:>8} {:>10}",
+        "group", "parameters", "verify", "allocs", "bytes"
+    );
+
+    for (name, group) in [
+        ("toy", Group::Toy),
+        ("rfc1024", Group::Rfc1024),
+        ("rfc2048", Group::Rfc2048),
+    ] {
+        // the parameters shared by the verifications of a server
+        let shared: Arc<[BigUint; 4]> = {
+            let zkp = ZKP::from_group(group);
+            Arc::new([zkp.p, zkp.q, zkp.alpha, zkp.beta])
+        };
+        let [p, q, alpha, beta] = &*shared;
+        let zkp_ref = ZkpRef { p, q, alpha, beta };
+
+        let x = ZKP::generate_random_number_below(q);
+        let k = ZKP::generate_random_number_below(q);
+        let (y1, y2) = zkp_ref.compute_pair(&x);
+        let (r1, r2) = zkp_ref.compute_pair(&k);
+        let c = ZKP::generate_random_number_below(q);
+        let s = zkp_ref.solve(&k, &c, &x);
+
+        let owned =
+            measure(iterations, || ZKP::new(p, q, alpha, beta).verify(&r1, &r2, &y1, &y2, &c, &s));
+        let borrowed =
+            measure(iterations, || ZkpRef { p, q, alpha, beta }.verify(&r1, &r2, &y1, &y2, &c, &s));
+
+        for (parameters, m) in [("ZKP::new (owned)", owned), ("ZkpRef (borrowed)", borrowed)] {
+            println!(
+                "{:<8} {:<18} {:>12.2?} {:>8} {:>10}",
+                name, parameters, m.time, m.allocations, m.bytes
+            );
+        }
+    }
+}
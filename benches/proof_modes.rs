@@ -0,0 +1,107 @@
+//! Compares the interactive protocol with the non-interactive (Fiat-Shamir) mode.
+//!
+//! Both modes run the same `compute_pair`, `solve` and `verify`; the non-interactive mode only
+//! adds the hash of `ZKP::fiat_shamir_challenge` in place of the verifier's challenge.
+//!
+//! ```text
+//! cargo bench --bench proof_modes [-- ITERATIONS]
+//! ```
+
+use std::time::{Duration, Instant};
+use zkp_chaum_pedersen::{Group, Proof, ZKP};
+
+const DEFAULT_ITERATIONS: u32 = 50;
+const CONTEXT: &[u8] = b"bench";
+
+/// Returns the mean time of `iterations` runs of `f`.
+fn mean(iterations: u32, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    let iterations = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS);
+
+    println!("{} iterations per measurement, mean times", iterations);
+    println!();
+    println!(
+        "{:<8} {:<16} {:>6} {:>11} {:>12} {:>12}",
+        "group", "mode", "moves", "size (B)", "prove", "verify"
+    );
+
+    for (name, group) in [
+        ("toy", Group::Toy),
+        ("rfc1024", Group::Rfc1024),
+        ("rfc2048", Group::Rfc2048),
+    ] {
+        let zkp = ZKP::from_group(group);
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+
+        let k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let c = zkp.issue_challenge();
+        let s = zkp.solve(&k, &c, &x);
+        let interactive_size = size(
+            &zkp,
+            &Proof {
+                r1: r1.clone(),
+                r2: r2.clone(),
+                c: c.clone(),
+                s: s.clone(),
+            },
+        );
+
+        let prove = mean(iterations, || {
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let _ = zkp.compute_pair(&k);
+            let c = zkp.issue_challenge();
+            let _ = zkp.solve(&k, &c, &x);
+        });
+        let verify = mean(iterations, || {
+            assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s));
+        });
+        row(name, "interactive", 3, interactive_size, prove, verify);
+
+        let proof = zkp.prove_non_interactive(&x, &y1, &y2, CONTEXT);
+        let prove = mean(iterations, || {
+            let _ = zkp.prove_non_interactive(&x, &y1, &y2, CONTEXT);
+        });
+        let verify = mean(iterations, || {
+            assert!(zkp.verify_non_interactive(&y1, &y2, &proof, CONTEXT));
+        });
+        row(name, "non-interactive", 1, size(&zkp, &proof), prove, verify);
+    }
+
+    println!();
+    println!("The prove time of the interactive mode excludes the network round trip between the");
+    println!("commitment and the answer, which the non-interactive mode does not need.");
+}
+
+/// Returns the number of bytes of `r1`, `r2`, `c` and `s`, as the client and the server encode
+/// them. In the interactive mode they are spread over the three moves, in the non-interactive
+/// mode they are sent at once.
+fn size(zkp: &ZKP, proof: &Proof) -> usize {
+    zkp.encode_fixed(&proof.r1).len()
+        + zkp.encode_fixed(&proof.r2).len()
+        + proof.c.to_bytes_be().len()
+        + proof.s.to_bytes_be().len()
+}
+
+fn row(group: &str, mode: &str, moves: u32, size: usize, prove: Duration, verify: Duration) {
+    println!(
+        "{:<8} {:<16} {:>6} {:>11} {:>12} {:>12}",
+        group,
+        mode,
+        moves,
+        size,
+        format!("{:.1?}", prove),
+        format!("{:.1?}", verify)
+    );
+}
@@ -1,7 +1,8 @@
 fn main() {
+    // the gRPC code is only needed (and tonic-build only available) with the `grpc` feature
+    #[cfg(feature = "grpc")]
     tonic_build::configure()
         .build_server(true)
-        .out_dir("src/") // you can change the generated code's location
         .compile_protos(
             &["proto/zkp_auth.proto"],
             &["proto/"], // specify the root location to search proto dependencies
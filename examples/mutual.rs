@@ -0,0 +1,89 @@
+//! Mutual authentication: the client proves its secret to the server, and the server proves its
+//! own to the client, so a client talking to a rogue server notices.
+//!
+//! The client registered `(y1, y2)` with the server, and pinned the server's `(y1, y2)` the way
+//! an SSH client pins a host key. Both sides then run the prover and the verifier halves of the
+//! protocol, see `zkp_chaum_pedersen::mutual`. The messages are printed as they would be sent.
+//!
+//! ```text
+//! cargo run --example mutual
+//! ```
+
+use num_bigint::BigUint;
+use zkp_chaum_pedersen::{mutual::Challenger, prover::Session, ZKP};
+
+/// Runs the exchange between a client holding `client_x` and a server holding `server_x`, and
+/// returns whether each side accepted the other.
+fn exchange(
+    zkp: &ZKP, client_x: BigUint, server_public: (&BigUint, &BigUint), server_x: BigUint,
+    client_public: (&BigUint, &BigUint),
+) -> (bool, bool) {
+    let client = Session::new(zkp, client_x).commit();
+    let (r1, r2) = client.commitment();
+    println!("client -> server: commitment r1={}, r2={}", short(r1), short(r2));
+    let server_challenger = Challenger::new(zkp, client_public, r1, r2);
+
+    let server = Session::new(zkp, server_x).commit();
+    let (r1, r2) = server.commitment();
+    let c = server_challenger.challenge();
+    println!(
+        "server -> client: commitment r1={}, r2={}, challenge c={}",
+        short(r1),
+        short(r2),
+        short(c)
+    );
+    let client_challenger = Challenger::new(zkp, server_public, r1, r2);
+
+    let client = client.respond(server_challenger.challenge());
+    let c = client_challenger.challenge();
+    println!(
+        "client -> server: challenge c={}, answer s={}",
+        short(c),
+        short(client.response())
+    );
+    let client_accepted = server_challenger.verify(client.response());
+
+    let server = server.respond(client_challenger.challenge());
+    println!("server -> client: answer s={}", short(server.response()));
+    let server_accepted = client_challenger.verify(server.response());
+
+    (client_accepted, server_accepted)
+}
+
+/// The first hex digits of `value`, enough to follow the exchange.
+fn short(value: &BigUint) -> String {
+    let hex = value.to_str_radix(16);
+    match hex.get(..12) {
+        Some(prefix) => format!("{}..", prefix),
+        None => hex,
+    }
+}
+
+fn main() {
+    let zkp = ZKP::default();
+    let (client_x, client_y1, client_y2) = zkp.generate_credential();
+    let (server_x, server_y1, server_y2) = zkp.generate_credential();
+    let client_public = (&client_y1, &client_y2);
+    let server_public = (&server_y1, &server_y2);
+
+    println!("== genuine server");
+    let (client_ok, server_ok) =
+        exchange(&zkp, client_x.clone(), server_public, server_x, client_public);
+    println!(
+        "server accepts the client: {}, client accepts the server: {}",
+        client_ok, server_ok
+    );
+    assert!(client_ok && server_ok);
+
+    // a server impersonating the genuine one knows the client's public values, not the
+    // server's secret
+    println!();
+    println!("== rogue server");
+    let (rogue_x, _, _) = zkp.generate_credential();
+    let (client_ok, server_ok) = exchange(&zkp, client_x, server_public, rogue_x, client_public);
+    println!(
+        "server accepts the client: {}, client accepts the server: {}",
+        client_ok, server_ok
+    );
+    assert!(client_ok && !server_ok);
+}
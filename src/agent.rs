@@ -0,0 +1,320 @@
+//! A local agent caching derived secrets, so repeated logins skip the key derivation.
+//!
+//! `ZKP::derive_secret` runs `PBKDF2_ROUNDS` rounds of PBKDF2 on purpose, which makes every
+//! login slow. Like `ssh-agent`, the agent is a small daemon listening on a Unix domain socket
+//! that keeps the secret `x` of each user in memory for a limited time: the client asks it for
+//! the secret before prompting for the password, and hands the secret over after a successful
+//! login.
+//!
+//! The socket is created with mode `0600`, and anyone who can connect to it gets the secrets, so
+//! put it in a directory only its owner can enter, such as `$XDG_RUNTIME_DIR`. The socket and its
+//! client need Unix domain sockets, the cache itself does not. Cached secrets
+//! are held in `Zeroizing` buffers, overwritten as soon as they expire or are replaced; the
+//! `BigUint` returned by `AgentClient::get` is the caller's to handle.
+//!
+//! The protocol is one request per connection, one line each way:
+//!
+//! ```text
+//! GET <key>        ->  OK <secret in hex> | MISS
+//! PUT <key> <hex>  ->  OK
+//! ```
+
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+#[cfg(unix)]
+use {
+    std::io,
+    std::os::unix::fs::{DirBuilderExt, PermissionsExt},
+    std::path::{Path, PathBuf},
+    std::sync::{Arc, Mutex},
+    tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    tokio::net::{UnixListener, UnixStream},
+};
+
+use crate::kdf::Kdf;
+use crate::ZKP;
+
+/// The domain separator of `cache_key`.
+pub const CACHE_KEY_DOMAIN: &[u8] = b"zkp-chaum-pedersen/agent-key/v1";
+
+/// How long the agent keeps a secret by default.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Returns the key the secret of `user`, derived with `kdf` in the group of `zkp`, is cached
+/// under.
+///
+/// The key is the SHA-256 of `CACHE_KEY_DOMAIN`, the group parameters, the name of the KDF and
+/// the username, so a secret is never handed out for another group, whose `q` it was not reduced
+/// modulo, nor for another KDF, which derives another secret from the same password.
+pub fn cache_key(zkp: &ZKP, kdf: Kdf, user: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(CACHE_KEY_DOMAIN);
+    for value in [&zkp.p, &zkp.q, &zkp.alpha, &zkp.beta] {
+        hasher.update(zkp.encode_fixed(value));
+    }
+    hasher.update([kdf.id().len() as u8]);
+    hasher.update(kdf.id().as_bytes());
+    hasher.update(user.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// The secrets held by the agent, each forgotten `ttl` after it was stored.
+pub struct SecretCache {
+    ttl: Duration,
+    secrets: HashMap<String, (Zeroizing<Vec<u8>>, Instant)>,
+}
+
+// only the number of secrets is printed
+impl std::fmt::Debug for SecretCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretCache")
+            .field("ttl", &self.ttl)
+            .field("secrets", &self.secrets.len())
+            .finish()
+    }
+}
+
+impl SecretCache {
+    /// Creates an empty cache keeping secrets for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            secrets: HashMap::new(),
+        }
+    }
+
+    /// Stores (or replaces) the secret under `key`, for `ttl` from now.
+    pub fn insert(&mut self, key: &str, secret: &BigUint) {
+        let expires_at = Instant::now() + self.ttl;
+        self.secrets
+            .insert(key.to_string(), (Zeroizing::new(secret.to_bytes_be()), expires_at));
+    }
+
+    /// Returns the secret stored under `key`, unless it has expired.
+    pub fn get(&mut self, key: &str) -> Option<BigUint> {
+        self.purge_expired();
+        let (secret, _) = self.secrets.get(key)?;
+        Some(BigUint::from_bytes_be(secret))
+    }
+
+    /// Drops, and so zeroizes, the expired secrets.
+    pub fn purge_expired(&mut self) {
+        let now = Instant::now();
+        self.secrets.retain(|_, (_, expires_at)| *expires_at > now);
+    }
+
+    /// Returns the number of secrets held, expired ones included until purged.
+    pub fn len(&self) -> usize {
+        self.secrets.len()
+    }
+
+    /// Returns whether no secret is held.
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+}
+
+/// Binds the socket of the agent at `path`, accessible by its owner only.
+///
+/// The socket is bound in a new directory next to `path` that only its owner can enter, restricted
+/// to `0600`, then linked at `path`: whatever the umask, it is never reachable by anyone else.
+///
+/// # Returns
+///
+/// A `Result` containing either the listener, or the error of the bind, e.g. if `path` exists.
+#[cfg(unix)]
+pub fn bind(path: &Path) -> io::Result<UnixListener> {
+    let name = path
+        .file_name()
+        .unwrap_or("agent".as_ref())
+        .to_string_lossy();
+    let dir = path.with_file_name(format!(".{}.{}", name, std::process::id()));
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let staged = dir.join("agent.sock");
+    let result = (|| -> io::Result<UnixListener> {
+        let listener = UnixListener::bind(&staged)?;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::hard_link(&staged, path)?;
+        Ok(listener)
+    })();
+    let _ = std::fs::remove_file(&staged);
+    std::fs::remove_dir(&dir)?;
+    result
+}
+
+/// Runs the agent on `listener` until the task is dropped, keeping secrets for `ttl`.
+///
+/// Expired secrets are purged every second, whether or not requests come in. A failed request
+/// only fails its connection, and is passed to `on_error`.
+#[cfg(unix)]
+pub async fn serve(
+    listener: UnixListener, ttl: Duration, on_error: impl Fn(io::Error) + Send + Sync + 'static,
+) -> io::Result<()> {
+    let cache = Arc::new(Mutex::new(SecretCache::new(ttl)));
+    let on_error = Arc::new(on_error);
+    let mut purge = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            _ = purge.tick() => cache.lock().unwrap().purge_expired(),
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let cache = cache.clone();
+                let on_error = on_error.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle(stream, &cache).await {
+                        on_error(e);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Answers the one request of a connection.
+#[cfg(unix)]
+async fn handle(stream: UnixStream, cache: &Mutex<SecretCache>) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut line = Zeroizing::new(String::new());
+    BufReader::new(reader).read_line(&mut line).await?;
+
+    let reply = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", key] => match cache.lock().unwrap().get(key) {
+            Some(secret) => Zeroizing::new(format!("OK {:x}\n", secret)),
+            None => Zeroizing::new("MISS\n".to_string()),
+        },
+        ["PUT", key, secret] => match BigUint::parse_bytes(secret.as_bytes(), 16) {
+            Some(secret) => {
+                cache.lock().unwrap().insert(key, &secret);
+                Zeroizing::new("OK\n".to_string())
+            }
+            None => Zeroizing::new("ERR invalid secret\n".to_string()),
+        },
+        _ => Zeroizing::new("ERR invalid request\n".to_string()),
+    };
+    writer.write_all(reply.as_bytes()).await
+}
+
+/// A client of the agent listening at a socket path.
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct AgentClient {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl AgentClient {
+    /// Creates a client of the agent listening at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Returns the secret cached under `key`, if the agent still holds it.
+    pub async fn get(&self, key: &str) -> io::Result<Option<BigUint>> {
+        let reply = self.request(&format!("GET {}\n", key)).await?;
+        if reply.trim() == "MISS" {
+            return Ok(None);
+        }
+        reply
+            .trim()
+            .strip_prefix("OK ")
+            .and_then(|secret| BigUint::parse_bytes(secret.as_bytes(), 16))
+            .map(Some)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid agent reply"))
+    }
+
+    /// Hands the secret to the agent, to cache under `key`.
+    pub async fn put(&self, key: &str, secret: &BigUint) -> io::Result<()> {
+        let reply = self.request(&format!("PUT {} {:x}\n", key, secret)).await?;
+        if reply.trim() != "OK" {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, reply.trim().to_string()));
+        }
+        Ok(())
+    }
+
+    async fn request(&self, request: &str) -> io::Result<Zeroizing<String>> {
+        let request = Zeroizing::new(request.to_string());
+        let (reader, mut writer) = UnixStream::connect(&self.path).await?.into_split();
+        writer.write_all(request.as_bytes()).await?;
+        let mut reply = Zeroizing::new(String::new());
+        BufReader::new(reader).read_line(&mut reply).await?;
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::toy_zkp;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_secrets_expire() {
+        let mut cache = SecretCache::new(Duration::from_millis(20));
+        cache.insert("alice", &BigUint::from(6u32));
+        assert_eq!(cache.get("alice"), Some(BigUint::from(6u32)));
+        assert_eq!(cache.get("bob"), None);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get("alice"), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_cache_keys_depend_on_group_kdf_and_user() {
+        let zkp = toy_zkp();
+        let key = cache_key(&zkp, Kdf::Pbkdf2, "alice");
+        assert_eq!(cache_key(&zkp, Kdf::Pbkdf2, "alice"), key);
+        assert_ne!(cache_key(&zkp, Kdf::Pbkdf2, "bob"), key);
+        assert_ne!(cache_key(&ZKP::default(), Kdf::Pbkdf2, "alice"), key);
+        #[cfg(feature = "kdf")]
+        assert_ne!(cache_key(&zkp, Kdf::Scrypt, "alice"), key);
+    }
+
+    #[test]
+    fn test_debug_hides_secrets() {
+        let mut cache = SecretCache::new(DEFAULT_TTL);
+        cache.insert("alice", &BigUint::from(6u32));
+        assert_eq!(format!("{:?}", cache), "SecretCache { ttl: 900s, secrets: 1 }");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_second_login_skips_derivation() {
+        let path = std::env::temp_dir().join(format!("zkp-agent-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = bind(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(bind(&path).is_err());
+        let agent = tokio::spawn(serve(listener, Duration::from_millis(200), |e| panic!("{e}")));
+
+        // the secret of a login, derived only if the agent does not hold it
+        let zkp = ZKP::default();
+        let derivations = AtomicU32::new(0);
+        let client = AgentClient::new(&path);
+        let key = cache_key(&zkp, Kdf::Pbkdf2, "alice");
+        let login = || async {
+            if let Some(x) = client.get(&key).await.unwrap() {
+                return x;
+            }
+            derivations.fetch_add(1, Ordering::SeqCst);
+            let x = zkp.derive_secret("correct horse", b"alice");
+            client.put(&key, &x).await.unwrap();
+            x
+        };
+
+        let x = login().await;
+        assert_eq!(login().await, x);
+        assert_eq!(derivations.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(login().await, x);
+        assert_eq!(derivations.load(Ordering::SeqCst), 2);
+
+        agent.abort();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+//! CBOR encoding of `Proof` and `ZKP`, a compact binary alternative to JSON.
+//!
+//! The encodings are the serde representations of the structs: a map from the field names to
+//! the big-endian bytes of each number.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Proof, Result, ZkpError, ZKP};
+
+fn to_cbor(value: &impl Serialize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).expect("writing to a Vec cannot fail");
+    bytes
+}
+
+fn from_cbor<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    ciborium::from_reader(bytes).map_err(|e| ZkpError::InvalidEncoding(e.to_string()))
+}
+
+impl Proof {
+    /// Encodes the proof as CBOR.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        to_cbor(self)
+    }
+
+    /// Decodes a proof encoded with `to_cbor`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the proof or `ZkpError::InvalidEncoding` if `bytes` is not a
+    /// CBOR encoded proof.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        from_cbor(bytes)
+    }
+}
+
+impl ZKP {
+    /// Encodes the group parameters as CBOR.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        to_cbor(self)
+    }
+
+    /// Decodes group parameters encoded with `to_cbor`.
+    ///
+    /// The parameters are not checked, see `ZKP::is_degenerate`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the parameters or `ZkpError::InvalidEncoding` if `bytes` is
+    /// not CBOR encoded parameters.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        from_cbor(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::valid_proof;
+    use crate::Group;
+
+    #[test]
+    fn test_proof_round_trip() {
+        let proof = valid_proof();
+        assert_eq!(Proof::from_cbor(&proof.to_cbor()), Ok(proof));
+
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&x);
+        let proof = Proof {
+            r1,
+            r2,
+            c: zkp.issue_challenge(),
+            s: ZKP::generate_random_number_below(&zkp.q),
+        };
+        let encoded = proof.to_cbor();
+        assert_eq!(Proof::from_cbor(&encoded), Ok(proof.clone()));
+
+        let json = serde_json::to_vec(&proof).unwrap();
+        assert!(encoded.len() < json.len(), "{} >= {}", encoded.len(), json.len());
+        assert_eq!(serde_json::from_slice::<Proof>(&json).unwrap(), proof);
+
+        assert!(matches!(
+            Proof::from_cbor(&encoded[..encoded.len() - 1]),
+            Err(ZkpError::InvalidEncoding(_))
+        ));
+        assert!(matches!(Proof::from_cbor(b"not cbor"), Err(ZkpError::InvalidEncoding(_))));
+    }
+
+    #[test]
+    fn test_params_round_trip() {
+        for group in [Group::Toy, Group::Rfc1024, Group::Rfc2048] {
+            let zkp = ZKP::from_group(group);
+            let encoded = zkp.to_cbor();
+            let decoded = ZKP::from_cbor(&encoded).unwrap();
+            assert_eq!(
+                (&decoded.p, &decoded.q, &decoded.alpha, &decoded.beta),
+                (&zkp.p, &zkp.q, &zkp.alpha, &zkp.beta)
+            );
+
+            let json = serde_json::to_vec(&zkp).unwrap();
+            assert!(encoded.len() < json.len(), "{group:?}");
+        }
+    }
+}
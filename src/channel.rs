@@ -0,0 +1,42 @@
+//! Opening gRPC channels to the server, over TCP or a Unix domain socket.
+//!
+//! A server started with `--uds <path>` is reached with the endpoint `unix://<path>`, e.g.
+//! `unix:///run/zkp/auth.sock`, which saves the TCP stack for services on the same host.
+
+use tonic::transport::{Channel, Endpoint, Error};
+
+/// The scheme of the endpoints naming a Unix domain socket.
+pub const UNIX_SCHEME: &str = "unix://";
+
+/// Connects to the server at `endpoint`.
+///
+/// # Arguments
+///
+/// * `endpoint` - Either a URI like `http://127.0.0.1:50051`, or on Unix `unix://` followed by
+///   the path of the socket of the server.
+///
+/// # Returns
+///
+/// A `Result` containing either the connected `Channel`, or the transport error.
+pub async fn connect(endpoint: &str) -> Result<Channel, Error> {
+    #[cfg(unix)]
+    if let Some(path) = endpoint.strip_prefix(UNIX_SCHEME) {
+        return connect_unix(path.into()).await;
+    }
+    Endpoint::from_shared(endpoint.to_string())?.connect().await
+}
+
+#[cfg(unix)]
+async fn connect_unix(path: std::path::PathBuf) -> Result<Channel, Error> {
+    use hyper_util::rt::TokioIo;
+    use tokio::net::UnixStream;
+    use tonic::transport::Uri;
+
+    // the URI only fills the `:authority` of the requests, the connector ignores it
+    Endpoint::from_static("http://localhost")
+        .connect_with_connector(tower::service_fn(move |_: Uri| {
+            let path = path.clone();
+            async move { Ok::<_, std::io::Error>(TokioIo::new(UnixStream::connect(path).await?)) }
+        }))
+        .await
+}
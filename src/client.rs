@@ -1,84 +1,614 @@
 use anyhow::{anyhow, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use num_bigint::BigUint;
 use rpassword::prompt_password;
+use serde::{Deserialize, Serialize};
+use std::error::Error as _;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-pub mod zkp_auth {
-    include!("./zkp_auth.rs");
-}
-
-use zkp_auth::{
+use zkp_chaum_pedersen::agent;
+#[cfg(unix)]
+use zkp_chaum_pedersen::agent::AgentClient;
+use zkp_chaum_pedersen::channel;
+use zkp_chaum_pedersen::kdf::{Kdf, SecretKdf};
+use zkp_chaum_pedersen::loadtest;
+use zkp_chaum_pedersen::pb::{
     auth_client::AuthClient, AuthenticationAnswerRequest, AuthenticationChallengeRequest,
     RegisterRequest,
 };
-use zkp_chaum_pedersen::ZKP;
+use zkp_chaum_pedersen::retry::{retry_unavailable, RetryPolicy, DEFAULT_MAX_RETRIES};
+use zkp_chaum_pedersen::transcript::{Outcome, SavedProof, Transcript};
+use zkp_chaum_pedersen::{
+    format_biguint_grouped, params::fetch_parameters_with_endianness, password::PasswordPolicy,
+    Endianness, Group, PROTOCOL_VERSION, ZKP,
+};
 
 #[derive(Debug, Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 /// Chaum-Pedersen Zero Knowledge Proof (client)
 struct Args {
     /// User name
     #[arg(short, long, required = true)]
-    user_name: String,
+    user_name: Option<String>,
 
-    /// Server endpoint
+    /// Server endpoint, or `unix://<path>` for a server listening on a Unix domain socket
     #[arg(
         short,
         long,
+        global = true,
         required = false,
         default_value = "http://127.0.0.1:50051"
     )]
     server: String,
+
+    /// Group parameters, must match the server's
+    #[arg(short, long, global = true, value_enum, default_value_t = Group::default())]
+    group: Group,
+
+    /// Use the group parameters sent by the server instead of `--group`
+    #[arg(long, global = true, conflicts_with = "group")]
+    trust_server_params: bool,
+
+    /// Byte order of the integers exchanged with the server, which must match the server's;
+    /// with `--trust-server-params`, the server's is used instead
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = Endianness::default(),
+        conflicts_with = "trust_server_params"
+    )]
+    endianness: Endianness,
+
+    /// Key derivation function stretching the password into the secret, the same at
+    /// registration and login
+    #[arg(long, value_enum, default_value_t = Kdf::default())]
+    kdf: Kdf,
+
+    /// Register even if the password does not meet the strength policy
+    #[arg(long)]
+    allow_weak_password: bool,
+
+    /// Derive the nonce from the password and the login time instead of sampling it
+    #[arg(long)]
+    deterministic_nonce: bool,
+
+    /// Number of times to retry a request while the server is starting up
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_RETRIES)]
+    retries: u32,
+
+    /// Log in without registering, for servers preloaded with the public values
+    #[arg(long)]
+    skip_register: bool,
+
+    /// Registration token handed out by the server's administrator, for servers requiring one
+    #[arg(long, conflicts_with = "skip_register")]
+    registration_token: Option<String>,
+
+    /// Record the servers and user names registered with in this file (never the secrets)
+    #[arg(long, value_name = "PATH")]
+    state_file: Option<PathBuf>,
+
+    /// Skip the registration if the state file records one with this server and user name
+    #[arg(long, requires = "state_file", conflicts_with = "skip_register")]
+    assume_registered: bool,
+
+    /// Print how long each phase took, on the client and for the round trip, to stderr
+    #[arg(long)]
+    timings: bool,
+
+    /// Socket of an agent caching the secret (see the `agent` command): within its TTL, logins
+    /// skip the password prompts and the key derivation
+    #[arg(long, value_name = "SOCKET")]
+    agent: Option<PathBuf>,
+
+    /// Write the proof of the login and the server's verdict to this file as JSON, accepted or
+    /// not, to check it later with the `verify-proof` command
+    #[arg(long, value_name = "PATH")]
+    save_proof: Option<PathBuf>,
+
+    /// Do not print progress messages for the slow steps to stderr
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Run a command instead of registering and logging in
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the group parameters the client would use, in hex
+    Params,
+    /// Run an agent caching derived secrets in memory, listening on SOCKET (Unix only)
+    Agent {
+        /// Path of the socket to create, in a directory only you can enter
+        socket: PathBuf,
+
+        /// Seconds a secret is kept after the login that cached it
+        #[arg(long, default_value_t = agent::DEFAULT_TTL.as_secs())]
+        ttl: u64,
+    },
+    /// Measure the throughput and the latencies of the server under load, registering throwaway
+    /// users
+    Loadtest {
+        /// Number of concurrent clients, each with its own connection
+        #[arg(long, default_value_t = 10)]
+        clients: usize,
+
+        /// Number of authentications of each client, after its registration
+        #[arg(long, default_value_t = 100)]
+        rounds: usize,
+    },
+    /// Verify offline a proof written with `--save-proof`, without contacting the server
+    VerifyProof {
+        /// Path of the saved proof
+        path: PathBuf,
+    },
+}
+
+/// Runs the agent until Ctrl-C, then removes its socket.
+#[cfg(unix)]
+async fn run_agent(socket: &Path, ttl: Duration) -> Result<()> {
+    let listener = agent::bind(socket)
+        .map_err(|e| anyhow!("Could not listen on {}: {}", socket.display(), e))?;
+    println!("✅ Agent listening on {}, keeping secrets for {:?}", socket.display(), ttl);
+    let result = tokio::select! {
+        result = agent::serve(listener, ttl, |e| println!("⚠️ Agent request failed: {}", e)) => {
+            result.map_err(Into::into)
+        }
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+    std::fs::remove_file(socket)?;
+    result
+}
+
+#[cfg(not(unix))]
+async fn run_agent(_socket: &Path, _ttl: Duration) -> Result<()> {
+    Err(anyhow!("The agent needs Unix domain sockets"))
+}
+
+/// Returns the secret of `user`, derived with `kdf`, held by the agent at `socket`, if any.
+///
+/// The agent only saves time, so failing to reach it is a warning rather than an error.
+#[cfg(unix)]
+async fn cached_secret(socket: &Path, zkp: &ZKP, kdf: Kdf, user: &str) -> Option<BigUint> {
+    let key = agent::cache_key(zkp, kdf, user);
+    match AgentClient::new(socket).get(&key).await {
+        Ok(secret) => secret,
+        Err(e) => {
+            println!("⚠️ Could not reach the agent at {}: {}", socket.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn cached_secret(_socket: &Path, _zkp: &ZKP, _kdf: Kdf, _user: &str) -> Option<BigUint> {
+    println!("⚠️ The agent needs Unix domain sockets, ignoring --agent");
+    None
+}
+
+/// Hands the secret of `user` to the agent at `socket`, see `cached_secret`.
+#[cfg(unix)]
+async fn cache_secret(socket: &Path, zkp: &ZKP, kdf: Kdf, user: &str, x: &BigUint) {
+    let key = agent::cache_key(zkp, kdf, user);
+    match AgentClient::new(socket).put(&key, x).await {
+        Ok(()) => println!("🔑 Secret cached by the agent"),
+        Err(e) => println!("⚠️ Could not cache the secret in the agent: {}", e),
+    }
+}
+
+#[cfg(not(unix))]
+async fn cache_secret(_socket: &Path, _zkp: &ZKP, _kdf: Kdf, _user: &str, _x: &BigUint) {}
+
+/// Lets the user know the server was unavailable, see `retry_unavailable`.
+fn report_retry(status: &tonic::Status, backoff: Duration) {
+    println!("⏳ Server unavailable ({}), retrying in {:?}", status.message(), backoff);
+}
+
+/// Verifies the proof saved at `path`, printing the result next to the server's verdict.
+///
+/// # Returns
+///
+/// A `Result` containing whether the proof is valid, or an error if the file cannot be read.
+fn verify_saved_proof(path: &Path) -> Result<bool> {
+    let saved: SavedProof = serde_json::from_slice(&std::fs::read(path)?)
+        .map_err(|e| anyhow!("{} is not a saved proof: {}", path.display(), e))?;
+    let valid = saved.verify();
+    let verdict = match saved.transcript.outcome {
+        Outcome::Accepted => "accepted",
+        Outcome::Rejected => "rejected",
+    };
+    if valid {
+        println!("✅ The proof of {:?} is valid", saved.transcript.user);
+    } else {
+        println!("❌ The proof of {:?} is invalid", saved.transcript.user);
+    }
+    println!("   the server {} it", verdict);
+    if valid != (saved.transcript.outcome == Outcome::Accepted) {
+        println!("⚠️ The server checked it against other public values than the saved ones");
+    }
+    Ok(valid)
+}
+
+/// The registrations recorded in the `--state-file`, so later runs can skip registering again.
+///
+/// Only the server and the user name are recorded, the secret and the password never are.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct RegistrationState {
+    registrations: Vec<Registration>,
+}
+
+/// One registration recorded in a `RegistrationState`.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Registration {
+    server: String,
+    user: String,
+    /// Seconds since the Unix epoch.
+    registered_at: u64,
+}
+
+impl RegistrationState {
+    /// Reads the state at `path`, empty if the file does not exist yet.
+    fn load(path: &Path) -> Result<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| anyhow!("{} is not a client state file: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(anyhow!("Could not read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Writes the state to `path`, replacing the file.
+    fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)
+            .map_err(|e| anyhow!("Could not write {}: {}", path.display(), e))
+    }
+
+    /// Returns whether a registration of `user` with `server` is recorded.
+    fn is_registered(&self, server: &str, user: &str) -> bool {
+        self.registrations
+            .iter()
+            .any(|r| r.server == server && r.user == user)
+    }
+
+    /// Records a registration of `user` with `server`, replacing an earlier one.
+    fn record(&mut self, server: &str, user: &str, registered_at: u64) {
+        self.registrations
+            .retain(|r| !(r.server == server && r.user == user));
+        self.registrations.push(Registration {
+            server: server.to_string(),
+            user: user.to_string(),
+            registered_at,
+        });
+    }
+}
+
+/// Returns whether the registration can be skipped: with `--assume-registered`, when the state
+/// file records a registration of `user` with the server.
+fn assumed_registered(args: &Args, user: &str) -> Result<bool> {
+    match (&args.state_file, args.assume_registered) {
+        (Some(path), true) => Ok(RegistrationState::load(path)?.is_registered(&args.server, user)),
+        _ => Ok(false),
+    }
+}
+
+/// Records in the `--state-file`, if any, that `user` registered with the server.
+fn record_registration(args: &Args, user: &str) -> Result<()> {
+    let Some(path) = &args.state_file else {
+        return Ok(());
+    };
+    let mut state = RegistrationState::load(path)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    state.record(&args.server, user, now);
+    state.save(path)
+}
+
+/// Formats the group parameters of `zkp`, each labeled and in hex grouped like in RFC 5114.
+fn format_params(zkp: &ZKP) -> String {
+    [
+        ("p", &zkp.p),
+        ("q", &zkp.q),
+        ("alpha", &zkp.alpha),
+        ("beta", &zkp.beta),
+    ]
+    .iter()
+    .map(|(label, value)| {
+        let value = format_biguint_grouped(value, 8).replace('\n', "\n        ");
+        format!("{:<5} = {}\n", label, value)
+    })
+    .collect()
+}
+
+/// Progress messages for the steps that take noticeable time in large groups.
+struct Progress {
+    quiet: bool,
+}
+
+impl Progress {
+    /// Runs `f`, telling on `out` what it is doing and how long it took unless quiet.
+    fn run<T>(&self, out: &mut impl Write, message: &str, f: impl FnOnce() -> T) -> T {
+        if self.quiet {
+            return f();
+        }
+        // progress is best effort, failing to print it must not fail the login
+        let _ = write!(out, "⏳ {}... ", message).and_then(|_| out.flush());
+        let start = Instant::now();
+        let value = f();
+        let _ = writeln!(out, "done in {:.1?}", start.elapsed());
+        value
+    }
+}
+
+/// The durations of the protocol phases, split between the computations of the client (`local`)
+/// and the request to the server (`round trip`, network and server side work).
+#[derive(Debug, Default)]
+struct Timings {
+    phases: Vec<(&'static str, Duration, Duration)>,
+}
+
+impl Timings {
+    fn record(&mut self, phase: &'static str, local: Duration, round_trip: Duration) {
+        self.phases.push((phase, local, round_trip));
+    }
+
+    /// Writes one line per phase, after a header line.
+    fn report(&self, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(out, "{:<12} {:>12} {:>12}", "phase", "local", "round trip")?;
+        for (phase, local, round_trip) in &self.phases {
+            writeln!(out, "{:<12} {:>12.2?} {:>12.2?}", phase, local, round_trip)?;
+        }
+        Ok(())
+    }
+}
+
+/// The likely cause of a failure to connect to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConnectFailure {
+    InvalidUri,
+    Dns,
+    ConnectionRefused,
+    Tls,
+    Timeout,
+    Other,
+}
+
+impl fmt::Display for ConnectFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidUri => {
+                "invalid server address, expected a URI like http://127.0.0.1:50051"
+            }
+            Self::Dns => "could not resolve the server host name, check its spelling and DNS",
+            Self::ConnectionRefused => {
+                "connection refused, check that the server is running and listening on that port"
+            }
+            Self::Tls => "TLS handshake failed, check the scheme and the server certificate",
+            Self::Timeout => "timed out, check the address and any firewall in between",
+            Self::Other => "could not connect",
+        })
+    }
+}
+
+/// Categorizes a connection error by walking its chain of sources.
+///
+/// tonic and hyper only expose most causes as messages, so apart from the I/O error kinds this
+/// matches on the messages of the errors in the chain.
+fn diagnose(error: &tonic::transport::Error) -> ConnectFailure {
+    let mut source = error.source();
+    while let Some(e) = source {
+        if let Some(io) = e.downcast_ref::<std::io::Error>() {
+            match io.kind() {
+                std::io::ErrorKind::ConnectionRefused => return ConnectFailure::ConnectionRefused,
+                std::io::ErrorKind::TimedOut => return ConnectFailure::Timeout,
+                _ => {}
+            }
+        }
+        let message = e.to_string().to_lowercase();
+        if message.contains("invalid uri") || message.contains("scheme is missing") {
+            return ConnectFailure::InvalidUri;
+        }
+        if message.contains("dns error") {
+            return ConnectFailure::Dns;
+        }
+        if message.contains("tls") || message.contains("certificate") {
+            return ConnectFailure::Tls;
+        }
+        if message.contains("timed out") {
+            return ConnectFailure::Timeout;
+        }
+        source = e.source();
+    }
+    ConnectFailure::Other
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
-    let (alpha, beta, p, q) = ZKP::get_constants();
-    let zkp = ZKP::new(&p, &q, &alpha, &beta);
+    if matches!(args.command, Some(Command::Params)) && !args.trust_server_params {
+        print!("{}", format_params(&ZKP::from_group(args.group)));
+        return Ok(());
+    }
+    if let Some(Command::Agent { socket, ttl }) = &args.command {
+        return run_agent(socket, Duration::from_secs(*ttl)).await;
+    }
+    if let Some(Command::VerifyProof { path }) = &args.command {
+        if !verify_saved_proof(path)? {
+            return Err(anyhow!("The proof does not verify"));
+        }
+        return Ok(());
+    }
 
-    let mut client = match AuthClient::connect(args.server.clone()).await {
-        Ok(x) => x,
+    let mut client = match channel::connect(&args.server).await {
+        Ok(x) => AuthClient::new(x),
         Err(e) => {
-            return Err(anyhow!("Failed to register user on server {}: {:?}", args.server, e));
+            return Err(anyhow!(
+                "Failed to connect to server {}: {} ({:?})",
+                args.server,
+                diagnose(&e),
+                e
+            ));
         }
     };
 
     println!("✅ Connected to the server");
-    let password = prompt_password("Password: ")?;
-    let password = BigUint::from_bytes_be(password.trim().as_bytes());
-    let (y1, y2) = zkp.compute_pair(&password);
 
-    let username = args.user_name.clone();
-    let request = RegisterRequest {
-        user: username.clone(),
-        y1: y1.to_bytes_be(),
-        y2: y2.to_bytes_be(),
+    let (zkp, endianness) = if args.trust_server_params {
+        println!("⚠️ Using the group parameters of the server, they are not proven to be sound");
+        match fetch_parameters_with_endianness(&mut client).await {
+            Ok(negotiated) => negotiated,
+            Err(e) => {
+                return Err(anyhow!("Could not get the group parameters from server {:?}", e));
+            }
+        }
+    } else {
+        (ZKP::from_group(args.group), args.endianness)
     };
 
-    let _ = match client.register(request).await {
-        Ok(x) => x,
-        Err(e) => {
-            return Err(anyhow!("Could not register user name with server {:?}", e));
-        }
+    if matches!(args.command, Some(Command::Params)) {
+        print!("{}", format_params(&zkp));
+        return Ok(());
+    }
+    if let Some(Command::Loadtest { clients, rounds }) = args.command {
+        println!("⏳ Running {} clients of {} authentications each", clients, rounds);
+        let report = loadtest::run(&args.server, &zkp, endianness, clients, rounds)
+            .await
+            .map_err(|e| anyhow!("Load test could not connect to {}: {:?}", args.server, e))?;
+        println!("{}", report);
+        return Ok(());
+    }
+
+    let username = args.user_name.clone().expect("required without a command");
+    let cached = match &args.agent {
+        Some(socket) => cached_secret(socket, &zkp, args.kdf, &username).await,
+        None => None,
     };
-    println!("✅ Registration was successful");
+    if cached.is_some() {
+        println!("🔑 Using the secret cached by the agent");
+    }
+    let mut timings = Timings::default();
+    let progress = Progress { quiet: args.quiet };
+    let retry = RetryPolicy {
+        max_retries: args.retries,
+        ..Default::default()
+    };
+    // with --skip-register, the server already knows the public values
+    let mut registered = None;
+    let skip_register = args.skip_register || assumed_registered(&args, &username)?;
+    if skip_register && args.assume_registered {
+        println!("✅ Already registered according to the state file, skipping the registration");
+    }
+    if !skip_register {
+        let (x, y1, y2, start) = match &cached {
+            Some(x) => {
+                let start = Instant::now();
+                let (y1, y2) = zkp.compute_pair(x);
+                (x.clone(), y1, y2, start)
+            }
+            None => {
+                let password = prompt_password("Password: ")?;
+                if !args.allow_weak_password {
+                    if let Err(e) = PasswordPolicy::default().check(password.trim()) {
+                        return Err(anyhow!(
+                            "{} (pass --allow-weak-password to register anyway)",
+                            e
+                        ));
+                    }
+                }
+                let start = Instant::now();
+                let (x, y1, y2) =
+                    progress.run(&mut std::io::stderr(), "Deriving the public values", || {
+                        let x = args.kdf.derive(&zkp, password.trim(), username.as_bytes());
+                        let (y1, y2) = zkp.compute_pair(&x);
+                        (x, y1, y2)
+                    });
+                (x, y1, y2, start)
+            }
+        };
+        let proof = zkp.prove_possession(&x, &y1, &y2, &username);
+
+        let local = start.elapsed();
 
-    println!("Please provide the password (to login):");
-    let password = prompt_password("Password (to login): ")?;
-    let password = BigUint::from_bytes_be(password.trim().as_bytes());
+        let request = RegisterRequest {
+            user: username.clone(),
+            y1: zkp.encode_fixed_with(&y1, endianness),
+            y2: zkp.encode_fixed_with(&y2, endianness),
+            protocol_version: PROTOCOL_VERSION,
+            possession_proof: proof.to_bytes()?,
+            registration_token: args.registration_token.clone().unwrap_or_default(),
+            ..Default::default()
+        };
 
-    let k = ZKP::generate_random_number_below(&q);
-    let (r1, r2) = zkp.compute_pair(&k);
+        let register = retry_unavailable(
+            &retry,
+            || {
+                let (mut client, request) = (client.clone(), request.clone());
+                async move { client.register(request).await }
+            },
+            report_retry,
+        );
+        let start = Instant::now();
+        let _ = match register.await {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(anyhow!("Could not register user name with server {:?}", e));
+            }
+        };
+        timings.record("registration", local, start.elapsed());
+        println!("✅ Registration was successful");
+        record_registration(&args, &username)?;
+        registered = Some((y1, y2));
+    }
+
+    let password = match cached {
+        Some(_) => None,
+        None => {
+            println!("Please provide the password (to login):");
+            Some(prompt_password("Password (to login): ")?)
+        }
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+    let start = Instant::now();
+    let (password, k, r1, r2) =
+        progress.run(&mut std::io::stderr(), "Computing the commitment", || {
+            let password = match &password {
+                Some(password) => args.kdf.derive(&zkp, password.trim(), username.as_bytes()),
+                None => cached.clone().expect("the agent holds the secret"),
+            };
+            let k = if args.deterministic_nonce {
+                zkp.deterministic_nonce(&password, format!("{}:{}", username, now).as_bytes())?
+            } else {
+                ZKP::generate_random_number_below(&zkp.q)
+            };
+            let (r1, r2) = zkp.compute_pair(&k);
+            Ok::<_, anyhow::Error>((password, k, r1, r2))
+        })?;
+
+    let local = start.elapsed();
 
     let request = AuthenticationChallengeRequest {
-        user: username,
-        r1: r1.to_bytes_be(),
-        r2: r2.to_bytes_be(),
+        user: username.clone(),
+        r1: zkp.encode_fixed_with(&r1, endianness),
+        r2: zkp.encode_fixed_with(&r2, endianness),
+        protocol_version: PROTOCOL_VERSION,
+        ..Default::default()
     };
 
-    let response = match client.create_authentication_challenge(request).await {
+    let challenge = retry_unavailable(
+        &retry,
+        || {
+            let (mut client, request) = (client.clone(), request.clone());
+            async move { client.create_authentication_challenge(request).await }
+        },
+        report_retry,
+    );
+    let start = Instant::now();
+    let response = match challenge.await {
         Ok(x) => x,
         Err(e) => {
             return Err(anyhow!("Could not request challenge to server {:?}", e));
@@ -86,16 +616,52 @@ async fn main() -> Result<()> {
     }
     .into_inner();
 
+    timings.record("challenge", local, start.elapsed());
+
     let auth_id = response.auth_id;
-    let c = BigUint::from_bytes_be(&response.c);
+    let start = Instant::now();
+    let c = endianness.decode(&response.c);
     let s = zkp.solve(&k, &c, &password);
+    let local = start.elapsed();
 
     let request = AuthenticationAnswerRequest {
         auth_id,
-        s: s.to_bytes_be(),
+        s: endianness.encode(&s),
+        ..Default::default()
     };
 
-    let response = match client.verify_authentication(request).await {
+    let start = Instant::now();
+    let response = client.verify_authentication(request).await;
+    if let Some(path) = &args.save_proof {
+        // only a verdict of the server on the proof is worth saving
+        let outcome = match &response {
+            Ok(_) => Some(Outcome::Accepted),
+            Err(e) if e.code() == tonic::Code::PermissionDenied => Some(Outcome::Rejected),
+            Err(_) => None,
+        };
+        if let Some(outcome) = outcome {
+            // the values registered by this run, otherwise those of the login's secret
+            let (y1, y2) = registered.unwrap_or_else(|| zkp.compute_pair(&password));
+            let saved = SavedProof {
+                params: zkp.clone(),
+                transcript: Transcript {
+                    user: username.clone(),
+                    y1,
+                    y2,
+                    r1,
+                    r2,
+                    c,
+                    s,
+                    timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+                    outcome,
+                },
+            };
+            std::fs::write(path, serde_json::to_vec_pretty(&saved)?)
+                .map_err(|e| anyhow!("Could not save the proof to {}: {}", path.display(), e))?;
+            println!("💾 Proof saved to {}", path.display());
+        }
+    }
+    let response = match response {
         Ok(x) => x,
         Err(e) => {
             return Err(anyhow!("Could not verify authentication in server {:?}", e));
@@ -103,6 +669,311 @@ async fn main() -> Result<()> {
     }
     .into_inner();
 
+    timings.record("verification", local, start.elapsed());
+
     println!("✅Login successful! session_id: {}", response.session_id);
+    if let (Some(socket), None) = (&args.agent, &cached) {
+        cache_secret(socket, &zkp, args.kdf, &username, &password).await;
+    }
+    if args.timings {
+        timings.report(&mut std::io::stderr())?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use prost::Message;
+
+    #[test]
+    fn test_pb_types_accessible() {
+        let request = RegisterRequest {
+            user: "alice".to_string(),
+            y1: vec![1, 2, 3],
+            y2: vec![4, 5, 6],
+            ..Default::default()
+        };
+        let bytes = request.encode_to_vec();
+        assert_eq!(RegisterRequest::decode(&bytes[..]).unwrap(), request);
+    }
+
+    #[test]
+    fn test_group_flag() {
+        let args = Args::try_parse_from(["client", "--user-name", "alice"]).unwrap();
+        assert_eq!(args.group, Group::Rfc1024);
+        assert_eq!(args.user_name.as_deref(), Some("alice"));
+        assert!(Args::try_parse_from(["client"]).is_err());
+
+        let args =
+            Args::try_parse_from(["client", "--user-name", "alice", "--group", "rfc2048"]).unwrap();
+        assert_eq!(args.group, Group::Rfc2048);
+        assert_eq!(ZKP::from_group(args.group).p.bits(), 2048);
+
+        assert!(Args::try_parse_from(["client", "--user-name", "a", "--group", "rfc4096"]).is_err());
+
+        let args =
+            Args::try_parse_from(["client", "--user-name", "alice", "--trust-server-params"])
+                .unwrap();
+        assert!(args.trust_server_params);
+        assert!(!args.skip_register);
+
+        let args = Args::try_parse_from(["client", "-u", "alice", "--skip-register"]).unwrap();
+        assert!(args.skip_register);
+
+        let args =
+            Args::try_parse_from(["client", "-u", "alice", "--registration-token", "zkr1.a.b"])
+                .unwrap();
+        assert_eq!(args.registration_token.as_deref(), Some("zkr1.a.b"));
+        assert!(Args::try_parse_from([
+            "client",
+            "-u",
+            "alice",
+            "--skip-register",
+            "--registration-token",
+            "zkr1.a.b"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_params_command() {
+        let args = Args::try_parse_from(["client", "params"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Params)));
+        assert_eq!(args.user_name, None);
+
+        let params = format_params(&ZKP::from_group(args.group));
+        let p: Vec<&str> = params.lines().take(4).collect();
+        assert_eq!(
+            p,
+            [
+                "p     = B10B8F96 A080E01D DE92DE5E AE5D54EC 52C99FBC FB06A3C6 9A6A9DCA 52D23B61",
+                "        6073E286 75A23D18 9838EF1E 2EE652C0 13ECB4AE A9061123 24975C3C D49B83BF",
+                "        ACCBDD7D 90C4BD70 98488E9C 219A7372 4EFFD6FA E5644738 FAA31A4F F55BCCC0",
+                "        A151AF5F 0DC8B4BD 45BF37DF 365C1A65 E68CFDA7 6D4DA708 DF1FB2BC 2E4A4371",
+            ]
+        );
+        assert_eq!(
+            params.lines().nth(4),
+            Some("q     = F518AA87 81A8DF27 8ABA4E7D 64B7CB9D 49462353")
+        );
+        assert_eq!(params.lines().count(), 13);
+
+        let args = Args::try_parse_from(["client", "params", "--group", "toy"]).unwrap();
+        let params = format_params(&ZKP::from_group(args.group));
+        assert_eq!(params, "p     = 17\nq     = B\nalpha = 4\nbeta  = 9\n");
+    }
+
+    #[test]
+    fn test_agent_args() {
+        let args = Args::try_parse_from(["client", "agent", "/tmp/zkp-agent.sock"]).unwrap();
+        match args.command {
+            Some(Command::Agent { socket, ttl }) => {
+                assert_eq!(socket, PathBuf::from("/tmp/zkp-agent.sock"));
+                assert_eq!(ttl, agent::DEFAULT_TTL.as_secs());
+            }
+            command => panic!("unexpected command {:?}", command),
+        }
+        assert!(Args::try_parse_from(["client", "agent"]).is_err());
+
+        let args =
+            Args::try_parse_from(["client", "-u", "alice", "--agent", "/tmp/zkp-agent.sock"])
+                .unwrap();
+        assert_eq!(args.agent, Some(PathBuf::from("/tmp/zkp-agent.sock")));
+    }
+
+    #[test]
+    fn test_loadtest_args() {
+        let args = Args::try_parse_from(["client", "loadtest", "--clients", "4"]).unwrap();
+        match args.command {
+            Some(Command::Loadtest { clients, rounds }) => assert_eq!((clients, rounds), (4, 100)),
+            command => panic!("unexpected command {:?}", command),
+        }
+    }
+
+    #[test]
+    fn test_save_proof_args() {
+        let args =
+            Args::try_parse_from(["client", "-u", "alice", "--save-proof", "proof.json"]).unwrap();
+        assert_eq!(args.save_proof, Some(PathBuf::from("proof.json")));
+
+        let args = Args::try_parse_from(["client", "verify-proof", "proof.json"]).unwrap();
+        match args.command {
+            Some(Command::VerifyProof { path }) => assert_eq!(path, PathBuf::from("proof.json")),
+            command => panic!("unexpected command {:?}", command),
+        }
+        assert!(Args::try_parse_from(["client", "verify-proof"]).is_err());
+    }
+
+    #[test]
+    fn test_registration_state() {
+        let path = std::env::temp_dir().join(format!("zkp-client-state-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let path_arg = path.to_str().unwrap();
+
+        // a first run registers, and records it
+        let args = Args::try_parse_from([
+            "client",
+            "-u",
+            "alice",
+            "--state-file",
+            path_arg,
+            "--assume-registered",
+        ])
+        .unwrap();
+        assert!(!path.exists());
+        assert!(!assumed_registered(&args, "alice").unwrap());
+        record_registration(&args, "alice").unwrap();
+        assert!(path.exists());
+
+        // the next login takes the fast path, for that server and user only
+        assert!(assumed_registered(&args, "alice").unwrap());
+        assert!(!assumed_registered(&args, "bob").unwrap());
+        let other_server = Args::try_parse_from([
+            "client",
+            "-u",
+            "alice",
+            "--server",
+            "http://10.0.0.1:50051",
+            "--state-file",
+            path_arg,
+            "--assume-registered",
+        ])
+        .unwrap();
+        assert!(!assumed_registered(&other_server, "alice").unwrap());
+        let without_flag =
+            Args::try_parse_from(["client", "-u", "alice", "--state-file", path_arg]).unwrap();
+        assert!(!assumed_registered(&without_flag, "alice").unwrap());
+
+        // registering again replaces the record
+        record_registration(&args, "alice").unwrap();
+        let state = RegistrationState::load(&path).unwrap();
+        assert_eq!(state.registrations.len(), 1);
+        assert_eq!(state.registrations[0].server, "http://127.0.0.1:50051");
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(
+            json["registrations"][0]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            ["registered_at", "server", "user"]
+        );
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(assumed_registered(&args, "alice").is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(Args::try_parse_from(["client", "-u", "alice", "--assume-registered"]).is_err());
+        assert!(Args::try_parse_from([
+            "client",
+            "-u",
+            "alice",
+            "--state-file",
+            path_arg,
+            "--assume-registered",
+            "--skip-register"
+        ])
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_diagnostics() {
+        // a port that was just free, so nothing is listening on it
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let e = AuthClient::connect(format!("http://127.0.0.1:{}", port))
+            .await
+            .unwrap_err();
+        assert_eq!(diagnose(&e), ConnectFailure::ConnectionRefused);
+
+        for uri in ["not a uri", "127.0.0.1:50051"] {
+            let e = AuthClient::connect(uri.to_string()).await.unwrap_err();
+            assert_eq!(diagnose(&e), ConnectFailure::InvalidUri, "{uri:?}");
+        }
+    }
+
+    #[test]
+    fn test_quiet_progress() {
+        let args = Args::try_parse_from(["client", "--user-name", "alice", "--quiet"]).unwrap();
+        assert!(args.quiet);
+        let args = Args::try_parse_from(["client", "--user-name", "alice"]).unwrap();
+        assert!(!args.quiet);
+
+        let zkp = ZKP::from_group(Group::Rfc2048);
+        let x = BigUint::from(6u32);
+
+        let mut out = Vec::new();
+        let quiet = Progress { quiet: true };
+        let pair = quiet.run(&mut out, "Deriving the public values", || zkp.compute_pair(&x));
+        assert_eq!(pair, zkp.compute_pair(&x));
+        assert!(out.is_empty());
+
+        let verbose = Progress { quiet: false };
+        verbose.run(&mut out, "Deriving the public values", || zkp.compute_pair(&x));
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("⏳ Deriving the public values... done in "), "{out:?}");
+    }
+
+    #[test]
+    fn test_timings() {
+        let args = Args::try_parse_from(["client", "--user-name", "alice", "--timings"]).unwrap();
+        assert!(args.timings);
+
+        let mut timings = Timings::default();
+        timings.record("registration", Duration::from_millis(3), Duration::from_millis(1));
+        timings.record("challenge", Duration::from_micros(1500), Duration::from_millis(2));
+        timings.record("verification", Duration::from_micros(700), Duration::from_millis(4));
+        let mut out = Vec::new();
+        timings.report(&mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 4, "{out}");
+        assert!(lines[0].starts_with("phase") && lines[0].ends_with("round trip"));
+        for (line, phase) in lines[1..]
+            .iter()
+            .zip(["registration", "challenge", "verification"])
+        {
+            assert!(line.starts_with(phase), "{line:?}");
+        }
+        assert!(lines[2].contains("1.50ms") && lines[2].ends_with("2.00ms"), "{out}");
+    }
+
+    #[test]
+    fn test_kdf_flag() {
+        let args = Args::try_parse_from(["client", "--user-name", "alice"]).unwrap();
+        assert_eq!(args.kdf, Kdf::Pbkdf2);
+        #[cfg(feature = "kdf")]
+        for (name, kdf) in [("scrypt", Kdf::Scrypt), ("argon2id", Kdf::Argon2id)] {
+            let args = Args::try_parse_from(["client", "--user-name", "alice", "--kdf", name]);
+            assert_eq!(args.unwrap().kdf, kdf);
+        }
+        #[cfg(not(feature = "kdf"))]
+        assert!(Args::try_parse_from(["client", "--user-name", "a", "--kdf", "scrypt"]).is_err());
+        assert!(Args::try_parse_from(["client", "--user-name", "a", "--kdf", "md5"]).is_err());
+    }
+
+    #[test]
+    fn test_endianness_flag() {
+        let args = Args::try_parse_from(["client", "--user-name", "alice"]).unwrap();
+        assert_eq!(args.endianness, Endianness::Big);
+        let args =
+            Args::try_parse_from(["client", "--user-name", "alice", "--endianness", "little"]);
+        assert_eq!(args.unwrap().endianness, Endianness::Little);
+        // the server's byte order is used with its parameters
+        assert!(Args::try_parse_from([
+            "client",
+            "--user-name",
+            "alice",
+            "--endianness",
+            "little",
+            "--trust-server-params"
+        ])
+        .is_err());
+    }
+}
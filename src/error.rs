@@ -0,0 +1,107 @@
+use std::fmt;
+
+/// The errors returned by the library.
+///
+/// Callers can match on the variants to tell a malformed input from an unsupported format or a
+/// failing environment:
+///
+/// ```
+/// use zkp_chaum_pedersen::{Proof, ZkpError};
+///
+/// fn describe(encoded: &str) -> String {
+///     match Proof::from_transport_string(encoded) {
+///         Ok(proof) => format!("proof with challenge {}", proof.c),
+///         Err(ZkpError::UnsupportedVersion(version)) => format!("upgrade to read {}", version),
+///         Err(ZkpError::InvalidEncoding(reason)) => format!("corrupted proof: {}", reason),
+///         Err(e) => format!("unexpected: {}", e),
+///     }
+/// }
+///
+/// assert_eq!(describe("zkp9:AAAA"), "upgrade to read zkp9");
+/// assert!(describe("zkp1:!").starts_with("corrupted proof"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ZkpError {
+    /// A serialized value could not be decoded.
+    InvalidEncoding(String),
+    /// A serialized value uses a format version this library does not support.
+    UnsupportedVersion(String),
+    /// The random number generator failed to produce random bytes.
+    RandomnessUnavailable(String),
+    /// A signed value was not signed with the expected key, or was modified.
+    InvalidSignature,
+    /// A value with an expiry, such as a session token, has expired.
+    Expired,
+    /// A file could not be read or written.
+    Io(String),
+    /// A password does not meet the `PasswordPolicy`.
+    WeakPassword(String),
+    /// A value is outside of the range it must be in.
+    OutOfRange(String),
+    /// A known-answer check of `ZKP::self_test` did not get the expected value.
+    SelfTestFailed(String),
+}
+
+impl fmt::Display for ZkpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidEncoding(reason) => write!(f, "invalid encoding: {}", reason),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported version: {}", version),
+            Self::RandomnessUnavailable(reason) => write!(f, "randomness unavailable: {}", reason),
+            Self::InvalidSignature => write!(f, "invalid signature"),
+            Self::Expired => write!(f, "expired"),
+            Self::Io(reason) => write!(f, "i/o error: {}", reason),
+            Self::WeakPassword(reason) => write!(f, "password too weak: {}", reason),
+            Self::OutOfRange(reason) => write!(f, "out of range: {}", reason),
+            Self::SelfTestFailed(check) => write!(f, "self-test failed: {}", check),
+        }
+    }
+}
+
+impl std::error::Error for ZkpError {}
+
+impl From<std::io::Error> for ZkpError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<hex::FromHexError> for ZkpError {
+    fn from(e: hex::FromHexError) -> Self {
+        Self::InvalidEncoding(e.to_string())
+    }
+}
+
+/// The result of the library's fallible functions.
+pub type Result<T> = std::result::Result<T, ZkpError>;
+
+/// The reasons a proof can fail `ZKP::verify_with_reason`.
+///
+/// None of them carries a value, so they can be logged without leaking anything about the proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationFailure {
+    /// `r1 != alpha^s * y1^c mod p`.
+    R1Mismatch,
+    /// `r2 != beta^s * y2^c mod p`.
+    R2Mismatch,
+    /// The response `s` is not below `q`.
+    ResponseOutOfRange,
+    /// The commitment `r1` or `r2` is `0` or `1`, which no random nonce should produce.
+    CommitmentDegenerate,
+    /// `r1`, `r2`, `y1` or `y2` is not below `p`, e.g. because it belongs to another group.
+    ElementOutOfRange,
+}
+
+impl fmt::Display for VerificationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::R1Mismatch => write!(f, "r1 does not match"),
+            Self::R2Mismatch => write!(f, "r2 does not match"),
+            Self::ResponseOutOfRange => write!(f, "response out of range"),
+            Self::CommitmentDegenerate => write!(f, "degenerate commitment"),
+            Self::ElementOutOfRange => write!(f, "group element out of range"),
+        }
+    }
+}
+
+impl std::error::Error for VerificationFailure {}
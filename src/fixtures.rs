@@ -0,0 +1,46 @@
+//! Known parameters and proofs shared by the tests of the library, the client and the server.
+//!
+//! Everything here uses the toy group of the examples, with fixed secret, nonce and challenge,
+//! so the values can be checked by hand. Enabled in the library's own tests, and for other
+//! targets with the `test-util` feature.
+
+use num_bigint::BigUint;
+
+use crate::{Group, Proof, ZKP};
+
+/// The secret `x` of the fixtures.
+pub const SECRET: u32 = 6;
+
+/// The nonce `k` of the fixture proofs.
+pub const NONCE: u32 = 7;
+
+/// The challenge `c` of the fixture proofs.
+pub const CHALLENGE: u32 = 4;
+
+/// The toy group `p = 23`, `q = 11`, `alpha = 4`, `beta = 9`.
+pub fn toy_zkp() -> ZKP {
+    ZKP::from_group(Group::Toy)
+}
+
+/// The public values `(y1, y2)` of `SECRET` in the toy group.
+pub fn public_keys() -> (BigUint, BigUint) {
+    toy_zkp().compute_pair(&BigUint::from(SECRET))
+}
+
+/// A valid proof of knowledge of `SECRET` for `public_keys`.
+pub fn valid_proof() -> Proof {
+    proof_for(&BigUint::from(SECRET))
+}
+
+/// A proof for `public_keys` made with the wrong secret, which must not verify.
+pub fn forged_proof() -> Proof {
+    proof_for(&BigUint::from(SECRET + 1))
+}
+
+fn proof_for(x: &BigUint) -> Proof {
+    let zkp = toy_zkp();
+    let (k, c) = (BigUint::from(NONCE), BigUint::from(CHALLENGE));
+    let (r1, r2) = zkp.compute_pair(&k);
+    let s = zkp.solve(&k, &c, x);
+    Proof { r1, r2, c, s }
+}
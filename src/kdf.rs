@@ -0,0 +1,239 @@
+//! Pluggable key derivation functions stretching passwords into secrets.
+//!
+//! `ZKP::derive_secret` uses PBKDF2-HMAC-SHA256, but deployments standardized on scrypt or
+//! Argon2id can derive their secrets with those instead, with the `kdf` feature. Every `SecretKdf` stretches the
+//! password into 16 bytes more than the byte length of `q`, reduced modulo `q`, so `x` is in
+//! `[0, q)` with a negligible bias. The prover must use the same KDF, with the same parameters, at
+//! registration and login: another KDF derives another secret.
+
+use num_bigint::BigUint;
+#[cfg(feature = "kdf")]
+use sha2::Digest;
+use sha2::Sha256;
+
+#[cfg(feature = "kdf")]
+use crate::{Result, ZkpError};
+use crate::{PBKDF2_ROUNDS, ZKP};
+
+/// Stretches a password and a salt into a secret.
+pub trait SecretKdf {
+    /// Fills `output` with the key stretched from `password` and `salt`.
+    fn fill(&self, password: &str, salt: &[u8], output: &mut [u8]);
+
+    /// Derives the secret `x` of `password` and `salt` in the group of `zkp`.
+    ///
+    /// # Returns
+    ///
+    /// A `BigUint` representing the secret `x`, in `[0, q)`.
+    fn derive(&self, zkp: &ZKP, password: &str, salt: &[u8]) -> BigUint {
+        let mut key = vec![0u8; zkp.q_byte_len() + 16];
+        self.fill(password, salt, &mut key);
+        BigUint::from_bytes_be(&key) % &zkp.q
+    }
+}
+
+/// PBKDF2-HMAC-SHA256, the KDF of `ZKP::derive_secret`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pbkdf2 {
+    /// The number of rounds, `PBKDF2_ROUNDS` by default.
+    pub rounds: u32,
+}
+
+impl Default for Pbkdf2 {
+    fn default() -> Self {
+        Self {
+            rounds: PBKDF2_ROUNDS,
+        }
+    }
+}
+
+impl SecretKdf for Pbkdf2 {
+    fn fill(&self, password: &str, salt: &[u8], output: &mut [u8]) {
+        pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, self.rounds, output);
+    }
+}
+
+/// scrypt, with the recommended parameters (`log_n = 17`, `r = 8`, `p = 1`) by default.
+#[cfg(feature = "kdf")]
+#[derive(Debug, Clone, Copy)]
+pub struct Scrypt {
+    params: scrypt::Params,
+}
+
+#[cfg(feature = "kdf")]
+impl Scrypt {
+    /// Creates the KDF with the cost parameters `2^log_n`, `r` and `p`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the KDF, or `ZkpError::OutOfRange` if scrypt does not
+    /// accept the parameters.
+    pub fn new(log_n: u8, r: u32, p: u32) -> Result<Self> {
+        let params = scrypt::Params::new(log_n, r, p, scrypt::Params::RECOMMENDED_LEN)
+            .map_err(|e| ZkpError::OutOfRange(format!("scrypt parameters: {}", e)))?;
+        Ok(Self { params })
+    }
+}
+
+#[cfg(feature = "kdf")]
+impl Default for Scrypt {
+    fn default() -> Self {
+        Self {
+            params: scrypt::Params::recommended(),
+        }
+    }
+}
+
+#[cfg(feature = "kdf")]
+impl SecretKdf for Scrypt {
+    fn fill(&self, password: &str, salt: &[u8], output: &mut [u8]) {
+        scrypt::scrypt(password.as_bytes(), salt, &self.params, output)
+            .expect("the output of derive is never empty");
+    }
+}
+
+/// Argon2id, with the default parameters of the `argon2` crate (19 MiB, 2 passes, 1 lane) by
+/// default.
+///
+/// Argon2 requires salts of at least 8 bytes, so the salt is hashed with SHA-256 first: short
+/// salts such as usernames are accepted too.
+#[cfg(feature = "kdf")]
+#[derive(Debug, Clone, Default)]
+pub struct Argon2id {
+    params: argon2::Params,
+}
+
+#[cfg(feature = "kdf")]
+impl Argon2id {
+    /// Creates the KDF using `m_cost` KiB of memory, `t_cost` passes and `p_cost` lanes.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the KDF, or `ZkpError::OutOfRange` if Argon2 does not
+    /// accept the parameters.
+    pub fn new(m_cost: u32, t_cost: u32, p_cost: u32) -> Result<Self> {
+        let params = argon2::Params::new(m_cost, t_cost, p_cost, None)
+            .map_err(|e| ZkpError::OutOfRange(format!("argon2 parameters: {}", e)))?;
+        Ok(Self { params })
+    }
+}
+
+#[cfg(feature = "kdf")]
+impl SecretKdf for Argon2id {
+    fn fill(&self, password: &str, salt: &[u8], output: &mut [u8]) {
+        let argon2 = argon2::Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            self.params.clone(),
+        );
+        argon2
+            .hash_password_into(password.as_bytes(), &Sha256::digest(salt), output)
+            .expect("the output of derive is longer than the minimum of Argon2");
+    }
+}
+
+/// The KDFs to choose from, each with its default parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Kdf {
+    /// PBKDF2-HMAC-SHA256, see `Pbkdf2`.
+    #[default]
+    Pbkdf2,
+    /// scrypt, see `Scrypt`.
+    #[cfg(feature = "kdf")]
+    Scrypt,
+    /// Argon2id, see `Argon2id`.
+    #[cfg(feature = "kdf")]
+    Argon2id,
+}
+
+impl Kdf {
+    /// Returns the name of the KDF, as accepted by `--kdf`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Pbkdf2 => "pbkdf2",
+            #[cfg(feature = "kdf")]
+            Self::Scrypt => "scrypt",
+            #[cfg(feature = "kdf")]
+            Self::Argon2id => "argon2id",
+        }
+    }
+}
+
+impl SecretKdf for Kdf {
+    fn fill(&self, password: &str, salt: &[u8], output: &mut [u8]) {
+        match self {
+            Self::Pbkdf2 => Pbkdf2::default().fill(password, salt, output),
+            #[cfg(feature = "kdf")]
+            Self::Scrypt => Scrypt::default().fill(password, salt, output),
+            #[cfg(feature = "kdf")]
+            Self::Argon2id => Argon2id::default().fill(password, salt, output),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Cheap instances of each KDF, the defaults are deliberately slow.
+    fn kdfs() -> Vec<(&'static str, Box<dyn SecretKdf>)> {
+        vec![
+            ("pbkdf2", Box::new(Pbkdf2 { rounds: 1_000 })),
+            #[cfg(feature = "kdf")]
+            ("scrypt", Box::new(Scrypt::new(10, 8, 1).unwrap())),
+            #[cfg(feature = "kdf")]
+            ("argon2id", Box::new(Argon2id::new(1024, 1, 1).unwrap())),
+        ]
+    }
+
+    #[test]
+    fn test_secrets_are_in_range_and_deterministic() {
+        for zkp in [ZKP::default(), crate::fixtures::toy_zkp()] {
+            for (name, kdf) in kdfs() {
+                let x = kdf.derive(&zkp, "correct horse", b"alice");
+                assert!(x < zkp.q, "{name}");
+                assert_eq!(kdf.derive(&zkp, "correct horse", b"alice"), x, "{name}");
+            }
+        }
+
+        // the toy group has too few secrets not to collide
+        let zkp = ZKP::default();
+        for (name, kdf) in kdfs() {
+            let x = kdf.derive(&zkp, "correct horse", b"alice");
+            assert_ne!(kdf.derive(&zkp, "correct horse", b"bob"), x, "{name}");
+            assert_ne!(kdf.derive(&zkp, "battery staple", b"alice"), x, "{name}");
+        }
+    }
+
+    #[cfg(feature = "kdf")]
+    #[test]
+    fn test_kdfs_derive_different_secrets() {
+        let zkp = ZKP::default();
+        let secrets: Vec<_> = kdfs()
+            .iter()
+            .map(|(_, kdf)| kdf.derive(&zkp, "correct horse", b"alice"))
+            .collect();
+        assert_ne!(secrets[0], secrets[1]);
+        assert_ne!(secrets[1], secrets[2]);
+        assert_ne!(secrets[0], secrets[2]);
+    }
+
+    #[test]
+    fn test_pbkdf2_derives_the_secrets_of_derive_secret() {
+        // secrets registered before the KDFs were pluggable must still be derived
+        let zkp = ZKP::default();
+        let mut key = vec![0u8; zkp.q_byte_len() + 16];
+        pbkdf2::pbkdf2_hmac::<Sha256>(b"correct horse", b"alice", PBKDF2_ROUNDS, &mut key);
+        let x = BigUint::from_bytes_be(&key) % &zkp.q;
+        assert_eq!(Kdf::Pbkdf2.derive(&zkp, "correct horse", b"alice"), x);
+        assert_eq!(zkp.derive_secret("correct horse", b"alice"), x);
+    }
+
+    #[cfg(feature = "kdf")]
+    #[test]
+    fn test_invalid_parameters_are_rejected() {
+        assert!(matches!(Scrypt::new(10, 0, 1), Err(ZkpError::OutOfRange(_))));
+        assert!(matches!(Argon2id::new(0, 0, 0), Err(ZkpError::OutOfRange(_))));
+    }
+}
@@ -1,17 +1,133 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use kdf::SecretKdf;
 use num_bigint::{BigUint, RandBigInt};
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
+use rng::SecureRng;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[cfg(feature = "cbor")]
+mod cbor;
+mod error;
+#[cfg(any(test, feature = "test-util"))]
+pub mod fixtures;
+#[cfg(feature = "serde")]
+mod serde_biguint;
+pub use error::{Result, VerificationFailure, ZkpError};
+
+/// Evaluates `$body` inside a `tracing` span named `$name` with the `profile` feature, and just
+/// evaluates it otherwise, so the default build pays nothing for the profiling.
+macro_rules! profiled {
+    ($name:literal, $body:expr) => {{
+        #[cfg(feature = "profile")]
+        let _span = tracing::trace_span!($name).entered();
+        $body
+    }};
+}
+
+/// The number of PBKDF2-HMAC-SHA256 rounds used by `ZKP::derive_secret`.
+pub const PBKDF2_ROUNDS: u32 = 100_000;
+
+#[cfg(feature = "agent")]
+pub mod agent;
+#[cfg(feature = "grpc")]
+pub mod channel;
+pub mod kdf;
+#[cfg(feature = "grpc")]
+pub mod loadtest;
+pub mod mutual;
+#[cfg(feature = "otel")]
+pub mod otel;
+#[cfg(feature = "grpc")]
+pub mod params;
+pub mod password;
+#[cfg(feature = "grpc")]
+pub mod pool;
+pub mod prover;
+#[cfg(feature = "grpc")]
+pub mod retry;
+pub mod rng;
+#[cfg(feature = "secp256k1")]
+pub mod secp256k1;
+pub mod token;
+pub mod transcript;
+pub mod typed;
+#[cfg(feature = "verifier")]
+pub mod verifier;
+
+/// The wire protocol version clients send in the `protocol_version` of their registration and
+/// challenge requests. Clients predating the field send `0`.
+///
+/// Version 2 requires a proof of possession of every credential registered.
+#[cfg(feature = "grpc")]
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The gRPC messages and services generated from `proto/zkp_auth.proto`, shared by the
+/// client and server binaries.
+#[cfg(feature = "grpc")]
+pub mod pb {
+    tonic::include_proto!("zkp_auth");
+}
 
 /// A struct representing the Zero-Knowledge Proof (ZKP) parameters.
 ///
 /// This struct holds the parameters required for performing Zero-Knowledge Proofs,
 /// including the prime numbers `p` and `q`, and the generators `alpha` and `beta`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ZKP {
+    #[cfg_attr(feature = "serde", serde(with = "serde_biguint"))]
     pub p: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "serde_biguint"))]
     pub q: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "serde_biguint"))]
     pub alpha: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "serde_biguint"))]
     pub beta: BigUint,
 }
 
+/// Borrowed group parameters, for proving and verifying without a `ZKP` instance.
+///
+/// A server holding one parameter set shared by many verifications, e.g. behind an `Arc`, can
+/// borrow it rather than clone its four `BigUint`s into a `ZKP`. The methods of `ZKP` with the
+/// same names run these ones. See also `ZKP::params` and `ZKP::verify_stateless`.
+#[derive(Debug, Clone, Copy)]
+pub struct GroupParams<'a> {
+    pub p: &'a BigUint,
+    pub q: &'a BigUint,
+    pub alpha: &'a BigUint,
+    pub beta: &'a BigUint,
+}
+
+/// The borrowing counterpart of `ZKP`, see `GroupParams`.
+pub type ZkpRef<'a> = GroupParams<'a>;
+
+impl GroupParams<'_> {
+    /// Computes `(alpha^exp mod p, beta^exp mod p)`, see `ZKP::compute_pair`.
+    pub fn compute_pair(&self, exp: &BigUint) -> (BigUint, BigUint) {
+        let p1 = self.alpha.modpow(exp, self.p);
+        let p2 = self.beta.modpow(exp, self.p);
+        (p1, p2)
+    }
+
+    /// Computes the response `s = k - c * x mod q`, see `ZKP::solve`.
+    pub fn solve(&self, k: &BigUint, c: &BigUint, x: &BigUint) -> BigUint {
+        if *k >= c * x {
+            return (k - c * x).modpow(&BigUint::from(1u32), self.q);
+        }
+        (self.q - (c * x - k).modpow(&BigUint::from(1u32), self.q)) % self.q
+    }
+
+    /// Verifies a proof, see `ZKP::verify`.
+    pub fn verify(
+        &self, r1: &BigUint, r2: &BigUint, y1: &BigUint, y2: &BigUint, c: &BigUint, s: &BigUint,
+    ) -> bool {
+        ZKP::verify_stateless(*self, y1, y2, r1, r2, c, s)
+    }
+}
+
 impl ZKP {
     /// Creates a new `ZKP` instance with the specified parameters.
     ///
@@ -34,9 +150,33 @@ impl ZKP {
         }
     }
 
+    /// Creates a new `ZKP` instance like `new`, rejecting parameters the arithmetic would panic on.
+    ///
+    /// `p` and `q` are the moduli of every `modpow` and reduction, and `num-bigint` panics on a
+    /// zero modulus, so parameters from untrusted input, e.g. a configuration file, should be
+    /// passed through this constructor rather than `new`. Nothing more is checked: see
+    /// `is_degenerate` and `is_in_subgroup` for the soundness of the generators.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the new `ZKP` instance, or `ZkpError::OutOfRange` if `p` or
+    /// `q` is `0`.
+    pub fn new_validated(
+        p: &BigUint, q: &BigUint, alpha: &BigUint, beta: &BigUint,
+    ) -> Result<Self> {
+        let zero = BigUint::from(0u32);
+        if *p == zero || *q == zero {
+            return Err(ZkpError::OutOfRange("the moduli p and q must not be 0".to_string()));
+        }
+        Ok(Self::new(p, q, alpha, beta))
+    }
+
     /// Computes a pair of values based on the ZKP parameters and an exponent.
     ///
     /// This method calculates the pair of outputs as `(alpha^exp mod p, beta^exp mod p)`.
+    /// With the secret `x` as `exp`, it gives the public values `(y1, y2)` to register, whether
+    /// `x` comes from `derive_secret` or from the caller's own key management; `public_pair`
+    /// also checks that `x` is a valid secret.
     ///
     /// # Arguments
     ///
@@ -46,9 +186,25 @@ impl ZKP {
     ///
     /// A tuple containing the computed values as `BigUint`.
     pub fn compute_pair(&self, exp: &BigUint) -> (BigUint, BigUint) {
-        let p1 = self.alpha.modpow(exp, &self.p);
-        let p2 = self.beta.modpow(exp, &self.p);
-        (p1, p2)
+        self.params().compute_pair(exp)
+    }
+
+    /// Computes the public values `(y1, y2)` of a secret held by the caller, see `compute_pair`.
+    ///
+    /// # Arguments
+    ///
+    /// * `secret` - A reference to a `BigUint` representing the secret `x`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the public values `(y1, y2)`, or `ZkpError::OutOfRange` if
+    /// `secret` is not in `[1, q)`: `0` gives public values anyone can prove, and secrets from
+    /// `q` on alias smaller ones.
+    pub fn public_pair(&self, secret: &BigUint) -> Result<(BigUint, BigUint)> {
+        if *secret == BigUint::from(0u32) || *secret >= self.q {
+            return Err(ZkpError::OutOfRange("the secret must be in [1, q)".to_string()));
+        }
+        Ok(self.compute_pair(secret))
     }
 
     /// Solves for the value `s` based on the provided parameters.
@@ -65,10 +221,7 @@ impl ZKP {
     ///
     /// A `BigUint` representing the computed value `s`.
     pub fn solve(&self, k: &BigUint, c: &BigUint, x: &BigUint) -> BigUint {
-        if *k >= c * x {
-            return (k - c * x).modpow(&BigUint::from(1u32), &self.q);
-        }
-        &self.q - (c * x - k).modpow(&BigUint::from(1u32), &self.q)
+        self.params().solve(k, c, x)
     }
 
     /// Verifies the conditions for the ZKP.
@@ -77,6 +230,9 @@ impl ZKP {
     /// 1. `r1 = alpha^s * y1^c`
     /// 2. `r2 = beta^s * y2^c`
     ///
+    /// `r1`, `r2`, `y1` and `y2` must be elements of the group, i.e. below `p`. Values from
+    /// another group are rejected rather than reduced: `y1 + p` would otherwise pass as `y1`.
+    ///
     /// # Arguments
     ///
     /// * `r1` - A reference to a `BigUint` representing the first response.
@@ -92,296 +248,3485 @@ impl ZKP {
     pub fn verify(
         &self, r1: &BigUint, r2: &BigUint, y1: &BigUint, y2: &BigUint, c: &BigUint, s: &BigUint,
     ) -> bool {
-        let cond1 = *r1
-            == (&self.alpha.modpow(s, &self.p) * y1.modpow(c, &self.p))
-                .modpow(&BigUint::from(1u32), &self.p);
+        self.is_valid_response(s) && self.params().verify(r1, r2, y1, y2, c, s)
+    }
+
+    /// Verifies a proof like `verify`, telling why it failed.
+    ///
+    /// On top of the checks of `verify`, the commitment is rejected if `r1` or `r2` is `0` or `1`.
+    /// `r1 = 1` means the nonce `k` was `0`, in which case `s = -c * x mod q` gives the secret
+    /// away.
+    ///
+    /// # Arguments
+    ///
+    /// * `r1` - A reference to a `BigUint` representing the first response.
+    /// * `r2` - A reference to a `BigUint` representing the second response.
+    /// * `y1` - A reference to a `BigUint` representing the first witness.
+    /// * `y2` - A reference to a `BigUint` representing the second witness.
+    /// * `c`  - A reference to a `BigUint` representing the challenge value.
+    /// * `s`  - A reference to a `BigUint` representing the response value.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the proof is valid, or the first `VerificationFailure` found.
+    pub fn verify_with_reason(
+        &self, r1: &BigUint, r2: &BigUint, y1: &BigUint, y2: &BigUint, c: &BigUint, s: &BigUint,
+    ) -> std::result::Result<(), VerificationFailure> {
+        if !self.is_valid_response(s) {
+            return Err(VerificationFailure::ResponseOutOfRange);
+        }
+        if !all_below(&self.p, [r1, r2, y1, y2]) {
+            return Err(VerificationFailure::ElementOutOfRange);
+        }
 
-        let cond2 = *r2
-            == (&self.beta.modpow(s, &self.p) * y2.modpow(c, &self.p))
-                .modpow(&BigUint::from(1u32), &self.p);
+        let one = BigUint::from(1u32);
+        if *r1 <= one || *r2 <= one {
+            return Err(VerificationFailure::CommitmentDegenerate);
+        }
 
-        cond1 && cond2
+        let (expected_r1, expected_r2) = self.reconstruct_commitment(y1, y2, c, s);
+        if *r1 != expected_r1 {
+            return Err(VerificationFailure::R1Mismatch);
+        }
+        if *r2 != expected_r2 {
+            return Err(VerificationFailure::R2Mismatch);
+        }
+
+        Ok(())
     }
 
-    /// Generates a random alphanumeric string of the specified size.
+    /// Reconstructs the commitment a response answers, for diagnostics.
     ///
-    /// This method uses a secure random number generator to produce a random string containing
-    /// alphanumeric characters.
+    /// `verify` accepts a proof when the commitment `(r1, r2)` sent by the prover equals
+    /// `(alpha^s * y1^c, beta^s * y2^c) mod p`. When a proof fails, comparing the sent commitment
+    /// with this one shows which of `r1` and `r2` is off; it does not tell whether the secret, the
+    /// nonce or the challenge used by the prover was wrong.
     ///
     /// # Arguments
-    /// * `size` - The length of the string to be generated.
+    ///
+    /// * `y1`, `y2` - The public values of the prover.
+    /// * `c`  - The challenge value.
+    /// * `s`  - The response value.
     ///
     /// # Returns
-    /// A `String` representing the generated random alphanumeric string.
-    pub fn generate_random_number_below(bound: &BigUint) -> BigUint {
-        let mut rng = rand::thread_rng();
+    ///
+    /// The pair `(alpha^s * y1^c mod p, beta^s * y2^c mod p)`.
+    pub fn reconstruct_commitment(
+        &self, y1: &BigUint, y2: &BigUint, c: &BigUint, s: &BigUint,
+    ) -> (BigUint, BigUint) {
+        let r1 = (self.alpha.modpow(s, &self.p) * y1.modpow(c, &self.p)) % &self.p;
+        let r2 = (self.beta.modpow(s, &self.p) * y2.modpow(c, &self.p)) % &self.p;
+        (r1, r2)
+    }
 
-        rng.gen_biguint_below(bound)
+    /// Checks that a response `s` is in range `[0, q)`.
+    ///
+    /// `solve` always returns a value below `q`, so anything else is malformed and can be
+    /// rejected before doing the modular exponentiations of `verify`.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - A reference to a `BigUint` representing the response value.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether `s` is below `q`.
+    pub fn is_valid_response(&self, s: &BigUint) -> bool {
+        s < &self.q
     }
 
-    pub fn generate_random_string(size: usize) -> String {
-        rand::thread_rng()
-            .sample_iter(rand::distributions::Alphanumeric)
-            .take(size)
-            .map(char::from)
-            .collect()
+    /// Borrows the group parameters of this instance.
+    pub fn params(&self) -> GroupParams<'_> {
+        GroupParams {
+            p: &self.p,
+            q: &self.q,
+            alpha: &self.alpha,
+            beta: &self.beta,
+        }
     }
 
-    /// Retrieves the ZKP constants used in the Zero-Knowledge Proof protocol.
+    /// Verifies a proof given only borrowed group parameters and the public data.
     ///
-    /// This method returns the constants `alpha`, `beta`, `p`, and `q` which are
-    /// fundamental to the ZKP computations.
+    /// This performs the same checks as `verify`, without constructing a `ZKP`, for verifiers
+    /// that receive the parameters alongside forwarded proofs.
+    ///
+    /// With the `profile` feature, the verification runs in a `verify` trace span, with one child
+    /// span for each of `alpha^s`, `y1^c`, `beta^s` and `y2^c` (`alpha_pow_s`, ...) and one for
+    /// the final multiplications and comparisons (`multiply`), to time them with any `tracing`
+    /// subscriber.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The group parameters.
+    /// * `y1`, `y2` - The public values of the prover.
+    /// * `r1`, `r2` - The commitment.
+    /// * `c`  - The challenge value.
+    /// * `s`  - The response value.
     ///
     /// # Returns
     ///
-    /// A tuple containing the constants `(alpha, beta, p, q)` as `BigUint`.
-    #[rustfmt::skip]
-    pub fn get_constants() -> (BigUint, BigUint, BigUint, BigUint)
-    {
-        let p = BigUint::from_bytes_be(&hex::decode(
-            "B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B6160\
-             73E28675A23D189838EF1E2EE652C013ECB4AEA906112324975C3CD49B83BFACCB\
-             DD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0A151AF\
-             5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371").unwrap());
-        let q = BigUint::from_bytes_be(
-            &hex::decode("F518AA8781A8DF278ABA4E7D64B7CB9D49462353").unwrap(),
-        );
+    /// A boolean indicating whether the verification conditions are met.
+    pub fn verify_stateless(
+        params: GroupParams<'_>, y1: &BigUint, y2: &BigUint, r1: &BigUint, r2: &BigUint,
+        c: &BigUint, s: &BigUint,
+    ) -> bool {
+        if s >= params.q || !all_below(params.p, [r1, r2, y1, y2]) {
+            return false;
+        }
 
-        let alpha = BigUint::from_bytes_be(
-            &hex::decode("A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31266FEA1E5C41564B777E690F5504F213160217B4B01B886A5E91547F9E2749F4D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28AD662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5").unwrap(),
-        );
+        profiled!("verify", {
+            let p = params.p;
+            let alpha_s = profiled!("alpha_pow_s", params.alpha.modpow(s, p));
+            let y1_c = profiled!("y1_pow_c", y1.modpow(c, p));
+            let beta_s = profiled!("beta_pow_s", params.beta.modpow(s, p));
+            let y2_c = profiled!("y2_pow_c", y2.modpow(c, p));
+            profiled!("multiply", {
+                let cond1 = *r1 == (alpha_s * y1_c) % p;
+                let cond2 = *r2 == (beta_s * y2_c) % p;
+                cond1 && cond2
+            })
+        })
+    }
 
-        // beta = alpha^i is also a generator
-        let exp = BigUint::from_bytes_be(&hex::decode("266FEA1E5C41564B777E69").unwrap());
-        let beta = alpha.modpow(&exp, &p);
+    /// Finishes a verification whose exponentiations were computed elsewhere, e.g. in an HSM.
+    ///
+    /// Only the final multiplications and comparisons of `verify` run here:
+    /// `r1 = alpha^s * y1^c mod p` and `r2 = beta^s * y2^c mod p`, with the four powers as given.
+    ///
+    /// # Trust boundary
+    ///
+    /// Nothing here ties the powers to `s`, `c`, `y1` or `y2`: `alpha_s = r1` with `y1_c = 1`
+    /// passes for any commitment. The verification is only as sound as whatever computes the
+    /// powers, which must
+    /// - raise the registered `(y1, y2)` of the prover to the challenge `c` the verifier issued,
+    ///   and `alpha`, `beta` to the response `s` it received, checked to be below `q`;
+    /// - check that `y1`, `y2` are elements of the group, as `verify` does;
+    /// - hand the powers back over a channel the caller trusts, since a party able to alter them
+    ///   can get any proof accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `r1`, `r2` - The prover's commitment.
+    /// * `alpha_s`, `beta_s` - `alpha^s mod p` and `beta^s mod p`.
+    /// * `y1_c`, `y2_c` - `y1^c mod p` and `y2^c mod p`.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the verification conditions are met for these powers; values
+    /// that are not below `p` are rejected.
+    pub fn verify_with_external_powers(
+        &self, r1: &BigUint, r2: &BigUint, alpha_s: &BigUint, beta_s: &BigUint, y1_c: &BigUint,
+        y2_c: &BigUint,
+    ) -> bool {
+        if !all_below(&self.p, [r1, r2, alpha_s, beta_s, y1_c, y2_c]) {
+            return false;
+        }
+        *r1 == (alpha_s * y1_c) % &self.p && *r2 == (beta_s * y2_c) % &self.p
+    }
 
-        (alpha, beta, p, q)
+    /// Returns the byte length of `p`, which is also the width of an encoded group element
+    /// (`y1`, `y2`, `r1`, `r2`), see `encode_fixed`.
+    pub fn p_byte_len(&self) -> usize {
+        self.p.bits().div_ceil(8) as usize
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /// Returns the byte length of `q`, the maximum length of a challenge `c`, a response `s`,
+    /// or a secret `x`.
+    pub fn q_byte_len(&self) -> usize {
+        self.q.bits().div_ceil(8) as usize
+    }
 
-    #[test]
-    fn test_toy_example() {
-        let alpha = BigUint::from(4u32);
-        let beta = BigUint::from(9u32);
-        let p = BigUint::from(23u32);
-        let q = BigUint::from(11u32);
-        let zkp = ZKP::new(&p, &q, &alpha, &beta);
+    /// Encodes a group element as big-endian bytes of fixed width.
+    ///
+    /// The encoding is left-padded with zeros to the byte length of `p`, so that every element
+    /// below `p` has the same length no matter how many of its high-order bytes are zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A reference to a `BigUint` below `p`.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<u8>` with the fixed-width encoding of `value`.
+    pub fn encode_fixed(&self, value: &BigUint) -> Vec<u8> {
+        self.encode_fixed_with(value, Endianness::Big)
+    }
 
-        let x = BigUint::from(6u32);
-        let k = BigUint::from(7u32);
+    /// Encodes a group element as bytes of fixed width in the byte order `endianness`.
+    ///
+    /// The encoding is padded with zeros to the byte length of `p`: on the left for
+    /// `Endianness::Big`, as `encode_fixed`, and on the right for `Endianness::Little`.
+    pub fn encode_fixed_with(&self, value: &BigUint, endianness: Endianness) -> Vec<u8> {
+        let bytes = endianness.encode(value);
+        let padding = vec![0u8; self.p_byte_len().saturating_sub(bytes.len())];
+        match endianness {
+            Endianness::Big => [padding, bytes].concat(),
+            Endianness::Little => [bytes, padding].concat(),
+        }
+    }
 
-        let c = BigUint::from(4u32);
+    /// Decodes a group element encoded with `encode_fixed`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The fixed-width big-endian encoding.
+    ///
+    /// # Returns
+    ///
+    /// The decoded `BigUint`, or `None` if `bytes` does not have the byte length of `p`.
+    pub fn decode_fixed(&self, bytes: &[u8]) -> Option<BigUint> {
+        self.decode_fixed_with(bytes, Endianness::Big)
+    }
 
-        let (y1, y2) = zkp.compute_pair(&x);
-        assert_eq!(y1, BigUint::from(2u32));
-        assert_eq!(y2, BigUint::from(3u32));
+    /// Decodes a group element encoded with `encode_fixed_with` in the byte order `endianness`.
+    ///
+    /// # Returns
+    ///
+    /// The decoded `BigUint`, or `None` if `bytes` does not have the byte length of `p`.
+    pub fn decode_fixed_with(&self, bytes: &[u8], endianness: Endianness) -> Option<BigUint> {
+        if bytes.len() != self.p_byte_len() {
+            return None;
+        }
+        Some(endianness.decode(bytes))
+    }
 
-        let (r1, r2) = zkp.compute_pair(&k);
-        assert_eq!(r1, BigUint::from(8u32));
-        assert_eq!(r2, BigUint::from(4u32));
+    /// Derives the secret `x` from a password.
+    ///
+    /// The password is stretched with PBKDF2-HMAC-SHA256 (`PBKDF2_ROUNDS` rounds) into 16 bytes
+    /// more than the byte length of `q`, and the result is reduced modulo `q`, so `x` is in
+    /// `[0, q)` with a negligible bias. The salt must be the same at registration and login;
+    /// the client uses the username. This is `kdf::Pbkdf2`, see `kdf` for the other KDFs.
+    ///
+    /// # Arguments
+    ///
+    /// * `password` - The password.
+    /// * `salt` - The salt.
+    ///
+    /// # Returns
+    ///
+    /// A `BigUint` representing the secret `x`.
+    pub fn derive_secret(&self, password: &str, salt: &[u8]) -> BigUint {
+        kdf::Pbkdf2::default().derive(self, password, salt)
+    }
 
-        let s = zkp.solve(&k, &c, &x);
-        assert_eq!(s, BigUint::from(5u32));
+    /// Generates a random credential, for provisioning secrets that are not derived from a
+    /// password.
+    ///
+    /// # Returns
+    ///
+    /// A tuple `(x, y1, y2)` with the secret `x`, sampled uniformly below `q`, and its public
+    /// values `(y1, y2) = compute_pair(x)`.
+    pub fn generate_credential(&self) -> (BigUint, BigUint, BigUint) {
+        let x = Self::generate_random_number_below(&self.q);
+        let (y1, y2) = self.compute_pair(&x);
+        (x, y1, y2)
+    }
 
-        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s);
-        assert!(result);
+    /// Derives the nonce `k` deterministically from the secret and a context, in the spirit of
+    /// RFC 6979.
+    ///
+    /// HMAC-SHA256 keyed with `x` is run in counter mode over `context` until 16 bytes more than
+    /// the byte length of `q` are produced, and the result is reduced into `[1, q)`. The prover
+    /// does not need an RNG, but the context must never repeat for the same secret: two proofs
+    /// with the same nonce and different challenges give `x` away.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - A reference to a `BigUint` representing the secret.
+    /// * `context` - Bytes unique to this proof (e.g. the user and a timestamp).
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the nonce `k`, or `ZkpError::OutOfRange` if `q` is below `2`, which
+    /// leaves no nonce in `[1, q)`.
+    pub fn deterministic_nonce(&self, x: &BigUint, context: &[u8]) -> Result<BigUint> {
+        if self.q < BigUint::from(2u32) {
+            return Err(ZkpError::OutOfRange("q must be at least 2 to derive a nonce".to_string()));
+        }
+        let q_len = self.q_byte_len();
+        let mut key = vec![0u8; q_len];
+        let x = (x % &self.q).to_bytes_be();
+        key[q_len - x.len()..].copy_from_slice(&x);
 
-        // fake secret
-        let x_fake = BigUint::from(7u32);
-        let s_fake = zkp.solve(&k, &c, &x_fake);
+        let mut bytes = Vec::with_capacity(q_len + 16 + 32);
+        let mut counter = 0u32;
+        while bytes.len() < q_len + 16 {
+            let mut mac =
+                Hmac::<Sha256>::new_from_slice(&key).expect("HMAC takes keys of any size");
+            mac.update(&counter.to_be_bytes());
+            mac.update(context);
+            bytes.extend_from_slice(&mac.finalize().into_bytes());
+            counter += 1;
+        }
+        bytes.truncate(q_len + 16);
 
-        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s_fake);
-        assert!(!result);
+        Ok(BigUint::from_bytes_be(&bytes) % (&self.q - 1u32) + 1u32)
     }
 
-    #[test]
-    fn test_toy_example_with_random_numbers() {
-        let alpha = BigUint::from(4u32);
-        let beta = BigUint::from(9u32);
-        let p = BigUint::from(23u32);
-        let q = BigUint::from(11u32);
-        let zkp = ZKP {
-            p: p.clone(),
-            q: q.clone(),
-            alpha: alpha.clone(),
-            beta: beta.clone(),
-        };
-
-        let x = BigUint::from(6u32);
-        let k = ZKP::generate_random_number_below(&q);
-
-        let c = ZKP::generate_random_number_below(&q);
+    /// Computes the public values `(y1, y2)` to register for a password.
+    ///
+    /// This chains `derive_secret` and `compute_pair`, so the secret never has to be handled by
+    /// the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `password` - The password.
+    /// * `salt` - The salt, see `derive_secret`.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the public values `(y1, y2)` as `BigUint`.
+    pub fn public_keys_from_password(&self, password: &str, salt: &[u8]) -> (BigUint, BigUint) {
+        self.compute_pair(&self.derive_secret(password, salt))
+    }
 
-        let (y1, y2) = zkp.compute_pair(&x);
-        assert_eq!(y1, BigUint::from(2u32));
-        assert_eq!(y2, BigUint::from(3u32));
+    /// Samples a challenge uniformly from `[0, q)`.
+    ///
+    /// The challenge is sampled by rejection, like every random number below a bound of the
+    /// crate (see `generate_random_number_below`): random values of the bit length of `q` are
+    /// drawn until one is below `q`. Reducing a fixed-width random value modulo `q` instead would
+    /// favour the smaller challenges.
+    ///
+    /// # Returns
+    ///
+    /// A `BigUint` representing the challenge value `c`.
+    pub fn random_challenge(&self) -> BigUint {
+        Self::generate_random_number_below(&self.q)
+    }
 
-        let (r1, r2) = zkp.compute_pair(&k);
+    /// Issues a challenge for the verifier side of the protocol.
+    ///
+    /// The challenge `c` is sampled uniformly at random from `[0, q)`, see `random_challenge`.
+    /// This is the second move of the protocol, sent back after the prover's commitment
+    /// `(r1, r2)`.
+    ///
+    /// # Returns
+    ///
+    /// A `BigUint` representing the challenge value `c`.
+    pub fn issue_challenge(&self) -> BigUint {
+        self.random_challenge()
+    }
 
-        let s = zkp.solve(&k, &c, &x);
+    /// Issues a challenge of at most `bits` bits, sampled uniformly from `[0, min(2^bits, q))`.
+    ///
+    /// A shorter challenge is cheaper for the prover, but weakens soundness: a prover who does
+    /// not know `x` can still answer a challenge it guessed before committing, which succeeds
+    /// with probability `2^-bits` per attempt instead of `1/q`. Prefer 80 bits or more, with
+    /// lockouts bounding the attempts.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - The bit length of the challenge space, `q` bounds it whatever its value.
+    ///
+    /// # Returns
+    ///
+    /// A `BigUint` representing the challenge value `c`.
+    pub fn issue_challenge_bits(&self, bits: u64) -> BigUint {
+        // clamped first, so that a huge `bits` does not allocate a huge power of two
+        let bits = bits.min(self.q.bits());
+        let bound = (BigUint::from(1u32) << bits).min(self.q.clone());
+        Self::generate_random_number_below(&bound)
+    }
 
-        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s);
-        assert!(result);
+    /// Finishes the verifier side of the protocol once the prover has answered.
+    ///
+    /// This is stateless: the caller keeps the commitment `(r1, r2)`, the registered
+    /// public values `(y1, y2)` and the issued challenge `c`, and passes them in
+    /// together with the prover's answer `s`.
+    ///
+    /// # Arguments
+    ///
+    /// * `r1`, `r2` - The prover's commitment.
+    /// * `y1`, `y2` - The prover's registered public values.
+    /// * `c` - The challenge returned by `issue_challenge`.
+    /// * `s` - The prover's answer to the challenge.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the prover proved knowledge of the secret.
+    pub fn finish(
+        &self, r1: &BigUint, r2: &BigUint, y1: &BigUint, y2: &BigUint, c: &BigUint, s: &BigUint,
+    ) -> bool {
+        self.verify(r1, r2, y1, y2, c, s)
     }
 
-    #[rustfmt::skip]
-    #[test]
-    fn test_1024_bits_constants()
-    {
-        //
-        // Reference: https://www.rfc-editor.org/rfc/rfc5114#page-15
-        //
-        let p = BigUint::from_bytes_be(&hex::decode(
+    /// Verifies a batch of proofs.
+    ///
+    /// # Arguments
+    ///
+    /// * `proofs` - The proofs, each with the public values `(y1, y2)` of its prover.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<bool>` telling, for each proof in order, whether it is valid.
+    pub fn verify_batch(&self, proofs: &[(BigUint, BigUint, Proof)]) -> Vec<bool> {
+        self.verify_batch_with_progress(proofs, |_, _| {})
+    }
+
+    /// Verifies a batch of proofs like `verify_batch`, reporting progress.
+    ///
+    /// `callback(done, total)` is invoked every `BATCH_PROGRESS_CHUNK` proofs and once the whole
+    /// batch is verified, so `done` increases up to `total`.
+    ///
+    /// # Arguments
+    ///
+    /// * `proofs` - The proofs, each with the public values `(y1, y2)` of its prover.
+    /// * `callback` - Called with the number of proofs verified so far and the size of the batch.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<bool>` telling, for each proof in order, whether it is valid.
+    pub fn verify_batch_with_progress(
+        &self, proofs: &[(BigUint, BigUint, Proof)], mut callback: impl FnMut(usize, usize),
+    ) -> Vec<bool> {
+        let total = proofs.len();
+        let mut results = Vec::with_capacity(total);
+        for chunk in proofs.chunks(BATCH_PROGRESS_CHUNK) {
+            results.extend(
+                chunk
+                    .iter()
+                    .map(|(y1, y2, proof)| self.verify_proof(y1, y2, proof)),
+            );
+            callback(results.len(), total);
+        }
+        if total == 0 {
+            callback(0, 0);
+        }
+        results
+    }
+
+    /// Verifies every proof of a batch file, for an audit of many proofs at once.
+    ///
+    /// The file is a JSON array of `ProofFileEntry`, each proof with the public values
+    /// `(y1, y2)` of its prover, verified in this group with `verify_batch`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the batch file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the `BatchReport` of the file, `ZkpError::Io` if it cannot
+    /// be read, or `ZkpError::InvalidEncoding` if it is not a JSON array of entries.
+    #[cfg(feature = "serde")]
+    pub fn verify_proof_file(&self, path: impl AsRef<Path>) -> Result<BatchReport> {
+        let entries: Vec<ProofFileEntry> = serde_json::from_slice(&std::fs::read(path)?)
+            .map_err(|e| ZkpError::InvalidEncoding(e.to_string()))?;
+        let proofs: Vec<_> = entries
+            .into_iter()
+            .map(|entry| (entry.y1, entry.y2, entry.proof))
+            .collect();
+        Ok(BatchReport::from_results(&self.verify_batch(&proofs)))
+    }
+
+    /// Verifies a batch of proofs like `verify_batch`, spreading them over the rayon thread pool.
+    ///
+    /// Each proof is verified independently, and a `ZKP` is never modified once built, so the
+    /// threads share `self` without locking. The results are in the order of `proofs`.
+    ///
+    /// # Arguments
+    ///
+    /// * `proofs` - The proofs, each with the public values `(y1, y2)` of its prover.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<bool>` telling, for each proof in order, whether it is valid.
+    #[cfg(feature = "rayon")]
+    pub fn verify_batch_parallel(&self, proofs: &[(BigUint, BigUint, Proof)]) -> Vec<bool> {
+        use rayon::prelude::*;
+
+        proofs
+            .par_iter()
+            .map(|(y1, y2, proof)| self.verify_proof(y1, y2, proof))
+            .collect()
+    }
+
+    /// Verifies a batch of proofs like `verify_batch`, a lane of `BATCH_LANES` proofs at a time.
+    ///
+    /// This is the scaffolding of a batched modular exponentiation path. Each lane is verified
+    /// stage by stage instead of proof by proof: the range checks of all its proofs, then
+    /// `alpha^s` and `beta^s` for all of them (the same bases, whose limbs stay in cache), then
+    /// `y1^c` and `y2^c`, then the comparisons. Proofs failing the range checks skip the
+    /// exponentiations. The results are exactly those of `verify_batch`.
+    ///
+    /// The stages still call `BigUint::modpow` once per proof. The way towards SIMD is to
+    /// replace each stage by a multi-lane Montgomery exponentiation, with the limbs of the lane's
+    /// operands interleaved so that one vector instruction processes the same limb of every
+    /// proof, and the exponents padded to the bit length of `q` so that the lanes run the same
+    /// number of squarings and multiplications.
+    ///
+    /// # Arguments
+    ///
+    /// * `proofs` - The proofs, each with the public values `(y1, y2)` of its prover.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<bool>` telling, for each proof in order, whether it is valid.
+    #[cfg(feature = "experimental")]
+    pub fn verify_batch_simd(&self, proofs: &[(BigUint, BigUint, Proof)]) -> Vec<bool> {
+        let p = &self.p;
+        let mut results = Vec::with_capacity(proofs.len());
+        for lane in proofs.chunks(BATCH_LANES) {
+            let in_range: Vec<bool> = lane
+                .iter()
+                .map(|(y1, y2, proof)| {
+                    self.is_valid_response(&proof.s) & all_below(p, [&proof.r1, &proof.r2, y1, y2])
+                })
+                .collect();
+            let powers_of_s: Vec<Option<(BigUint, BigUint)>> = lane
+                .iter()
+                .zip(&in_range)
+                .map(|((_, _, proof), in_range)| {
+                    in_range
+                        .then(|| (self.alpha.modpow(&proof.s, p), self.beta.modpow(&proof.s, p)))
+                })
+                .collect();
+            let powers_of_c: Vec<Option<(BigUint, BigUint)>> = lane
+                .iter()
+                .zip(&in_range)
+                .map(|((y1, y2, proof), in_range)| {
+                    in_range.then(|| (y1.modpow(&proof.c, p), y2.modpow(&proof.c, p)))
+                })
+                .collect();
+            results.extend(
+                lane.iter()
+                    .zip(powers_of_s.into_iter().zip(powers_of_c))
+                    .map(|((_, _, proof), powers)| match powers {
+                        (Some((alpha_s, beta_s)), Some((y1_c, y2_c))) => {
+                            proof.r1 == alpha_s * y1_c % p && proof.r2 == beta_s * y2_c % p
+                        }
+                        _ => false,
+                    }),
+            );
+        }
+        results
+    }
+
+    /// Verifies several statements answering the same challenge `c`, like `verify` on each.
+    ///
+    /// The only work depending on `c` is `y1^c` and `y2^c`, so it is done once per distinct
+    /// public value: statements by the same prover (e.g. several commitments of one user) share
+    /// their exponentiations. The range checks of `verify` apply to every statement.
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - The challenge shared by the statements.
+    /// * `statements` - The statements `(r1, r2, y1, y2, s)`.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<bool>` telling, for each statement in order, whether it is valid.
+    pub fn verify_shared_challenge(
+        &self, c: &BigUint, statements: &[(BigUint, BigUint, BigUint, BigUint, BigUint)],
+    ) -> Vec<bool> {
+        let p = &self.p;
+        let mut powers_of_c: HashMap<&BigUint, BigUint> = HashMap::new();
+        statements
+            .iter()
+            .map(|(r1, r2, y1, y2, s)| {
+                if !self.is_valid_response(s) || !all_below(p, [r1, r2, y1, y2]) {
+                    return false;
+                }
+                let mut power_of_c = |y| {
+                    powers_of_c
+                        .entry(y)
+                        .or_insert_with(|| y.modpow(c, p))
+                        .clone()
+                };
+                let (y1_c, y2_c) = (power_of_c(y1), power_of_c(y2));
+                let cond1 = *r1 == (self.alpha.modpow(s, p) * y1_c) % p;
+                let cond2 = *r2 == (self.beta.modpow(s, p) * y2_c) % p;
+                cond1 && cond2
+            })
+            .collect()
+    }
+
+    /// Aggregates proofs of the same credential into one `AggregateProof`.
+    ///
+    /// The responses are combined with the random linear combination `s = sum(w_i * s_i) mod q`,
+    /// where the weights `w_i` are 128-bit values hashed from the group, the public values and
+    /// every commitment and challenge (see `verify_aggregate`). Anyone holding the proofs can
+    /// aggregate them, the secret is not needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `y1`, `y2` - The public values of the credential all the proofs are for.
+    /// * `proofs` - The proofs, typically answering the challenges of several verifiers.
+    ///
+    /// # Returns
+    ///
+    /// The `AggregateProof`, verified with `verify_aggregate`.
+    pub fn aggregate(&self, y1: &BigUint, y2: &BigUint, proofs: &[Proof]) -> AggregateProof {
+        let mut aggregate = AggregateProof {
+            r1: proofs.iter().map(|proof| proof.r1.clone()).collect(),
+            r2: proofs.iter().map(|proof| proof.r2.clone()).collect(),
+            c: proofs.iter().map(|proof| proof.c.clone()).collect(),
+            s: BigUint::from(0u32),
+        };
+        let weights = self.aggregation_weights(y1, y2, &aggregate);
+        aggregate.s = proofs
+            .iter()
+            .zip(&weights)
+            .fold(BigUint::from(0u32), |s, (proof, w)| (s + w * &proof.s) % &self.q);
+        aggregate
+    }
+
+    /// Verifies an `AggregateProof` for the public values `(y1, y2)`.
+    ///
+    /// With the weights `w_i` of `aggregate`, `C = sum(w_i * c_i) mod q` and
+    /// `R1 = prod(r1_i^w_i) mod p` (and `R2` alike), this checks the single Chaum-Pedersen
+    /// equations `R1 == alpha^s * y1^C` and `R2 == beta^s * y2^C`. Checking `n` proofs takes four
+    /// full exponentiations plus `2n` exponentiations by 128-bit weights, instead of the `4n` full
+    /// exponentiations of `verify` on each.
+    ///
+    /// Soundness assumptions:
+    ///
+    /// - The weights are hashed from every commitment and challenge, and modeled as random: a
+    ///   forged or altered proof makes the check fail unless its weight cancels the error, with
+    ///   probability about `2^-128`. This needs `q` well above `2^128`, so not the toy group.
+    /// - Like a single proof, the aggregate only proves knowledge of the secret if each
+    ///   challenge was issued after its commitment was sent (or is a Fiat-Shamir challenge).
+    ///   Each verifier must therefore check, with `AggregateProof::includes`, that the
+    ///   commitment it received and the challenge it issued are part of the aggregate.
+    /// - The commitments are only checked to be below `p`, not to be in the subgroup: entries
+    ///   other than a verifier's own can be malleable, which does not help to prove its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `y1`, `y2` - The public values of the credential.
+    /// * `aggregate` - The proof returned by `aggregate`.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether every aggregated proof is valid, `false` for an empty
+    /// aggregate or out-of-range values.
+    pub fn verify_aggregate(&self, y1: &BigUint, y2: &BigUint, aggregate: &AggregateProof) -> bool {
+        let p = &self.p;
+        let n = aggregate.c.len();
+        if n == 0 || aggregate.r1.len() != n || aggregate.r2.len() != n {
+            return false;
+        }
+        let in_range = all_below(p, [y1, y2])
+            && aggregate.r1.iter().chain(&aggregate.r2).all(|r| r < p)
+            && aggregate.c.iter().all(|c| c < &self.q)
+            && self.is_valid_response(&aggregate.s);
+        if !in_range {
+            return false;
+        }
+
+        let weights = self.aggregation_weights(y1, y2, aggregate);
+        let combine = |rs: &[BigUint]| {
+            rs.iter()
+                .zip(&weights)
+                .fold(BigUint::from(1u32), |acc, (r, w)| acc * r.modpow(w, p) % p)
+        };
+        let c = aggregate
+            .c
+            .iter()
+            .zip(&weights)
+            .fold(BigUint::from(0u32), |acc, (c, w)| (acc + w * c) % &self.q);
+        let s = &aggregate.s;
+        let cond1 = combine(&aggregate.r1) == self.alpha.modpow(s, p) * y1.modpow(&c, p) % p;
+        let cond2 = combine(&aggregate.r2) == self.beta.modpow(s, p) * y2.modpow(&c, p) % p;
+        cond1 && cond2
+    }
+
+    /// Returns the weights of the proofs of `aggregate`, see `verify_aggregate`.
+    ///
+    /// A seed is hashed from `AGGREGATE_DOMAIN`, the group parameters, the public values and each
+    /// commitment and challenge, all encoded with `encode_fixed`; the weight of the `i`-th proof
+    /// is `1` plus the first 16 bytes of the SHA-256 of the seed and `i`, so it is never `0`.
+    fn aggregation_weights(
+        &self, y1: &BigUint, y2: &BigUint, aggregate: &AggregateProof,
+    ) -> Vec<BigUint> {
+        let mut hasher = Sha256::new();
+        hasher.update(AGGREGATE_DOMAIN);
+        for value in [&self.p, &self.q, &self.alpha, &self.beta, y1, y2] {
+            hasher.update(self.encode_fixed(value));
+        }
+        hasher.update((aggregate.c.len() as u64).to_be_bytes());
+        for ((r1, r2), c) in aggregate.r1.iter().zip(&aggregate.r2).zip(&aggregate.c) {
+            for value in [r1, r2, c] {
+                hasher.update(self.encode_fixed(value));
+            }
+        }
+        let seed = hasher.finalize();
+
+        (0..aggregate.c.len() as u64)
+            .map(|i| {
+                let digest = Sha256::new()
+                    .chain_update(seed)
+                    .chain_update(i.to_be_bytes())
+                    .finalize();
+                BigUint::from_bytes_be(&digest[..16]) + 1u32
+            })
+            .collect()
+    }
+
+    /// Verifies a `Proof` against the public values of a `VerifierKey`, see `verify_proof`.
+    pub fn verify_against(&self, key: &VerifierKey, proof: &Proof) -> bool {
+        self.verify_proof(&key.y1, &key.y2, proof)
+    }
+
+    /// Verifies a `Proof` for the public values `(y1, y2)`, see `verify`.
+    pub fn verify_proof(&self, y1: &BigUint, y2: &BigUint, proof: &Proof) -> bool {
+        self.verify(&proof.r1, &proof.r2, y1, y2, &proof.c, &proof.s)
+    }
+
+    /// Computes the Fiat-Shamir challenge of a commitment.
+    ///
+    /// The challenge is the SHA-256 of `FIAT_SHAMIR_DOMAIN`, the group parameters, the public
+    /// values `(y1, y2)` and the commitment `(r1, r2)` (all encoded with `encode_fixed`) and
+    /// `context`, reduced modulo `q`. It stands in for the challenge of the verifier in the
+    /// non-interactive mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `y1`, `y2` - The prover's public values.
+    /// * `r1`, `r2` - The prover's commitment.
+    /// * `context` - Bytes binding the proof to its use (e.g. the user and a timestamp).
+    ///
+    /// # Returns
+    ///
+    /// A `BigUint` representing the challenge `c`.
+    pub fn fiat_shamir_challenge(
+        &self, y1: &BigUint, y2: &BigUint, r1: &BigUint, r2: &BigUint, context: &[u8],
+    ) -> BigUint {
+        let mut hasher = Sha256::new();
+        hasher.update(FIAT_SHAMIR_DOMAIN);
+        for value in [&self.p, &self.q, &self.alpha, &self.beta, y1, y2, r1, r2] {
+            hasher.update(self.encode_fixed(value));
+        }
+        hasher.update(context);
+        self.challenge_from_digest(&hasher.finalize())
+    }
+
+    /// Reduces a hash digest to a challenge, as Fiat-Shamir verifiers using a hash (e.g. a
+    /// 32-byte SHA-256) as the challenge do.
+    ///
+    /// The digest is read as a big-endian number and reduced modulo `q`. With a digest longer
+    /// than `q`, like SHA-256 in the RFC 5114 groups, the bias of the reduction is negligible.
+    ///
+    /// # Arguments
+    ///
+    /// * `digest` - The hash digest.
+    ///
+    /// # Returns
+    ///
+    /// A `BigUint` representing the challenge `c`, in `[0, q)`.
+    pub fn challenge_from_digest(&self, digest: &[u8]) -> BigUint {
+        BigUint::from_bytes_be(digest) % &self.q
+    }
+
+    /// Verifies a proof whose challenge was supplied as a hash digest, see
+    /// `challenge_from_digest`.
+    ///
+    /// # Arguments
+    ///
+    /// * `r1`, `r2` - The prover's commitment.
+    /// * `y1`, `y2` - The prover's public values.
+    /// * `digest` - The digest the challenge is reduced from.
+    /// * `s` - The prover's response.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the proof is valid for the reduced challenge, see `verify`.
+    pub fn verify_with_digest_challenge(
+        &self, r1: &BigUint, r2: &BigUint, y1: &BigUint, y2: &BigUint, digest: &[u8], s: &BigUint,
+    ) -> bool {
+        self.verify(r1, r2, y1, y2, &self.challenge_from_digest(digest), s)
+    }
+
+    /// Proves knowledge of `x` without a verifier (Fiat-Shamir).
+    ///
+    /// This runs the same moves as the interactive protocol, `compute_pair` and `solve`, with the
+    /// challenge computed by `fiat_shamir_challenge` instead of issued by the verifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - A reference to a `BigUint` representing the secret.
+    /// * `y1`, `y2` - The public values of `x`, as returned by `compute_pair`.
+    /// * `context` - Bytes binding the proof to its use, the verifier must pass the same.
+    ///
+    /// # Returns
+    ///
+    /// The `Proof`, verified with `verify_non_interactive`.
+    pub fn prove_non_interactive(
+        &self, x: &BigUint, y1: &BigUint, y2: &BigUint, context: &[u8],
+    ) -> Proof {
+        let k = Self::generate_random_number_below(&self.q);
+        let (r1, r2) = self.compute_pair(&k);
+        let c = self.fiat_shamir_challenge(y1, y2, &r1, &r2, context);
+        let s = self.solve(&k, &c, x);
+        Proof { r1, r2, c, s }
+    }
+
+    /// Verifies a proof made with `prove_non_interactive`.
+    ///
+    /// The challenge of the proof must be the Fiat-Shamir challenge of its commitment, then the
+    /// proof is checked with `verify_proof`.
+    ///
+    /// # Arguments
+    ///
+    /// * `y1`, `y2` - The prover's public values.
+    /// * `proof` - The proof.
+    /// * `context` - The context the proof was made for.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the prover proved knowledge of the secret.
+    pub fn verify_non_interactive(
+        &self, y1: &BigUint, y2: &BigUint, proof: &Proof, context: &[u8],
+    ) -> bool {
+        proof.c == self.fiat_shamir_challenge(y1, y2, &proof.r1, &proof.r2, context)
+            && self.verify_proof(y1, y2, proof)
+    }
+
+    /// Proves, when registering `(y1, y2)` for `user`, that the registrant knows their secret.
+    ///
+    /// Without it anyone could register public values they do not control, e.g. copied from
+    /// another user. The proof is a non-interactive one bound to `POSSESSION_DOMAIN` and the
+    /// username, so it cannot be replayed to register the same values for another user.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - A reference to a `BigUint` representing the secret.
+    /// * `y1`, `y2` - The public values of `x`, as returned by `compute_pair`.
+    /// * `user` - The username the values are registered for.
+    ///
+    /// # Returns
+    ///
+    /// The `Proof`, verified with `verify_possession`.
+    pub fn prove_possession(&self, x: &BigUint, y1: &BigUint, y2: &BigUint, user: &str) -> Proof {
+        self.prove_non_interactive(x, y1, y2, &Self::possession_context(user))
+    }
+
+    /// Verifies a proof made with `prove_possession`.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the registrant of `(y1, y2)` for `user` knows the secret.
+    pub fn verify_possession(&self, y1: &BigUint, y2: &BigUint, user: &str, proof: &Proof) -> bool {
+        self.verify_non_interactive(y1, y2, proof, &Self::possession_context(user))
+    }
+
+    fn possession_context(user: &str) -> Vec<u8> {
+        [POSSESSION_DOMAIN, user.as_bytes()].concat()
+    }
+
+    /// Proves, when registering `(y1, y2)` as the additional credential `label` of `user`, that
+    /// the registrant knows their secret, see `prove_possession`.
+    ///
+    /// The proof is bound to `CREDENTIAL_POSSESSION_DOMAIN`, the username and the label, so it
+    /// cannot be replayed for another user, another credential or a primary credential.
+    ///
+    /// # Returns
+    ///
+    /// The `Proof`, verified with `verify_credential_possession`.
+    pub fn prove_credential_possession(
+        &self, x: &BigUint, y1: &BigUint, y2: &BigUint, user: &str, label: &str,
+    ) -> Proof {
+        self.prove_non_interactive(x, y1, y2, &Self::credential_possession_context(user, label))
+    }
+
+    /// Verifies a proof made with `prove_credential_possession`.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the registrant of `(y1, y2)` as the credential `label` of
+    /// `user` knows the secret.
+    pub fn verify_credential_possession(
+        &self, y1: &BigUint, y2: &BigUint, user: &str, label: &str, proof: &Proof,
+    ) -> bool {
+        self.verify_non_interactive(
+            y1,
+            y2,
+            proof,
+            &Self::credential_possession_context(user, label),
+        )
+    }
+
+    fn credential_possession_context(user: &str, label: &str) -> Vec<u8> {
+        let user_len = (user.len() as u64).to_be_bytes();
+        [
+            CREDENTIAL_POSSESSION_DOMAIN,
+            &user_len,
+            user.as_bytes(),
+            label.as_bytes(),
+        ]
+        .concat()
+    }
+
+    /// Checks whether `y` is in the subgroup of order `q`, i.e. `0 < y < p` and `y^q mod p == 1`.
+    ///
+    /// Every `alpha^x` and `beta^x` is, so a public value outside the subgroup was not computed
+    /// by an honest prover, and could confine the proofs to a small subgroup. The check costs one
+    /// modular exponentiation with a `q`-bit exponent, about a quarter of `verify`.
+    ///
+    /// # Arguments
+    ///
+    /// * `y` - A reference to a `BigUint` representing the value to check.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether `y` is in the subgroup.
+    pub fn is_in_subgroup(&self, y: &BigUint) -> bool {
+        *y > BigUint::from(0u32) && *y < self.p && y.modpow(&self.q, &self.p) == BigUint::from(1u32)
+    }
+
+    /// Checks whether the parameters are degenerate.
+    ///
+    /// The proof relies on `alpha` and `beta` being two independent generators. If either of them
+    /// is `0` or `1`, or if `beta == alpha`, the two pairs collapse into one and the proof's
+    /// soundness is weakened.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the parameters are degenerate.
+    pub fn is_degenerate(&self) -> bool {
+        let one = BigUint::from(1u32);
+        self.alpha <= one || self.beta <= one || self.beta == self.alpha
+    }
+
+    /// Derives the second generator `beta = alpha^exp mod p`, rejecting degenerate exponents.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - A reference to a `BigUint` representing the generator `alpha`.
+    /// * `exp` - A reference to a `BigUint` representing the exponent.
+    /// * `p` - A reference to a `BigUint` representing the prime number `p`.
+    ///
+    /// # Returns
+    ///
+    /// `Some(beta)`, or `None` if `exp <= 1` or the resulting `beta` is `0`, `1` or `alpha`.
+    pub fn beta_from_exponent(alpha: &BigUint, exp: &BigUint, p: &BigUint) -> Option<BigUint> {
+        if *exp <= BigUint::from(1u32) {
+            return None;
+        }
+        let beta = alpha.modpow(exp, p);
+        if beta <= BigUint::from(1u32) || beta == *alpha {
+            return None;
+        }
+        Some(beta)
+    }
+
+    /// Generates a random second generator `beta = alpha^i mod p`.
+    ///
+    /// The exponent `i` is sampled from `[0, q)` and sampled again until `beta_from_exponent`
+    /// accepts it, so the result is never degenerate. `alpha` must generate the subgroup of
+    /// order `q > 2`, otherwise no such exponent exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - A reference to a `BigUint` representing the generator `alpha`.
+    /// * `p` - A reference to a `BigUint` representing the prime number `p`.
+    /// * `q` - A reference to a `BigUint` representing the prime number `q`.
+    ///
+    /// # Returns
+    ///
+    /// A `BigUint` representing the generator `beta`.
+    pub fn generate_beta(alpha: &BigUint, p: &BigUint, q: &BigUint) -> BigUint {
+        loop {
+            let exp = Self::generate_random_number_below(q);
+            if let Some(beta) = Self::beta_from_exponent(alpha, &exp, p) {
+                return beta;
+            }
+        }
+    }
+
+    /// Derives the generators `alpha` and `beta` of the subgroup of order `q` from a public seed.
+    ///
+    /// This is a "nothing up my sleeve" construction, in the spirit of the verifiable canonical
+    /// generation of FIPS 186-4 (appendix A.2.3): anyone can recompute the generators from the
+    /// seed, so whoever picked them could not have chosen `beta` with a known `log_alpha(beta)`,
+    /// which would let them forge proofs.
+    ///
+    /// For the generator with index `i` (`0` for `alpha`, `1` for `beta`) and a counter starting at
+    /// `1`, the seed is hashed into `w`: the SHA-256 blocks of `NUMS_DOMAIN || i || counter ||
+    /// block || seed` (with `i` as one byte, `counter` and `block` as big-endian `u32`s), enough
+    /// of them for 16 bytes more than the byte length of `p`, reduced modulo `p`. The candidate
+    /// `g = w^((p - 1) / q) mod p` has order `q` unless it is `1`, in which case (or if `beta`
+    /// would equal `alpha`) the counter is incremented, up to `NUMS_MAX_COUNTER`.
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - The prime modulus.
+    /// * `q` - The prime order of the subgroup, which must divide `p - 1`.
+    /// * `seed` - The public seed, e.g. a description of the deployment.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the generators `(alpha, beta)`, or `ZkpError::OutOfRange` if `q`
+    /// does not divide `p - 1`, or if no counter up to `NUMS_MAX_COUNTER` gives a generator, as
+    /// when the subgroup is too small for two distinct ones (e.g. `q = 2`).
+    pub fn nums_generators(p: &BigUint, q: &BigUint, seed: &str) -> Result<(BigUint, BigUint)> {
+        let one = BigUint::from(1u32);
+        if *q <= one || *p <= one || (p - 1u32) % q != BigUint::from(0u32) {
+            return Err(ZkpError::OutOfRange("q does not divide p - 1".to_string()));
+        }
+        let cofactor = (p - 1u32) / q;
+        let width = p.bits().div_ceil(8) as usize + 16;
+
+        let generator = |index: u8, other: Option<&BigUint>| {
+            (1..=NUMS_MAX_COUNTER)
+                .map(|counter| {
+                    let mut w = Vec::with_capacity(width + 32);
+                    for block in 0u32.. {
+                        if w.len() >= width {
+                            break;
+                        }
+                        let mut hasher = Sha256::new();
+                        hasher.update(NUMS_DOMAIN);
+                        hasher.update([index]);
+                        hasher.update(counter.to_be_bytes());
+                        hasher.update(block.to_be_bytes());
+                        hasher.update(seed.as_bytes());
+                        w.extend_from_slice(&hasher.finalize());
+                    }
+                    (BigUint::from_bytes_be(&w[..width]) % p).modpow(&cofactor, p)
+                })
+                .find(|g| *g > one && Some(g) != other)
+                .ok_or_else(|| {
+                    ZkpError::OutOfRange(format!(
+                        "no generator of order q within {} counters",
+                        NUMS_MAX_COUNTER
+                    ))
+                })
+        };
+        let alpha = generator(0, None)?;
+        let beta = generator(1, Some(&alpha))?;
+        Ok((alpha, beta))
+    }
+
+    /// Returns a copy of this instance with a new random `beta`, see `generate_beta`.
+    ///
+    /// `p`, `q` and `alpha` are kept, so the copy is the same group with another second
+    /// generator, e.g. to check that proofs do not carry over between generators.
+    ///
+    /// # Returns
+    ///
+    /// A new `ZKP` instance whose `beta` differs from this one's.
+    pub fn with_new_beta(&self) -> ZKP {
+        loop {
+            let beta = Self::generate_beta(&self.alpha, &self.p, &self.q);
+            if beta != self.beta {
+                return Self::new(&self.p, &self.q, &self.alpha, &beta);
+            }
+        }
+    }
+
+    /// Generates a random number sampled uniformly below `bound`, with `rng::SecureRng`.
+    ///
+    /// The number is sampled by rejection (`gen_biguint_below`), so it carries no modulo bias.
+    ///
+    /// # Arguments
+    /// * `bound` - The exclusive upper bound, must not be zero.
+    ///
+    /// # Returns
+    /// A `BigUint` below `bound`.
+    pub fn generate_random_number_below(bound: &BigUint) -> BigUint {
+        SecureRng::default().gen_biguint_below(bound)
+    }
+
+    /// Generates a random alphanumeric string of the specified size.
+    ///
+    /// This method uses `rng::SecureRng` to produce a random string containing alphanumeric
+    /// characters.
+    ///
+    /// # Arguments
+    /// * `size` - The length of the string to be generated.
+    ///
+    /// # Returns
+    /// A `String` representing the generated random alphanumeric string.
+    pub fn generate_random_string(size: usize) -> String {
+        SecureRng::default()
+            .sample_iter(rand::distributions::Alphanumeric)
+            .take(size)
+            .map(char::from)
+            .collect()
+    }
+
+    /// Fallible version of `generate_random_number_below`.
+    ///
+    /// `generate_random_number_below` panics if the OS entropy source fails, which can happen on
+    /// sandboxed targets without entropy; this reports the failure instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `bound` - The exclusive upper bound, must not be zero.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `BigUint` sampled uniformly below `bound`,
+    /// `ZkpError::OutOfRange` if `bound` is zero, or `ZkpError::RandomnessUnavailable` if the
+    /// RNG failed.
+    pub fn try_generate_random_number_below(bound: &BigUint) -> Result<BigUint> {
+        Self::try_generate_random_number_below_with(bound, &mut SecureRng::default())
+    }
+
+    /// Like `try_generate_random_number_below`, drawing from a caller supplied CSPRNG.
+    ///
+    /// The number is sampled by rejection: random bytes are drawn with `try_fill_bytes`, masked
+    /// to the bit length of `bound`, until they are below `bound`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bound` - The exclusive upper bound, must not be zero.
+    /// * `rng` - The random number generator.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `BigUint` sampled uniformly below `bound`,
+    /// `ZkpError::OutOfRange` if `bound` is zero, or `ZkpError::RandomnessUnavailable` if `rng`
+    /// failed.
+    pub fn try_generate_random_number_below_with<R: RngCore + CryptoRng + ?Sized>(
+        bound: &BigUint, rng: &mut R,
+    ) -> Result<BigUint> {
+        if *bound == BigUint::from(0u32) {
+            return Err(ZkpError::OutOfRange("the bound must not be zero".to_string()));
+        }
+
+        let bits = bound.bits();
+        let mut bytes = vec![0u8; bits.div_ceil(8) as usize];
+        loop {
+            rng.try_fill_bytes(&mut bytes)
+                .map_err(|e| ZkpError::RandomnessUnavailable(e.to_string()))?;
+            // clear the bits above the bit length of `bound`
+            bytes[0] &= 0xFF >> (bytes.len() as u64 * 8 - bits);
+
+            let candidate = BigUint::from_bytes_be(&bytes);
+            if candidate < *bound {
+                return Ok(candidate);
+            }
+        }
+    }
+
+    /// Creates a `ZKP` instance with the parameters of a built-in group.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The built-in group.
+    ///
+    /// # Returns
+    ///
+    /// A new `ZKP` instance initialized with the parameters of `group`.
+    pub fn from_group(group: Group) -> Self {
+        let (alpha, beta, p, q) = group.constants();
+        Self::new(&p, &q, &alpha, &beta)
+    }
+
+    /// Identifies the standard group the parameters are, for audits.
+    ///
+    /// `p`, `q` and `alpha` are compared with those of the built-in groups (`Group`) and of the
+    /// RFC 3526 MODP groups, whose generator is `2` and `q = (p - 1) / 2`. `beta` is not
+    /// compared: the RFCs define a single generator, and every crate picks its second one.
+    ///
+    /// # Returns
+    ///
+    /// The group the parameters are, or `None` for custom parameters.
+    pub fn identify_group(&self) -> Option<KnownGroup> {
+        for (known, group) in [
+            (KnownGroup::Toy, Group::Toy),
+            (KnownGroup::Rfc5114_1024, Group::Rfc1024),
+            (KnownGroup::Rfc5114_2048, Group::Rfc2048),
+        ] {
+            let (alpha, _, p, q) = group.constants();
+            if self.p == p && self.q == q && self.alpha == alpha {
+                return Some(known);
+            }
+        }
+
+        let bits = self.p.bits();
+        let (known, p) = RFC3526_PRIMES
+            .iter()
+            .find(|(_, p)| p.len() as u64 * 4 == bits)?;
+        let p = BigUint::from_bytes_be(&hex::decode(p).expect("built-in primes are valid hex"));
+        let generated = self.alpha == BigUint::from(2u32) && self.q == (&p - 1u32) >> 1;
+        (self.p == p && generated).then_some(*known)
+    }
+
+    /// Checks the arithmetic of the protocol against values computed by hand.
+    ///
+    /// In the toy group (`p = 23`, `q = 11`, `alpha = 4`, `beta = 9`), the secret `x = 6` has
+    /// the public values `(2, 3)`, the nonce `k = 7` the commitment `(8, 4)`, and the challenge
+    /// `c = 4` the response `s = 5`. The proof must verify, and the same proof with another
+    /// response must not. A miscompiled or broken big-integer backend fails one of the checks, so
+    /// run this before trusting any proof, e.g. when a server starts.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either nothing, or `ZkpError::SelfTestFailed` naming the first check
+    /// that did not get the expected value.
+    pub fn self_test() -> Result<()> {
+        Self::from_group(Group::Toy).known_answer_test()
+    }
+
+    /// Runs the checks of `self_test` with the arithmetic of `self`.
+    fn known_answer_test(&self) -> Result<()> {
+        let n = BigUint::from;
+        let check = |name: &str, actual: &BigUint, expected: u32| {
+            if *actual == n(expected) {
+                Ok(())
+            } else {
+                Err(ZkpError::SelfTestFailed(format!(
+                    "{} is {}, expected {}",
+                    name, actual, expected
+                )))
+            }
+        };
+
+        let (y1, y2) = self.compute_pair(&n(6));
+        check("y1", &y1, 2)?;
+        check("y2", &y2, 3)?;
+        let (r1, r2) = self.compute_pair(&n(7));
+        check("r1", &r1, 8)?;
+        check("r2", &r2, 4)?;
+        let s = self.solve(&n(7), &n(4), &n(6));
+        check("s", &s, 5)?;
+
+        if !self.verify(&r1, &r2, &y1, &y2, &n(4), &s) {
+            return Err(ZkpError::SelfTestFailed("a valid proof was rejected".into()));
+        }
+        if self.verify(&r1, &r2, &y1, &y2, &n(4), &n(6)) {
+            return Err(ZkpError::SelfTestFailed("a forged proof was accepted".into()));
+        }
+        Ok(())
+    }
+
+    /// Retrieves the ZKP constants used in the Zero-Knowledge Proof protocol.
+    ///
+    /// This method returns the constants `alpha`, `beta`, `p`, and `q` which are
+    /// fundamental to the ZKP computations. The built-in constants are known to be
+    /// valid hex, so this cannot fail; use `try_get_constants` or `constants_from_hex`
+    /// when the error should be handled instead.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the constants `(alpha, beta, p, q)` as `BigUint`.
+    pub fn get_constants() -> (BigUint, BigUint, BigUint, BigUint) {
+        Self::try_get_constants().expect("built-in ZKP constants are valid hex")
+    }
+
+    /// Fallible version of `get_constants`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the constants `(alpha, beta, p, q)` as `BigUint`, or
+    /// `ZkpError::InvalidEncoding` if they are not valid hex.
+    #[rustfmt::skip]
+    pub fn try_get_constants() -> Result<(BigUint, BigUint, BigUint, BigUint)> {
+        Self::constants_from_hex(
             "B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B6160\
              73E28675A23D189838EF1E2EE652C013ECB4AEA906112324975C3CD49B83BFACCB\
              DD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0A151AF\
-             5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371").unwrap());
-        let q = BigUint::from_bytes_be(
-            &hex::decode("F518AA8781A8DF278ABA4E7D64B7CB9D49462353").unwrap());
-
-        let alpha = BigUint::from_bytes_be(&hex::decode(
-            "A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D3126\
-             6FEA1E5C41564B777E690F5504F213160217B4B01B886A5E91547F9E2749F4D7FB\
-             D7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28AD662A4\
-             D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5").unwrap(),
+             5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371",
+            "F518AA8781A8DF278ABA4E7D64B7CB9D49462353",
+            "A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31266FEA1E5C41564B777E690F5504F213160217B4B01B886A5E91547F9E2749F4D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28AD662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5",
+            // beta = alpha^i is also a generator
+            "266FEA1E5C41564B777E69",
+        )
+    }
+
+    /// Builds the ZKP constants from hex encoded values.
+    ///
+    /// `beta` is derived as `alpha^beta_exp mod p`.
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - Hex encoding of the prime `p`.
+    /// * `q` - Hex encoding of the prime `q`.
+    /// * `alpha` - Hex encoding of the generator `alpha`.
+    /// * `beta_exp` - Hex encoding of the exponent used to derive `beta` from `alpha`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the constants `(alpha, beta, p, q)` as `BigUint`, or
+    /// `ZkpError::InvalidEncoding` for the first value that is not valid hex.
+    pub fn constants_from_hex(
+        p: &str, q: &str, alpha: &str, beta_exp: &str,
+    ) -> Result<(BigUint, BigUint, BigUint, BigUint)> {
+        let p = BigUint::from_bytes_be(&hex::decode(p)?);
+        let q = BigUint::from_bytes_be(&hex::decode(q)?);
+        let alpha = BigUint::from_bytes_be(&hex::decode(alpha)?);
+        let exp = BigUint::from_bytes_be(&hex::decode(beta_exp)?);
+        let beta = alpha.modpow(&exp, &p);
+
+        Ok((alpha, beta, p, q))
+    }
+}
+
+/// The byte order of the integers exchanged by the prover and the verifier.
+///
+/// The protocol encodes integers big-endian by default. Peers that encode them little-endian
+/// can use `Endianness::Little`, as long as both sides agree, e.g. through the
+/// `GetParameters` handshake of the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Endianness {
+    /// Most significant byte first.
+    #[default]
+    Big,
+    /// Least significant byte first.
+    Little,
+}
+
+impl Endianness {
+    /// Encodes `value` with the minimal number of bytes in this byte order.
+    pub fn encode(self, value: &BigUint) -> Vec<u8> {
+        match self {
+            Self::Big => value.to_bytes_be(),
+            Self::Little => value.to_bytes_le(),
+        }
+    }
+
+    /// Decodes bytes in this byte order, of any length.
+    pub fn decode(self, bytes: &[u8]) -> BigUint {
+        match self {
+            Self::Big => BigUint::from_bytes_be(bytes),
+            Self::Little => BigUint::from_bytes_le(bytes),
+        }
+    }
+}
+
+/// The built-in groups the prover and the verifier can agree on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Group {
+    /// The toy group `p = 23`, `q = 11` of the examples, only meant for testing.
+    Toy,
+    /// The RFC 5114 1024-bit group with 160-bit prime order subgroup, see `ZKP::get_constants`.
+    #[default]
+    Rfc1024,
+    /// The RFC 5114 2048-bit group with 224-bit prime order subgroup.
+    Rfc2048,
+}
+
+impl Group {
+    /// Retrieves the constants of the group.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the constants `(alpha, beta, p, q)` as `BigUint`.
+    pub fn constants(self) -> (BigUint, BigUint, BigUint, BigUint) {
+        match self {
+            // beta = alpha^8 mod p
+            Self::Toy => ZKP::constants_from_hex("17", "0B", "04", "08"),
+            Self::Rfc1024 => ZKP::try_get_constants(),
+            Self::Rfc2048 => Self::rfc5114_2048(),
+        }
+        .expect("built-in ZKP constants are valid hex")
+    }
+
+    #[rustfmt::skip]
+    fn rfc5114_2048() -> Result<(BigUint, BigUint, BigUint, BigUint)> {
+        //
+        //    Reference: https://www.rfc-editor.org/rfc/rfc5114#page-15
+        //
+        //    The hexadecimal value of the prime is:
+        //
+        //    p =  AD107E1E 9123A9D0 D660FAA7 9559C51F A20D64E5 683B9FD1
+        //         B54B1597 B61D0A75 E6FA141D F95A56DB AF9A3C40 7BA1DF15
+        //         EB3D688A 309C180E 1DE6B85A 1274A0A6 6D3F8152 AD6AC212
+        //         9037C9ED EFDA4DF8 D91E8FEF 55B7394B 7AD5B7D0 B6C12207
+        //         C9F98D11 ED34DBF6 C6BA0B2C 8BBC27BE 6A00E0A0 B9C49708
+        //         B3BF8A31 70918836 81286130 BC8985DB 1602E714 415D9330
+        //         278273C7 DE31EFDC 7310F712 1FD5A074 15987D9A DC0A486D
+        //         CDF93ACC 44328387 315D75E1 98C641A4 80CD86A1 B9E587E8
+        //         BE60E69C C928B2B9 C52172E4 13042E9B 23F10B0E 16E79763
+        //         C9B53DCF 4BA80A29 E3FB73C1 6B8E75B9 7EF363E2 FFA31F71
+        //         CF9DE538 4E71B81C 0AC4DFFE 0C10E64F
+        //
+        //    The hexadecimal value of the generator is:
+        //
+        //    g =  AC4032EF 4F2D9AE3 9DF30B5C 8FFDAC50 6CDEBE7B 89998CAF
+        //         74866A08 CFE4FFE3 A6824A4E 10B9A6F0 DD921F01 A70C4AFA
+        //         AB739D77 00C29F52 C57DB17C 620A8652 BE5E9001 A8D66AD7
+        //         C1766910 1999024A F4D02727 5AC1348B B8A762D0 521BC98A
+        //         E2471504 22EA1ED4 09939D54 DA7460CD B5F6C6B2 50717CBE
+        //         F180EB34 118E98D1 19529A45 D6F83456 6E3025E3 16A330EF
+        //         BB77A86F 0C1AB15B 051AE3D4 28C8F8AC B70A8137 150B8EEB
+        //         10E183ED D19963DD D9E263E4 770589EF 6AA21E7F 5F2FF381
+        //         B539CCE3 409D13CD 566AFBB4 8D6C0191 81E1BCFE 94B30269
+        //         EDFE72FE 9B6AA4BD 7B5A0F1C 71CFFF4C 19C418E1 F6EC0179
+        //         81BC087F 2A7065B3 84B890D3 191F2BFA
+        //
+        //    The generator generates a prime-order subgroup of size:
+        //
+        //    q =  801C0D34 C58D93FE 99717710 1F80535A 4738CEBC BF389A99
+        //         B36371EB
+        //
+
+        ZKP::constants_from_hex(
+            "AD107E1E9123A9D0D660FAA79559C51FA20D64E5683B9FD1B54B1597B61D0A75E6F\
+             A141DF95A56DBAF9A3C407BA1DF15EB3D688A309C180E1DE6B85A1274A0A66D3F81\
+             52AD6AC2129037C9EDEFDA4DF8D91E8FEF55B7394B7AD5B7D0B6C12207C9F98D11E\
+             D34DBF6C6BA0B2C8BBC27BE6A00E0A0B9C49708B3BF8A317091883681286130BC89\
+             85DB1602E714415D9330278273C7DE31EFDC7310F7121FD5A07415987D9ADC0A486\
+             DCDF93ACC44328387315D75E198C641A480CD86A1B9E587E8BE60E69CC928B2B9C5\
+             2172E413042E9B23F10B0E16E79763C9B53DCF4BA80A29E3FB73C16B8E75B97EF36\
+             3E2FFA31F71CF9DE5384E71B81C0AC4DFFE0C10E64F",
+            "801C0D34C58D93FE997177101F80535A4738CEBCBF389A99B36371EB",
+            "AC4032EF4F2D9AE39DF30B5C8FFDAC506CDEBE7B89998CAF74866A08CFE4FFE\
+             3A6824A4E10B9A6F0DD921F01A70C4AFAAB739D7700C29F52C57DB17C620A86\
+             52BE5E9001A8D66AD7C17669101999024AF4D027275AC1348BB8A762D0521BC\
+             98AE247150422EA1ED409939D54DA7460CDB5F6C6B250717CBEF180EB34118E\
+             98D119529A45D6F834566E3025E316A330EFBB77A86F0C1AB15B051AE3D428C\
+             8F8ACB70A8137150B8EEB10E183EDD19963DDD9E263E4770589EF6AA21E7F5F\
+             2FF381B539CCE3409D13CD566AFBB48D6C019181E1BCFE94B30269EDFE72FE9\
+             B6AA4BD7B5A0F1C71CFFF4C19C418E1F6EC017981BC087F2A7065B384B890D3\
+             191F2BFA",
+            // beta = alpha^i is also a generator
+            "1999024AF4D027275AC134",
+        )
+    }
+}
+
+/// The standard groups `ZKP::identify_group` recognizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownGroup {
+    /// The toy group of `Group::Toy`, which is not a standard group and only meant for testing.
+    Toy,
+    /// The RFC 5114 1024-bit group with 160-bit prime order subgroup, `Group::Rfc1024`.
+    Rfc5114_1024,
+    /// The RFC 5114 2048-bit group with 224-bit prime order subgroup, `Group::Rfc2048`.
+    Rfc5114_2048,
+    /// The RFC 3526 1536-bit MODP group (group 5).
+    Rfc3526_1536,
+    /// The RFC 3526 2048-bit MODP group (group 14).
+    Rfc3526_2048,
+    /// The RFC 3526 3072-bit MODP group (group 15).
+    Rfc3526_3072,
+    /// The RFC 3526 4096-bit MODP group (group 16).
+    Rfc3526_4096,
+    /// The RFC 3526 6144-bit MODP group (group 17).
+    Rfc3526_6144,
+    /// The RFC 3526 8192-bit MODP group (group 18).
+    Rfc3526_8192,
+}
+
+impl KnownGroup {
+    /// Returns the name of the group, e.g. `rfc5114-1024` or `rfc3526-2048`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Toy => "toy",
+            Self::Rfc5114_1024 => "rfc5114-1024",
+            Self::Rfc5114_2048 => "rfc5114-2048",
+            Self::Rfc3526_1536 => "rfc3526-1536",
+            Self::Rfc3526_2048 => "rfc3526-2048",
+            Self::Rfc3526_3072 => "rfc3526-3072",
+            Self::Rfc3526_4096 => "rfc3526-4096",
+            Self::Rfc3526_6144 => "rfc3526-6144",
+            Self::Rfc3526_8192 => "rfc3526-8192",
+        }
+    }
+}
+
+/// The primes of the RFC 3526 MODP groups, hex encoded. Each group has the generator `2` of the
+/// subgroup of order `q = (p - 1) / 2`.
+///
+/// Reference: https://www.rfc-editor.org/rfc/rfc3526
+#[rustfmt::skip]
+const RFC3526_PRIMES: [(KnownGroup, &str); 6] = [
+    (
+        KnownGroup::Rfc3526_1536,
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+         020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+         4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+         EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+         98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+         9ED529077096966D670C354E4ABC9804F1746C08CA237327FFFFFFFFFFFFFFFF",
+    ),
+    (
+        KnownGroup::Rfc3526_2048,
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+         020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+         4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+         EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+         98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+         9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+         E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+         3995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFF",
+    ),
+    (
+        KnownGroup::Rfc3526_3072,
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+         020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+         4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+         EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+         98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+         9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+         E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+         3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33\
+         A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7\
+         ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864\
+         D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2\
+         08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A93AD2CAFFFFFFFFFFFFFFFF",
+    ),
+    (
+        KnownGroup::Rfc3526_4096,
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+         020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+         4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+         EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+         98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+         9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+         E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+         3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33\
+         A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7\
+         ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864\
+         D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2\
+         08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7\
+         88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8\
+         DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2\
+         233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9\
+         93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C934063199FFFFFFFFFFFFFFFF",
+    ),
+    (
+        KnownGroup::Rfc3526_6144,
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+         020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+         4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+         EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+         98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+         9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+         E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+         3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33\
+         A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7\
+         ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864\
+         D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2\
+         08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7\
+         88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8\
+         DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2\
+         233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9\
+         93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C93402849236C3FAB4D27C7026\
+         C1D4DCB2602646DEC9751E763DBA37BDF8FF9406AD9E530EE5DB382F413001AE\
+         B06A53ED9027D831179727B0865A8918DA3EDBEBCF9B14ED44CE6CBACED4BB1B\
+         DB7F1447E6CC254B332051512BD7AF426FB8F401378CD2BF5983CA01C64B92EC\
+         F032EA15D1721D03F482D7CE6E74FEF6D55E702F46980C82B5A84031900B1C9E\
+         59E7C97FBEC7E8F323A97A7E36CC88BE0F1D45B7FF585AC54BD407B22B4154AA\
+         CC8F6D7EBF48E1D814CC5ED20F8037E0A79715EEF29BE32806A1D58BB7C5DA76\
+         F550AA3D8A1FBFF0EB19CCB1A313D55CDA56C9EC2EF29632387FE8D76E3C0468\
+         043E8F663F4860EE12BF2D5B0B7474D6E694F91E6DCC4024FFFFFFFFFFFFFFFF",
+    ),
+    (
+        KnownGroup::Rfc3526_8192,
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+         020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+         4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+         EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+         98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+         9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+         E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+         3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33\
+         A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7\
+         ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864\
+         D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2\
+         08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7\
+         88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8\
+         DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2\
+         233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9\
+         93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C93402849236C3FAB4D27C7026\
+         C1D4DCB2602646DEC9751E763DBA37BDF8FF9406AD9E530EE5DB382F413001AE\
+         B06A53ED9027D831179727B0865A8918DA3EDBEBCF9B14ED44CE6CBACED4BB1B\
+         DB7F1447E6CC254B332051512BD7AF426FB8F401378CD2BF5983CA01C64B92EC\
+         F032EA15D1721D03F482D7CE6E74FEF6D55E702F46980C82B5A84031900B1C9E\
+         59E7C97FBEC7E8F323A97A7E36CC88BE0F1D45B7FF585AC54BD407B22B4154AA\
+         CC8F6D7EBF48E1D814CC5ED20F8037E0A79715EEF29BE32806A1D58BB7C5DA76\
+         F550AA3D8A1FBFF0EB19CCB1A313D55CDA56C9EC2EF29632387FE8D76E3C0468\
+         043E8F663F4860EE12BF2D5B0B7474D6E694F91E6DBE115974A3926F12FEE5E4\
+         38777CB6A932DF8CD8BEC4D073B931BA3BC832B68D9DD300741FA7BF8AFC47ED\
+         2576F6936BA424663AAB639C5AE4F5683423B4742BF1C978238F16CBE39D652D\
+         E3FDB8BEFC848AD922222E04A4037C0713EB57A81A23F0C73473FC646CEA306B\
+         4BCBC8862F8385DDFA9D4B7FA2C087E879683303ED5BDD3A062B3CF5B3A278A6\
+         6D2A13F83F44F82DDF310EE074AB6A364597E899A0255DC164F31CC50846851D\
+         F9AB48195DED7EA1B1D510BD7EE74D73FAF36BC31ECFA268359046F4EB879F92\
+         4009438B481C6CD7889A002ED5EE382BC9190DA6FC026E479558E4475677E9AA\
+         9E3050E2765694DFC81F56E880B96E7160C980DD98EDD3DFFFFFFFFFFFFFFFFF",
+    ),
+];
+
+/// A proof of knowledge of a secret `x`, as exchanged in the three moves of the protocol.
+///
+/// - `r1`, `r2`: The prover's commitment `(alpha^k mod p, beta^k mod p)`.
+/// - `c`: The verifier's challenge.
+/// - `s`: The prover's solution `k - c * x mod q`.
+///
+/// Proofs compare and hash by the values of their numbers, so proofs decoded from encodings
+/// that differ only by leading zero bytes are equal; see `canonical_bytes`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Proof {
+    #[cfg_attr(feature = "serde", serde(with = "serde_biguint"))]
+    pub r1: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "serde_biguint"))]
+    pub r2: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "serde_biguint"))]
+    pub c: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "serde_biguint"))]
+    pub s: BigUint,
+}
+
+/// One proof of a batch file read by `ZKP::verify_proof_file`, with the public values of its
+/// prover.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProofFileEntry {
+    #[serde(with = "serde_biguint")]
+    pub y1: BigUint,
+    #[serde(with = "serde_biguint")]
+    pub y2: BigUint,
+    pub proof: Proof,
+}
+
+/// The summary of a batch of verifications, see `ZKP::verify_proof_file`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    /// The number of valid proofs.
+    pub valid: usize,
+    /// The number of invalid proofs.
+    pub invalid: usize,
+    /// The indices of the invalid proofs in the batch, in increasing order.
+    pub failing: Vec<usize>,
+}
+
+impl BatchReport {
+    /// Summarizes the results of `ZKP::verify_batch`, one per proof in order.
+    pub fn from_results(results: &[bool]) -> Self {
+        let failing: Vec<usize> = (0..results.len()).filter(|&i| !results[i]).collect();
+        Self {
+            valid: results.len() - failing.len(),
+            invalid: failing.len(),
+            failing,
+        }
+    }
+
+    /// Returns the number of proofs in the batch.
+    pub fn total(&self) -> usize {
+        self.valid + self.invalid
+    }
+
+    /// Returns whether every proof of the batch is valid.
+    pub fn all_valid(&self) -> bool {
+        self.invalid == 0
+    }
+}
+
+/// Proofs of one credential aggregated by `ZKP::aggregate`, verified with
+/// `ZKP::verify_aggregate`.
+///
+/// The commitments and challenges of the proofs are kept, in order, to recompute the weights of
+/// the combination, while their responses are combined into `s`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateProof {
+    pub r1: Vec<BigUint>,
+    pub r2: Vec<BigUint>,
+    pub c: Vec<BigUint>,
+    /// The weighted sum of the responses, modulo `q`.
+    pub s: BigUint,
+}
+
+impl AggregateProof {
+    /// Returns whether the proof with the commitment `(r1, r2)` and the challenge `c` is part
+    /// of the aggregate, which each verifier checks for the challenge it issued.
+    pub fn includes(&self, r1: &BigUint, r2: &BigUint, c: &BigUint) -> bool {
+        (0..self.c.len()).any(|i| self.r1[i] == *r1 && self.r2[i] == *r2 && self.c[i] == *c)
+    }
+}
+
+/// The verifier side of one run of the interactive protocol, between the challenge and the
+/// response.
+///
+/// The verifier receives the commitment `(r1, r2)`, issues the challenge `c`, and only later
+/// receives the response `s`. `IncrementalVerifier` holds `(r1, r2, y1, y2, c)` in between, as
+/// the server does for each outstanding challenge, and `finalize` consumes it, so a challenge is
+/// answered at most once.
+///
+/// ```
+/// use zkp_chaum_pedersen::{prover::Session, IncrementalVerifier, ZKP};
+/// # use num_bigint::BigUint;
+///
+/// let zkp = ZKP::default();
+/// let x = BigUint::from(6u32);
+/// let (y1, y2) = zkp.compute_pair(&x);
+///
+/// let session = Session::new(&zkp, x).commit();
+/// let (r1, r2) = session.commitment();
+/// let verifier = IncrementalVerifier::new(&zkp, (&y1, &y2), (r1, r2));
+/// let session = session.respond(verifier.challenge());
+/// assert!(verifier.finalize(session.response()));
+/// ```
+#[derive(Debug)]
+pub struct IncrementalVerifier<'a> {
+    zkp: &'a ZKP,
+    y1: BigUint,
+    y2: BigUint,
+    r1: BigUint,
+    r2: BigUint,
+    c: BigUint,
+}
+
+impl<'a> IncrementalVerifier<'a> {
+    /// Receives the commitment `(r1, r2)` of the prover of `(y1, y2)` and issues a random
+    /// challenge, see `ZKP::issue_challenge`.
+    pub fn new(
+        zkp: &'a ZKP, (y1, y2): (&BigUint, &BigUint), (r1, r2): (&BigUint, &BigUint),
+    ) -> Self {
+        Self::with_challenge(zkp, (y1, y2), (r1, r2), zkp.issue_challenge())
+    }
+
+    /// Receives the commitment `(r1, r2)` of the prover of `(y1, y2)` with the challenge `c`
+    /// issued for it, e.g. by `ZKP::issue_challenge_bits`.
+    pub fn with_challenge(
+        zkp: &'a ZKP, (y1, y2): (&BigUint, &BigUint), (r1, r2): (&BigUint, &BigUint), c: BigUint,
+    ) -> Self {
+        Self {
+            zkp,
+            y1: y1.clone(),
+            y2: y2.clone(),
+            r1: r1.clone(),
+            r2: r2.clone(),
+            c,
+        }
+    }
+
+    /// Returns the challenge `c` to send to the prover.
+    pub fn challenge(&self) -> &BigUint {
+        &self.c
+    }
+
+    /// Checks the response `s` of the prover, see `ZKP::verify`.
+    ///
+    /// # Returns
+    ///
+    /// `true` if `s` answers the challenge for the commitment and the public values, `false`
+    /// otherwise.
+    pub fn finalize(self, s: &BigUint) -> bool {
+        self.zkp
+            .verify(&self.r1, &self.r2, &self.y1, &self.y2, &self.c, s)
+    }
+}
+
+/// The number of proofs verified between two progress reports of
+/// `ZKP::verify_batch_with_progress`.
+pub const BATCH_PROGRESS_CHUNK: usize = 64;
+
+/// The number of proofs `ZKP::verify_batch_simd` verifies together.
+#[cfg(feature = "experimental")]
+pub const BATCH_LANES: usize = 8;
+
+/// The domain separation tag hashed first by `ZKP::nums_generators`.
+pub const NUMS_DOMAIN: &[u8] = b"zkp-chaum-pedersen/nums-generators/v1";
+
+/// The last counter `ZKP::nums_generators` tries for each generator. A counter fails with
+/// probability about `2 / q`, so this is only reached in degenerate subgroups.
+pub const NUMS_MAX_COUNTER: u32 = 1024;
+
+/// The domain separation tag hashed first by the weights of `ZKP::aggregate`.
+pub const AGGREGATE_DOMAIN: &[u8] = b"zkp-chaum-pedersen/aggregate/v1";
+
+/// The domain separation tag hashed first by `ZKP::fiat_shamir_challenge`.
+pub const FIAT_SHAMIR_DOMAIN: &[u8] = b"zkp-chaum-pedersen/fiat-shamir/v1";
+
+/// The domain separation tag of the context of `ZKP::prove_possession`.
+pub const POSSESSION_DOMAIN: &[u8] = b"zkp-chaum-pedersen/possession/v1";
+
+/// The domain separation tag of the context of `ZKP::prove_credential_possession`.
+pub const CREDENTIAL_POSSESSION_DOMAIN: &[u8] = b"zkp-chaum-pedersen/credential-possession/v1";
+
+/// The version prefix of the current `VerifierKey` file format.
+pub const VERIFIER_KEY_VERSION: &str = "zkvk1";
+
+/// The version prefix of the current `Proof` transport string format.
+pub const PROOF_TRANSPORT_VERSION: &str = "zkp1";
+
+impl Proof {
+    /// Serializes the proof as bytes.
+    ///
+    /// Each of `r1`, `r2`, `c` and `s` is written, in that order, as a big-endian `u16` length
+    /// followed by its big-endian bytes.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either a `Vec<u8>` with the serialized proof, or
+    /// `ZkpError::OutOfRange` if a number is longer than the `u16` length can tell.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        for (name, value) in [("r1", &self.r1), ("r2", &self.r2), ("c", &self.c), ("s", &self.s)] {
+            let value = value.to_bytes_be();
+            let len = u16::try_from(value.len()).map_err(|_| {
+                ZkpError::OutOfRange(format!("{} must be at most {} bytes", name, u16::MAX))
+            })?;
+            bytes.extend_from_slice(&len.to_be_bytes());
+            bytes.extend_from_slice(&value);
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the canonical serialization of the proof, e.g. to deduplicate captured proofs.
+    ///
+    /// This is `to_bytes`, which writes every number without leading zero bytes. `from_bytes`
+    /// also accepts numbers padded with leading zeros, so two encodings of the same proof may
+    /// differ byte for byte, but decode to equal proofs with the same canonical bytes.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either a `Vec<u8>` equal for equal proofs, or the error of
+    /// `to_bytes`.
+    pub fn canonical_bytes(&self) -> Result<Vec<u8>> {
+        self.to_bytes()
+    }
+
+    /// Checks that every number of the proof is in its range, before any modular
+    /// exponentiation is spent on it.
+    ///
+    /// An honest prover always sends commitments in `[1, p)` and a challenge and a response in
+    /// `[0, q)`, so a proof failing this is garbage. Passing it does not make the proof valid.
+    ///
+    /// # Arguments
+    ///
+    /// * `zkp` - The group the proof was made in.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the proof is well formed, or `ZkpError::OutOfRange` naming the first number
+    /// out of its range.
+    pub fn validate_structure(&self, zkp: &ZKP) -> Result<()> {
+        let zero = BigUint::from(0u32);
+        for (name, value) in [("r1", &self.r1), ("r2", &self.r2)] {
+            if *value == zero || *value >= zkp.p {
+                return Err(ZkpError::OutOfRange(format!("{} must be in [1, p)", name)));
+            }
+        }
+        for (name, value) in [("c", &self.c), ("s", &self.s)] {
+            if *value >= zkp.q {
+                return Err(ZkpError::OutOfRange(format!("{} must be in [0, q)", name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Deserializes a proof serialized with `to_bytes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The serialized proof.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the proof or `ZkpError::InvalidEncoding` if `bytes` is
+    /// truncated or has trailing data.
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let mut values = Vec::with_capacity(4);
+        for name in ["r1", "r2", "c", "s"] {
+            let truncated = || ZkpError::InvalidEncoding(format!("proof truncated at {}", name));
+            let (len, rest) = bytes.split_first_chunk::<2>().ok_or_else(truncated)?;
+            let len = u16::from_be_bytes(*len) as usize;
+            if rest.len() < len {
+                return Err(truncated());
+            }
+            values.push(BigUint::from_bytes_be(&rest[..len]));
+            bytes = &rest[len..];
+        }
+        if !bytes.is_empty() {
+            return Err(ZkpError::InvalidEncoding("trailing bytes after proof".to_string()));
+        }
+
+        let [r1, r2, c, s] = values.try_into().expect("4 values were decoded");
+        Ok(Self { r1, r2, c, s })
+    }
+
+    /// Renders the proof as a compact string, e.g. to show it as a QR code.
+    ///
+    /// The string is the version prefix `PROOF_TRANSPORT_VERSION`, a `:`, and the unpadded
+    /// base64url encoding of `to_bytes`, so it only contains URL and QR friendly characters.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either a `String` with the transport encoding of the proof, or the
+    /// error of `to_bytes`.
+    pub fn to_transport_string(&self) -> Result<String> {
+        Ok(format!(
+            "{}:{}",
+            PROOF_TRANSPORT_VERSION,
+            URL_SAFE_NO_PAD.encode(self.to_bytes()?)
+        ))
+    }
+
+    /// Parses a proof rendered with `to_transport_string`.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoded` - The transport string.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the proof, `ZkpError::UnsupportedVersion` if the version
+    /// prefix is not `PROOF_TRANSPORT_VERSION`, or `ZkpError::InvalidEncoding` if the string is
+    /// otherwise malformed.
+    pub fn from_transport_string(encoded: &str) -> Result<Self> {
+        let (version, payload) = encoded
+            .split_once(':')
+            .ok_or_else(|| ZkpError::InvalidEncoding("missing version prefix".to_string()))?;
+        if version != PROOF_TRANSPORT_VERSION {
+            return Err(ZkpError::UnsupportedVersion(version.to_string()));
+        }
+
+        let bytes = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|e| ZkpError::InvalidEncoding(e.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// The registered public values `(y1, y2)` of each user, keyed by username.
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    users: HashMap<String, (BigUint, BigUint)>,
+}
+
+impl Registry {
+    /// Registers (or replaces) the public values `(y1, y2)` of `user`.
+    pub fn register(&mut self, user: &str, y1: BigUint, y2: BigUint) {
+        self.users.insert(user.to_string(), (y1, y2));
+    }
+
+    /// Returns the public values `(y1, y2)` of `user`, if registered.
+    pub fn get(&self, user: &str) -> Option<&(BigUint, BigUint)> {
+        self.users.get(user)
+    }
+
+    /// Returns the registered user a proof authenticates, if any.
+    ///
+    /// With a `hint`, only the public values of that user are tried. Without one, the proof is
+    /// tried against every registered user, which costs a full `ZKP::verify` per user: keep that
+    /// for small registries, or for when the user cannot be known in advance. If several users
+    /// registered the same public values, any of them may be returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `zkp` - The group the public values were registered in.
+    /// * `proof` - The proof to identify.
+    /// * `hint` - The user the proof is expected to come from, if known.
+    pub fn identify(&self, zkp: &ZKP, proof: &Proof, hint: Option<&str>) -> Option<&str> {
+        match hint {
+            Some(user) => {
+                let (user, (y1, y2)) = self.users.get_key_value(user)?;
+                zkp.verify_proof(y1, y2, proof).then_some(user.as_str())
+            }
+            None => self
+                .users
+                .iter()
+                .find(|(_, (y1, y2))| zkp.verify_proof(y1, y2, proof))
+                .map(|(user, _)| user.as_str()),
+        }
+    }
+}
+
+/// The public values `(y1, y2)` of one prover, as stored by a verifier.
+///
+/// A verifier key can be written to a file by the registration service and loaded by a separate
+/// verification service, which then needs no shared state to check proofs with
+/// `ZKP::verify_against`. The file holds a single line: the version prefix
+/// `VERIFIER_KEY_VERSION`, then `y1` and `y2` hex encoded, separated by `:`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifierKey {
+    pub y1: BigUint,
+    pub y2: BigUint,
+}
+
+impl VerifierKey {
+    /// Encodes the key in the format of its file.
+    pub fn encode(&self) -> String {
+        format!("{}:{:x}:{:x}", VERIFIER_KEY_VERSION, self.y1, self.y2)
+    }
+
+    /// Decodes a key encoded with `encode`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the key, `ZkpError::UnsupportedVersion` if the version prefix
+    /// is not `VERIFIER_KEY_VERSION`, or `ZkpError::InvalidEncoding` if it is otherwise
+    /// malformed.
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let mut fields = encoded.trim().split(':');
+        let version = fields.next().unwrap_or_default();
+        if version != VERIFIER_KEY_VERSION {
+            return Err(ZkpError::UnsupportedVersion(version.to_string()));
+        }
+
+        let mut value = |name: &str| {
+            let field = fields
+                .next()
+                .ok_or_else(|| ZkpError::InvalidEncoding(format!("missing {}", name)))?;
+            BigUint::parse_bytes(field.as_bytes(), 16)
+                .ok_or_else(|| ZkpError::InvalidEncoding(format!("{} is not hex", name)))
+        };
+        let (y1, y2) = (value("y1")?, value("y2")?);
+        if fields.next().is_some() {
+            return Err(ZkpError::InvalidEncoding("trailing fields after y2".to_string()));
+        }
+        Ok(Self { y1, y2 })
+    }
+
+    /// Loads a key from a file written with `to_file`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the key, `ZkpError::Io` if the file cannot be read, or the
+    /// error of `decode`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::decode(&std::fs::read_to_string(path)?)
+    }
+
+    /// Writes the key to a file, to be loaded with `from_file`.
+    pub fn to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.encode() + "\n")?;
+        Ok(())
+    }
+}
+
+impl Default for ZKP {
+    /// The built-in group returned by `get_constants`.
+    fn default() -> Self {
+        let (alpha, beta, p, q) = Self::get_constants();
+        Self { p, q, alpha, beta }
+    }
+}
+
+/// The number of groups per line of `format_biguint_grouped`.
+pub const GROUPS_PER_LINE: usize = 8;
+
+/// Formats a number in uppercase hex split into groups, for human inspection.
+///
+/// The digits are grouped from the least significant one, so only the first group may be
+/// shorter than `group_len`. Groups are separated by spaces, with a newline after every
+/// `GROUPS_PER_LINE` groups: with `group_len = 8`, the parameters of RFC 5114 come out in the
+/// layout of the RFC.
+///
+/// # Arguments
+///
+/// * `value` - The number to format.
+/// * `group_len` - The number of hex digits per group, `0` for a single group.
+///
+/// # Returns
+///
+/// A `String` with the grouped hex digits, without a trailing newline.
+pub fn format_biguint_grouped(value: &BigUint, group_len: usize) -> String {
+    let hex = format!("{:X}", value);
+    if group_len == 0 {
+        return hex;
+    }
+    let first = match hex.len() % group_len {
+        0 => group_len,
+        partial => partial,
+    };
+    let groups: Vec<&str> = std::iter::once(&hex[..first])
+        .chain(
+            (first..hex.len())
+                .step_by(group_len)
+                .map(|i| &hex[i..i + group_len]),
+        )
+        .collect();
+    groups
+        .chunks(GROUPS_PER_LINE)
+        .map(|line| line.join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Checks that every value is below `p`, comparing all of them instead of stopping at the first
+/// out of range one, so the timing does not tell which one it was.
+fn all_below<const N: usize>(p: &BigUint, values: [&BigUint; N]) -> bool {
+    values
+        .iter()
+        .fold(true, |in_range, value| in_range & (*value < p))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use fixtures::{forged_proof, public_keys, toy_zkp, valid_proof};
+    use std::time::{Duration, Instant};
+
+    /// Runs `f` `iterations` times and fails if the average run exceeds `budget`.
+    ///
+    /// This is a coarse guard against accidental complexity blowups, not a benchmark, so the
+    /// budgets passed in are kept far above the expected timings (also in debug builds).
+    fn assert_within_budget<F: FnMut()>(label: &str, iterations: u32, budget: Duration, mut f: F) {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            f();
+        }
+        let average = start.elapsed() / iterations;
+        assert!(average <= budget, "{label} took {average:?} on average, budget is {budget:?}");
+    }
+
+    #[test]
+    fn test_toy_example() {
+        let alpha = BigUint::from(4u32);
+        let beta = BigUint::from(9u32);
+        let p = BigUint::from(23u32);
+        let q = BigUint::from(11u32);
+        let zkp = ZKP::new(&p, &q, &alpha, &beta);
+
+        let x = BigUint::from(6u32);
+        let k = BigUint::from(7u32);
+
+        let c = BigUint::from(4u32);
+
+        let (y1, y2) = zkp.compute_pair(&x);
+        assert_eq!(y1, BigUint::from(2u32));
+        assert_eq!(y2, BigUint::from(3u32));
+
+        let (r1, r2) = zkp.compute_pair(&k);
+        assert_eq!(r1, BigUint::from(8u32));
+        assert_eq!(r2, BigUint::from(4u32));
+
+        let s = zkp.solve(&k, &c, &x);
+        assert_eq!(s, BigUint::from(5u32));
+
+        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s);
+        assert!(result);
+
+        // fake secret
+        let x_fake = BigUint::from(7u32);
+        let s_fake = zkp.solve(&k, &c, &x_fake);
+
+        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s_fake);
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_toy_example_with_random_numbers() {
+        let alpha = BigUint::from(4u32);
+        let beta = BigUint::from(9u32);
+        let p = BigUint::from(23u32);
+        let q = BigUint::from(11u32);
+        let zkp = ZKP {
+            p: p.clone(),
+            q: q.clone(),
+            alpha: alpha.clone(),
+            beta: beta.clone(),
+        };
+
+        let x = BigUint::from(6u32);
+        let k = ZKP::generate_random_number_below(&q);
+
+        let c = ZKP::generate_random_number_below(&q);
+
+        let (y1, y2) = zkp.compute_pair(&x);
+        assert_eq!(y1, BigUint::from(2u32));
+        assert_eq!(y2, BigUint::from(3u32));
+
+        let (r1, r2) = zkp.compute_pair(&k);
+
+        let s = zkp.solve(&k, &c, &x);
+
+        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s);
+        assert!(result);
+    }
+
+    /// Every built-in group, with its name for assertion messages. New groups added here are
+    /// covered by all the tests running over this list.
+    fn built_in_groups() -> Vec<(&'static str, ZKP)> {
+        vec![
+            ("toy", toy_zkp()),
+            ("rfc5114-1024", ZKP::from_group(Group::Rfc1024)),
+            ("rfc5114-2048", ZKP::from_group(Group::Rfc2048)),
+        ]
+    }
+
+    #[test]
+    fn test_group_constants() {
+        assert_eq!(Group::default().constants(), ZKP::get_constants());
+        assert_eq!(toy_zkp().beta, BigUint::from(9u32));
+
+        for (name, zkp) in built_in_groups() {
+            // both generators are of order q
+            let one = BigUint::from(1u32);
+            assert_eq!(zkp.alpha.modpow(&zkp.q, &zkp.p), one, "{name}");
+            assert_eq!(zkp.beta.modpow(&zkp.q, &zkp.p), one, "{name}");
+            assert!(!zkp.is_degenerate(), "{name}");
+        }
+        assert_eq!(ZKP::from_group(Group::Rfc2048).p.bits(), 2048);
+    }
+
+    #[test]
+    fn test_byte_lengths() {
+        let zkp = ZKP::from_group(Group::Rfc1024);
+        assert_eq!(zkp.p_byte_len(), 128);
+        assert_eq!(zkp.q_byte_len(), 20);
+
+        let zkp = ZKP::from_group(Group::Rfc2048);
+        assert_eq!(zkp.p_byte_len(), 256);
+        assert_eq!(zkp.q_byte_len(), 28);
+
+        let zkp = toy_zkp();
+        assert_eq!((zkp.p_byte_len(), zkp.q_byte_len()), (1, 1));
+        assert_eq!(zkp.encode_fixed(&BigUint::from(5u32)).len(), zkp.p_byte_len());
+    }
+
+    #[test]
+    fn test_protocol_over_all_groups() {
+        for (name, zkp) in built_in_groups() {
+            let x = ZKP::generate_random_number_below(&zkp.q);
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            // a zero challenge would accept any secret
+            let c = ZKP::generate_random_number_below(&(&zkp.q - 1u32)) + 1u32;
+
+            let (y1, y2) = zkp.compute_pair(&x);
+            let (r1, r2) = zkp.compute_pair(&k);
+
+            // honest prover
+            let s = zkp.solve(&k, &c, &x);
+            assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s), "honest proof rejected for {name}");
+
+            // forged secret
+            let x_fake = (&x + 1u32) % &zkp.q;
+            let s_fake = zkp.solve(&k, &c, &x_fake);
+            assert!(
+                !zkp.verify(&r1, &r2, &y1, &y2, &c, &s_fake),
+                "forged proof accepted for {name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_stateless_matches_verify() {
+        for (name, zkp) in built_in_groups() {
+            let x = ZKP::generate_random_number_below(&zkp.q);
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let c = ZKP::generate_random_number_below(&(&zkp.q - 1u32)) + 1u32;
+
+            let (y1, y2) = zkp.compute_pair(&x);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let s = zkp.solve(&k, &c, &x);
+            let s_fake = (&s + 1u32) % &zkp.q;
+
+            let params = GroupParams {
+                p: &zkp.p,
+                q: &zkp.q,
+                alpha: &zkp.alpha,
+                beta: &zkp.beta,
+            };
+            for s in [&s, &s_fake] {
+                assert_eq!(
+                    ZKP::verify_stateless(params, &y1, &y2, &r1, &r2, &c, s),
+                    zkp.verify(&r1, &r2, &y1, &y2, &c, s),
+                    "verify_stateless and verify disagree for {name}"
+                );
+            }
+            assert!(ZKP::verify_stateless(params, &y1, &y2, &r1, &r2, &c, &s));
+            assert!(!ZKP::verify_stateless(params, &y1, &y2, &r1, &r2, &c, &s_fake));
+        }
+    }
+
+    #[test]
+    fn test_response_range() {
+        for (name, zkp) in built_in_groups() {
+            let x = ZKP::generate_random_number_below(&zkp.q);
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let c = ZKP::generate_random_number_below(&zkp.q);
+
+            let (y1, y2) = zkp.compute_pair(&x);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let s = zkp.solve(&k, &c, &x);
+            assert!(zkp.is_valid_response(&s), "{name}");
+            assert!(zkp.is_valid_response(&BigUint::from(0u32)), "{name}");
+            assert!(zkp.is_valid_response(&(&zkp.q - 1u32)), "{name}");
+
+            // s + q passes the group equations, but is not a response `solve` can produce
+            let s_wrapped = &s + &zkp.q;
+            assert!(!zkp.is_valid_response(&zkp.q), "{name}");
+            assert!(!zkp.is_valid_response(&s_wrapped), "{name}");
+            assert!(!zkp.verify(&r1, &r2, &y1, &y2, &c, &s_wrapped), "{name}");
+            assert!(!ZKP::verify_stateless(zkp.params(), &y1, &y2, &r1, &r2, &c, &s_wrapped));
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_verify_batch_parallel() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ZKP>();
+
+        let zkp = ZKP::default();
+        let proofs: Vec<_> = (0..200)
+            .map(|i| {
+                let x = ZKP::generate_random_number_below(&zkp.q);
+                let k = ZKP::generate_random_number_below(&zkp.q);
+                let c = ZKP::generate_random_number_below(&zkp.q);
+                let (y1, y2) = zkp.compute_pair(&x);
+                let (r1, r2) = zkp.compute_pair(&k);
+                // every third proof answers for another secret
+                let s = zkp.solve(&k, &c, &(&x + (i % 3 == 0) as u32));
+                (y1, y2, Proof { r1, r2, c, s })
+            })
+            .collect();
+
+        let results = zkp.verify_batch_parallel(&proofs);
+        assert_eq!(results, zkp.verify_batch(&proofs));
+        assert_eq!(results.iter().filter(|valid| **valid).count(), 133);
+        assert!(zkp.verify_batch_parallel(&[]).is_empty());
+    }
+
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn test_verify_batch_simd_matches_verify_batch() {
+        for (name, zkp) in built_in_groups() {
+            // not a multiple of `BATCH_LANES`, so the last lane is partial
+            let proofs: Vec<_> = (0..3 * BATCH_LANES + 5)
+                .map(|i| {
+                    let x = ZKP::generate_random_number_below(&zkp.q);
+                    let k = ZKP::generate_random_number_below(&zkp.q);
+                    let c = ZKP::generate_random_number_below(&zkp.q);
+                    let (mut y1, y2) = zkp.compute_pair(&x);
+                    let (r1, r2) = zkp.compute_pair(&k);
+                    // every third proof answers for another secret
+                    let mut s = zkp.solve(&k, &c, &(&x + (i % 3 == 0) as u32));
+                    match i % 7 {
+                        1 => s += &zkp.q,
+                        2 => y1 += &zkp.p,
+                        _ => {}
+                    }
+                    (y1, y2, Proof { r1, r2, c, s })
+                })
+                .collect();
+
+            let results = zkp.verify_batch_simd(&proofs);
+            assert_eq!(results, zkp.verify_batch(&proofs), "{name}");
+            assert!(results.iter().any(|valid| *valid), "{name}");
+            assert!(results.iter().any(|valid| !*valid), "{name}");
+        }
+        assert!(ZKP::default().verify_batch_simd(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_verify_shared_challenge() {
+        for (name, zkp) in built_in_groups() {
+            let c = zkp.issue_challenge();
+            let secrets: Vec<BigUint> = (0..3)
+                .map(|_| ZKP::generate_random_number_below(&zkp.q))
+                .collect();
+            // three statements by each prover, every fourth one answering for another secret
+            let mut statements: Vec<_> = (0..9)
+                .map(|i| {
+                    let x = &secrets[i % 3];
+                    let (y1, y2) = zkp.compute_pair(x);
+                    let k = ZKP::generate_random_number_below(&zkp.q);
+                    let (r1, r2) = zkp.compute_pair(&k);
+                    let s = zkp.solve(&k, &c, &(x + (i % 4 == 0) as u32));
+                    (r1, r2, y1, y2, s)
+                })
+                .collect();
+            statements[5].4 += &zkp.q;
+            statements[7].2 += &zkp.p;
+
+            let results = zkp.verify_shared_challenge(&c, &statements);
+            let expected: Vec<bool> = statements
+                .iter()
+                .map(|(r1, r2, y1, y2, s)| zkp.verify(r1, r2, y1, y2, &c, s))
+                .collect();
+            assert_eq!(results, expected, "{name}");
+            assert!(results.iter().any(|valid| *valid), "{name}");
+        }
+        assert!(ZKP::default()
+            .verify_shared_challenge(&BigUint::from(1u32), &[])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_non_interactive_proof() {
+        for (name, zkp) in built_in_groups() {
+            let x = ZKP::generate_random_number_below(&zkp.q);
+            let (y1, y2) = zkp.compute_pair(&x);
+
+            let proof = zkp.prove_non_interactive(&x, &y1, &y2, b"alice:1");
+            assert!(zkp.verify_non_interactive(&y1, &y2, &proof, b"alice:1"), "{name}");
+            assert!(zkp.verify_proof(&y1, &y2, &proof), "{name}");
+            // the toy group's challenges collide too often for the checks below
+            if zkp.q.bits() > 64 {
+                assert!(!zkp.verify_non_interactive(&y1, &y2, &proof, b"alice:2"), "{name}");
+                let forged = zkp.prove_non_interactive(&(&x + 1u32), &y1, &y2, b"alice:1");
+                assert!(!zkp.verify_non_interactive(&y1, &y2, &forged, b"alice:1"), "{name}");
+            }
+
+            // a valid interactive proof, but with a challenge the prover picked
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let c = (zkp.fiat_shamir_challenge(&y1, &y2, &r1, &r2, b"alice:1") + 1u32) % &zkp.q;
+            let s = zkp.solve(&k, &c, &x);
+            let proof = Proof { r1, r2, c, s };
+            assert!(zkp.verify_proof(&y1, &y2, &proof), "{name}");
+            assert!(!zkp.verify_non_interactive(&y1, &y2, &proof, b"alice:1"), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_is_in_subgroup() {
+        for (name, zkp) in built_in_groups() {
+            let x = ZKP::generate_random_number_below(&zkp.q);
+            let (y1, y2) = zkp.compute_pair(&x);
+            assert!(zkp.is_in_subgroup(&y1) && zkp.is_in_subgroup(&y2), "{name}");
+            assert!(zkp.is_in_subgroup(&BigUint::from(1u32)), "{name}");
+
+            // p - 1 has order 2
+            assert!(!zkp.is_in_subgroup(&(&zkp.p - 1u32)), "{name}");
+            assert!(!zkp.is_in_subgroup(&BigUint::from(0u32)), "{name}");
+            assert!(!zkp.is_in_subgroup(&(&y1 + &zkp.p)), "{name}");
+        }
+
+        // in the toy group, the subgroup of order 11 is the quadratic residues mod 23
+        let zkp = toy_zkp();
+        let residues: Vec<u32> = (1..23u32)
+            .filter(|y| zkp.is_in_subgroup(&BigUint::from(*y)))
+            .collect();
+        assert_eq!(residues, [1, 2, 3, 4, 6, 8, 9, 12, 13, 16, 18]);
+    }
+
+    #[test]
+    fn test_public_pair() {
+        for (name, zkp) in built_in_groups() {
+            let x = ZKP::generate_random_number_below(&(&zkp.q - 1u32)) + 1u32;
+            assert_eq!(zkp.public_pair(&x), Ok(zkp.compute_pair(&x)), "{name}");
+            let one = BigUint::from(1u32);
+            assert_eq!(zkp.public_pair(&one), Ok((zkp.alpha.clone(), zkp.beta.clone())));
+            let last = &zkp.q - 1u32;
+            assert_eq!(zkp.public_pair(&last), Ok(zkp.compute_pair(&last)), "{name}");
+
+            for secret in [BigUint::from(0u32), zkp.q.clone(), &zkp.q + &x, zkp.p.clone()] {
+                assert!(
+                    matches!(zkp.public_pair(&secret), Err(ZkpError::OutOfRange(_))),
+                    "{name}: {secret}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_is_below_q() {
+        let zkp = toy_zkp();
+
+        // c * x = k + q, so s = 0 and not q
+        let (k, c, x) = (BigUint::from(1u32), BigUint::from(3u32), BigUint::from(4u32));
+        let s = zkp.solve(&k, &c, &x);
+        assert_eq!(s, BigUint::from(0u32));
+
+        let (y1, y2) = zkp.compute_pair(&x);
+        let (r1, r2) = zkp.compute_pair(&k);
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s));
+    }
+
+    #[test]
+    fn test_verify_with_reason() {
+        let zkp = toy_zkp();
+        let (y1, y2) = public_keys();
+        let Proof { r1, r2, c, s } = valid_proof();
+        assert_eq!(zkp.verify_with_reason(&r1, &r2, &y1, &y2, &c, &s), Ok(()));
+
+        let forged = forged_proof();
+        let result = zkp.verify_with_reason(&forged.r1, &forged.r2, &y1, &y2, &forged.c, &forged.s);
+        assert_eq!(result, Err(VerificationFailure::R1Mismatch));
+
+        let result = zkp.verify_with_reason(&r1, &r2, &y1, &y2, &c, &(&s + &zkp.q));
+        assert_eq!(result, Err(VerificationFailure::ResponseOutOfRange));
+
+        let one = BigUint::from(1u32);
+        let result = zkp.verify_with_reason(&one, &r2, &y1, &y2, &c, &s);
+        assert_eq!(result, Err(VerificationFailure::CommitmentDegenerate));
+        let result = zkp.verify_with_reason(&r1, &BigUint::from(0u32), &y1, &y2, &c, &s);
+        assert_eq!(result, Err(VerificationFailure::CommitmentDegenerate));
+
+        // r1 from another nonce, r2 still right
+        let k = BigUint::from(fixtures::NONCE);
+        let (other_r1, _) = zkp.compute_pair(&(&k + 1u32));
+        let result = zkp.verify_with_reason(&other_r1, &r2, &y1, &y2, &c, &s);
+        assert_eq!(result, Err(VerificationFailure::R1Mismatch));
+
+        // r1 right, r2 from another nonce
+        let (_, other_r2) = zkp.compute_pair(&(&k + 1u32));
+        let result = zkp.verify_with_reason(&r1, &other_r2, &y1, &y2, &c, &s);
+        assert_eq!(result, Err(VerificationFailure::R2Mismatch));
+
+        let result = zkp.verify_with_reason(&r1, &r2, &(&y1 + &zkp.p), &y2, &c, &s);
+        assert_eq!(result, Err(VerificationFailure::ElementOutOfRange));
+
+        assert_eq!(VerificationFailure::R1Mismatch.to_string(), "r1 does not match");
+    }
+
+    #[test]
+    fn test_verify_rejects_elements_out_of_range() {
+        let zkp = toy_zkp();
+        let (y1, y2) = public_keys();
+        let Proof { r1, r2, c, s } = valid_proof();
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s));
+
+        // congruent modulo p, so they would pass if reduced
+        let p = &zkp.p;
+        let (r1_p, r2_p, y1_p, y2_p) = (&r1 + p, &r2 + p, &y1 + p, &y2 + p);
+        assert!(!zkp.verify(&r1_p, &r2, &y1, &y2, &c, &s));
+        assert!(!zkp.verify(&r1, &r2_p, &y1, &y2, &c, &s));
+        assert!(!zkp.verify(&r1, &r2, &y1_p, &y2, &c, &s));
+        assert!(!zkp.verify(&r1, &r2, &y1, &y2_p, &c, &s));
+
+        // public values of the 1024-bit group checked in the toy group
+        let rfc1024 = ZKP::from_group(Group::Rfc1024);
+        let (big_y1, big_y2) = rfc1024.compute_pair(&BigUint::from(fixtures::SECRET));
+        assert!(!zkp.verify(&r1, &r2, &big_y1, &big_y2, &c, &s));
+        assert_eq!(
+            zkp.verify_with_reason(&r1, &r2, &big_y1, &big_y2, &c, &s),
+            Err(VerificationFailure::ElementOutOfRange)
         );
+        assert!(!ZKP::verify_stateless(zkp.params(), &big_y1, &big_y2, &r1, &r2, &c, &s));
+    }
+
+    #[test]
+    fn test_generate_credential() {
+        for (name, zkp) in built_in_groups() {
+            let (x, y1, y2) = zkp.generate_credential();
+            assert!(x < zkp.q, "{name}");
+            assert_eq!((y1.clone(), y2.clone()), zkp.compute_pair(&x), "{name}");
+
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let c = zkp.issue_challenge();
+            let s = zkp.solve(&k, &c, &x);
+            assert!(zkp.finish(&r1, &r2, &y1, &y2, &c, &s), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_verify_batch_with_progress() {
+        let zkp = toy_zkp();
+
+        // every seventh proof is made for the wrong secret
+        let proofs: Vec<_> = (0..150)
+            .map(|i| {
+                let (x, y1, y2) = zkp.generate_credential();
+                let k = ZKP::generate_random_number_below(&zkp.q);
+                let (r1, r2) = zkp.compute_pair(&k);
+                let c = BigUint::from(3u32);
+                let secret = if i % 7 == 0 { &x + 1u32 } else { x };
+                let s = zkp.solve(&k, &c, &secret);
+                (y1, y2, Proof { r1, r2, c, s })
+            })
+            .collect();
+
+        let mut reports = Vec::new();
+        let results = zkp.verify_batch_with_progress(&proofs, |done, total| {
+            reports.push((done, total));
+        });
+        assert_eq!(reports, [(64, 150), (128, 150), (150, 150)]);
+        assert!(reports.windows(2).all(|w| w[0].0 < w[1].0));
+
+        let expected: Vec<_> = (0..150).map(|i| i % 7 != 0).collect();
+        assert_eq!(results, expected);
+        assert_eq!(zkp.verify_batch(&proofs), expected);
+
+        let mut reports = Vec::new();
+        zkp.verify_batch_with_progress(&[], |done, total| reports.push((done, total)));
+        assert_eq!(reports, [(0, 0)]);
+    }
+
+    #[test]
+    fn test_try_generate_random_number_below() {
+        /// An RNG without entropy, as on some sandboxed targets.
+        struct FailingRng;
+
+        impl RngCore for FailingRng {
+            fn next_u32(&mut self) -> u32 {
+                panic!("no entropy")
+            }
+            fn next_u64(&mut self) -> u64 {
+                panic!("no entropy")
+            }
+            fn fill_bytes(&mut self, _: &mut [u8]) {
+                panic!("no entropy")
+            }
+            fn try_fill_bytes(&mut self, _: &mut [u8]) -> std::result::Result<(), rand::Error> {
+                Err(rand::Error::new("no entropy"))
+            }
+        }
+
+        impl CryptoRng for FailingRng {}
+
+        let bound = BigUint::from(11u32);
+        let result = ZKP::try_generate_random_number_below_with(&bound, &mut FailingRng);
+        assert!(matches!(result, Err(ZkpError::RandomnessUnavailable(_))));
+        let result = ZKP::try_generate_random_number_below(&BigUint::from(0u32));
+        assert!(matches!(result, Err(ZkpError::OutOfRange(_))));
+
+        for (name, zkp) in built_in_groups() {
+            for _ in 0..20 {
+                let n = ZKP::try_generate_random_number_below(&zkp.q).unwrap();
+                assert!(n < zkp.q, "{name}");
+            }
+        }
+
+        // every value below a small bound comes up
+        let mut seen = [false; 11];
+        for _ in 0..1000 {
+            let n = ZKP::try_generate_random_number_below_with(&bound, &mut SecureRng::default());
+            seen[n.unwrap().to_u32_digits().first().copied().unwrap_or(0) as usize] = true;
+        }
+        assert!(seen.iter().all(|&x| x));
+    }
+
+    #[test]
+    fn test_deterministic_nonce() {
+        for (name, zkp) in built_in_groups() {
+            let x = ZKP::generate_random_number_below(&zkp.q);
+
+            let k = zkp.deterministic_nonce(&x, b"alice:1").unwrap();
+            assert_eq!(k, zkp.deterministic_nonce(&x, b"alice:1").unwrap(), "{name}");
+            assert!(k > BigUint::from(0u32) && k < zkp.q, "{name}");
+
+            // the toy group has only 10 nonces, collisions are expected there
+            if zkp.q > BigUint::from(u32::MAX) {
+                assert_ne!(k, zkp.deterministic_nonce(&x, b"alice:2").unwrap(), "{name}");
+                let other = zkp.deterministic_nonce(&(&x + 1u32), b"alice:1").unwrap();
+                assert_ne!(k, other, "{name}");
+            }
+
+            let c = zkp.issue_challenge();
+            let (y1, y2) = zkp.compute_pair(&x);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let s = zkp.solve(&k, &c, &x);
+            assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s), "{name}");
+        }
+
+        // no nonce is left in [1, q) for a degenerate q
+        let toy = toy_zkp();
+        for q in [0u32, 1] {
+            let zkp = ZKP::new(&toy.p, &BigUint::from(q), &toy.alpha, &toy.beta);
+            assert!(matches!(
+                zkp.deterministic_nonce(&BigUint::from(6u32), b"alice:1"),
+                Err(ZkpError::OutOfRange(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        let alpha = BigUint::from(4u32);
+        let beta = BigUint::from(9u32);
+        let p = BigUint::from(23u32);
+        let q = BigUint::from(11u32);
+        let zkp = ZKP::new(&p, &q, &alpha, &beta);
+
+        let x = BigUint::from(6u32);
+        let k = BigUint::from(7u32);
+        let c = BigUint::from(4u32);
+
+        let (y1, y2) = zkp.compute_pair(&x);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let s = zkp.solve(&k, &c, &x);
+
+        // Verify with an invalid c value
+        let invalid_c = BigUint::from(999u32); // A value that's expected to fail verification
+        let result = zkp.verify(&r1, &r2, &y1, &y2, &invalid_c, &s);
+        assert!(!result); // Expect the verification to fail
+    }
+
+    #[test]
+    fn test_constants_from_malformed_hex() {
+        assert_eq!(ZKP::try_get_constants().unwrap(), ZKP::get_constants());
+
+        let result = ZKP::constants_from_hex("B10B8F96", "F518AA87", "A4D1CBZZ", "266F");
+        let expected = hex::FromHexError::InvalidHexCharacter { c: 'Z', index: 6 };
+        assert_eq!(result, Err(ZkpError::InvalidEncoding(expected.to_string())));
 
-        // beta = alpha^i is also a generator
-        let beta = alpha.modpow(&ZKP::generate_random_number_below(&q), &p);
+        let result = ZKP::constants_from_hex("B10B8F9", "F518AA87", "A4D1CBD5", "266F");
+        let expected = hex::FromHexError::OddLength;
+        assert_eq!(result, Err(ZkpError::InvalidEncoding(expected.to_string())));
+    }
 
+    #[test]
+    fn test_issue_challenge_and_finish() {
+        let (alpha, beta, p, q) = ZKP::get_constants();
         let zkp = ZKP::new(&p, &q, &alpha, &beta);
 
         let x = ZKP::generate_random_number_below(&q);
         let k = ZKP::generate_random_number_below(&q);
+        let (y1, y2) = zkp.compute_pair(&x);
+        let (r1, r2) = zkp.compute_pair(&k);
 
-        let c = ZKP::generate_random_number_below(&q);
+        let c = zkp.issue_challenge();
+        assert!(c < q);
 
-        let (y1, y2) = zkp.compute_pair(&x);
+        let s = zkp.solve(&k, &c, &x);
+        assert!(zkp.finish(&r1, &r2, &y1, &y2, &c, &s));
+        assert_eq!(zkp.finish(&r1, &r2, &y1, &y2, &c, &s), zkp.verify(&r1, &r2, &y1, &y2, &c, &s));
 
-        let (r1, r2) = zkp.compute_pair(&k);
+        let s_fake = zkp.solve(&k, &c, &(x + 1u32));
+        assert!(!zkp.finish(&r1, &r2, &y1, &y2, &c, &s_fake));
+    }
+
+    #[test]
+    fn test_timing_budgets() {
+        let (alpha, beta, p, q) = ZKP::get_constants();
+        let zkp = ZKP::new(&p, &q, &alpha, &beta);
 
+        let x = ZKP::generate_random_number_below(&q);
+        let k = ZKP::generate_random_number_below(&q);
+        let c = ZKP::generate_random_number_below(&q);
+        let (y1, y2) = zkp.compute_pair(&x);
+        let (r1, r2) = zkp.compute_pair(&k);
         let s = zkp.solve(&k, &c, &x);
 
-        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s);
-        assert!(result);
+        assert_within_budget("compute_pair", 10, Duration::from_millis(500), || {
+            zkp.compute_pair(&k);
+        });
+        assert_within_budget("verify", 10, Duration::from_secs(1), || {
+            assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s));
+        });
     }
 
     #[test]
-    fn test_2048_bits_constants() {
-        //
-        //    Reference: https://www.rfc-editor.org/rfc/rfc5114#page-15
-        //
-        //    The hexadecimal value of the prime is:
-        //
-        //    p =  AD107E1E 9123A9D0 D660FAA7 9559C51F A20D64E5 683B9FD1
-        //         B54B1597 B61D0A75 E6FA141D F95A56DB AF9A3C40 7BA1DF15
-        //         EB3D688A 309C180E 1DE6B85A 1274A0A6 6D3F8152 AD6AC212
-        //         9037C9ED EFDA4DF8 D91E8FEF 55B7394B 7AD5B7D0 B6C12207
-        //         C9F98D11 ED34DBF6 C6BA0B2C 8BBC27BE 6A00E0A0 B9C49708
-        //         B3BF8A31 70918836 81286130 BC8985DB 1602E714 415D9330
-        //         278273C7 DE31EFDC 7310F712 1FD5A074 15987D9A DC0A486D
-        //         CDF93ACC 44328387 315D75E1 98C641A4 80CD86A1 B9E587E8
-        //         BE60E69C C928B2B9 C52172E4 13042E9B 23F10B0E 16E79763
-        //         C9B53DCF 4BA80A29 E3FB73C1 6B8E75B9 7EF363E2 FFA31F71
-        //         CF9DE538 4E71B81C 0AC4DFFE 0C10E64F
-        //
-        //    The hexadecimal value of the generator is:
-        //
-        //    g =  AC4032EF 4F2D9AE3 9DF30B5C 8FFDAC50 6CDEBE7B 89998CAF
-        //         74866A08 CFE4FFE3 A6824A4E 10B9A6F0 DD921F01 A70C4AFA
-        //         AB739D77 00C29F52 C57DB17C 620A8652 BE5E9001 A8D66AD7
-        //         C1766910 1999024A F4D02727 5AC1348B B8A762D0 521BC98A
-        //         E2471504 22EA1ED4 09939D54 DA7460CD B5F6C6B2 50717CBE
-        //         F180EB34 118E98D1 19529A45 D6F83456 6E3025E3 16A330EF
-        //         BB77A86F 0C1AB15B 051AE3D4 28C8F8AC B70A8137 150B8EEB
-        //         10E183ED D19963DD D9E263E4 770589EF 6AA21E7F 5F2FF381
-        //         B539CCE3 409D13CD 566AFBB4 8D6C0191 81E1BCFE 94B30269
-        //         EDFE72FE 9B6AA4BD 7B5A0F1C 71CFFF4C 19C418E1 F6EC0179
-        //         81BC087F 2A7065B3 84B890D3 191F2BFA
-        //
-        //    The generator generates a prime-order subgroup of size:
-        //
-        //    q =  801C0D34 C58D93FE 99717710 1F80535A 4738CEBC BF389A99
-        //         B36371EB
-        //
+    fn test_fixed_width_encoding() {
+        let zkp = ZKP::default();
 
-        #[rustfmt::skip]
-        let p = BigUint::from_bytes_be(&hex::decode(
-            "AD107E1E9123A9D0D660FAA79559C51FA20D64E5683B9FD1B54B1597B61D0A75E6F\
-             A141DF95A56DBAF9A3C407BA1DF15EB3D688A309C180E1DE6B85A1274A0A66D3F81\
-             52AD6AC2129037C9EDEFDA4DF8D91E8FEF55B7394B7AD5B7D0B6C12207C9F98D11E\
-             D34DBF6C6BA0B2C8BBC27BE6A00E0A0B9C49708B3BF8A317091883681286130BC89\
-             85DB1602E714415D9330278273C7DE31EFDC7310F7121FD5A07415987D9ADC0A486\
-             DCDF93ACC44328387315D75E198C641A480CD86A1B9E587E8BE60E69CC928B2B9C5\
-             2172E413042E9B23F10B0E16E79763C9B53DCF4BA80A29E3FB73C16B8E75B97EF36\
-             3E2FFA31F71CF9DE5384E71B81C0AC4DFFE0C10E64F").unwrap());
+        // an element with two high-order zero bytes
+        let mut bytes = vec![0u8; 128];
+        bytes[2] = 0x42;
+        bytes[127] = 0x01;
+        let value = BigUint::from_bytes_be(&bytes);
+        assert_eq!(value.to_bytes_be().len(), 126);
+
+        let encoded = zkp.encode_fixed(&value);
+        assert_eq!(encoded, bytes);
+        assert_eq!(zkp.decode_fixed(&encoded), Some(value));
+
+        let encoded = zkp.encode_fixed(&BigUint::from(0u32));
+        assert_eq!(encoded.len(), 128);
+        assert_eq!(zkp.decode_fixed(&encoded), Some(BigUint::from(0u32)));
+
+        // the length is checked
+        assert_eq!(zkp.decode_fixed(&encoded[1..]), None);
+        assert_eq!(zkp.decode_fixed(&[0u8; 129]), None);
+    }
+
+    #[test]
+    fn test_degenerate_beta() {
+        let alpha = BigUint::from(4u32);
+        let p = BigUint::from(23u32);
+        let q = BigUint::from(11u32);
+
+        assert_eq!(ZKP::beta_from_exponent(&alpha, &BigUint::from(0u32), &p), None);
+        assert_eq!(ZKP::beta_from_exponent(&alpha, &BigUint::from(1u32), &p), None);
+        // alpha has order q, so alpha^(q + 1) == alpha
+        assert_eq!(ZKP::beta_from_exponent(&alpha, &(&q + 1u32), &p), None);
+        assert_eq!(
+            ZKP::beta_from_exponent(&alpha, &BigUint::from(2u32), &p),
+            Some(BigUint::from(16u32))
+        );
+
+        for _ in 0..100 {
+            let beta = ZKP::generate_beta(&alpha, &p, &q);
+            assert!(!ZKP::new(&p, &q, &alpha, &beta).is_degenerate());
+        }
+
+        assert!(ZKP::new(&p, &q, &alpha, &alpha).is_degenerate());
+        assert!(ZKP::new(&p, &q, &alpha, &BigUint::from(1u32)).is_degenerate());
+        assert!(!ZKP::default().is_degenerate());
+    }
+
+    #[test]
+    fn test_with_new_beta() {
+        for (name, zkp) in built_in_groups() {
+            let other = zkp.with_new_beta();
+            assert_ne!(other.beta, zkp.beta, "{name}");
+            assert_eq!((&other.p, &other.q, &other.alpha), (&zkp.p, &zkp.q, &zkp.alpha), "{name}");
+            assert!(!other.is_degenerate(), "{name}");
+
+            let x = ZKP::generate_random_number_below(&zkp.q);
+            for instance in [&zkp, &other] {
+                let (y1, y2) = instance.compute_pair(&x);
+                let k = ZKP::generate_random_number_below(&instance.q);
+                let (r1, r2) = instance.compute_pair(&k);
+                let c = instance.issue_challenge();
+                let s = instance.solve(&k, &c, &x);
+                assert!(instance.verify(&r1, &r2, &y1, &y2, &c, &s), "{name}");
+            }
+
+            // y2 = beta^x depends on the generator, so public values do not carry over
+            if zkp.q.bits() > 64 {
+                assert_ne!(zkp.compute_pair(&x).1, other.compute_pair(&x).1, "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_nums_generators() {
+        for (name, zkp) in built_in_groups() {
+            let (alpha, beta) = ZKP::nums_generators(&zkp.p, &zkp.q, "zkp-cp test").unwrap();
+            assert_eq!(
+                ZKP::nums_generators(&zkp.p, &zkp.q, "zkp-cp test").unwrap(),
+                (alpha.clone(), beta.clone()),
+                "{name}"
+            );
+            for g in [&alpha, &beta] {
+                assert!(*g > BigUint::from(1u32) && *g < zkp.p, "{name}");
+                assert_eq!(g.modpow(&zkp.q, &zkp.p), BigUint::from(1u32), "{name}");
+            }
+
+            let nums = ZKP::new(&zkp.p, &zkp.q, &alpha, &beta);
+            assert!(!nums.is_degenerate(), "{name}");
+            let x = ZKP::generate_random_number_below(&nums.q);
+            let (y1, y2) = nums.compute_pair(&x);
+            let k = ZKP::generate_random_number_below(&nums.q);
+            let (r1, r2) = nums.compute_pair(&k);
+            let c = nums.issue_challenge();
+            let s = nums.solve(&k, &c, &x);
+            assert!(nums.verify(&r1, &r2, &y1, &y2, &c, &s), "{name}");
+
+            if zkp.q.bits() > 64 {
+                let other = ZKP::nums_generators(&zkp.p, &zkp.q, "zkp-cp other").unwrap();
+                assert_ne!(other, (alpha, beta), "{name}");
+            }
+        }
 
-        let q = BigUint::from_bytes_be(
-            &hex::decode("801C0D34C58D93FE997177101F80535A4738CEBCBF389A99B36371EB").unwrap(),
+        let zkp = toy_zkp();
+        assert!(matches!(
+            ZKP::nums_generators(&zkp.p, &BigUint::from(7u32), "seed"),
+            Err(ZkpError::OutOfRange(_))
+        ));
+
+        // the subgroup of order 2 has a single generator, so there is no beta
+        assert!(matches!(
+            ZKP::nums_generators(&zkp.p, &BigUint::from(2u32), "seed"),
+            Err(ZkpError::OutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn test_verifier_key_file() {
+        let zkp = toy_zkp();
+        let (y1, y2) = public_keys();
+        let path = std::env::temp_dir().join(format!("zkp-verifier-key-{}", std::process::id()));
+
+        VerifierKey { y1, y2 }.to_file(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "zkvk1:2:3\n");
+        let key = VerifierKey::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(zkp.verify_against(&key, &valid_proof()));
+        assert!(!zkp.verify_against(&key, &forged_proof()));
+
+        assert!(matches!(VerifierKey::from_file(&path), Err(ZkpError::Io(_))));
+        assert_eq!(
+            VerifierKey::decode("zkvk2:2:3"),
+            Err(ZkpError::UnsupportedVersion("zkvk2".to_string()))
         );
+        for malformed in ["zkvk1:2", "zkvk1:2:zz", "zkvk1:2:3:4", "zkvk1::3"] {
+            assert!(
+                matches!(VerifierKey::decode(malformed), Err(ZkpError::InvalidEncoding(_))),
+                "{malformed}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_proof_transport_string() {
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let c = zkp.issue_challenge();
+        let s = zkp.solve(&k, &c, &x);
+        let proof = Proof { r1, r2, c, s };
+
+        let encoded = proof.to_transport_string().unwrap();
+        assert!(encoded.starts_with("zkp1:"));
+        assert!(encoded[5..]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(Proof::from_transport_string(&encoded), Ok(proof.clone()));
+
+        let zero = BigUint::from(0u32);
+        let proof = Proof {
+            r1: zero.clone(),
+            r2: zero.clone(),
+            c: zero.clone(),
+            s: zero,
+        };
+        let encoded_zero = proof.to_transport_string().unwrap();
+        assert_eq!(Proof::from_transport_string(&encoded_zero), Ok(proof));
 
-        #[rustfmt::skip]
-        let alpha = BigUint::from_bytes_be(
-            &hex::decode(
-                "AC4032EF4F2D9AE39DF30B5C8FFDAC506CDEBE7B89998CAF74866A08CFE4FFE\
-                 3A6824A4E10B9A6F0DD921F01A70C4AFAAB739D7700C29F52C57DB17C620A86\
-                 52BE5E9001A8D66AD7C17669101999024AF4D027275AC1348BB8A762D0521BC\
-                 98AE247150422EA1ED409939D54DA7460CDB5F6C6B250717CBEF180EB34118E\
-                 98D119529A45D6F834566E3025E316A330EFBB77A86F0C1AB15B051AE3D428C\
-                 8F8ACB70A8137150B8EEB10E183EDD19963DDD9E263E4770589EF6AA21E7F5F\
-                 2FF381B539CCE3409D13CD566AFBB48D6C019181E1BCFE94B30269EDFE72FE9\
-                 B6AA4BD7B5A0F1C71CFFF4C19C418E1F6EC017981BC087F2A7065B384B890D3\
-                 191F2BFA").unwrap(),
+        let wrong_version = encoded.replacen("zkp1", "zkp2", 1);
+        assert_eq!(
+            Proof::from_transport_string(&wrong_version),
+            Err(ZkpError::UnsupportedVersion("zkp2".to_string()))
         );
+        assert!(matches!(
+            Proof::from_transport_string(&encoded[5..]),
+            Err(ZkpError::InvalidEncoding(_))
+        ));
+        assert!(matches!(
+            Proof::from_transport_string(&encoded[..encoded.len() - 4]),
+            Err(ZkpError::InvalidEncoding(_))
+        ));
+    }
 
-        // beta = alpha^i is also a generator
-        let beta = alpha.modpow(&ZKP::generate_random_number_below(&q), &p);
+    #[test]
+    fn test_public_keys_from_password() {
+        let zkp = ZKP::default();
+        let (y1, y2) = zkp.public_keys_from_password("correct horse", b"alice");
 
-        let zkp = ZKP {
-            p: p.clone(),
-            q: q.clone(),
-            alpha: alpha.clone(),
-            beta: beta.clone(),
+        let prove = |password: &str, salt: &[u8]| {
+            let x = zkp.derive_secret(password, salt);
+            assert!(x < zkp.q);
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let c = zkp.issue_challenge();
+            let s = zkp.solve(&k, &c, &x);
+            zkp.verify(&r1, &r2, &y1, &y2, &c, &s)
         };
 
-        let x = ZKP::generate_random_number_below(&q);
-        let k = ZKP::generate_random_number_below(&q);
+        assert!(prove("correct horse", b"alice"));
+        assert!(!prove("correct horse", b"bob"));
+        assert!(!prove("wrong horse", b"alice"));
+    }
 
-        let c = ZKP::generate_random_number_below(&q);
+    #[test]
+    fn test_registry_identifies_user() {
+        let zkp = ZKP::default();
+        let secrets: Vec<BigUint> = (0..3)
+            .map(|_| ZKP::generate_random_number_below(&zkp.q))
+            .collect();
+        let mut registry = Registry::default();
+        for (user, x) in ["alice", "bob", "carol"].iter().zip(&secrets) {
+            let (y1, y2) = zkp.compute_pair(x);
+            registry.register(user, y1, y2);
+        }
+
+        let prove = |x: &BigUint| {
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let c = zkp.issue_challenge();
+            let s = zkp.solve(&k, &c, x);
+            Proof { r1, r2, c, s }
+        };
+        let proof = prove(&secrets[1]);
+        assert_eq!(registry.identify(&zkp, &proof, None), Some("bob"));
+        assert_eq!(registry.identify(&zkp, &proof, Some("bob")), Some("bob"));
+        assert_eq!(registry.identify(&zkp, &proof, Some("alice")), None);
+        assert_eq!(registry.identify(&zkp, &proof, Some("mallory")), None);
+
+        let forged = prove(&(&secrets[1] + 1u32));
+        assert_eq!(registry.identify(&zkp, &forged, None), None);
+    }
+
+    #[test]
+    fn test_new_validated_rejects_zero_moduli() {
+        let zkp = toy_zkp();
+        let zero = BigUint::from(0u32);
+        for (p, q) in [(&zero, &zkp.q), (&zkp.p, &zero), (&zero, &zero)] {
+            assert!(matches!(
+                ZKP::new_validated(p, q, &zkp.alpha, &zkp.beta),
+                Err(ZkpError::OutOfRange(_))
+            ));
+        }
+        let validated = ZKP::new_validated(&zkp.p, &zkp.q, &zkp.alpha, &zkp.beta).unwrap();
+        assert!(validated.verify_proof(&public_keys().0, &public_keys().1, &valid_proof()));
+    }
 
+    #[test]
+    fn test_aggregate_proofs() {
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
         let (y1, y2) = zkp.compute_pair(&x);
-        let (r1, r2) = zkp.compute_pair(&k);
+        let prove = |x: &BigUint| {
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let c = zkp.issue_challenge();
+            let s = zkp.solve(&k, &c, x);
+            Proof { r1, r2, c, s }
+        };
+        let proofs: Vec<Proof> = (0..4).map(|_| prove(&x)).collect();
+
+        let aggregate = zkp.aggregate(&y1, &y2, &proofs);
+        assert!(zkp.verify_aggregate(&y1, &y2, &aggregate));
+        assert!(zkp.verify_aggregate(&y1, &y2, &zkp.aggregate(&y1, &y2, &proofs[..1])));
+        for proof in &proofs {
+            assert!(aggregate.includes(&proof.r1, &proof.r2, &proof.c));
+        }
+        assert!(!aggregate.includes(&proofs[0].r1, &proofs[0].r2, &proofs[1].c));
+
+        // one forged proof among valid ones
+        let mut forged = proofs.clone();
+        forged[2] = prove(&(&x + 1u32));
+        assert!(!zkp.verify_aggregate(&y1, &y2, &zkp.aggregate(&y1, &y2, &forged)));
+
+        // another credential, and altered aggregates
+        assert!(!zkp.verify_aggregate(&y2, &y1, &aggregate));
+        let mut altered = aggregate.clone();
+        altered.c.swap(0, 1);
+        assert!(!zkp.verify_aggregate(&y1, &y2, &altered));
+        let mut altered = aggregate.clone();
+        altered.s = (&altered.s + 1u32) % &zkp.q;
+        assert!(!zkp.verify_aggregate(&y1, &y2, &altered));
+        let mut altered = aggregate.clone();
+        altered.r1.pop();
+        assert!(!zkp.verify_aggregate(&y1, &y2, &altered));
+        assert!(!zkp.verify_aggregate(&y1, &y2, &zkp.aggregate(&y1, &y2, &[])));
+    }
+
+    #[test]
+    fn test_proofs_compare_by_value() {
+        let proof = valid_proof();
+        // the same proof with every number padded with two leading zero bytes
+        let mut padded = Vec::new();
+        for value in [&proof.r1, &proof.r2, &proof.c, &proof.s] {
+            let value = [vec![0, 0], value.to_bytes_be()].concat();
+            padded.extend_from_slice(&u16::try_from(value.len()).unwrap().to_be_bytes());
+            padded.extend_from_slice(&value);
+        }
+        assert_ne!(padded, proof.to_bytes().unwrap());
+
+        let decoded = Proof::from_bytes(&padded).unwrap();
+        assert_eq!(decoded, proof);
+        assert_eq!(decoded.canonical_bytes(), proof.canonical_bytes());
+        let unique: std::collections::HashSet<Proof> =
+            [proof.clone(), decoded].into_iter().collect();
+        assert_eq!(unique.len(), 1);
+        assert_ne!(forged_proof().canonical_bytes(), proof.canonical_bytes());
+
+        // a number too long for its length prefix is refused rather than truncated
+        let too_long = Proof {
+            s: BigUint::from(1u32) << (8 * usize::from(u16::MAX)),
+            ..proof
+        };
+        assert!(matches!(too_long.to_bytes(), Err(ZkpError::OutOfRange(_))));
+    }
+
+    #[test]
+    fn test_biguint_grouped() {
+        let value = BigUint::parse_bytes(b"123456789ABCDEF", 16).unwrap();
+        assert_eq!(format_biguint_grouped(&value, 4), "123 4567 89AB CDEF");
+        assert_eq!(format_biguint_grouped(&value, 5), "12345 6789A BCDEF");
+        assert_eq!(format_biguint_grouped(&value, 0), "123456789ABCDEF");
+        assert_eq!(format_biguint_grouped(&BigUint::from(0u32), 8), "0");
+
+        // the layout of RFC 5114, eight groups of eight digits per line
+        let p = format_biguint_grouped(&ZKP::from_group(Group::Rfc1024).p, 8);
+        assert_eq!(p.lines().count(), 4);
+        assert_eq!(
+            p.lines().next(),
+            Some("B10B8F96 A080E01D DE92DE5E AE5D54EC 52C99FBC FB06A3C6 9A6A9DCA 52D23B61")
+        );
+        assert!(p
+            .lines()
+            .all(|line| line.split(' ').all(|group| group.len() == 8)));
+    }
+
+    /// A `tracing` subscriber recording the names of the spans created.
+    #[cfg(feature = "profile")]
+    struct SpanRecorder {
+        names: std::sync::Arc<std::sync::Mutex<Vec<&'static str>>>,
+    }
+
+    #[cfg(feature = "profile")]
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut names = self.names.lock().unwrap();
+            names.push(span.metadata().name());
+            tracing::span::Id::from_u64(names.len() as u64)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
 
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, _: &tracing::Event<'_>) {}
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "profile")]
+    #[test]
+    fn test_profile_spans() {
+        let zkp = toy_zkp();
+        let (y1, y2) = public_keys();
+        for proof in [valid_proof(), forged_proof()] {
+            let expected = zkp.verify_proof(&y1, &y2, &proof);
+            let names = std::sync::Arc::default();
+            let recorder = SpanRecorder {
+                names: std::sync::Arc::clone(&names),
+            };
+            let valid =
+                tracing::subscriber::with_default(recorder, || zkp.verify_proof(&y1, &y2, &proof));
+            assert_eq!(valid, expected);
+            assert_eq!(
+                *names.lock().unwrap(),
+                [
+                    "verify",
+                    "alpha_pow_s",
+                    "y1_pow_c",
+                    "beta_pow_s",
+                    "y2_pow_c",
+                    "multiply"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_structure() {
+        let zkp = toy_zkp();
+        let proof = valid_proof();
+        assert_eq!(proof.validate_structure(&zkp), Ok(()));
+
+        type Malform = fn(&mut Proof, &ZKP);
+        let cases: [(&str, Malform); 6] = [
+            ("r1", |proof, _| proof.r1 = BigUint::from(0u32)),
+            ("r1", |proof, zkp| proof.r1 = zkp.p.clone()),
+            ("r2", |proof, _| proof.r2 = BigUint::from(0u32)),
+            ("r2", |proof, zkp| proof.r2 = zkp.p.clone()),
+            ("c", |proof, zkp| proof.c = zkp.q.clone()),
+            ("s", |proof, zkp| proof.s = zkp.q.clone()),
+        ];
+        for (name, malform) in cases {
+            let mut malformed = proof.clone();
+            malform(&mut malformed, &zkp);
+            match malformed.validate_structure(&zkp) {
+                Err(ZkpError::OutOfRange(reason)) => assert!(reason.starts_with(name), "{reason}"),
+                result => panic!("{name}: {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_possession_proof() {
+        let zkp = ZKP::default();
+        let (x, y1, y2) = zkp.generate_credential();
+        let proof = zkp.prove_possession(&x, &y1, &y2, "alice");
+        assert!(zkp.verify_possession(&y1, &y2, "alice", &proof));
+        assert!(!zkp.verify_possession(&y1, &y2, "bob", &proof));
+
+        // values copied from alice cannot be registered by someone who does not know x
+        let (other, _, _) = zkp.generate_credential();
+        let forged = zkp.prove_possession(&other, &y1, &y2, "mallory");
+        assert!(!zkp.verify_possession(&y1, &y2, "mallory", &forged));
+
+        // a labeled credential's proof is bound to the label as well
+        let proof = zkp.prove_credential_possession(&x, &y1, &y2, "alice", "token");
+        assert!(zkp.verify_credential_possession(&y1, &y2, "alice", "token", &proof));
+        assert!(!zkp.verify_credential_possession(&y1, &y2, "alice", "yubikey", &proof));
+        assert!(!zkp.verify_credential_possession(&y1, &y2, "bob", "token", &proof));
+        assert!(!zkp.verify_possession(&y1, &y2, "alice", &proof));
+    }
+
+    #[test]
+    fn test_challenge_bits() {
+        let zkp = ZKP::default();
+        for _ in 0..100 {
+            assert!(zkp.issue_challenge_bits(8) < BigUint::from(256u32));
+        }
+        // q bounds the challenge whatever the bit length
+        let toy = toy_zkp();
+        for _ in 0..100 {
+            assert!(toy.issue_challenge_bits(64) < toy.q);
+            assert!(toy.issue_challenge_bits(u64::MAX) < toy.q);
+        }
+    }
+
+    #[test]
+    fn test_digest_challenge() {
+        let zkp = ZKP::default();
+        let digest = Sha256::digest(b"transcript of another verifier");
+        let c = zkp.challenge_from_digest(&digest);
+        assert_eq!(zkp.challenge_from_digest(&digest), c);
+        assert_eq!(c, BigUint::from_bytes_be(&digest) % &zkp.q);
+        assert!(c < zkp.q);
+
+        let (x, y1, y2) = zkp.generate_credential();
+        let k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
         let s = zkp.solve(&k, &c, &x);
+        assert!(zkp.verify_with_digest_challenge(&r1, &r2, &y1, &y2, &digest, &s));
+        let other = Sha256::digest(b"another transcript");
+        assert!(!zkp.verify_with_digest_challenge(&r1, &r2, &y1, &y2, &other, &s));
+    }
 
-        let result = zkp.verify(&r1, &r2, &y1, &y2, &c, &s);
-        assert!(result);
+    #[test]
+    fn test_self_test() {
+        assert_eq!(ZKP::self_test(), Ok(()));
+
+        // stand-ins for broken arithmetic: the same protocol computing the wrong values
+        let n = BigUint::from;
+        let wrong_beta = ZKP::new(&n(23u32), &n(11u32), &n(4u32), &n(13u32));
+        match wrong_beta.known_answer_test() {
+            Err(ZkpError::SelfTestFailed(check)) => assert_eq!(check, "y2 is 6, expected 3"),
+            other => panic!("broken arithmetic passed the self-test: {:?}", other),
+        }
+        let wrong_modulus = ZKP::new(&n(29u32), &n(11u32), &n(4u32), &n(9u32));
+        assert!(matches!(wrong_modulus.known_answer_test(), Err(ZkpError::SelfTestFailed(_))));
     }
 
     #[test]
-    fn test_invalid_inputs() {
-        let alpha = BigUint::from(4u32);
-        let beta = BigUint::from(9u32);
-        let p = BigUint::from(23u32);
-        let q = BigUint::from(11u32);
-        let zkp = ZKP::new(&p, &q, &alpha, &beta);
+    fn test_endianness() {
+        let zkp = ZKP::default();
+        let (y1, _) = zkp.compute_pair(&BigUint::from(fixtures::SECRET));
+        let mut little = y1.to_bytes_be();
+        little.reverse();
+        assert_eq!(Endianness::Little.decode(&little), y1);
+        assert_eq!(Endianness::Little.encode(&y1), little);
+        assert_eq!(Endianness::Big.decode(&y1.to_bytes_be()), y1);
 
-        let x = BigUint::from(6u32);
-        let k = BigUint::from(7u32);
-        let c = BigUint::from(4u32);
+        let small = BigUint::from(0x0102u32);
+        let big = zkp.encode_fixed_with(&small, Endianness::Big);
+        assert_eq!(big, zkp.encode_fixed(&small));
+        assert_eq!(&big[big.len() - 2..], [1, 2]);
+        let little = zkp.encode_fixed_with(&small, Endianness::Little);
+        assert_eq!(little.len(), zkp.p_byte_len());
+        assert_eq!(&little[..2], [2, 1]);
+        assert!(little[2..].iter().all(|byte| *byte == 0));
+
+        for endianness in [Endianness::Big, Endianness::Little] {
+            for value in [&small, &y1, &BigUint::from(0u32)] {
+                let encoded = zkp.encode_fixed_with(value, endianness);
+                assert_eq!(zkp.decode_fixed_with(&encoded, endianness).as_ref(), Some(value));
+            }
+            assert_eq!(zkp.decode_fixed_with(&[1, 2], endianness), None);
+        }
+    }
+
+    #[test]
+    fn test_incremental_verifier() {
+        let zkp = toy_zkp();
+        let (y1, y2) = public_keys();
+        let proof = valid_proof();
+        let c = BigUint::from(fixtures::CHALLENGE);
+
+        // the commitment arrives, the challenge is issued, then the response arrives
+        let verifier =
+            IncrementalVerifier::with_challenge(&zkp, (&y1, &y2), (&proof.r1, &proof.r2), c);
+        assert_eq!(verifier.challenge(), &proof.c);
+        assert!(verifier.finalize(&proof.s));
+        let verifier = IncrementalVerifier::with_challenge(
+            &zkp,
+            (&y1, &y2),
+            (&proof.r1, &proof.r2),
+            proof.c.clone(),
+        );
+        assert!(!verifier.finalize(&forged_proof().s));
+
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+        let k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let verifier = IncrementalVerifier::new(&zkp, (&y1, &y2), (&r1, &r2));
+        assert!(verifier.challenge() < &zkp.q);
+        let s = zkp.solve(&k, verifier.challenge(), &x);
+        assert!(verifier.finalize(&s));
+    }
+
+    #[test]
+    fn test_reconstruct_commitment() {
+        let zkp = toy_zkp();
+        let (y1, y2) = public_keys();
+        let proof = valid_proof();
+        let reconstructed = zkp.reconstruct_commitment(&y1, &y2, &proof.c, &proof.s);
+        assert_eq!(reconstructed, (proof.r1.clone(), proof.r2.clone()));
+
+        // a response computed with the wrong secret points to another commitment
+        let forged = forged_proof();
+        let reconstructed = zkp.reconstruct_commitment(&y1, &y2, &forged.c, &forged.s);
+        assert_ne!(reconstructed, (forged.r1, forged.r2));
 
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
         let (y1, y2) = zkp.compute_pair(&x);
+        let k = ZKP::generate_random_number_below(&zkp.q);
         let (r1, r2) = zkp.compute_pair(&k);
+        let c = zkp.issue_challenge();
         let s = zkp.solve(&k, &c, &x);
+        assert_eq!(zkp.reconstruct_commitment(&y1, &y2, &c, &s), (r1, r2));
+    }
 
-        // Verify with an invalid c value
-        let invalid_c = BigUint::from(999u32); // A value that's expected to fail verification
-        let result = zkp.verify(&r1, &r2, &y1, &y2, &invalid_c, &s);
-        assert!(!result); // Expect the verification to fail
+    #[test]
+    fn test_zkp_ref_matches_zkp() {
+        for (name, zkp) in built_in_groups() {
+            let zkp_ref: ZkpRef<'_> = zkp.params();
+            let x = ZKP::generate_random_number_below(&zkp.q);
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let c = zkp.issue_challenge();
+
+            let (y1, y2) = zkp_ref.compute_pair(&x);
+            assert_eq!((y1.clone(), y2.clone()), zkp.compute_pair(&x), "{name}");
+            let (r1, r2) = zkp_ref.compute_pair(&k);
+            let s = zkp_ref.solve(&k, &c, &x);
+            assert_eq!(s, zkp.solve(&k, &c, &x), "{name}");
+            // k < c * x takes the other branch of solve
+            assert_eq!(zkp_ref.solve(&0u32.into(), &c, &x), zkp.solve(&0u32.into(), &c, &x));
+
+            for s in [s.clone(), (&s + 1u32) % &zkp.q, &s + &zkp.q] {
+                assert_eq!(
+                    zkp_ref.verify(&r1, &r2, &y1, &y2, &c, &s),
+                    zkp.verify(&r1, &r2, &y1, &y2, &c, &s),
+                    "ZkpRef and ZKP disagree for {name}"
+                );
+            }
+            assert!(zkp_ref.verify(&r1, &r2, &y1, &y2, &c, &s), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_identify_group() {
+        let (alpha, beta, p, q) = ZKP::get_constants();
+        let zkp = ZKP::new(&p, &q, &alpha, &beta);
+        assert_eq!(zkp.identify_group(), Some(KnownGroup::Rfc5114_1024));
+        assert_eq!(
+            ZKP::from_group(Group::Rfc2048).identify_group(),
+            Some(KnownGroup::Rfc5114_2048)
+        );
+        assert_eq!(crate::fixtures::toy_zkp().identify_group(), Some(KnownGroup::Toy));
+
+        // the MODP groups of RFC 3526, generated by 2
+        for (known, p) in RFC3526_PRIMES {
+            let p = BigUint::from_bytes_be(&hex::decode(p).unwrap());
+            assert_eq!(p.bits(), known.name()[8..].parse::<u64>().unwrap());
+            let q = (&p - 1u32) >> 1;
+            let two = BigUint::from(2u32);
+            assert_eq!(
+                ZKP::new(&p, &q, &two, &two.modpow(&5u32.into(), &p)).identify_group(),
+                Some(known)
+            );
+            assert_eq!(ZKP::new(&p, &q, &BigUint::from(3u32), &two).identify_group(), None);
+        }
+
+        // another beta is the same group, anything else is custom
+        let other_beta = ZKP::new(&p, &q, &alpha, &alpha.modpow(&3u32.into(), &p));
+        assert_eq!(other_beta.identify_group(), Some(KnownGroup::Rfc5114_1024));
+        assert_eq!(ZKP::new(&p, &q, &(&alpha + 1u32), &beta).identify_group(), None);
+        let random_p = ZKP::generate_random_number_below(&p) | BigUint::from(1u32);
+        assert_eq!(ZKP::new(&random_p, &q, &alpha, &beta).identify_group(), None);
+        assert_eq!(ZKP::new(&(&p + 2u32), &q, &alpha, &beta).identify_group(), None);
+    }
+
+    #[test]
+    fn test_random_challenge_is_uniform() {
+        // the chi-squared statistic of 1000 challenges per bucket, `bucket` mapping a challenge
+        // to one of `buckets` equal ranges of [0, q)
+        fn chi_squared(zkp: &ZKP, buckets: u32, bucket: impl Fn(&BigUint) -> usize) -> f64 {
+            let mut counts = vec![0u32; buckets as usize];
+            for _ in 0..1000 * buckets {
+                let c = zkp.random_challenge();
+                assert!(c < zkp.q);
+                counts[bucket(&c)] += 1;
+            }
+            counts
+                .iter()
+                .map(|&n| (n as f64 - 1000.0).powi(2) / 1000.0)
+                .sum()
+        }
+        let index = |n: BigUint| n.to_u64_digits().first().copied().unwrap_or(0) as usize;
+
+        // every challenge of the toy group, q = 11: 10 degrees of freedom, the statistic exceeds
+        // 45 with probability about 2e-6
+        let toy = toy_zkp();
+        let statistic = chi_squared(&toy, 11, |c| index(c.clone()));
+        assert!(statistic < 45.0, "chi-squared {} over the 11 challenges", statistic);
+
+        // 16 ranges of the 2048-bit group's q: 15 degrees of freedom, the statistic exceeds 55
+        // with probability about 2e-6
+        let zkp = ZKP::from_group(Group::Rfc2048);
+        let statistic = chi_squared(&zkp, 16, |c| index((c << 4u32) / &zkp.q));
+        assert!(statistic < 55.0, "chi-squared {} over 16 ranges of q", statistic);
     }
-}
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_verify_proof_file() {
+        let zkp = ZKP::default();
+        let (x, y1, y2) = zkp.generate_credential();
+        let prove = |x: &BigUint| {
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let c = zkp.random_challenge();
+            let s = zkp.solve(&k, &c, x);
+            Proof { r1, r2, c, s }
+        };
+        // proofs 1 and 4 are made with another secret, proof 3 is tampered with
+        let mut entries: Vec<ProofFileEntry> = (0..6)
+            .map(|i| ProofFileEntry {
+                y1: y1.clone(),
+                y2: y2.clone(),
+                proof: prove(&if i == 1 || i == 4 {
+                    &x + 1u32
+                } else {
+                    x.clone()
+                }),
+            })
+            .collect();
+        entries[3].proof.s += 1u32;
+
+        let path = std::env::temp_dir().join(format!("zkp-proof-file-{}", std::process::id()));
+        std::fs::write(&path, serde_json::to_vec(&entries).unwrap()).unwrap();
+        let report = zkp.verify_proof_file(&path).unwrap();
+        assert_eq!(
+            report,
+            BatchReport {
+                valid: 3,
+                invalid: 3,
+                failing: vec![1, 3, 4],
+            }
+        );
+        assert_eq!(report.total(), 6);
+        assert!(!report.all_valid());
+
+        std::fs::write(&path, serde_json::to_vec(&entries[..1]).unwrap()).unwrap();
+        assert!(zkp.verify_proof_file(&path).unwrap().all_valid());
+        std::fs::write(&path, "[]").unwrap();
+        assert_eq!(zkp.verify_proof_file(&path).unwrap(), BatchReport::default());
+        std::fs::write(&path, "{\"not\": \"a batch\"}").unwrap();
+        assert!(matches!(zkp.verify_proof_file(&path), Err(ZkpError::InvalidEncoding(_))));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(zkp.verify_proof_file(&path), Err(ZkpError::Io(_))));
+    }
+
+    #[test]
+    fn test_verify_with_external_powers() {
+        // the powers an HSM holding the public values would return
+        let powers = |zkp: &ZKP, y1: &BigUint, y2: &BigUint, c: &BigUint, s: &BigUint| {
+            let p = &zkp.p;
+            (zkp.alpha.modpow(s, p), zkp.beta.modpow(s, p), y1.modpow(c, p), y2.modpow(c, p))
+        };
+
+        let zkp = toy_zkp();
+        let (y1, y2) = crate::fixtures::public_keys();
+        let proof = valid_proof();
+        let (alpha_s, beta_s, y1_c, y2_c) = powers(&zkp, &y1, &y2, &proof.c, &proof.s);
+        assert!(
+            zkp.verify_with_external_powers(&proof.r1, &proof.r2, &alpha_s, &beta_s, &y1_c, &y2_c)
+        );
+
+        for zkp in [ZKP::default(), ZKP::from_group(Group::Rfc2048)] {
+            let (x, y1, y2) = zkp.generate_credential();
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let c = zkp.random_challenge();
+            for s in [zkp.solve(&k, &c, &x), zkp.solve(&k, &c, &(&x + 1u32))] {
+                let (alpha_s, beta_s, y1_c, y2_c) = powers(&zkp, &y1, &y2, &c, &s);
+                assert_eq!(
+                    zkp.verify_with_external_powers(&r1, &r2, &alpha_s, &beta_s, &y1_c, &y2_c),
+                    zkp.verify(&r1, &r2, &y1, &y2, &c, &s)
+                );
+            }
+
+            // the powers are trusted as given: see the trust boundary of the method
+            let one = BigUint::from(1u32);
+            assert!(zkp.verify_with_external_powers(&r1, &r2, &r1, &r2, &one, &one));
+            let r1_plus_p = &r1 + &zkp.p;
+            assert!(!zkp.verify_with_external_powers(&r1_plus_p, &r2, &r1_plus_p, &r2, &one, &one));
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! A load generator measuring the throughput and the latencies of a server, over the network.
+//!
+//! `run` starts concurrent clients, each with its own connection: every client registers a
+//! user of its own with a random secret, then authenticates it round after round (a challenge
+//! and an answer). Secrets are random rather than derived from a password, so the time measured
+//! is the network's and the server's, not the KDF's.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use tonic::transport::{Channel, Error};
+use tonic::Status;
+
+use crate::channel;
+use crate::pb::{
+    auth_client::AuthClient, AuthenticationAnswerRequest, AuthenticationChallengeRequest,
+    RegisterRequest,
+};
+use crate::{Endianness, PROTOCOL_VERSION, ZKP};
+
+/// The results of a load test.
+#[derive(Debug, Clone)]
+pub struct LoadReport {
+    /// The number of requests the server answered successfully.
+    pub requests: usize,
+    /// The number of requests that failed, counted apart from the latencies.
+    pub errors: usize,
+    /// The wall-clock time of the whole test.
+    pub elapsed: Duration,
+    /// The latency of each successful request, sorted.
+    latencies: Vec<Duration>,
+}
+
+impl LoadReport {
+    /// Returns the number of successful requests per second.
+    pub fn requests_per_sec(&self) -> f64 {
+        self.requests as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Returns the latency below which `percent` percent of the successful requests completed,
+    /// or zero if none did.
+    pub fn percentile(&self, percent: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (percent / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} requests ({} failed) in {:.2?}, {:.1} requests/s",
+            self.requests,
+            self.errors,
+            self.elapsed,
+            self.requests_per_sec()
+        )?;
+        write!(
+            f,
+            "latency p50 {:.2?}  p90 {:.2?}  p99 {:.2?}  max {:.2?}",
+            self.percentile(50.0),
+            self.percentile(90.0),
+            self.percentile(99.0),
+            self.percentile(100.0)
+        )
+    }
+}
+
+/// Runs `clients` concurrent clients against the server at `endpoint`, each authenticating
+/// `rounds` times after registering.
+///
+/// # Arguments
+///
+/// * `endpoint` - The server endpoint, see `channel::connect`.
+/// * `zkp` - The group of the server.
+/// * `endianness` - The byte order of the server.
+/// * `clients` - The number of concurrent clients, each with its own connection.
+/// * `rounds` - The number of authentications of each client.
+///
+/// # Returns
+///
+/// A `Result` containing either the `LoadReport`, or the transport error of the first client
+/// that could not connect.
+pub async fn run(
+    endpoint: &str, zkp: &ZKP, endianness: Endianness, clients: usize, rounds: usize,
+) -> Result<LoadReport, Error> {
+    let mut connections = Vec::with_capacity(clients);
+    for _ in 0..clients {
+        connections.push(AuthClient::new(channel::connect(endpoint).await?));
+    }
+
+    // users of earlier runs against the same server are left alone
+    let run_id = ZKP::generate_random_string(8);
+    let start = Instant::now();
+    let handles: Vec<_> = connections
+        .into_iter()
+        .enumerate()
+        .map(|(i, client)| {
+            let user = format!("loadtest-{}-{}", run_id, i);
+            tokio::spawn(client_loop(client, zkp.clone(), endianness, user, rounds))
+        })
+        .collect();
+
+    let mut total = Tally::default();
+    for handle in handles {
+        let tally = handle.await.expect("load test client panicked");
+        total.latencies.extend(tally.latencies);
+        total.errors += tally.errors;
+    }
+    let elapsed = start.elapsed();
+
+    total.latencies.sort();
+    Ok(LoadReport {
+        requests: total.latencies.len(),
+        errors: total.errors,
+        elapsed,
+        latencies: total.latencies,
+    })
+}
+
+/// The latencies of the successful requests of a client, and the number of failed ones.
+#[derive(Debug, Default)]
+struct Tally {
+    latencies: Vec<Duration>,
+    errors: usize,
+}
+
+impl Tally {
+    /// Records a request started at `start` that ended with `result`.
+    fn record<T>(&mut self, start: Instant, result: &Result<T, Status>) {
+        match result {
+            Ok(_) => self.latencies.push(start.elapsed()),
+            Err(_) => self.errors += 1,
+        }
+    }
+}
+
+/// Registers `user`, then authenticates it `rounds` times.
+async fn client_loop(
+    mut client: AuthClient<Channel>, zkp: ZKP, endianness: Endianness, user: String, rounds: usize,
+) -> Tally {
+    let mut tally = Tally::default();
+
+    let (x, y1, y2) = zkp.generate_credential();
+    let request = RegisterRequest {
+        user: user.clone(),
+        y1: zkp.encode_fixed_with(&y1, endianness),
+        y2: zkp.encode_fixed_with(&y2, endianness),
+        protocol_version: PROTOCOL_VERSION,
+        possession_proof: zkp
+            .prove_possession(&x, &y1, &y2, &user)
+            .to_bytes()
+            .expect("the numbers of a proof fit the encoding"),
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let result = client.register(request).await;
+    tally.record(start, &result);
+    if result.is_err() {
+        // every authentication of an unregistered user fails
+        tally.errors += 2 * rounds;
+        return tally;
+    }
+
+    for _ in 0..rounds {
+        let k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let request = AuthenticationChallengeRequest {
+            user: user.clone(),
+            r1: zkp.encode_fixed_with(&r1, endianness),
+            r2: zkp.encode_fixed_with(&r2, endianness),
+            protocol_version: PROTOCOL_VERSION,
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let result = client.create_authentication_challenge(request).await;
+        tally.record(start, &result);
+        let Ok(response) = result else {
+            // the answer is not sent
+            tally.errors += 1;
+            continue;
+        };
+
+        let response = response.into_inner();
+        let c = endianness.decode(&response.c);
+        let request = AuthenticationAnswerRequest {
+            auth_id: response.auth_id,
+            s: endianness.encode(&zkp.solve(&k, &c, &x)),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let result = client.verify_authentication(request).await;
+        tally.record(start, &result);
+    }
+    tally
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn report(latencies_ms: &[u64]) -> LoadReport {
+        LoadReport {
+            requests: latencies_ms.len(),
+            errors: 0,
+            elapsed: Duration::from_secs(2),
+            latencies: latencies_ms
+                .iter()
+                .map(|ms| Duration::from_millis(*ms))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let report = report(&(1..=100).collect::<Vec<_>>());
+        assert_eq!(report.requests_per_sec(), 50.0);
+        assert_eq!(report.percentile(50.0), Duration::from_millis(50));
+        assert_eq!(report.percentile(99.0), Duration::from_millis(99));
+        assert_eq!(report.percentile(100.0), Duration::from_millis(100));
+        assert_eq!(report.percentile(0.0), Duration::from_millis(1));
+        assert_eq!(self::report(&[]).percentile(50.0), Duration::ZERO);
+    }
+}
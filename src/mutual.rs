@@ -0,0 +1,209 @@
+//! Mutual authentication: the client proves knowledge of its secret to the server, and the
+//! server proves knowledge of its own to the client, so a rogue server is caught as well.
+//!
+//! Each side runs both halves of the protocol at once: a `prover::Session` for its own secret,
+//! and a `Challenger` for the registered public values of the other side (the client's `(y1, y2)`
+//! for the server, the server's pinned `(y1, y2)` for the client). The sides exchange their
+//! commitments, then their challenges, then their answers:
+//!
+//! ```text
+//! client                                  server
+//!   commitment (r1, r2)        ------->
+//!                              <-------   commitment (r1, r2), challenge c
+//!   challenge c, answer s      ------->
+//!                              <-------   answer s
+//! ```
+//!
+//! Each side only answers once it has sent its own challenge, so neither can choose its
+//! challenge after seeing the other's answer.
+//!
+//! ```
+//! use zkp_chaum_pedersen::{mutual::authenticate_mutually, ZKP};
+//!
+//! let zkp = ZKP::default();
+//! let (client_x, client_y1, client_y2) = zkp.generate_credential();
+//! let (server_x, server_y1, server_y2) = zkp.generate_credential();
+//!
+//! let outcome = authenticate_mutually(
+//!     &zkp,
+//!     (client_x, (&server_y1, &server_y2)),
+//!     (server_x, (&client_y1, &client_y2)),
+//! );
+//! assert!(outcome.is_mutual());
+//! ```
+
+use num_bigint::BigUint;
+
+use crate::prover::Session;
+use crate::ZKP;
+
+/// The verifier side of one run of the protocol, challenging the commitment of a prover whose
+/// public values are `(y1, y2)`.
+///
+/// The challenge is sampled when the commitment is received, and the answer is checked by
+/// `verify`, which consumes the challenger so a challenge is answered once.
+#[derive(Debug)]
+pub struct Challenger<'a> {
+    zkp: &'a ZKP,
+    y1: &'a BigUint,
+    y2: &'a BigUint,
+    r1: BigUint,
+    r2: BigUint,
+    c: BigUint,
+}
+
+impl<'a> Challenger<'a> {
+    /// Receives the commitment `(r1, r2)` of the prover of `(y1, y2)`, and samples the
+    /// challenge, see `ZKP::random_challenge`.
+    pub fn new(
+        zkp: &'a ZKP, (y1, y2): (&'a BigUint, &'a BigUint), r1: &BigUint, r2: &BigUint,
+    ) -> Self {
+        Self {
+            zkp,
+            y1,
+            y2,
+            r1: r1.clone(),
+            r2: r2.clone(),
+            c: zkp.random_challenge(),
+        }
+    }
+
+    /// Returns the challenge `c` to send to the prover.
+    pub fn challenge(&self) -> &BigUint {
+        &self.c
+    }
+
+    /// Checks the prover's answer `s`, see `ZKP::verify`.
+    pub fn verify(self, s: &BigUint) -> bool {
+        self.zkp
+            .verify(&self.r1, &self.r2, self.y1, self.y2, &self.c, s)
+    }
+}
+
+/// The outcome of `authenticate_mutually`, as seen by each side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MutualOutcome {
+    /// Whether the server accepted the client's proof.
+    pub client_authenticated: bool,
+    /// Whether the client accepted the server's proof.
+    pub server_authenticated: bool,
+}
+
+impl MutualOutcome {
+    /// Returns whether both sides authenticated each other.
+    pub fn is_mutual(&self) -> bool {
+        self.client_authenticated && self.server_authenticated
+    }
+}
+
+/// Runs both sides of a mutual authentication in memory, in the order of the module
+/// documentation.
+///
+/// # Arguments
+///
+/// * `zkp` - The group both sides registered their public values in.
+/// * `client` - The client's secret, and the public values it pinned for the server.
+/// * `server` - The server's secret, and the public values the client registered with it.
+///
+/// # Returns
+///
+/// A `MutualOutcome` telling which proofs were accepted.
+pub fn authenticate_mutually(
+    zkp: &ZKP, client: (BigUint, (&BigUint, &BigUint)), server: (BigUint, (&BigUint, &BigUint)),
+) -> MutualOutcome {
+    let (client_x, server_public) = client;
+    let (server_x, client_public) = server;
+
+    // client -> server: the client's commitment
+    let client_session = Session::new(zkp, client_x).commit();
+    let (r1, r2) = client_session.commitment();
+    let server_challenger = Challenger::new(zkp, client_public, r1, r2);
+
+    // server -> client: the server's commitment and its challenge
+    let server_session = Session::new(zkp, server_x).commit();
+    let (r1, r2) = server_session.commitment();
+    let client_challenger = Challenger::new(zkp, server_public, r1, r2);
+
+    // client -> server: the client's challenge and its answer
+    let client_s = client_session
+        .respond(server_challenger.challenge())
+        .response()
+        .clone();
+    let client_authenticated = server_challenger.verify(&client_s);
+
+    // server -> client: the server's answer
+    let server_s = server_session
+        .respond(client_challenger.challenge())
+        .response()
+        .clone();
+    let server_authenticated = client_challenger.verify(&server_s);
+
+    MutualOutcome {
+        client_authenticated,
+        server_authenticated,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::{self, toy_zkp, valid_proof};
+
+    #[test]
+    fn test_challenger_checks_fixture_answer() {
+        let zkp = toy_zkp();
+        let (y1, y2) = fixtures::public_keys();
+        let proof = valid_proof();
+        let challenger = Challenger::new(&zkp, (&y1, &y2), &proof.r1, &proof.r2);
+        assert!(challenger.challenge() < &zkp.q);
+
+        let session = Session::new(&zkp, BigUint::from(fixtures::SECRET))
+            .commit_with_nonce(BigUint::from(fixtures::NONCE))
+            .respond(challenger.challenge());
+        assert!(challenger.verify(session.response()));
+    }
+
+    #[test]
+    fn test_mutual_authentication() {
+        let zkp = ZKP::default();
+        let (client_x, client_y1, client_y2) = zkp.generate_credential();
+        let (server_x, server_y1, server_y2) = zkp.generate_credential();
+        let client_public = (&client_y1, &client_y2);
+        let server_public = (&server_y1, &server_y2);
+
+        let outcome = authenticate_mutually(
+            &zkp,
+            (client_x.clone(), server_public),
+            (server_x.clone(), client_public),
+        );
+        assert!(outcome.is_mutual());
+
+        // a client without the registered secret
+        let outcome = authenticate_mutually(
+            &zkp,
+            (&client_x + 1u32, server_public),
+            (server_x.clone(), client_public),
+        );
+        assert_eq!(
+            outcome,
+            MutualOutcome {
+                client_authenticated: false,
+                server_authenticated: true,
+            }
+        );
+        assert!(!outcome.is_mutual());
+
+        // a rogue server, without the secret of the pinned public values
+        let (rogue_x, _, _) = zkp.generate_credential();
+        let outcome =
+            authenticate_mutually(&zkp, (client_x, server_public), (rogue_x, client_public));
+        assert_eq!(
+            outcome,
+            MutualOutcome {
+                client_authenticated: true,
+                server_authenticated: false,
+            }
+        );
+        assert!(!outcome.is_mutual());
+    }
+}
@@ -0,0 +1,104 @@
+//! OpenTelemetry traces of the server's gRPC requests, exported over OTLP.
+//!
+//! `init` installs a `tracing` subscriber forwarding spans to an OTLP collector, and
+//! `request_span` opens the span of each request, passed to tonic's `Server::trace_fn`. A client
+//! sending a W3C `traceparent` header in its gRPC metadata gets the request's span, and the spans
+//! opened inside it (e.g. those of the `profile` feature), in its own trace.
+
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{TraceError, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use tonic::codegen::http::{HeaderMap, Request};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+
+/// The `service.name` the server's spans are exported with.
+pub const SERVICE_NAME: &str = "zkp-server";
+
+/// Exports the spans of the process to the OTLP collector at `endpoint`, e.g.
+/// `http://localhost:4317`, over gRPC.
+///
+/// # Returns
+///
+/// A `Result` containing either the provider, to `shutdown` before exiting so the last spans are
+/// flushed, or the error of the exporter or of installing the subscriber.
+pub fn init(endpoint: &str) -> Result<TracerProvider, TraceError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]))
+        .build();
+
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+        .map_err(|e| TraceError::Other(e.into()))?;
+    Ok(provider)
+}
+
+/// Opens the span of a gRPC request, named after its method.
+///
+/// The trace context of the request's `traceparent` header, if any, becomes the parent of the
+/// span; otherwise the span starts a new trace.
+pub fn request_span(request: &Request<()>) -> tracing::Span {
+    let span = tracing::info_span!("grpc_request", method = request.uri().path());
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(request.headers()));
+    span.set_parent(parent);
+    span
+}
+
+/// Reads the trace context from the headers of a request, i.e. its gRPC metadata.
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use opentelemetry::trace::{TraceContextExt, TraceId};
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+    /// Returns the trace ID of the span of `request`.
+    fn trace_id(request: &Request<()>) -> TraceId {
+        let provider = TracerProvider::builder().build();
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("test"));
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let span = request_span(request);
+            span.context().span().span_context().trace_id()
+        })
+    }
+
+    #[test]
+    fn test_spans_carry_the_incoming_trace_id() {
+        let request = Request::builder()
+            .uri("/zkp_auth.Auth/Register")
+            .header("traceparent", format!("00-{}-00f067aa0ba902b7-01", TRACE_ID))
+            .body(())
+            .unwrap();
+        assert_eq!(trace_id(&request), TraceId::from_hex(TRACE_ID).unwrap());
+
+        let request = Request::builder()
+            .uri("/zkp_auth.Auth/Register")
+            .body(())
+            .unwrap();
+        let fresh = trace_id(&request);
+        assert_ne!(fresh, TraceId::INVALID);
+        assert_ne!(fresh, TraceId::from_hex(TRACE_ID).unwrap());
+    }
+}
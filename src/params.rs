@@ -0,0 +1,98 @@
+//! Bootstrapping of the group parameters from the server, for clients that are not configured
+//! with a group.
+
+use num_bigint::BigUint;
+use tonic::{transport::Channel, Code, Status};
+
+use crate::pb::{auth_client::AuthClient, GetParametersRequest};
+use crate::{Endianness, ZKP};
+
+/// Fetches the group parameters of the server and builds a `ZKP` from them.
+///
+/// The parameters are only checked for consistency: both generators must be of order `q` and
+/// not degenerate. Nothing proves that `p` and `q` are prime or that the discrete logarithm of
+/// `beta` to the base `alpha` is unknown to the server, so the server has to be trusted to pick
+/// sound parameters.
+///
+/// # Arguments
+///
+/// * `client` - A client connected to the server.
+///
+/// # Returns
+///
+/// A `Result` containing either the `ZKP` of the server or the `Status` of the failed call;
+/// inconsistent parameters are reported with `Code::FailedPrecondition`.
+pub async fn fetch_parameters(client: &mut AuthClient<Channel>) -> Result<ZKP, Status> {
+    fetch_parameters_with_endianness(client)
+        .await
+        .map(|(zkp, _)| zkp)
+}
+
+/// Fetches the group parameters of the server, see `fetch_parameters`, and the byte order of the
+/// integers it exchanges.
+///
+/// Servers predating the negotiation of the byte order are big-endian.
+///
+/// # Returns
+///
+/// A `Result` containing either the `ZKP` and the `Endianness` of the server, or the `Status`
+/// of the failed call.
+pub async fn fetch_parameters_with_endianness(
+    client: &mut AuthClient<Channel>,
+) -> Result<(ZKP, Endianness), Status> {
+    let response = client
+        .get_parameters(GetParametersRequest {})
+        .await?
+        .into_inner();
+
+    let zkp = ZKP::new(
+        &BigUint::from_bytes_be(&response.p),
+        &BigUint::from_bytes_be(&response.q),
+        &BigUint::from_bytes_be(&response.alpha),
+        &BigUint::from_bytes_be(&response.beta),
+    );
+    if !is_consistent(&zkp) {
+        return Err(Status::new(
+            Code::FailedPrecondition,
+            "The server sent inconsistent group parameters",
+        ));
+    }
+    let endianness = if response.little_endian {
+        Endianness::Little
+    } else {
+        Endianness::Big
+    };
+    Ok((zkp, endianness))
+}
+
+/// Checks that `alpha` and `beta` are non-degenerate generators of a subgroup of order `q`.
+fn is_consistent(zkp: &ZKP) -> bool {
+    let one = BigUint::from(1u32);
+    zkp.p > one
+        && zkp.q > one
+        && !zkp.is_degenerate()
+        && zkp.alpha < zkp.p
+        && zkp.beta < zkp.p
+        && zkp.alpha.modpow(&zkp.q, &zkp.p) == one
+        && zkp.beta.modpow(&zkp.q, &zkp.p) == one
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Group;
+
+    #[test]
+    fn test_consistent_parameters() {
+        for group in [Group::Toy, Group::Rfc1024, Group::Rfc2048] {
+            assert!(is_consistent(&ZKP::from_group(group)));
+        }
+
+        // 5 is not of order 11 modulo 23
+        let zkp = ZKP::from_group(Group::Toy);
+        let bad = ZKP::new(&zkp.p, &zkp.q, &zkp.alpha, &BigUint::from(5u32));
+        assert!(!is_consistent(&bad));
+        let bad = ZKP::new(&zkp.p, &zkp.q, &zkp.alpha, &zkp.alpha);
+        assert!(!is_consistent(&bad));
+    }
+}
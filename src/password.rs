@@ -0,0 +1,118 @@
+//! A strength policy for the passwords secrets are derived from.
+//!
+//! The server only ever sees the public values derived from a password, so the policy has to be
+//! enforced by the prover before registering. The entropy of a password is estimated as its
+//! length times `log2` of the size of the character classes it uses (lowercase, uppercase,
+//! digits, other ASCII, non-ASCII), which overestimates dictionary words but rejects short and
+//! single-class passwords.
+
+use crate::{Result, ZkpError};
+
+/// The minimum password length of the default policy.
+pub const DEFAULT_MIN_PASSWORD_LENGTH: usize = 8;
+
+/// The minimum estimated entropy, in bits, of the default policy.
+pub const DEFAULT_MIN_ENTROPY_BITS: f64 = 50.0;
+
+/// The requirements a password must meet to derive a secret from it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PasswordPolicy {
+    /// The minimum number of characters.
+    pub min_length: usize,
+    /// The minimum estimated entropy, in bits, see `estimate_entropy_bits`.
+    pub min_entropy_bits: f64,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: DEFAULT_MIN_PASSWORD_LENGTH,
+            min_entropy_bits: DEFAULT_MIN_ENTROPY_BITS,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Checks `password` against the policy.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the password meets the policy, or `ZkpError::WeakPassword` telling which
+    /// requirement it misses.
+    pub fn check(&self, password: &str) -> Result<()> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(ZkpError::WeakPassword(format!(
+                "{} characters, at least {} are required",
+                length, self.min_length
+            )));
+        }
+
+        let bits = estimate_entropy_bits(password);
+        if bits < self.min_entropy_bits {
+            return Err(ZkpError::WeakPassword(format!(
+                "about {:.0} bits of entropy, at least {:.0} are required; use a longer password \
+                 or mix cases, digits and symbols",
+                bits, self.min_entropy_bits
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Estimates the entropy of `password` in bits, see the module documentation.
+pub fn estimate_entropy_bits(password: &str) -> f64 {
+    let uses = |is_in: fn(&char) -> bool| password.chars().any(|c| is_in(&c));
+    let charset = [
+        (uses(char::is_ascii_lowercase), 26),
+        (uses(char::is_ascii_uppercase), 26),
+        (uses(char::is_ascii_digit), 10),
+        (uses(|c| c.is_ascii() && !c.is_ascii_alphanumeric()), 33),
+        (uses(|c| !c.is_ascii()), 100),
+    ]
+    .iter()
+    .filter(|(used, _)| *used)
+    .map(|(_, size)| size)
+    .sum::<u32>();
+    if charset == 0 {
+        return 0.0;
+    }
+    password.chars().count() as f64 * f64::from(charset).log2()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_weak_passwords_are_rejected() {
+        let policy = PasswordPolicy::default();
+        for weak in ["", "abc", "Ab1!", "password", "12345678901234", "aaaaaaaaaa"] {
+            assert!(
+                matches!(policy.check(weak), Err(ZkpError::WeakPassword(_))),
+                "{weak:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn test_strong_passwords_are_accepted() {
+        let policy = PasswordPolicy::default();
+        for strong in ["correct horse battery staple", "Tr0ub4dor&3x", "k9#Qz!v2Lm"] {
+            assert_eq!(policy.check(strong), Ok(()), "{strong:?} was rejected");
+        }
+    }
+
+    #[test]
+    fn test_entropy_estimate() {
+        assert_eq!(estimate_entropy_bits(""), 0.0);
+        assert_eq!(estimate_entropy_bits("aaaa"), 4.0 * 26f64.log2());
+        assert_eq!(estimate_entropy_bits("aA1!"), 4.0 * 95f64.log2());
+
+        let lenient = PasswordPolicy {
+            min_length: 4,
+            min_entropy_bits: 0.0,
+        };
+        assert_eq!(lenient.check("abcd"), Ok(()));
+    }
+}
@@ -0,0 +1,55 @@
+//! A pool of reusable gRPC connections, for services running many authentications.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tonic::transport::{Channel, Error};
+
+use crate::channel;
+use crate::pb::auth_client::AuthClient;
+
+/// A fixed set of `AuthClient` connections handed out round-robin.
+///
+/// Opening a connection (TCP, and TLS if configured) for every authentication is expensive. The
+/// pool opens `size` connections once, and each `get` hands out a clone of one of them: clones of
+/// an `AuthClient` share its underlying connection, and HTTP/2 multiplexes concurrent calls on it.
+#[derive(Debug)]
+pub struct AuthClientPool {
+    clients: Vec<AuthClient<Channel>>,
+    next: AtomicUsize,
+}
+
+impl AuthClientPool {
+    /// Opens the pool's connections.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - The server endpoint, e.g. `http://127.0.0.1:50051`, see `channel::connect`.
+    /// * `size` - The number of connections to open, at least one is always opened.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the connected pool or the transport error of the first
+    /// connection that failed.
+    pub async fn connect(endpoint: &str, size: usize) -> Result<Self, Error> {
+        let mut clients = Vec::with_capacity(size.max(1));
+        for _ in 0..size.max(1) {
+            clients.push(AuthClient::new(channel::connect(endpoint).await?));
+        }
+
+        Ok(Self {
+            clients,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Hands out a client using the next connection of the pool.
+    pub fn get(&self) -> AuthClient<Channel> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.clients.len();
+        self.clients[index].clone()
+    }
+
+    /// Returns the number of connections of the pool.
+    pub fn size(&self) -> usize {
+        self.clients.len()
+    }
+}
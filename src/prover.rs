@@ -0,0 +1,188 @@
+//! The prover side of the protocol as a typestate machine.
+//!
+//! A `Session` moves from `Initial` to `Committed` with `commit`, then to `Responded` with
+//! `respond`, each step consuming the previous state. Calling the steps out of order, or
+//! answering two challenges with the same commitment (which would give the secret away), does
+//! not compile:
+//!
+//! ```compile_fail
+//! use zkp_chaum_pedersen::{prover::Session, ZKP};
+//! # use num_bigint::BigUint;
+//!
+//! let zkp = ZKP::default();
+//! let session = Session::new(&zkp, BigUint::from(6u32));
+//! session.respond(&BigUint::from(4u32)); // no commitment yet
+//! ```
+//!
+//! ```
+//! use zkp_chaum_pedersen::{prover::Session, ZKP};
+//! # use num_bigint::BigUint;
+//!
+//! let zkp = ZKP::default();
+//! let x = BigUint::from(6u32);
+//! let (y1, y2) = zkp.compute_pair(&x);
+//!
+//! let session = Session::new(&zkp, x).commit();
+//! let c = zkp.issue_challenge(); // sent by the verifier after receiving `session.commitment()`
+//! let proof = session.respond(&c).proof();
+//! assert!(zkp.verify_proof(&y1, &y2, &proof));
+//! ```
+
+use num_bigint::BigUint;
+
+use crate::{Proof, ZKP};
+
+/// The state of a `Session` before the commitment.
+#[derive(Debug)]
+pub struct Initial;
+
+/// The state of a `Session` once committed: the nonce `k` and the commitment `(r1, r2)`.
+pub struct Committed {
+    k: BigUint,
+    r1: BigUint,
+    r2: BigUint,
+}
+
+// the nonce gives the secret away along with the answer, so it is not printed
+impl std::fmt::Debug for Committed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Committed")
+            .field("r1", &self.r1)
+            .field("r2", &self.r2)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The state of a `Session` once the challenge `c` is answered with `s`.
+#[derive(Debug)]
+pub struct Responded {
+    proof: Proof,
+}
+
+/// One run of the protocol by the prover of the secret `x`, in the state `S`.
+pub struct Session<'a, S> {
+    zkp: &'a ZKP,
+    x: BigUint,
+    state: S,
+}
+
+// the secret is not printed
+impl<S: std::fmt::Debug> std::fmt::Debug for Session<'_, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session")
+            .field("zkp", self.zkp)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> Session<'a, Initial> {
+    /// Starts a run of the protocol proving knowledge of `x` in the group of `zkp`.
+    pub fn new(zkp: &'a ZKP, x: BigUint) -> Self {
+        Self {
+            zkp,
+            x,
+            state: Initial,
+        }
+    }
+
+    /// Samples a random nonce `k` and commits to it, see `ZKP::compute_pair`.
+    pub fn commit(self) -> Session<'a, Committed> {
+        let k = ZKP::generate_random_number_below(&self.zkp.q);
+        self.commit_with_nonce(k)
+    }
+
+    /// Commits to the nonce `k`, e.g. from `ZKP::deterministic_nonce`.
+    pub fn commit_with_nonce(self, k: BigUint) -> Session<'a, Committed> {
+        let (r1, r2) = self.zkp.compute_pair(&k);
+        Session {
+            zkp: self.zkp,
+            x: self.x,
+            state: Committed { k, r1, r2 },
+        }
+    }
+}
+
+impl<'a> Session<'a, Committed> {
+    /// Returns the commitment `(r1, r2)` to send to the verifier.
+    pub fn commitment(&self) -> (&BigUint, &BigUint) {
+        (&self.state.r1, &self.state.r2)
+    }
+
+    /// Answers the verifier's challenge `c`, see `ZKP::solve`.
+    ///
+    /// This consumes the nonce, so the commitment cannot answer another challenge.
+    pub fn respond(self, c: &BigUint) -> Session<'a, Responded> {
+        let Committed { k, r1, r2 } = self.state;
+        let s = self.zkp.solve(&k, c, &self.x);
+        Session {
+            zkp: self.zkp,
+            x: self.x,
+            state: Responded {
+                proof: Proof {
+                    r1,
+                    r2,
+                    c: c.clone(),
+                    s,
+                },
+            },
+        }
+    }
+}
+
+impl Session<'_, Responded> {
+    /// Returns the answer `s` to send to the verifier.
+    pub fn response(&self) -> &BigUint {
+        &self.state.proof.s
+    }
+
+    /// Returns the transcript of the run as a `Proof`.
+    pub fn proof(self) -> Proof {
+        self.state.proof
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::{self, toy_zkp, valid_proof};
+
+    #[test]
+    fn test_session_matches_fixture_proof() {
+        let zkp = toy_zkp();
+        let session = Session::new(&zkp, BigUint::from(fixtures::SECRET))
+            .commit_with_nonce(BigUint::from(fixtures::NONCE));
+        let proof = valid_proof();
+        assert_eq!(session.commitment(), (&proof.r1, &proof.r2));
+
+        let session = session.respond(&BigUint::from(fixtures::CHALLENGE));
+        assert_eq!(session.response(), &proof.s);
+        assert_eq!(session.proof(), proof);
+    }
+
+    #[test]
+    fn test_session_proves_knowledge() {
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+
+        let session = Session::new(&zkp, x).commit();
+        let c = zkp.issue_challenge();
+        let proof = session.respond(&c).proof();
+        assert!(zkp.verify_proof(&y1, &y2, &proof));
+    }
+
+    #[test]
+    fn test_session_debug_hides_secrets() {
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let k = ZKP::generate_random_number_below(&zkp.q);
+
+        let session = Session::new(&zkp, x.clone());
+        assert!(!format!("{:?}", session).contains(&x.to_string()));
+        let session = session.commit_with_nonce(k.clone());
+        let debug = format!("{:?}", session);
+        assert!(debug.contains(&session.commitment().0.to_string()));
+        assert!(!debug.contains(&x.to_string()) && !debug.contains(&k.to_string()));
+    }
+}
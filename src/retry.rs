@@ -0,0 +1,137 @@
+//! Retrying calls the server refused as `Code::Unavailable`, with exponential backoff.
+//!
+//! The server answers `Unavailable` while it is starting up (e.g. restoring its state file), so
+//! a client started at the same time as the server should retry rather than fail.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tonic::{Code, Status};
+
+/// The number of retries of `RetryPolicy::default`.
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// The wait before the first retry of `RetryPolicy::default`.
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The longest wait between two retries of `RetryPolicy::default`.
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(2);
+
+/// How many times, and how patiently, to retry an unavailable server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The number of retries after the first attempt, `0` to never retry.
+    pub max_retries: u32,
+    /// The wait before the first retry, doubled after each retry.
+    pub initial_backoff: Duration,
+    /// The longest wait between two retries.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+}
+
+/// Runs `call` until it returns anything but `Code::Unavailable`, or the retries run out.
+///
+/// # Arguments
+///
+/// * `policy` - The number of retries and the waits between them.
+/// * `call` - Makes one attempt, e.g. with clones of the `AuthClient` and of the request moved
+///   into an `async` block.
+/// * `on_retry` - Told the status of each attempt about to be retried, and the wait before the
+///   retry, e.g. to let the user know.
+///
+/// # Returns
+///
+/// The result of the last attempt.
+pub async fn retry_unavailable<T, F, Fut>(
+    policy: &RetryPolicy, mut call: F, mut on_retry: impl FnMut(&Status, Duration),
+) -> Result<T, Status>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Status>>,
+{
+    let mut backoff = policy.initial_backoff;
+    let mut retries = 0;
+    loop {
+        match call().await {
+            Err(status) if status.code() == Code::Unavailable && retries < policy.max_retries => {
+                retries += 1;
+                on_retry(&status, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(policy.max_backoff);
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+        }
+    }
+
+    /// A call failing with `code` for its first `failures` attempts.
+    async fn flaky(attempts: &AtomicU32, failures: u32, code: Code) -> Result<u32, Status> {
+        let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+        if attempt < failures {
+            return Err(Status::new(code, "not yet"));
+        }
+        Ok(attempt)
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_available() {
+        let attempts = AtomicU32::new(0);
+        let mut backoffs = Vec::new();
+        let result = retry_unavailable(
+            &fast_policy(5),
+            || flaky(&attempts, 3, Code::Unavailable),
+            |status, backoff| {
+                assert_eq!(status.code(), Code::Unavailable);
+                backoffs.push(backoff.as_millis());
+            },
+        )
+        .await;
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(backoffs, [1, 2, 4]);
+
+        let attempts = AtomicU32::new(0);
+        let result = retry_unavailable(
+            &fast_policy(2),
+            || flaky(&attempts, 3, Code::Unavailable),
+            |_, _| {},
+        )
+        .await;
+        assert_eq!(result.unwrap_err().code(), Code::Unavailable);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried() {
+        let attempts = AtomicU32::new(0);
+        let result = retry_unavailable(
+            &fast_policy(5),
+            || flaky(&attempts, 3, Code::NotFound),
+            |_, _| panic!("retried"),
+        )
+        .await;
+        assert_eq!(result.unwrap_err().code(), Code::NotFound);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
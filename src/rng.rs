@@ -0,0 +1,72 @@
+//! The source of every random value of the crate: secrets, nonces, challenges, authentication
+//! IDs and keys.
+//!
+//! `SecureRng` is the operating system's CSPRNG (`getrandom`), and nothing else: it has no seed,
+//! no state that could be cloned into two processes, and no constructor taking another RNG. The
+//! functions taking a caller supplied RNG, such as `ZKP::try_generate_random_number_below_with`,
+//! require `CryptoRng`, so a weak generator does not type check.
+
+use rand::{CryptoRng, RngCore};
+
+/// The CSPRNG `SecureRng` draws from.
+pub type Csprng = rand::rngs::OsRng;
+
+/// The operating system's cryptographically secure RNG, see the module documentation.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SecureRng(Csprng);
+
+impl RngCore for SecureRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for SecureRng {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use num_bigint::BigUint;
+    use std::any::TypeId;
+
+    use crate::ZKP;
+
+    fn assert_csprng<R: RngCore + CryptoRng>(_: &R) {}
+
+    #[test]
+    fn test_draws_from_the_os() {
+        assert_eq!(TypeId::of::<Csprng>(), TypeId::of::<rand::rngs::OsRng>());
+        assert_csprng(&SecureRng::default());
+
+        let mut a = [0u8; 32];
+        let mut b = [0u8; 32];
+        SecureRng::default().fill_bytes(&mut a);
+        SecureRng::default().fill_bytes(&mut b);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_nonces_are_in_range() {
+        for zkp in [ZKP::default(), crate::fixtures::toy_zkp()] {
+            for _ in 0..100 {
+                assert!(ZKP::generate_random_number_below(&zkp.q) < zkp.q);
+                assert!(zkp.issue_challenge() < zkp.q);
+            }
+        }
+        let bound = BigUint::from(11u32);
+        let n = ZKP::try_generate_random_number_below_with(&bound, &mut SecureRng::default());
+        assert!(n.unwrap() < bound);
+    }
+}
@@ -0,0 +1,192 @@
+//! Chaum-Pedersen over the secp256k1 curve, for reusing existing secp256k1 key material.
+//!
+//! This mirrors `ZKP` with the group written additively: the prover shows that
+//! `y1 = x * G` and `y2 = x * H` share the same discrete logarithm `x`. `G` is the standard
+//! secp256k1 generator and `H` is hashed to the curve, so nobody knows its discrete logarithm to
+//! the base `G`. Secrets, nonces, challenges and responses are 32-byte scalars; public values
+//! and commitments are encoded as 33-byte compressed points.
+
+use k256::elliptic_curve::{
+    group::GroupEncoding,
+    hash2curve::{ExpandMsgXmd, GroupDigest},
+    Field, PrimeField,
+};
+use k256::{CompressedPoint, FieldBytes, Secp256k1};
+pub use k256::{ProjectivePoint, Scalar};
+use sha2::Sha256;
+
+/// The domain separation tag used to hash the second generator `H` to the curve.
+pub const H_DST: &[u8] = b"zkp-chaum-pedersen:secp256k1:H";
+
+/// The byte length of a compressed point.
+pub const POINT_LEN: usize = 33;
+
+/// The two independent generators `G` and `H` of the secp256k1 backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Secp256k1Zkp {
+    pub g: ProjectivePoint,
+    pub h: ProjectivePoint,
+}
+
+impl Default for Secp256k1Zkp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Secp256k1Zkp {
+    /// Creates the backend with the standard generator `G` and the hashed generator `H`.
+    pub fn new() -> Self {
+        let h = Secp256k1::hash_from_bytes::<ExpandMsgXmd<Sha256>>(&[b"H"], &[H_DST])
+            .expect("hashing to secp256k1 cannot fail with a non-empty tag");
+        Self {
+            g: ProjectivePoint::GENERATOR,
+            h,
+        }
+    }
+
+    /// Computes the pair `(exp * G, exp * H)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `exp` - The secret `x` for the public values, or the nonce `k` for the commitment.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the two points.
+    pub fn compute_pair(&self, exp: &Scalar) -> (ProjectivePoint, ProjectivePoint) {
+        (self.g * exp, self.h * exp)
+    }
+
+    /// Solves for the response `s = k - c * x` modulo the group order.
+    ///
+    /// # Arguments
+    ///
+    /// * `k` - The nonce.
+    /// * `c` - The challenge.
+    /// * `x` - The secret.
+    ///
+    /// # Returns
+    ///
+    /// The response `s`.
+    pub fn solve(&self, k: &Scalar, c: &Scalar, x: &Scalar) -> Scalar {
+        k - &(c * x)
+    }
+
+    /// Verifies `r1 = s * G + c * y1` and `r2 = s * H + c * y2`.
+    ///
+    /// # Arguments
+    ///
+    /// * `r1`, `r2` - The commitment.
+    /// * `y1`, `y2` - The public values of the prover.
+    /// * `c` - The challenge.
+    /// * `s` - The response.
+    ///
+    /// # Returns
+    ///
+    /// A boolean indicating whether the verification conditions are met.
+    pub fn verify(
+        &self, r1: &ProjectivePoint, r2: &ProjectivePoint, y1: &ProjectivePoint,
+        y2: &ProjectivePoint, c: &Scalar, s: &Scalar,
+    ) -> bool {
+        *r1 == self.g * s + y1 * c && *r2 == self.h * s + y2 * c
+    }
+
+    /// Samples a uniformly random non-zero scalar, for a nonce, a challenge or a secret.
+    pub fn random_scalar() -> Scalar {
+        loop {
+            let scalar = Scalar::random(&mut crate::rng::SecureRng::default());
+            if !bool::from(scalar.is_zero()) {
+                return scalar;
+            }
+        }
+    }
+
+    /// Reads a 32-byte big-endian secret, such as an existing secp256k1 private key.
+    ///
+    /// # Returns
+    ///
+    /// The scalar, or `None` if it is zero or not below the group order.
+    pub fn secret_from_bytes(bytes: &[u8; 32]) -> Option<Scalar> {
+        let scalar = Option::<Scalar>::from(Scalar::from_repr(FieldBytes::from(*bytes)))?;
+        (!bool::from(scalar.is_zero())).then_some(scalar)
+    }
+
+    /// Encodes a point as `POINT_LEN` compressed bytes.
+    pub fn encode_point(point: &ProjectivePoint) -> [u8; POINT_LEN] {
+        point.to_bytes().into()
+    }
+
+    /// Decodes a point encoded with `encode_point`.
+    ///
+    /// # Returns
+    ///
+    /// The point, or `None` if `bytes` is not a compressed point on the curve or is the identity.
+    pub fn decode_point(bytes: &[u8]) -> Option<ProjectivePoint> {
+        let bytes: [u8; POINT_LEN] = bytes.try_into().ok()?;
+        let point = Option::<ProjectivePoint>::from(ProjectivePoint::from_bytes(
+            &CompressedPoint::from(bytes),
+        ))?;
+        (point != ProjectivePoint::IDENTITY).then_some(point)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_equal_discrete_logs() {
+        let zkp = Secp256k1Zkp::new();
+        assert_ne!(zkp.g, zkp.h);
+
+        let x = Secp256k1Zkp::random_scalar();
+        let (y1, y2) = zkp.compute_pair(&x);
+
+        let k = Secp256k1Zkp::random_scalar();
+        let (r1, r2) = zkp.compute_pair(&k);
+        let c = Secp256k1Zkp::random_scalar();
+        let s = zkp.solve(&k, &c, &x);
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s));
+
+        // y2 for another secret: the discrete logs differ, no response works for both
+        let other = zkp.h * Secp256k1Zkp::random_scalar();
+        assert!(!zkp.verify(&r1, &r2, &y1, &other, &c, &s));
+    }
+
+    #[test]
+    fn test_forged_proof_is_rejected() {
+        let zkp = Secp256k1Zkp::new();
+
+        let x = Secp256k1Zkp::random_scalar();
+        let (y1, y2) = zkp.compute_pair(&x);
+
+        let k = Secp256k1Zkp::random_scalar();
+        let (r1, r2) = zkp.compute_pair(&k);
+        let c = Secp256k1Zkp::random_scalar();
+        let forged = zkp.solve(&k, &c, &(x + Scalar::ONE));
+        assert!(!zkp.verify(&r1, &r2, &y1, &y2, &c, &forged));
+    }
+
+    #[test]
+    fn test_encodings() {
+        let zkp = Secp256k1Zkp::new();
+
+        // an existing private key as the secret
+        let mut key = [0u8; 32];
+        key[31] = 42;
+        let x = Secp256k1Zkp::secret_from_bytes(&key).unwrap();
+        let (y1, _) = zkp.compute_pair(&x);
+
+        let encoded = Secp256k1Zkp::encode_point(&y1);
+        assert_eq!(encoded.len(), POINT_LEN);
+        assert!(encoded[0] == 2 || encoded[0] == 3);
+        assert_eq!(Secp256k1Zkp::decode_point(&encoded), Some(y1));
+
+        assert_eq!(Secp256k1Zkp::decode_point(&encoded[1..]), None);
+        assert_eq!(Secp256k1Zkp::decode_point(&[0u8; POINT_LEN]), None);
+
+        assert_eq!(Secp256k1Zkp::secret_from_bytes(&[0u8; 32]), None);
+        assert_eq!(Secp256k1Zkp::secret_from_bytes(&[0xFF; 32]), None);
+    }
+}
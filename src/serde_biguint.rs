@@ -0,0 +1,73 @@
+//! Serializes a `BigUint` for `#[serde(with = "serde_biguint")]`.
+//!
+//! Human-readable formats such as JSON store it as the hex string of its big-endian bytes; binary
+//! formats such as CBOR store the bytes as a byte string. Either is read back, as are the arrays
+//! of byte values earlier versions wrote to JSON.
+
+use num_bigint::BigUint;
+use serde::{de, Deserializer, Serializer};
+use std::fmt;
+
+pub fn serialize<S: Serializer>(value: &BigUint, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&hex::encode(value.to_bytes_be()))
+    } else {
+        serializer.serialize_bytes(&value.to_bytes_be())
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigUint, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(BytesVisitor)
+    } else {
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> de::Visitor<'de> for BytesVisitor {
+    type Value = BigUint;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the big-endian bytes of an unsigned integer, or their hex string")
+    }
+
+    fn visit_str<E: de::Error>(self, hex: &str) -> Result<BigUint, E> {
+        let bytes = hex::decode(hex).map_err(|e| E::custom(format!("invalid hex: {}", e)))?;
+        Ok(BigUint::from_bytes_be(&bytes))
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<BigUint, E> {
+        Ok(BigUint::from_bytes_be(bytes))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<BigUint, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        Ok(BigUint::from_bytes_be(&bytes))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fixtures::valid_proof;
+    use crate::Proof;
+
+    #[test]
+    fn test_json_uses_hex_strings() {
+        let proof = valid_proof();
+        let json = serde_json::to_value(&proof).unwrap();
+        assert_eq!(json, serde_json::json!({ "r1": "08", "r2": "04", "c": "04", "s": "05" }));
+        assert_eq!(serde_json::from_value::<Proof>(json).unwrap(), proof);
+
+        // the byte arrays of earlier versions
+        let legacy = serde_json::json!({ "r1": [8], "r2": [4], "c": [4], "s": [5] });
+        assert_eq!(serde_json::from_value::<Proof>(legacy).unwrap(), proof);
+
+        let invalid = serde_json::json!({ "r1": "zz", "r2": "04", "c": "04", "s": "05" });
+        assert!(serde_json::from_value::<Proof>(invalid).is_err());
+    }
+}
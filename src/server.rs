@@ -1,20 +1,50 @@
+// `tonic::Status` is large, but it is what every handler and helper here returns.
+#![allow(clippy::result_large_err)]
+
 use anyhow::{anyhow, Result};
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
 use clap::Parser;
-use std::{collections::HashMap, sync::Mutex};
-
-use num_bigint::BigUint;
-use tonic::{transport::Server, Code, Request, Response, Status};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fs::File,
+    io::{self, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use zkp_chaum_pedersen::ZKP;
+use metrics::{describe_histogram, histogram, Unit};
+use metrics_exporter_prometheus::PrometheusBuilder;
 
-pub mod zkp_auth {
-    include!("./zkp_auth.rs");
-}
+use hmac::{Hmac, Mac};
+use num_bigint::BigUint;
+use prost::Message;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tonic::{transport::Server, Code, Request, Response, Status, Streaming};
 
-use zkp_auth::{
+use zkp_chaum_pedersen::pb::{
     auth_server::{Auth, AuthServer},
-    AuthenticationAnswerRequest, AuthenticationAnswerResponse, AuthenticationChallengeRequest,
-    AuthenticationChallengeResponse, RegisterRequest, RegisterResponse,
+    Answer, AuthenticationAnswerRequest, AuthenticationAnswerResponse,
+    AuthenticationChallengeRequest, AuthenticationChallengeResponse, CancelAuthenticationRequest,
+    CancelAuthenticationResponse, GetParametersRequest, GetParametersResponse,
+    InvalidateSessionsRequest, InvalidateSessionsResponse, KeepAliveChallenge, KeepAliveRequest,
+    RegisterRequest, RegisterResponse, ValidateSessionRequest, ValidateSessionResponse,
+};
+use zkp_chaum_pedersen::rng::SecureRng;
+use zkp_chaum_pedersen::token::{RegistrationClaims, SessionClaims, TokenKey};
+use zkp_chaum_pedersen::transcript::{Outcome, Transcript, TranscriptSink};
+use zkp_chaum_pedersen::{
+    Endianness, Group, Proof, VerifierKey, ZkpError, FIAT_SHAMIR_DOMAIN, PBKDF2_ROUNDS,
+    POSSESSION_DOMAIN, PROTOCOL_VERSION, ZKP,
 };
 
 #[derive(Debug, Parser)]
@@ -24,14 +54,222 @@ struct Args {
     /// Server listening endpoint
     #[arg(short, long, required = false, default_value = "127.0.0.1:50051")]
     endpoint: String,
+
+    /// Group parameters, clients must use the same
+    #[arg(short, long, value_enum, default_value_t = Group::default())]
+    group: Group,
+
+    /// Refuse registration and challenge requests of clients older than this protocol version,
+    /// `0` to accept clients predating versioning
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u32).range(..=PROTOCOL_VERSION as i64))]
+    min_protocol_version: u32,
+
+    /// Refuse to start with a modulus `p` shorter than this many bits
+    #[arg(long, default_value_t = DEFAULT_MIN_P_BITS)]
+    min_p_bits: u64,
+
+    /// Start even if `p` is shorter than `--min-p-bits` (e.g. with the toy group), for testing only
+    #[arg(long)]
+    allow_insecure_params: bool,
+
+    /// Write a hex dump of every registration, challenge and answer message to this file
+    #[arg(long)]
+    capture: Option<PathBuf>,
+
+    /// Append the transcript of every completed authentication to this file, as JSON lines
+    #[arg(long)]
+    transcript_log: Option<PathBuf>,
+
+    /// How to store the transcripts of `--transcript-log`, the same as the existing log's
+    #[arg(long, value_enum, default_value_t = TranscriptCompression::default())]
+    transcript_compression: TranscriptCompression,
+
+    /// Print the transcripts of a `--transcript-log` file, compressed or not, as JSON lines and
+    /// exit
+    #[arg(long, value_name = "PATH")]
+    dump_transcripts: Option<PathBuf>,
+
+    /// Serve Prometheus metrics (e.g. verification latency percentiles) on this address
+    #[arg(long)]
+    metrics: Option<SocketAddr>,
+
+    /// Number of accepted commitments remembered to refuse replays
+    #[arg(long, default_value_t = DEFAULT_ACCEPTED_COMMITMENTS)]
+    replay_cache_size: usize,
+
+    /// Which accepted commitment to forget once the replay cache is full
+    #[arg(long, value_enum, default_value_t = Eviction::default())]
+    replay_cache_eviction: Eviction,
+
+    /// Issue challenges of this many bits instead of the full `[0, q)`, see
+    /// `ZKP::issue_challenge_bits` for what shorter challenges cost in soundness
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..=MAX_CHALLENGE_BITS))]
+    challenge_bits: Option<u64>,
+
+    /// Byte order of the integers exchanged with the clients, advertised with the group
+    /// parameters
+    #[arg(long, value_enum, default_value_t = Endianness::default())]
+    endianness: Endianness,
+
+    /// Number of challenges awaiting an answer the server keeps at most
+    #[arg(long, default_value_t = DEFAULT_MAX_OUTSTANDING_CHALLENGES)]
+    max_outstanding_challenges: usize,
+
+    /// Number of verifications running at once at most, further ones wait for a slot
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    max_concurrent_verifications: Option<u64>,
+
+    /// What to do with a new challenge once `--max-outstanding-challenges` are outstanding
+    #[arg(long, value_enum, default_value_t = ChallengeOverflow::default())]
+    challenge_overflow: ChallengeOverflow,
+
+    /// Number of consecutive failed authentications locking a user out (0 to never lock)
+    #[arg(long, default_value_t = DEFAULT_MAX_FAILED_ATTEMPTS)]
+    max_failed_attempts: u32,
+
+    /// Seconds a user stays locked out after too many failed authentications
+    #[arg(long, default_value_t = DEFAULT_LOCKOUT_COOLDOWN.as_secs())]
+    lockout_cooldown: u64,
+
+    /// Trim and lowercase user names, so that `Alice` and `alice` are the same user
+    #[arg(long)]
+    normalize_user_names: bool,
+
+    /// Key the user store by the SHA-256 of the user names instead of the user names
+    #[arg(long)]
+    hash_user_names: bool,
+
+    /// Also serve the registration and authentication as HTTP/JSON on this address
+    #[arg(long)]
+    http: Option<SocketAddr>,
+
+    /// Issue session IDs as tokens signed with the key in this file (64 hex characters), which
+    /// services holding the key can validate without the server
+    #[arg(long)]
+    session_token_key: Option<PathBuf>,
+
+    /// Restore the registrations and sessions from this file at startup, if it exists, and save
+    /// them to it on Ctrl-C
+    #[arg(long, conflicts_with = "ephemeral")]
+    state_file: Option<PathBuf>,
+
+    /// Register the users listed in this file at startup, one `<user> <verifier key>` per line
+    /// with the key as written by `VerifierKey::to_file`
+    #[arg(long)]
+    preload_keys: Option<PathBuf>,
+
+    /// Refuse registrations, e.g. when the public values are preloaded with `--preload-keys`
+    #[arg(long)]
+    disable_registration: bool,
+
+    /// Require on every registration a single-use token signed with the key in this file (64 hex
+    /// characters), see `--issue-registration-token`
+    #[arg(long)]
+    registration_token_key: Option<PathBuf>,
+
+    /// Print a registration token for this user, signed with `--registration-token-key`, and exit
+    #[arg(long, requires = "registration_token_key")]
+    issue_registration_token: Option<String>,
+
+    /// Seconds a token printed by `--issue-registration-token` is valid
+    #[arg(long, default_value_t = DEFAULT_REGISTRATION_TOKEN_TTL.as_secs())]
+    registration_token_ttl: u64,
+
+    /// Forget registrations and sessions after `--ephemeral-ttl`, for demos and CI
+    #[arg(long)]
+    ephemeral: bool,
+
+    /// Seconds registrations and sessions live in `--ephemeral` mode
+    #[arg(long, default_value_t = DEFAULT_EPHEMERAL_TTL.as_secs(), requires = "ephemeral")]
+    ephemeral_ttl: u64,
+
+    /// Serve gRPC on a Unix domain socket created at this path instead of the TCP endpoint, for
+    /// clients connecting to `unix://<path>`
+    #[arg(long)]
+    uds: Option<PathBuf>,
+
+    /// Export OpenTelemetry traces of the requests to the OTLP collector at this endpoint, e.g.
+    /// `http://localhost:4317`
+    #[cfg(feature = "otel")]
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    otel_endpoint: Option<String>,
+
+    /// Seconds between two challenges on a keep-alive stream
+    #[arg(long, default_value_t = DEFAULT_KEEP_ALIVE_INTERVAL.as_secs())]
+    keep_alive_interval: u64,
+
+    /// Replace the key binding authentication IDs to challenges every this many seconds
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    auth_id_key_rotation: Option<u64>,
+
+    /// Seconds the key replaced by a rotation is still accepted, for the challenges issued
+    /// before it
+    #[arg(
+        long,
+        default_value_t = DEFAULT_AUTH_ID_KEY_ROTATION_WINDOW.as_secs(),
+        requires = "auth_id_key_rotation"
+    )]
+    auth_id_key_rotation_window: u64,
+
+    /// Number of random alphanumeric characters starting each authentication ID
+    #[arg(
+        long,
+        default_value_t = DEFAULT_AUTH_ID_NONCE_LEN as u64,
+        value_parser = clap::value_parser!(u64).range(8..=64)
+    )]
+    auth_id_length: u64,
+
+    /// Also accept gRPC-web requests (HTTP/1.1), e.g. from browsers, on the same port
+    #[arg(long)]
+    grpc_web: bool,
+
+    /// Print the security-relevant settings as JSON, for audit tools, and exit
+    #[arg(long)]
+    describe: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
+    if let Err(e) = ZKP::self_test() {
+        return Err(anyhow!("refusing to start: {}", e));
+    }
     let addr = args.endpoint.clone();
+    let zkp = zkp_from_args(&args)?;
+    let rotation = auth_id_key_rotation_from_args(&args)?;
+
+    if let Some(path) = &args.dump_transcripts {
+        for transcript in TranscriptLog::read(path)? {
+            println!("{}", serde_json::to_string(&transcript)?);
+        }
+        return Ok(());
+    }
+    if args.describe {
+        let mut auth_impl = auth_impl_from_args(&args, zkp);
+        if let Some(path) = &args.session_token_key {
+            auth_impl.session_tokens = Some(read_token_key(path)?);
+        }
+        if let Some(path) = &args.registration_token_key {
+            auth_impl.registration_tokens = Some(read_token_key(path)?);
+        }
+        println!("{}", serde_json::to_string_pretty(&auth_impl.describe())?);
+        return Ok(());
+    }
+    if let (Some(user), Some(path)) = (&args.issue_registration_token, &args.registration_token_key)
+    {
+        let exp = SystemTime::now() + Duration::from_secs(args.registration_token_ttl);
+        let claims = RegistrationClaims::new(
+            user,
+            exp.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        );
+        println!("{}", read_token_key(path)?.issue_registration(&claims));
+        return Ok(());
+    }
 
-    println!("✅ Server listening at {}", addr);
+    match &args.uds {
+        Some(path) => println!("✅ Server listening at unix://{}", path.display()),
+        None => println!("✅ Server listening at {}", addr),
+    }
 
     let addr = match addr.parse() {
         Ok(a) => a,
@@ -40,227 +278,5225 @@ async fn main() -> Result<()> {
         }
     };
 
-    match Server::builder()
-        .add_service(AuthServer::new(AuthImpl::default()))
-        .serve(addr)
-        .await
-    {
-        Ok(x) => x,
-        Err(e) => {
-            return Err(anyhow!("Server create failed endpoint:{} error:{:?}", args.endpoint, e));
+    #[cfg(feature = "otel")]
+    let tracer_provider = match &args.otel_endpoint {
+        Some(endpoint) => match zkp_chaum_pedersen::otel::init(endpoint) {
+            Ok(provider) => {
+                println!("✅ Exporting traces to {}", endpoint);
+                Some(provider)
+            }
+            Err(e) => {
+                return Err(anyhow!("could not export traces to:{} error:{:?}", endpoint, e));
+            }
+        },
+        None => None,
+    };
+
+    if let Some(metrics_addr) = args.metrics {
+        if let Err(e) = PrometheusBuilder::new()
+            .with_http_listener(metrics_addr)
+            .install()
+        {
+            return Err(anyhow!("could not serve metrics at:{} error:{:?}", metrics_addr, e));
+        }
+        describe_histogram!(
+            VERIFICATION_SECONDS,
+            Unit::Seconds,
+            "Duration of the verification of one credential"
+        );
+        println!("✅ Metrics served at {}", metrics_addr);
+    }
+
+    let mut auth_impl = auth_impl_from_args(&args, zkp);
+    if args.ephemeral {
+        println!(
+            "⚠️ Ephemeral mode, registrations and sessions expire after {}s",
+            args.ephemeral_ttl
+        );
+    }
+    if let Some(path) = &args.capture {
+        auth_impl.capture = match MessageCapture::create(path) {
+            Ok(capture) => Some(Arc::new(capture)),
+            Err(e) => {
+                return Err(anyhow!("could not create capture file:{:?} error:{:?}", path, e));
+            }
+        };
+        println!("⚠️ Capturing protocol messages to {:?}", path);
+    }
+    if let Some(path) = &args.transcript_log {
+        auth_impl.transcripts = match TranscriptLog::open(path, args.transcript_compression) {
+            Ok(log) => Some(Arc::new(log)),
+            Err(e) => {
+                return Err(anyhow!("could not open transcript log:{:?} error:{:?}", path, e));
+            }
+        };
+        println!("✅ Logging authentication transcripts to {:?}", path);
+    }
+    if let Some(path) = &args.session_token_key {
+        auth_impl.session_tokens = match read_token_key(path) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                return Err(anyhow!("could not read session token key:{:?} error:{:?}", path, e));
+            }
+        };
+        println!("✅ Issuing signed session tokens");
+    }
+    if let Some(path) = &args.registration_token_key {
+        auth_impl.registration_tokens = match read_token_key(path) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                return Err(anyhow!(
+                    "could not read registration token key:{:?} error:{:?}",
+                    path,
+                    e
+                ));
+            }
+        };
+        println!("✅ Registration requires a registration token");
+    }
+    if let Some(path) = &args.preload_keys {
+        let preloaded = std::fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|keys| auth_impl.preload_keys(&keys));
+        match preloaded {
+            Ok(count) => println!("✅ Preloaded {} keys from {:?}", count, path),
+            Err(e) => {
+                return Err(anyhow!("could not preload keys:{:?} error:{:?}", path, e));
+            }
+        }
+    }
+    if auth_impl.registration_disabled {
+        println!("✅ Registration is disabled");
+    }
+    if let Some((interval, window)) = rotation {
+        let auth = auth_impl.clone();
+        tokio::spawn(async move {
+            let mut ticks =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                ticks.tick().await;
+                auth.rotate_auth_id_key(window);
+                println!("🔑 Rotated the authentication ID key");
+            }
+        });
+        println!(
+            "✅ Rotating the authentication ID key every {:?}, accepting the previous one for {:?}",
+            interval, window
+        );
+    }
+    // the state is restored while serving, and the store answers `Unavailable` until it is done
+    let (restore_failed, mut restore_failure) = tokio::sync::oneshot::channel();
+    if let Some(path) = args.state_file.clone().filter(|path| path.exists()) {
+        auth_impl.ready.store(false, Ordering::SeqCst);
+        let auth = auth_impl.clone();
+        tokio::task::spawn_blocking(move || {
+            let restored = std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|state| auth.import_state(&state));
+            match restored {
+                Ok(()) => {
+                    auth.ready.store(true, Ordering::SeqCst);
+                    println!("✅ Restored state from {:?}", path);
+                }
+                Err(e) => {
+                    let _ = restore_failed.send(anyhow!(
+                        "could not restore state file:{:?} error:{:?}",
+                        path,
+                        e
+                    ));
+                }
+            }
+        });
+    }
+
+    if let Some(http_addr) = args.http {
+        let listener = match tokio::net::TcpListener::bind(http_addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                return Err(anyhow!("could not serve HTTP at:{} error:{:?}", http_addr, e));
+            }
+        };
+        let gateway = http_gateway(auth_impl.clone());
+        tokio::spawn(async move { axum::serve(listener, gateway).await });
+        println!("✅ HTTP/JSON gateway listening at {}", http_addr);
+    }
+
+    let mut restore_error = None;
+    let shutdown = async {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            Ok(e) = &mut restore_failure => restore_error = Some(e),
         }
     };
+    let server = Server::builder();
+    #[cfg(feature = "otel")]
+    let server = server.trace_fn(zkp_chaum_pedersen::otel::request_span);
+    if args.grpc_web {
+        println!("✅ Accepting gRPC-web requests");
+    }
+    let router = auth_router(server, auth_impl.clone(), args.grpc_web);
+    let served = match &args.uds {
+        Some(path) => serve_uds(router, path, shutdown).await,
+        None => router
+            .serve_with_shutdown(addr, shutdown)
+            .await
+            .map_err(Into::into),
+    };
+    if let Err(e) = served {
+        return Err(anyhow!("Server create failed endpoint:{} error:{:?}", args.endpoint, e));
+    }
+
+    #[cfg(feature = "otel")]
+    if let Some(provider) = tracer_provider {
+        // flushes the spans still batched
+        let _ = provider.shutdown();
+    }
+    if let Some(e) = restore_error {
+        return Err(e);
+    }
+    // a partial store must not overwrite the state file
+    if let Some(path) = args.state_file.as_ref().filter(|_| auth_impl.is_ready()) {
+        if let Err(e) = std::fs::write(path, auth_impl.export_state(true)) {
+            return Err(anyhow!("could not save state file:{:?} error:{:?}", path, e));
+        }
+        println!("✅ Saved state to {:?}", path);
+    }
 
     Ok(())
 }
 
-/// An implementation of the `Auth` trait for handling user registration, authentication challenge creation, 
+/// Returns the interval and the window of `--auth-id-key-rotation`, refusing a window longer than
+/// the interval: only the key replaced by the last rotation is kept, so the window would be cut
+/// short at the next one.
+fn auth_id_key_rotation_from_args(args: &Args) -> Result<Option<(Duration, Duration)>> {
+    let Some(interval) = args.auth_id_key_rotation else {
+        return Ok(None);
+    };
+    if args.auth_id_key_rotation_window > interval {
+        return Err(anyhow!(
+            "refusing to start with --auth-id-key-rotation-window {}s longer than \
+             --auth-id-key-rotation {}s",
+            args.auth_id_key_rotation_window,
+            interval
+        ));
+    }
+    Ok(Some((
+        Duration::from_secs(interval),
+        Duration::from_secs(args.auth_id_key_rotation_window),
+    )))
+}
+
+/// The shortest modulus `p`, in bits, the server starts with by default.
+pub const DEFAULT_MIN_P_BITS: u64 = 1024;
+
+/// The longest `--challenge-bits` accepted; `q` bounds the challenges well below it anyway.
+pub const MAX_CHALLENGE_BITS: u64 = 8192;
+
+/// Builds the group parameters of `--group`, refusing ones with a modulus shorter than
+/// `--min-p-bits` unless `--allow-insecure-params` is passed.
+fn zkp_from_args(args: &Args) -> Result<ZKP> {
+    let zkp = ZKP::from_group(args.group);
+    if zkp.p.bits() < args.min_p_bits {
+        if !args.allow_insecure_params {
+            return Err(anyhow!(
+                "refusing to start with a {}-bit p, below the minimum of {} bits \
+                 (pass --allow-insecure-params to start anyway)",
+                zkp.p.bits(),
+                args.min_p_bits
+            ));
+        }
+        println!("⚠️ Using a {}-bit p, proofs are NOT secure", zkp.p.bits());
+    }
+    Ok(zkp)
+}
+
+/// The lifetime of registrations and sessions in `--ephemeral` mode by default.
+pub const DEFAULT_EPHEMERAL_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// The lifetime of the tokens printed by `--issue-registration-token` by default.
+pub const DEFAULT_REGISTRATION_TOKEN_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Builds the `AuthImpl` configured by `args`, without the capture and token keys.
+fn auth_impl_from_args(args: &Args, zkp: ZKP) -> AuthImpl {
+    let mut auth_impl = AuthImpl {
+        zkp,
+        user_ids: UserIds {
+            normalize: args.normalize_user_names,
+            hash: args.hash_user_names,
+        },
+        accepted_commitments: Arc::new(Mutex::new(AcceptedCommitments::new(
+            args.replay_cache_size,
+            args.replay_cache_eviction,
+        ))),
+        auth_id_to_user: Arc::new(Mutex::new(OutstandingChallenges::new(
+            args.max_outstanding_challenges,
+            args.challenge_overflow,
+        ))),
+        lockouts: Arc::new(Mutex::new(Lockouts::new(
+            args.max_failed_attempts,
+            Duration::from_secs(args.lockout_cooldown),
+        ))),
+        keep_alive_interval: Duration::from_secs(args.keep_alive_interval),
+        min_protocol_version: args.min_protocol_version,
+        registration_disabled: args.disable_registration,
+        challenge_bits: args.challenge_bits,
+        endianness: args.endianness,
+        auth_id_keys: Arc::new(Mutex::new(AuthIdKeys::with_nonce_len(
+            args.auth_id_length as usize,
+        ))),
+        verification_limit: args
+            .max_concurrent_verifications
+            .map(|limit| VerificationLimit::new(limit as usize)),
+        ..Default::default()
+    };
+    if args.ephemeral {
+        let ttl = Duration::from_secs(args.ephemeral_ttl);
+        auth_impl.registration_ttl = Some(ttl);
+        auth_impl.sessions = Arc::new(Mutex::new(SessionStore::with_ttl(ttl)));
+    }
+    auth_impl
+}
+
+/// Adds the `Auth` service to `server`.
+///
+/// With `grpc_web`, the service also answers gRPC-web requests, e.g. from browsers, over HTTP/1.1
+/// next to native gRPC over HTTP/2 on the same port, with CORS allowing any origin.
+fn auth_router(
+    mut server: Server, auth_impl: AuthImpl, grpc_web: bool,
+) -> tonic::transport::server::Router {
+    let service = AuthServer::new(auth_impl);
+    if grpc_web {
+        server
+            .accept_http1(true)
+            .add_service(tonic_web::enable(service))
+    } else {
+        server.add_service(service)
+    }
+}
+
+/// Serves `router` on a Unix domain socket created at `path` until `shutdown` completes, then
+/// removes the socket.
+#[cfg(unix)]
+async fn serve_uds(
+    router: tonic::transport::server::Router, path: &Path,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> Result<()> {
+    let listener = tokio::net::UnixListener::bind(path)?;
+    let served = router
+        .serve_with_incoming_shutdown(
+            tokio_stream::wrappers::UnixListenerStream::new(listener),
+            shutdown,
+        )
+        .await;
+    std::fs::remove_file(path)?;
+    Ok(served?)
+}
+
+#[cfg(not(unix))]
+async fn serve_uds(
+    _router: tonic::transport::server::Router, _path: &Path,
+    _shutdown: impl std::future::Future<Output = ()>,
+) -> Result<()> {
+    Err(anyhow!("Unix domain sockets are not supported on this platform"))
+}
+
+/// Reads a session or registration token key, hex encoded, from `path`.
+fn read_token_key(path: &Path) -> Result<TokenKey> {
+    let key = hex::decode(std::fs::read_to_string(path)?.trim())?;
+    let key = key
+        .try_into()
+        .map_err(|key: Vec<u8>| anyhow!("the key is {} bytes instead of 32", key.len()))?;
+    Ok(TokenKey::from_bytes(key))
+}
+
+/// An implementation of the `Auth` trait for handling user registration, authentication challenge creation,
 /// and verification in the Chaum-Pedersen Zero Knowledge Proof (ZKP) protocol.
 ///
-/// `AuthImpl` manages two key data structures that are protected by mutexes for concurrent access:
+/// `AuthImpl` holds the ZKP group parameters `zkp` shared by all users, and manages four key data structures
+/// that are protected by mutexes for concurrent access, and shared by the clones of an `AuthImpl`:
 ///
 /// - `user_info`: A `Mutex`-protected `HashMap` that stores user registration and authentication details,
 ///   with the username as the key and the associated `UserInfo` as the value. It holds the ZKP data for each user.
 ///
-/// - `auth_id_to_user`: A `Mutex`-protected `HashMap` that maps an authentication ID (generated during 
+/// - `auth_id_to_user`: A `Mutex`-protected `OutstandingChallenges` that maps an authentication ID (generated during
 ///   challenge creation) to the corresponding username. This is used to match a challenge response with the correct user.
 ///
-/// This struct is the main server-side component responsible for processing requests for registration, 
+/// - `sessions`: A `Mutex`-protected `SessionStore` holding the sessions created by successful authentications.
+///
+/// - `seen_commitments`: A `Mutex`-protected `SeenCommitments` remembering recent commitments, to refuse
+///   challenges for a reused nonce.
+///
+/// - `accepted_commitments`: A `Mutex`-protected `AcceptedCommitments` remembering the commitments of recent
+///   successful proofs, to refuse replays of them.
+///
+/// `lockouts` counts the failed authentications of each user, see `Lockouts`.
+///
+/// `user_ids` turns the user names of the requests into the keys of `user_info`.
+///
+/// `auth_id_keys` authenticate the authentication IDs issued with each challenge, and are
+/// rotated with `rotate_auth_id_key`.
+///
+/// When `session_tokens` is set, the session IDs are tokens signed with it, carrying the user and
+/// the expiry of the session, see `zkp_chaum_pedersen::token`.
+///
+/// `ready` is cleared while the store is being initialized (e.g. restored from `--state-file`);
+/// until it is set again, the requests using the store are refused with `Code::Unavailable`.
+///
+/// When `registration_ttl` is set, registrations are forgotten, along with their sessions, once
+/// they are older than it, see `expire_registrations`.
+///
+/// When `capture` is set, the registration, challenge and answer messages are also dumped to it.
+///
+/// When `transcripts` is set, the transcript of every completed authentication is handed to it,
+/// accepted or not, see `zkp_chaum_pedersen::transcript`.
+///
+/// `keep_alive_interval` is the time between two challenges on a keep-alive stream.
+///
+/// When `registration_disabled` is set, registrations are refused with `Code::PermissionDenied`:
+/// the users are provisioned out of band, e.g. with `preload_keys`.
+///
+/// When `registration_tokens` is set, registrations must carry a registration token signed with
+/// it for the same user, which is accepted once: the nonces of the tokens used are kept in
+/// `redeemed_registration_tokens` until the tokens expire, see `check_registration_token`.
+///
+/// Registration and challenge requests with a `protocol_version` below `min_protocol_version`,
+/// or above the `PROTOCOL_VERSION` of the server, are refused, see `check_protocol_version`.
+///
+/// When `challenge_bits` is set, challenges are drawn from `[0, 2^challenge_bits)` (still below
+/// `q`) instead of `[0, q)`, see `issue_challenge`.
+///
+/// The integers of the requests and responses, except the group parameters, are encoded in the
+/// byte order `endianness`, advertised to the clients by `get_parameters`.
+///
+/// When `verification_limit` is set, the answers, keep-alive answers and proofs of possession
+/// are verified by at most that many requests at once, see `VerificationLimit`.
+///
+/// This struct is the main server-side component responsible for processing requests for registration,
 /// challenge creation, and authentication verification in the ZKP protocol.
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
 pub struct AuthImpl {
-    pub user_info: Mutex<HashMap<String, UserInfo>>,
-    pub auth_id_to_user: Mutex<HashMap<String, String>>,
+    pub zkp: ZKP,
+    pub user_info: Arc<Mutex<HashMap<String, UserInfo>>>,
+    pub auth_id_to_user: Arc<Mutex<OutstandingChallenges>>,
+    pub sessions: Arc<Mutex<SessionStore>>,
+    pub seen_commitments: Arc<Mutex<SeenCommitments>>,
+    pub accepted_commitments: Arc<Mutex<AcceptedCommitments>>,
+    pub lockouts: Arc<Mutex<Lockouts>>,
+    pub user_ids: UserIds,
+    pub auth_id_keys: Arc<Mutex<AuthIdKeys>>,
+    pub session_tokens: Option<TokenKey>,
+    pub registration_ttl: Option<Duration>,
+    pub ready: Arc<AtomicBool>,
+    pub capture: Option<Arc<MessageCapture>>,
+    pub transcripts: Option<Arc<dyn TranscriptSink>>,
+    pub keep_alive_interval: Duration,
+    pub min_protocol_version: u32,
+    pub registration_disabled: bool,
+    pub registration_tokens: Option<TokenKey>,
+    pub redeemed_registration_tokens: Arc<Mutex<HashMap<[u8; 16], u64>>>,
+    pub challenge_bits: Option<u64>,
+    pub endianness: Endianness,
+    pub verification_limit: Option<VerificationLimit>,
 }
 
-/// A struct representing the information needed for the authentication and verification process
-/// using the Chaum-Pedersen Zero Knowledge Proof (ZKP) protocol.
-///
-/// `UserInfo` contains data for registration, authorization, and verification steps, including:
-///
-/// - **Registration**:
-///   - `user_name`: The username of the user.
-///   - `y1`, `y2`: The values used for registration, representing the user's computed public keys.
-///
-/// - **Authorization**:
-///   - `r1`, `r2`: The values sent by the user during the authentication challenge, computed as part of the ZKP protocol.
+/// Caps the number of verifications running at once, so they cannot take every CPU of a shared
+/// host.
 ///
-/// - **Verification**:
-///   - `c`: The challenge value generated by the server during the authentication process.
-///   - `s`: The solution provided by the user to prove they know the correct password.
-///   - `session_id`: A unique identifier for the authenticated session, generated after successful verification.
-#[derive(Debug, Default)]
-pub struct UserInfo {
-    //
-    // registration
-    pub user_name: String,
-    pub y1: BigUint,
-    pub y2: BigUint,
-    //
-    // authorization
-    pub r1: BigUint,
-    pub r2: BigUint,
-    //
-    // verification
-    pub c: BigUint,
-    pub s: BigUint,
-    pub session_id: String,
+/// Verifications beyond `limit` wait for a slot, in the order they asked for one. The slot is
+/// taken in the async request handlers, so the waiting requests do not block a runtime thread.
+#[derive(Debug, Clone)]
+pub struct VerificationLimit {
+    limit: usize,
+    slots: Arc<tokio::sync::Semaphore>,
 }
 
-#[tonic::async_trait]
-impl Auth for AuthImpl {
-    /// Registers a new user by storing their information in the `user_info` hashmap.
-    ///
-    /// This method takes a `RegisterRequest` containing the user's name and two big integers
-    /// (y1 and y2) that are part of the Chaum-Pedersen ZKP protocol. If registration is successful,
-    /// it logs a success message and returns an empty `RegisterResponse`.
-    ///
-    /// # Arguments
-    ///
-    /// * `request` - A `Request<RegisterRequest>` containing the user's registration details.
-    ///
-    /// # Returns
-    ///
-    /// A `Result` containing either a `Response<RegisterResponse>` on success or a `Status` error.
-    async fn register(
-        &self, request: Request<RegisterRequest>,
-    ) -> Result<Response<RegisterResponse>, Status> {
-        let request = request.into_inner();
+impl VerificationLimit {
+    /// Creates a limit of `limit` concurrent verifications.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            slots: Arc::new(tokio::sync::Semaphore::new(limit)),
+        }
+    }
 
-        let user_name = request.user;
-        println!("Processing Registration username: {:?}", user_name);
+    /// Returns the number of concurrent verifications allowed.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
 
-        let user_info = UserInfo {
-            user_name: user_name.clone(),
-            y1: BigUint::from_bytes_be(&request.y1),
-            y2: BigUint::from_bytes_be(&request.y2),
-            ..Default::default()
-        };
+    /// Waits for a free slot, held until the permit is dropped.
+    pub async fn acquire(&self) -> tokio::sync::SemaphorePermit<'_> {
+        self.slots
+            .acquire()
+            .await
+            .expect("the semaphore is never closed")
+    }
+}
 
-        {
-            let user_info_hashmap = &mut self.user_info.lock().unwrap();
-            user_info_hashmap.insert(user_name.clone(), user_info);
-        }
+/// The histogram recording the duration of each verification, see `AuthImpl::verify_logged`.
+pub const VERIFICATION_SECONDS: &str = "zkp_verification_seconds";
 
-        println!("✅ Successful Registration username: {:?}", user_name);
-        Ok(Response::new(RegisterResponse {}))
+/// The time between two challenges on a keep-alive stream by default.
+pub const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+impl Default for AuthImpl {
+    fn default() -> Self {
+        Self {
+            zkp: ZKP::default(),
+            user_info: Default::default(),
+            auth_id_to_user: Default::default(),
+            sessions: Default::default(),
+            seen_commitments: Default::default(),
+            accepted_commitments: Default::default(),
+            lockouts: Default::default(),
+            user_ids: UserIds::default(),
+            auth_id_keys: Default::default(),
+            session_tokens: None,
+            registration_ttl: None,
+            ready: Arc::new(AtomicBool::new(true)),
+            capture: None,
+            transcripts: None,
+            keep_alive_interval: DEFAULT_KEEP_ALIVE_INTERVAL,
+            min_protocol_version: 0,
+            registration_disabled: false,
+            registration_tokens: None,
+            redeemed_registration_tokens: Default::default(),
+            challenge_bits: None,
+            endianness: Endianness::default(),
+            verification_limit: None,
+        }
     }
+}
 
-    /// Creates an authentication challenge for a registered user.
-    ///
-    /// This method generates a random challenge value and an authentication ID for the user specified
-    /// in the `AuthenticationChallengeRequest`. It updates the user's information with the received
-    /// values and logs a success message upon completion.
-    ///
-    /// # Arguments
-    ///
-    /// * `request` - A `Request<AuthenticationChallengeRequest>` containing the user's name and 
-    ///               the values r1 and r2 for the challenge.
-    ///
-    /// # Returns
-    ///
-    /// A `Result` containing either a `Response<AuthenticationChallengeResponse>` with the challenge details 
-    /// or a `Status` error if the user is not found.
-    async fn create_authentication_challenge(
-        &self, request: Request<AuthenticationChallengeRequest>,
-    ) -> Result<Response<AuthenticationChallengeResponse>, Status> {
-        let request = request.into_inner();
+/// A debug capture of the protocol messages, written as hex dumps to a file.
+///
+/// Each message is written as a header line with its direction (`>>>` received, `<<<` sent),
+/// type and length, followed by a line with the hex encoding of its protobuf bytes. Nothing is
+/// redacted, so this is only meant for post-mortem debugging.
+///
+/// The dump is taken in the handlers rather than in a tonic interceptor, since interceptors
+/// only see the request metadata, not the message.
+#[derive(Debug)]
+pub struct MessageCapture {
+    file: Mutex<File>,
+}
 
-        let user_name = request.user;
-        println!("Processing Challenge Request username: {:?}", user_name);
+impl MessageCapture {
+    /// Creates (or truncates) the capture file at `path`.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            file: Mutex::new(File::create(path)?),
+        })
+    }
 
-        let user_info_hashmap = &mut self.user_info.lock().unwrap();
+    /// Appends one message to the capture.
+    pub fn record(&self, direction: &str, name: &str, message: &impl Message) -> io::Result<()> {
+        let bytes = message.encode_to_vec();
+        let file = &mut self.file.lock().unwrap();
+        writeln!(file, "{} {} {} bytes", direction, name, bytes.len())?;
+        writeln!(file, "{}", hex::encode(&bytes))?;
+        file.flush()
+    }
+}
 
-        if let Some(user_info) = user_info_hashmap.get_mut(&user_name) {
-            let (_, _, _, q) = ZKP::get_constants();
-            let c = ZKP::generate_random_number_below(&q);
-            let auth_id = ZKP::generate_random_string(12);
+/// How `TranscriptLog` stores the transcripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TranscriptCompression {
+    /// One line of JSON per transcript.
+    #[default]
+    None,
+    /// One zstd frame per transcript, holding its line of JSON.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
 
-            user_info.c.clone_from(&c);
-            user_info.r1 = BigUint::from_bytes_be(&request.r1);
-            user_info.r2 = BigUint::from_bytes_be(&request.r2);
+/// The first bytes of a zstd frame, which tell a compressed log from a JSON one.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
 
-            let auth_id_to_user = &mut self.auth_id_to_user.lock().unwrap();
-            auth_id_to_user.insert(auth_id.clone(), user_name.clone());
+/// The zstd level of the compressed transcript logs.
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 3;
 
-            println!("✅ Successful Challenge Request username: {:?}", user_name);
+/// A `TranscriptSink` appending each transcript to a file as a line of JSON, optionally
+/// compressed.
+///
+/// Compressed transcripts are written as one zstd frame each, so every record is complete on
+/// disk once written, and the file is one valid zstd stream. `read` tells both formats apart.
+///
+/// Failing to write a transcript is logged, but does not fail the authentication.
+#[derive(Debug)]
+pub struct TranscriptLog {
+    file: Mutex<File>,
+    compression: TranscriptCompression,
+}
 
-            Ok(Response::new(AuthenticationChallengeResponse {
-                auth_id,
-                c: c.to_bytes_be(),
-            }))
-        } else {
-            Err(Status::new(
-                Code::NotFound,
-                format!("User: {} not found in database", user_name),
-            ))
+impl TranscriptLog {
+    /// Opens the log at `path` for appending, creating it if needed.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the log, or an `InvalidInput` error if the existing log is
+    /// stored with another `compression`, since the two cannot be mixed in one file.
+    pub fn open(path: &Path, compression: TranscriptCompression) -> io::Result<Self> {
+        let mut file = File::options()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        let mut magic = [0u8; ZSTD_MAGIC.len()];
+        let read = io::Read::read(&mut file, &mut magic)?;
+        if read > 0
+            && Self::is_compressed(&magic[..read]) != (compression != TranscriptCompression::None)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the existing log is stored with another compression",
+            ));
         }
+        Ok(Self {
+            file: Mutex::new(file),
+            compression,
+        })
     }
 
-    /// Verifies the solution to an authentication challenge.
-    ///
-    /// This method checks the provided solution against the stored user information using the 
-    /// ZKP verification process. If successful, it generates a session ID and returns it in the 
-    /// `AuthenticationAnswerResponse`. If the verification fails, an error status is returned.
-    ///
-    /// # Arguments
-    ///
-    /// * `request` - A `Request<AuthenticationAnswerRequest>` containing the authentication ID and the solution.
+    /// Reads back the transcripts of the log at `path`, decompressing them if needed.
+    pub fn read(path: &Path) -> io::Result<Vec<Transcript>> {
+        let bytes = std::fs::read(path)?;
+        let json = if Self::is_compressed(&bytes) {
+            Self::decompress(&bytes)?
+        } else {
+            bytes
+        };
+        let json = String::from_utf8(json).map_err(io::Error::other)?;
+        json.lines()
+            .map(|line| serde_json::from_str(line).map_err(io::Error::other))
+            .collect()
+    }
+
+    fn is_compressed(bytes: &[u8]) -> bool {
+        bytes.starts_with(&ZSTD_MAGIC)
+    }
+
+    #[cfg(feature = "zstd")]
+    fn decompress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+        zstd::decode_all(bytes)
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    fn decompress(_bytes: &[u8]) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the log is compressed with zstd, which needs the zstd feature",
+        ))
+    }
+
+    /// Encodes one transcript as stored in the log.
+    fn encode(&self, transcript: &Transcript) -> io::Result<Vec<u8>> {
+        let mut line = serde_json::to_vec(transcript).expect("transcripts serialize to JSON");
+        line.push(b'\n');
+        match self.compression {
+            TranscriptCompression::None => Ok(line),
+            #[cfg(feature = "zstd")]
+            TranscriptCompression::Zstd => zstd::encode_all(&line[..], ZSTD_LEVEL),
+        }
+    }
+}
+
+impl TranscriptSink for TranscriptLog {
+    fn record(&self, transcript: &Transcript) {
+        let file = &mut self.file.lock().unwrap();
+        let written = self
+            .encode(transcript)
+            .and_then(|bytes| file.write_all(&bytes))
+            .and_then(|_| file.flush());
+        if let Err(e) = written {
+            println!("⚠️ Could not log transcript error: {:?}", e);
+        }
+    }
+}
+
+/// The lifetime of a session by default, from its creation or its last keep-alive proof.
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// The active sessions, mapping each session ID to the username it was created for.
+///
+/// A session expires `ttl` after its creation, unless it is extended with `extend`.
+#[derive(Debug)]
+pub struct SessionStore {
+    ttl: Duration,
+    session_id_to_user: HashMap<String, Session>,
+}
+
+/// A session of `SessionStore`.
+#[derive(Debug)]
+struct Session {
+    user_name: String,
+    expires_at: SystemTime,
+}
+
+impl Default for SessionStore {
+    fn default() -> Self {
+        Self::with_ttl(DEFAULT_SESSION_TTL)
+    }
+}
+
+impl SessionStore {
+    /// Creates an empty store whose sessions live for `ttl`.
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            session_id_to_user: HashMap::new(),
+        }
+    }
+
+    /// Records a new session for `user_name`.
+    pub fn insert(&mut self, session_id: &str, user_name: &str) {
+        let session = Session {
+            user_name: user_name.to_string(),
+            expires_at: SystemTime::now() + self.ttl,
+        };
+        self.session_id_to_user
+            .insert(session_id.to_string(), session);
+    }
+
+    /// Returns the username of an active session, if any.
+    pub fn user(&self, session_id: &str) -> Option<&str> {
+        self.session_id_to_user
+            .get(session_id)
+            .filter(|session| session.expires_at > SystemTime::now())
+            .map(|session| session.user_name.as_str())
+    }
+
+    /// Returns the expiry of an active session, if any.
+    pub fn expires_at(&self, session_id: &str) -> Option<SystemTime> {
+        self.session_id_to_user
+            .get(session_id)
+            .map(|session| session.expires_at)
+            .filter(|expires_at| *expires_at > SystemTime::now())
+    }
+
+    /// Pushes the expiry of an active session to `ttl` from now and returns it.
+    pub fn extend(&mut self, session_id: &str) -> Option<SystemTime> {
+        let now = SystemTime::now();
+        let session = self
+            .session_id_to_user
+            .get_mut(session_id)
+            .filter(|session| session.expires_at > now)?;
+        session.expires_at = now + self.ttl;
+        Some(session.expires_at)
+    }
+
+    /// Returns how long a new session lives.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Records a session for `user_name` expiring at `expires_at`, e.g. restored from a backup.
+    pub fn insert_until(&mut self, session_id: &str, user_name: &str, expires_at: SystemTime) {
+        let session = Session {
+            user_name: user_name.to_string(),
+            expires_at,
+        };
+        self.session_id_to_user
+            .insert(session_id.to_string(), session);
+    }
+
+    /// Returns the ID, username and expiry of every active session.
+    pub fn active(&self) -> impl Iterator<Item = (&str, &str, SystemTime)> {
+        let now = SystemTime::now();
+        self.session_id_to_user
+            .iter()
+            .filter(move |(_, session)| session.expires_at > now)
+            .map(|(session_id, session)| {
+                (session_id.as_str(), session.user_name.as_str(), session.expires_at)
+            })
+    }
+
+    /// Removes a session.
+    pub fn remove(&mut self, session_id: &str) {
+        self.session_id_to_user.remove(session_id);
+    }
+
+    /// Removes every session of `user_name` and returns how many were removed.
+    pub fn remove_user(&mut self, user_name: &str) -> usize {
+        let before = self.session_id_to_user.len();
+        self.session_id_to_user
+            .retain(|_, session| session.user_name != user_name);
+        before - self.session_id_to_user.len()
+    }
+}
+
+/// The number of recent commitments remembered by default to detect nonce reuse.
+pub const DEFAULT_SEEN_COMMITMENTS: usize = 100_000;
+
+/// The most recently seen commitments `(r1, r2)`, used to detect nonce reuse.
+///
+/// A commitment is `r1 = alpha^k mod p`, `r2 = beta^k mod p` for the prover's random nonce `k`.
+/// Answering two different challenges `c1` and `c2` with the same nonce gives the secret away:
+/// `s1 - s2 = (c2 - c1) * x mod q`, so `x = (s1 - s2) * (c2 - c1)^-1 mod q`. The server never sees
+/// `k`, but a repeated `(r1, r2)` means a repeated `k`, so a challenge for it is refused before the
+/// prover gets the chance to leak its secret.
+///
+/// Only the last `capacity` commitments are remembered, to bound memory use.
+#[derive(Debug)]
+pub struct SeenCommitments {
+    capacity: usize,
+    order: VecDeque<(BigUint, BigUint)>,
+    seen: HashSet<(BigUint, BigUint)>,
+}
+
+impl Default for SeenCommitments {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_SEEN_COMMITMENTS)
+    }
+}
+
+impl SeenCommitments {
+    /// Creates an empty log remembering at most `capacity` commitments.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Returns whether the commitment was seen recently.
+    pub fn contains(&self, commitment: &(BigUint, BigUint)) -> bool {
+        self.seen.contains(commitment)
+    }
+
+    /// Records a commitment, forgetting the oldest one if full.
     ///
-    /// # Returns
+    /// Returns `false` if the commitment was already seen.
+    pub fn insert(&mut self, commitment: (BigUint, BigUint)) -> bool {
+        if self.seen.contains(&commitment) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(commitment.clone());
+        self.seen.insert(commitment);
+        true
+    }
+}
+
+/// How user names are turned into the user IDs keying the user store.
+///
+/// With `normalize`, user names are trimmed and lowercased, so `Alice` and `alice` are the same
+/// user. With `hash`, the ID is the hex encoded `SHA-256` of the (normalized) user name, so the
+/// raw user names are not kept in the store. Both are off by default: the ID is the user name.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserIds {
+    pub normalize: bool,
+    pub hash: bool,
+}
+
+impl UserIds {
+    /// Returns the user ID of `user_name`.
+    pub fn id(&self, user_name: &str) -> String {
+        let user_name = if self.normalize {
+            user_name.trim().to_lowercase()
+        } else {
+            user_name.to_string()
+        };
+        if self.hash {
+            hex::encode(Sha256::digest(user_name.as_bytes()))
+        } else {
+            user_name
+        }
+    }
+}
+
+/// The server key binding each authentication ID to the challenge it was issued with.
+///
+/// An authentication ID is a random nonce followed by `-` and a truncated
+/// `HMAC-SHA256(key, nonce || c)`. The answer to a challenge is only checked if its
+/// authentication ID carries a valid tag for the stored challenge, so an entry planted in the
+/// store without the key cannot be used to get a forged challenge verified.
+#[derive(Clone)]
+pub struct AuthIdKey([u8; 32]);
+
+impl std::fmt::Debug for AuthIdKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AuthIdKey(..)")
+    }
+}
+
+impl AuthIdKey {
+    /// The number of HMAC bytes kept in an authentication ID.
+    const TAG_LEN: usize = 16;
+
+    /// Generates a random key.
+    pub fn generate() -> Self {
+        Self(SecureRng::default().gen())
+    }
+
+    /// Issues a new authentication ID for the challenge `c`.
+    pub fn issue(&self, c: &BigUint) -> String {
+        self.issue_with_nonce(&ZKP::generate_random_string(DEFAULT_AUTH_ID_NONCE_LEN), c)
+    }
+
+    /// Issues an authentication ID for the challenge `c`, starting with `nonce`.
+    pub fn issue_with_nonce(&self, nonce: &str, c: &BigUint) -> String {
+        format!("{}-{}", nonce, hex::encode(self.tag(nonce, c)))
+    }
+
+    /// Checks that `auth_id` was issued by `issue` with this key for the challenge `c`.
+    pub fn was_issued(&self, auth_id: &str, c: &BigUint) -> bool {
+        let Some((nonce, tag)) = auth_id.split_once('-') else {
+            return false;
+        };
+        let Ok(tag) = hex::decode(tag) else {
+            return false;
+        };
+        tag.len() == Self::TAG_LEN && self.mac(nonce, c).verify_truncated_left(&tag).is_ok()
+    }
+
+    fn tag(&self, nonce: &str, c: &BigUint) -> Vec<u8> {
+        self.mac(nonce, c).finalize().into_bytes()[..Self::TAG_LEN].to_vec()
+    }
+
+    fn mac(&self, nonce: &str, c: &BigUint) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes keys of any size");
+        mac.update(nonce.as_bytes());
+        mac.update(&c.to_bytes_be());
+        mac
+    }
+}
+
+/// The keys binding authentication IDs to challenges, rotated with `rotate`.
+///
+/// New authentication IDs are issued with the `current` key, each starting with a random nonce
+/// of `nonce_len` alphanumeric characters drawn by `nonce_source`. After a rotation, the
+/// `previous` key is still accepted until its deadline, so the challenges issued just before it
+/// can still be answered, and is dropped afterwards. A rotation within the window of the
+/// previous one drops the older key at once.
+#[derive(Debug, Clone)]
+pub struct AuthIdKeys {
+    current: AuthIdKey,
+    previous: Option<(AuthIdKey, Instant)>,
+    nonce_len: usize,
+    nonce_source: fn(usize) -> String,
+}
+
+impl Default for AuthIdKeys {
+    fn default() -> Self {
+        Self::with_nonce_len(DEFAULT_AUTH_ID_NONCE_LEN)
+    }
+}
+
+impl AuthIdKeys {
+    /// Creates a random key issuing authentication IDs with nonces of `nonce_len` characters.
+    pub fn with_nonce_len(nonce_len: usize) -> Self {
+        Self {
+            current: AuthIdKey::generate(),
+            previous: None,
+            nonce_len,
+            nonce_source: ZKP::generate_random_string,
+        }
+    }
+
+    /// Issues a new authentication ID for the challenge `c` with the current key.
     ///
-    /// A `Result` containing either a `Response<AuthenticationAnswerResponse>` with the session ID 
-    /// or a `Status` error if the authentication ID is not found or the solution is incorrect.
-     async fn verify_authentication(
-        &self, request: Request<AuthenticationAnswerRequest>,
-    ) -> Result<Response<AuthenticationAnswerResponse>, Status> {
-        let request = request.into_inner();
+    /// Two IDs only collide if both their nonces and their challenges do, see
+    /// `AuthImpl::issue_auth_id` for what happens then.
+    pub fn issue(&self, c: &BigUint) -> String {
+        self.current
+            .issue_with_nonce(&(self.nonce_source)(self.nonce_len), c)
+    }
 
-        let auth_id = request.auth_id;
-        println!("Processing Challenge Solution auth_id: {:?}", auth_id);
+    /// Checks that `auth_id` was issued for the challenge `c` with the current key, or with the
+    /// previous one within its window.
+    pub fn was_issued(&mut self, auth_id: &str, c: &BigUint) -> bool {
+        if self.current.was_issued(auth_id, c) {
+            return true;
+        }
+        match &self.previous {
+            Some((_, deadline)) if Instant::now() >= *deadline => {
+                self.previous = None;
+                false
+            }
+            Some((previous, _)) => previous.was_issued(auth_id, c),
+            None => false,
+        }
+    }
 
-        let auth_id_to_user_hashmap = &mut self.auth_id_to_user.lock().unwrap();
+    /// Replaces the current key with a new random one, accepting the replaced one for `window`.
+    pub fn rotate(&mut self, window: Duration) {
+        let previous = std::mem::replace(&mut self.current, AuthIdKey::generate());
+        self.previous = Some((previous, Instant::now() + window));
+    }
 
-        if let Some(user_name) = auth_id_to_user_hashmap.get(&auth_id) {
-            let user_info_hashmap = &mut self.user_info.lock().unwrap();
-            let user_info = user_info_hashmap.get_mut(user_name).ok_or_else(|| {
-                Status::new(Code::NotFound, format!("AuthId {} not found in database", auth_id))
-            })?;
-
-            let s = BigUint::from_bytes_be(&request.s);
-            user_info.s = s;
-
-            let (alpha, beta, p, q) = ZKP::get_constants();
-            let zkp = ZKP { alpha, beta, p, q };
-
-            let verification = zkp.verify(
-                &user_info.r1,
-                &user_info.r2,
-                &user_info.y1,
-                &user_info.y2,
-                &user_info.c,
-                &user_info.s,
-            );
+    /// Drops the previous key before the end of its window.
+    pub fn drop_previous(&mut self) {
+        self.previous = None;
+    }
+}
 
-            if verification {
-                let session_id = ZKP::generate_random_string(12);
+/// The number of characters of the nonce of an authentication ID by default, about 71 random
+/// bits, see `AuthIdKeys`.
+pub const DEFAULT_AUTH_ID_NONCE_LEN: usize = 12;
 
-                println!("✅ Correct Challenge Solution username: {:?}", user_name);
+/// The number of authentication IDs drawn for a challenge before giving up, if each collides
+/// with an outstanding one.
+pub const AUTH_ID_ATTEMPTS: usize = 8;
 
-                Ok(Response::new(AuthenticationAnswerResponse { session_id }))
-            } else {
-                println!("❌ Wrong Challenge Solution username: {:?}", user_name);
+/// How long the key replaced by a rotation is still accepted by default, see `AuthIdKeys`.
+pub const DEFAULT_AUTH_ID_KEY_ROTATION_WINDOW: Duration = Duration::from_secs(5 * 60);
 
-                Err(Status::new(
-                    Code::PermissionDenied,
-                    format!("AuthId: {} bad solution to the challenge", auth_id),
-                ))
+/// The number of accepted commitments remembered by default to detect replays.
+pub const DEFAULT_ACCEPTED_COMMITMENTS: usize = 100_000;
+
+/// How `AcceptedCommitments` makes room for a new entry once full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Eviction {
+    /// Forget the least recently used entry, a replay attempt counts as a use.
+    #[default]
+    Lru,
+    /// Forget the oldest entry.
+    Fifo,
+}
+
+/// The commitments `(r1, r2)` of recently accepted proofs, keyed by user.
+///
+/// Someone who captured a successful `(r1, r2, s)` could try to replay it. A challenge for an
+/// accepted commitment is refused with `Code::AlreadyExists`, so the captured solution is never
+/// checked against a new challenge.
+///
+/// Only the last `capacity` commitments are remembered, to bound memory use.
+#[derive(Debug)]
+pub struct AcceptedCommitments {
+    capacity: usize,
+    eviction: Eviction,
+    // use order, each entry with the tick of the use; an entry used again is pushed anew and its
+    // earlier uses, whose tick is stale, are skipped and removed lazily
+    order: VecDeque<(u64, AcceptedCommitment)>,
+    // the tick of the last use of each entry
+    accepted: HashMap<AcceptedCommitment, u64>,
+    tick: u64,
+}
+
+/// A user and the commitment `(r1, r2)` of one of its accepted proofs.
+type AcceptedCommitment = (String, BigUint, BigUint);
+
+impl Default for AcceptedCommitments {
+    fn default() -> Self {
+        Self::new(DEFAULT_ACCEPTED_COMMITMENTS, Eviction::default())
+    }
+}
+
+impl AcceptedCommitments {
+    /// Creates an empty cache remembering at most `capacity` commitments.
+    pub fn new(capacity: usize, eviction: Eviction) -> Self {
+        Self {
+            capacity,
+            eviction,
+            order: VecDeque::new(),
+            accepted: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Records the commitment of an accepted proof of `user_name`, forgetting one entry if full.
+    pub fn insert(&mut self, user_name: &str, (r1, r2): (BigUint, BigUint)) {
+        let entry = (user_name.to_string(), r1, r2);
+        if self.accepted.contains_key(&entry) || self.capacity == 0 {
+            return;
+        }
+        if self.accepted.len() >= self.capacity {
+            while let Some((tick, oldest)) = self.order.pop_front() {
+                if self.accepted.get(&oldest) == Some(&tick) {
+                    self.accepted.remove(&oldest);
+                    break;
+                }
             }
-        } else {
-            Err(Status::new(
-                Code::NotFound,
-                format!("AuthId: {} not found in database", auth_id),
-            ))
         }
+        self.accepted.insert(entry.clone(), self.tick);
+        self.push(entry);
+    }
+
+    /// Checks whether a commitment of `user_name` was accepted, marking it as used.
+    pub fn contains(&mut self, user_name: &str, (r1, r2): &(BigUint, BigUint)) -> bool {
+        let entry = (user_name.to_string(), r1.clone(), r2.clone());
+        let Some(last_use) = self.accepted.get_mut(&entry) else {
+            return false;
+        };
+        if self.eviction == Eviction::Lru {
+            *last_use = self.tick;
+            self.push(entry);
+        }
+        true
+    }
+
+    /// Appends a use of `entry` at the current tick, the one `accepted` was just given.
+    fn push(&mut self, entry: AcceptedCommitment) {
+        self.order.push_back((self.tick, entry));
+        self.tick += 1;
+        // drop the stale uses once they make up most of `order`
+        if self.order.len() >= 2 * self.capacity.max(1) {
+            let accepted = &self.accepted;
+            self.order
+                .retain(|(tick, entry)| accepted.get(entry) == Some(tick));
+        }
+    }
+}
+
+/// The number of outstanding challenges kept at most by default.
+pub const DEFAULT_MAX_OUTSTANDING_CHALLENGES: usize = 100_000;
+
+/// What `OutstandingChallenges` does with a new challenge once full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ChallengeOverflow {
+    /// Refuse the new challenge with `Code::ResourceExhausted`.
+    #[default]
+    Reject,
+    /// Forget the oldest outstanding challenge, whose answer will then be refused.
+    EvictOldest,
+}
+
+/// The challenges awaiting an answer, mapping each authentication ID to the username it was
+/// issued for.
+///
+/// A challenge is forgotten once answered, right or wrong, or cancelled, and a user has at most
+/// one: a new challenge replaces the previous one, which could not be answered anyway since the
+/// server only keeps the latest challenge of each user. Clients that never answer would let the
+/// map grow without bound, so at most `capacity` challenges are kept; what happens to the next
+/// one is set by `overflow`.
+#[derive(Debug)]
+pub struct OutstandingChallenges {
+    capacity: usize,
+    overflow: ChallengeOverflow,
+    // issue order, with the IDs of answered challenges removed lazily
+    order: VecDeque<String>,
+    auth_id_to_user: HashMap<String, String>,
+    user_to_auth_id: HashMap<String, String>,
+}
+
+impl Default for OutstandingChallenges {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_OUTSTANDING_CHALLENGES, ChallengeOverflow::default())
+    }
+}
+
+impl OutstandingChallenges {
+    /// Creates an empty map keeping at most `capacity` challenges.
+    pub fn new(capacity: usize, overflow: ChallengeOverflow) -> Self {
+        Self {
+            capacity,
+            overflow,
+            order: VecDeque::new(),
+            auth_id_to_user: HashMap::new(),
+            user_to_auth_id: HashMap::new(),
+        }
+    }
+
+    /// Records a challenge issued for `user_name`, replacing its outstanding one if any.
+    ///
+    /// Returns `false`, without recording it, if the map is full and `overflow` is `Reject`.
+    pub fn insert(&mut self, auth_id: String, user_name: String) -> bool {
+        let replaced = self.user_to_auth_id.contains_key(&user_name);
+        if !replaced && self.auth_id_to_user.len() >= self.capacity {
+            if self.overflow == ChallengeOverflow::Reject || self.capacity == 0 {
+                return false;
+            }
+            while let Some(oldest) = self.order.pop_front() {
+                if self.remove(&oldest).is_some() {
+                    break;
+                }
+            }
+        }
+        if let Some(previous) = self.user_to_auth_id.remove(&user_name) {
+            self.auth_id_to_user.remove(&previous);
+        }
+        // drop the IDs of the answered challenges once they make up most of `order`
+        if self.order.len() >= 2 * self.capacity.max(1) {
+            let auth_id_to_user = &self.auth_id_to_user;
+            self.order
+                .retain(|auth_id| auth_id_to_user.contains_key(auth_id));
+        }
+        self.order.push_back(auth_id.clone());
+        if let Some(previous_user) = self
+            .auth_id_to_user
+            .insert(auth_id.clone(), user_name.clone())
+        {
+            self.user_to_auth_id.remove(&previous_user);
+        }
+        self.user_to_auth_id.insert(user_name, auth_id);
+        true
+    }
+
+    /// Returns whether a challenge with this authentication ID is outstanding.
+    pub fn contains(&self, auth_id: &str) -> bool {
+        self.auth_id_to_user.contains_key(auth_id)
+    }
+
+    /// Returns the username a challenge was issued for.
+    pub fn get(&self, auth_id: &str) -> Option<&String> {
+        self.auth_id_to_user.get(auth_id)
+    }
+
+    /// Forgets a challenge, returning the username it was issued for.
+    pub fn remove(&mut self, auth_id: &str) -> Option<String> {
+        let user_name = self.auth_id_to_user.remove(auth_id)?;
+        self.user_to_auth_id.remove(&user_name);
+        Some(user_name)
+    }
+
+    /// Returns the number of outstanding challenges.
+    pub fn len(&self) -> usize {
+        self.auth_id_to_user.len()
+    }
+
+    /// Returns whether no challenge is outstanding.
+    pub fn is_empty(&self) -> bool {
+        self.auth_id_to_user.is_empty()
+    }
+}
+
+/// The number of consecutive failed authentications locking a user out by default.
+pub const DEFAULT_MAX_FAILED_ATTEMPTS: u32 = 5;
+
+/// How long a user stays locked out by default.
+pub const DEFAULT_LOCKOUT_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+/// The consecutive failed authentications of each user.
+///
+/// After `max_failures` wrong solutions in a row, the user is locked out for `cooldown`: its
+/// challenges and answers are refused with `Code::ResourceExhausted`, which slows down online
+/// brute-forcing of weak passwords. The count starts over once locked, and is cleared by a
+/// successful authentication. A `max_failures` of `0` disables the lockout.
+#[derive(Debug)]
+pub struct Lockouts {
+    max_failures: u32,
+    cooldown: Duration,
+    users: HashMap<String, FailedAttempts>,
+}
+
+/// The failed attempts of one user in `Lockouts`.
+#[derive(Debug, Default)]
+struct FailedAttempts {
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+impl Default for Lockouts {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FAILED_ATTEMPTS, DEFAULT_LOCKOUT_COOLDOWN)
+    }
+}
+
+impl Lockouts {
+    /// Creates a tracker locking users out for `cooldown` after `max_failures` failures.
+    pub fn new(max_failures: u32, cooldown: Duration) -> Self {
+        Self {
+            max_failures,
+            cooldown,
+            users: HashMap::new(),
+        }
+    }
+
+    /// Returns how long `user_name` stays locked out, if it is.
+    pub fn locked_for(&self, user_name: &str) -> Option<Duration> {
+        let locked_until = self.users.get(user_name)?.locked_until?;
+        let now = Instant::now();
+        (locked_until > now).then(|| locked_until - now)
+    }
+
+    /// Records a failed authentication of `user_name`, and returns whether it locked the user.
+    pub fn record_failure(&mut self, user_name: &str) -> bool {
+        if self.max_failures == 0 {
+            return false;
+        }
+        let attempts = self.users.entry(user_name.to_string()).or_default();
+        attempts.failures += 1;
+        if attempts.failures < self.max_failures {
+            return false;
+        }
+        attempts.failures = 0;
+        attempts.locked_until = Some(Instant::now() + self.cooldown);
+        true
+    }
+
+    /// Records a successful authentication of `user_name`, clearing its failures.
+    pub fn record_success(&mut self, user_name: &str) {
+        self.users.remove(user_name);
+    }
+}
+
+/// A struct representing the information needed for the authentication and verification process
+/// using the Chaum-Pedersen Zero Knowledge Proof (ZKP) protocol.
+///
+/// `UserInfo` contains data for registration, authorization, and verification steps, including:
+///
+/// - **Registration**:
+///   - `user_name`: The username of the user.
+///   - `y1`, `y2`: The values used for registration, representing the user's computed public keys.
+///   - `expires_at`: When the registration is forgotten, if the server has a `registration_ttl`.
+///
+/// - **Authorization**:
+///   - `r1`, `r2`: The values sent by the user during the authentication challenge, computed as part of the ZKP protocol.
+///
+/// - **Verification**:
+///   - `c`: The challenge value generated by the server during the authentication process.
+///   - `s`: The solution provided by the user to prove they know the correct password.
+///   - `session_id`: A unique identifier for the authenticated session, generated after successful verification.
+///
+/// - **Additional credentials**:
+///   - `credentials`: Labeled credentials registered next to the primary `y1`, `y2` pair (e.g. a
+///     hardware token). Every one of them must be proven, with the same challenge `c`, to authenticate.
+#[derive(Debug, Default)]
+pub struct UserInfo {
+    //
+    // registration
+    pub user_name: String,
+    pub y1: BigUint,
+    pub y2: BigUint,
+    pub expires_at: Option<SystemTime>,
+    //
+    // authorization
+    pub r1: BigUint,
+    pub r2: BigUint,
+    //
+    // verification
+    pub c: BigUint,
+    pub s: BigUint,
+    pub session_id: String,
+    //
+    // additional credentials, keyed by label
+    pub credentials: BTreeMap<String, CredentialInfo>,
+}
+
+/// The per-credential part of `UserInfo` for an additional labeled credential.
+///
+/// - `y1`, `y2`: The registered public values of the credential.
+/// - `r1`, `r2`: The commitment sent for the credential during the authentication challenge.
+/// - `s`: The solution provided for the credential.
+#[derive(Debug, Clone, Default)]
+pub struct CredentialInfo {
+    pub y1: BigUint,
+    pub y2: BigUint,
+    pub r1: BigUint,
+    pub r2: BigUint,
+    pub s: BigUint,
+}
+
+/// An answer to a challenge with the values it is checked against, copied out of the user store
+/// so that it is verified without holding its lock, see `AuthImpl::check_answer`.
+///
+/// The primary credential is kept as a `CredentialInfo` too. A labeled credential the answer has
+/// no solution for is `None`.
+#[derive(Debug)]
+struct PendingAnswer {
+    user_name: String,
+    c: BigUint,
+    primary: CredentialInfo,
+    credentials: Vec<(String, Option<CredentialInfo>)>,
+}
+
+/// The HTTP/JSON gateway, for clients that cannot speak gRPC.
+///
+/// `POST /register`, `POST /challenge` and `POST /answer` take and return the JSON counterparts
+/// of `RegisterRequest`, `AuthenticationChallengeRequest` and `AuthenticationAnswerRequest`
+/// (with numbers hex encoded big-endian whatever the server's `--endianness`, and without
+/// additional credentials), and are served by the same `Auth` handlers as the gRPC requests,
+/// the numbers converted to and from the server's byte order. Errors are returned as `{"error": message}` with the
+/// HTTP status matching the gRPC code.
+fn http_gateway(auth: AuthImpl) -> Router {
+    Router::new()
+        .route("/register", post(http_register))
+        .route("/challenge", post(http_challenge))
+        .route("/answer", post(http_answer))
+        .with_state(auth)
+}
+
+/// The body of `POST /register`, see `RegisterRequest`.
+#[derive(Debug, Serialize, Deserialize)]
+struct HttpRegisterRequest {
+    user: String,
+    y1: String,
+    y2: String,
+    possession_proof: String,
+    #[serde(default)]
+    registration_token: String,
+}
+
+/// The body of `POST /challenge`, see `AuthenticationChallengeRequest`.
+#[derive(Debug, Serialize, Deserialize)]
+struct HttpChallengeRequest {
+    user: String,
+    r1: String,
+    r2: String,
+}
+
+/// The response to `POST /challenge`, see `AuthenticationChallengeResponse`.
+#[derive(Debug, Serialize, Deserialize)]
+struct HttpChallengeResponse {
+    auth_id: String,
+    c: String,
+}
+
+/// The body of `POST /answer`, see `AuthenticationAnswerRequest`.
+#[derive(Debug, Serialize, Deserialize)]
+struct HttpAnswerRequest {
+    auth_id: String,
+    s: String,
+}
+
+/// The response to `POST /answer`, see `AuthenticationAnswerResponse`.
+#[derive(Debug, Serialize, Deserialize)]
+struct HttpAnswerResponse {
+    session_id: String,
+}
+
+/// An error of the HTTP/JSON gateway: the HTTP status and the JSON body.
+type HttpError = (StatusCode, Json<serde_json::Value>);
+
+/// Maps a `Status` of the `Auth` handlers to an `HttpError`.
+fn http_error(status: Status) -> HttpError {
+    let code = match status.code() {
+        Code::InvalidArgument => StatusCode::BAD_REQUEST,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::AlreadyExists => StatusCode::CONFLICT,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (code, Json(serde_json::json!({ "error": status.message() })))
+}
+
+/// Decodes a hex encoded field of a gateway request.
+fn http_hex(value: &str, name: &str) -> Result<Vec<u8>, HttpError> {
+    hex::decode(value).map_err(|e| {
+        http_error(Status::new(
+            Code::InvalidArgument,
+            format!("{} is not hex encoded: {}", name, e),
+        ))
+    })
+}
+
+/// Decodes a hex encoded big-endian number of a gateway request into the byte order
+/// `endianness` of the server, keeping its width.
+fn http_number(value: &str, name: &str, endianness: Endianness) -> Result<Vec<u8>, HttpError> {
+    let mut bytes = http_hex(value, name)?;
+    if endianness == Endianness::Little {
+        bytes.reverse();
+    }
+    Ok(bytes)
+}
+
+async fn http_register(
+    State(auth): State<AuthImpl>, Json(request): Json<HttpRegisterRequest>,
+) -> Result<Json<serde_json::Value>, HttpError> {
+    let request = RegisterRequest {
+        user: request.user,
+        y1: http_number(&request.y1, "y1", auth.endianness)?,
+        y2: http_number(&request.y2, "y2", auth.endianness)?,
+        protocol_version: PROTOCOL_VERSION,
+        possession_proof: http_hex(&request.possession_proof, "possession_proof")?,
+        registration_token: request.registration_token,
+        ..Default::default()
+    };
+    auth.register(Request::new(request))
+        .await
+        .map_err(http_error)?;
+    Ok(Json(serde_json::json!({})))
+}
+
+async fn http_challenge(
+    State(auth): State<AuthImpl>, Json(request): Json<HttpChallengeRequest>,
+) -> Result<Json<HttpChallengeResponse>, HttpError> {
+    let request = AuthenticationChallengeRequest {
+        user: request.user,
+        r1: http_number(&request.r1, "r1", auth.endianness)?,
+        r2: http_number(&request.r2, "r2", auth.endianness)?,
+        protocol_version: PROTOCOL_VERSION,
+        ..Default::default()
+    };
+    let response = auth
+        .create_authentication_challenge(Request::new(request))
+        .await
+        .map_err(http_error)?
+        .into_inner();
+    Ok(Json(HttpChallengeResponse {
+        auth_id: response.auth_id,
+        c: hex::encode(auth.endianness.decode(&response.c).to_bytes_be()),
+    }))
+}
+
+async fn http_answer(
+    State(auth): State<AuthImpl>, Json(request): Json<HttpAnswerRequest>,
+) -> Result<Json<HttpAnswerResponse>, HttpError> {
+    let request = AuthenticationAnswerRequest {
+        auth_id: request.auth_id,
+        s: http_number(&request.s, "s", auth.endianness)?,
+        ..Default::default()
+    };
+    let response = auth
+        .verify_authentication(Request::new(request))
+        .await
+        .map_err(http_error)?
+        .into_inner();
+    Ok(Json(HttpAnswerResponse {
+        session_id: response.session_id,
+    }))
+}
+
+/// The version of the `AuthImpl::export_state` format.
+pub const STATE_VERSION: u32 = 1;
+
+/// A backup of the registrations, and optionally the sessions, of an `AuthImpl`.
+///
+/// Numbers are hex encoded big-endian. `p` is the modulus of the group the public values belong
+/// to, so a backup cannot be restored into a server using another group. Sessions expire at
+/// `expires_at` seconds since the Unix epoch.
+#[derive(Debug, Serialize, Deserialize)]
+struct StateSnapshot {
+    version: u32,
+    p: String,
+    users: Vec<UserSnapshot>,
+    sessions: Vec<SessionSnapshot>,
+    #[serde(default)]
+    redeemed_registration_tokens: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UserSnapshot {
+    user_name: String,
+    y1: String,
+    y2: String,
+    credentials: BTreeMap<String, (String, String)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionSnapshot {
+    session_id: String,
+    user_name: String,
+    expires_at: u64,
+}
+
+/// The security-relevant settings of a server, for audit tools, see `AuthImpl::describe`.
+///
+/// Numbers of the group are hex encoded big-endian, durations are in seconds, and the settings
+/// chosen among values (`endianness`, `replay_cache_eviction`, `challenge_overflow`) are named
+/// as on the command line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolDescriptor {
+    pub protocol: String,
+    pub protocol_version: u32,
+    pub min_protocol_version: u32,
+    pub group: GroupDescriptor,
+    /// The bit length challenges are drawn below, see `--challenge-bits`.
+    pub challenge_bits: u64,
+    pub endianness: String,
+    pub fiat_shamir_hash: String,
+    pub fiat_shamir_domain: String,
+    pub possession_domain: String,
+    pub auth_id_mac: String,
+    /// The MAC of signed session tokens, if they are issued.
+    pub session_tokens: Option<String>,
+    /// The MAC of registration tokens, if registrations require one.
+    pub registration_tokens: Option<String>,
+    /// The KDF of clients not passing `--kdf`; the server cannot tell which one a client uses.
+    pub default_kdf: String,
+    pub pbkdf2_rounds: u32,
+    pub session_ttl_secs: u64,
+    pub registration_ttl_secs: Option<u64>,
+    pub keep_alive_interval_secs: u64,
+    pub max_failed_attempts: u32,
+    pub lockout_cooldown_secs: u64,
+    pub replay_cache_size: usize,
+    pub replay_cache_eviction: String,
+    pub max_outstanding_challenges: usize,
+    pub challenge_overflow: String,
+    /// The number of verifications running at once at most, if limited.
+    pub max_concurrent_verifications: Option<usize>,
+    pub registration_disabled: bool,
+    pub normalize_user_names: bool,
+    pub hash_user_names: bool,
+}
+
+/// The group parameters of a `ProtocolDescriptor`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupDescriptor {
+    pub p: String,
+    pub q: String,
+    pub alpha: String,
+    pub beta: String,
+    pub p_bits: u64,
+    pub q_bits: u64,
+    /// The standard group of the parameters, if any, see `ZKP::identify_group`.
+    pub known_group: Option<String>,
+}
+
+/// Returns the command line name of `value`.
+fn value_name(value: impl clap::ValueEnum) -> String {
+    let value = value.to_possible_value().expect("no value is skipped");
+    value.get_name().to_string()
+}
+
+#[tonic::async_trait]
+impl Auth for AuthImpl {
+    /// Registers a new user by storing their information in the `user_info` hashmap.
+    ///
+    /// This method takes a `RegisterRequest` containing the user's name and two big integers
+    /// (y1 and y2) that are part of the Chaum-Pedersen ZKP protocol. If registration is successful,
+    /// it logs a success message and returns an empty `RegisterResponse`.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - A `Request<RegisterRequest>` containing the user's registration details.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either a `Response<RegisterResponse>` on success or a `Status` error.
+    async fn register(
+        &self, request: Request<RegisterRequest>,
+    ) -> Result<Response<RegisterResponse>, Status> {
+        let request = request.into_inner();
+        self.capture(">>>", "RegisterRequest", &request);
+
+        println!("Processing Registration username: {:?}", request.user);
+        self.check_ready()?;
+        self.check_protocol_version(request.protocol_version)?;
+        if self.registration_disabled {
+            println!("⚠️ Registration refused, it is disabled username: {:?}", request.user);
+            return Err(Status::permission_denied(
+                "Registration is disabled, users are provisioned by the server's administrator",
+            ));
+        }
+        self.expire_registrations();
+        let user_name = self.user_ids.id(&request.user);
+        let registration_token =
+            self.check_registration_token(&user_name, &request.registration_token)?;
+
+        let mut credentials = BTreeMap::new();
+        let mut credential_proofs = Vec::new();
+        for credential in request.credentials {
+            if credential.label.is_empty() || credentials.contains_key(&credential.label) {
+                return Err(Status::new(
+                    Code::InvalidArgument,
+                    format!("Credential label {:?} is empty or duplicated", credential.label),
+                ));
+            }
+            let credential_info = CredentialInfo {
+                y1: self.decode_public_value(&credential.y1, "y1")?,
+                y2: self.decode_public_value(&credential.y2, "y2")?,
+                ..Default::default()
+            };
+            credential_proofs.push((credential.label.clone(), credential.possession_proof));
+            credentials.insert(credential.label, credential_info);
+        }
+
+        let user_info = UserInfo {
+            user_name: user_name.clone(),
+            y1: self.decode_public_value(&request.y1, "y1")?,
+            y2: self.decode_public_value(&request.y2, "y2")?,
+            expires_at: self.registration_ttl.map(|ttl| SystemTime::now() + ttl),
+            credentials,
+            ..Default::default()
+        };
+        let slot = self.verification_slot().await;
+        self.check_possession(&request.user, &user_info, &request.possession_proof)?;
+        for (label, possession_proof) in &credential_proofs {
+            self.check_credential_possession(&request.user, &user_info, label, possession_proof)?;
+        }
+        drop(slot);
+
+        {
+            let user_info_hashmap = &mut self.user_info.lock().unwrap();
+            // the possession proof is for the keys of the request, so only their owner may
+            // register the name again
+            if let Some(registered) = user_info_hashmap.get(&user_name) {
+                if (&registered.y1, &registered.y2) != (&user_info.y1, &user_info.y2) {
+                    println!("⚠️ Registration of a taken user name username: {:?}", user_name);
+                    return Err(Status::already_exists(format!(
+                        "User: {} is already registered with other keys",
+                        user_name
+                    )));
+                }
+            }
+            if let Some(claims) = registration_token {
+                self.redeem_registration_token(&claims)?;
+            }
+            user_info_hashmap.insert(user_name.clone(), user_info);
+        }
+
+        println!("✅ Successful Registration username: {:?}", user_name);
+        let response = RegisterResponse {};
+        self.capture("<<<", "RegisterResponse", &response);
+        Ok(Response::new(response))
+    }
+
+    /// Creates an authentication challenge for a registered user.
+    ///
+    /// This method generates a random challenge value and an authentication ID for the user specified
+    /// in the `AuthenticationChallengeRequest`. It updates the user's information with the received
+    /// values and logs a success message upon completion.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - A `Request<AuthenticationChallengeRequest>` containing the user's name and
+    ///               the values r1 and r2 for the challenge, plus one labeled commitment for each
+    ///               additional credential of the user.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either a `Response<AuthenticationChallengeResponse>` with the challenge details
+    /// or a `Status` error if the user is not found or a credential's commitment is missing.
+    async fn create_authentication_challenge(
+        &self, request: Request<AuthenticationChallengeRequest>,
+    ) -> Result<Response<AuthenticationChallengeResponse>, Status> {
+        let request = request.into_inner();
+        self.capture(">>>", "AuthenticationChallengeRequest", &request);
+
+        println!("Processing Challenge Request username: {:?}", request.user);
+        self.check_ready()?;
+        self.check_protocol_version(request.protocol_version)?;
+        let user_name = self.user_ids.id(&request.user);
+        self.check_not_locked(&user_name)?;
+        self.expire_registrations();
+
+        let user_info_hashmap = &mut self.user_info.lock().unwrap();
+
+        if let Some(user_info) = user_info_hashmap.get_mut(&user_name) {
+            let commitment =
+                (self.decode_element(&request.r1, "r1")?, self.decode_element(&request.r2, "r2")?);
+            let mut commitments = HashMap::new();
+            for labeled in request.commitments {
+                let r1 = self.decode_element(&labeled.r1, "r1")?;
+                let r2 = self.decode_element(&labeled.r2, "r2")?;
+                commitments.insert(labeled.label, (r1, r2));
+            }
+
+            for label in user_info.credentials.keys() {
+                if !commitments.contains_key(label) {
+                    return Err(Status::new(
+                        Code::InvalidArgument,
+                        format!("Missing commitment for credential {:?}", label),
+                    ));
+                }
+            }
+            if let Some(label) = commitments
+                .keys()
+                .find(|label| !user_info.credentials.contains_key(*label))
+            {
+                return Err(Status::new(
+                    Code::InvalidArgument,
+                    format!("Unknown credential {:?} for user {}", label, user_name),
+                ));
+            }
+
+            // the commitments are only recorded as seen once the challenge is issued, so that a
+            // refused request does not burn them
+            let fresh: Vec<_> = std::iter::once(&commitment)
+                .chain(commitments.values())
+                .cloned()
+                .collect();
+            for (i, commitment) in fresh.iter().enumerate() {
+                if fresh[..i].contains(commitment) {
+                    return Err(self.nonce_reuse(&user_name));
+                }
+                self.check_fresh(commitment, &user_name)?;
+            }
+
+            let c = self.issue_challenge();
+            let auth_id_to_user = &mut self.auth_id_to_user.lock().unwrap();
+            let auth_id = self.issue_auth_id(auth_id_to_user, &c)?;
+
+            // the challenge is recorded before the user's state changes, so that a refused one
+            // leaves the outstanding challenge of the user answerable
+            if !auth_id_to_user.insert(auth_id.clone(), user_name.clone()) {
+                println!("⚠️ Too many outstanding challenges username: {:?}", user_name);
+                return Err(Status::resource_exhausted(
+                    "Too many outstanding challenges, retry later",
+                ));
+            }
+            self.record_seen(fresh);
+
+            for (label, credential) in user_info.credentials.iter_mut() {
+                (credential.r1, credential.r2) = commitments.remove(label).unwrap_or_default();
+            }
+            user_info.c.clone_from(&c);
+            (user_info.r1, user_info.r2) = commitment;
+
+            println!("✅ Successful Challenge Request username: {:?}", user_name);
+
+            let response = AuthenticationChallengeResponse {
+                auth_id,
+                c: self.endianness.encode(&c),
+            };
+            self.capture("<<<", "AuthenticationChallengeResponse", &response);
+            Ok(Response::new(response))
+        } else {
+            Err(Status::new(
+                Code::NotFound,
+                format!("User: {} not found in database", user_name),
+            ))
+        }
+    }
+
+    /// Verifies the solution to an authentication challenge.
+    ///
+    /// This method checks the provided solution against the stored user information using the
+    /// ZKP verification process. If successful, it generates a session ID, stores it in the
+    /// `SessionStore` and returns it in the `AuthenticationAnswerResponse`. If the verification
+    /// fails, an error status is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - A `Request<AuthenticationAnswerRequest>` containing the authentication ID and the solution,
+    ///               plus one labeled solution for each additional credential of the user.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either a `Response<AuthenticationAnswerResponse>` with the session ID
+    /// or a `Status` error if the authentication ID is not found or the solution is incorrect.
+    async fn verify_authentication(
+        &self, request: Request<AuthenticationAnswerRequest>,
+    ) -> Result<Response<AuthenticationAnswerResponse>, Status> {
+        let request = request.into_inner();
+        self.capture(">>>", "AuthenticationAnswerRequest", &request);
+
+        let user_name = self
+            .check_answer(&request.auth_id, &request.s, request.answers)
+            .await?;
+
+        let mut sessions = self.sessions.lock().unwrap();
+        let session_id = match &self.session_tokens {
+            Some(key) => {
+                // whole seconds, so that the session expires when the token does
+                let exp = (SystemTime::now() + sessions.ttl())
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let session_id = key.issue(&SessionClaims::new(&user_name, exp));
+                let expires_at = UNIX_EPOCH + Duration::from_secs(exp);
+                sessions.insert_until(&session_id, &user_name, expires_at);
+                session_id
+            }
+            None => {
+                let session_id = ZKP::generate_random_string(12);
+                sessions.insert(&session_id, &user_name);
+                session_id
+            }
+        };
+        drop(sessions);
+        if let Some(user_info) = self.user_info.lock().unwrap().get_mut(&user_name) {
+            user_info.session_id.clone_from(&session_id);
+        }
+
+        let response = AuthenticationAnswerResponse { session_id };
+        self.capture("<<<", "AuthenticationAnswerResponse", &response);
+        Ok(Response::new(response))
+    }
+
+    /// Cancels an in-flight authentication.
+    ///
+    /// The challenge identified by the authentication ID is forgotten right away, instead of
+    /// being kept around for an answer that will never come.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - A `Request<CancelAuthenticationRequest>` containing the authentication ID.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either an empty `Response<CancelAuthenticationResponse>` or a
+    /// `Status` error if the authentication ID is not found.
+    async fn cancel_authentication(
+        &self, request: Request<CancelAuthenticationRequest>,
+    ) -> Result<Response<CancelAuthenticationResponse>, Status> {
+        let auth_id = request.into_inner().auth_id;
+        println!("Processing Cancel Authentication auth_id: {:?}", auth_id);
+        self.check_ready()?;
+
+        match self.auth_id_to_user.lock().unwrap().remove(&auth_id) {
+            Some(user_name) => {
+                println!("✅ Cancelled Authentication username: {:?}", user_name);
+                Ok(Response::new(CancelAuthenticationResponse {}))
+            }
+            None => Err(Status::new(
+                Code::NotFound,
+                format!("AuthId: {} not found in database", auth_id),
+            )),
+        }
+    }
+
+    /// Checks that a session ID belongs to an active session.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - A `Request<ValidateSessionRequest>` containing the session ID.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either a `Response<ValidateSessionResponse>` with the user name the
+    /// session belongs to or a `Status` error if the session is not found.
+    async fn validate_session(
+        &self, request: Request<ValidateSessionRequest>,
+    ) -> Result<Response<ValidateSessionResponse>, Status> {
+        let session_id = request.into_inner().session_id;
+        self.check_ready()?;
+        self.expire_registrations();
+
+        match self.sessions.lock().unwrap().user(&session_id) {
+            Some(user) => Ok(Response::new(ValidateSessionResponse {
+                user: user.to_string(),
+            })),
+            None => Err(Status::new(
+                Code::NotFound,
+                format!("Session: {} not found in database", session_id),
+            )),
+        }
+    }
+
+    /// Invalidates every session of a user after a fresh proof of knowledge of the secret.
+    ///
+    /// The proof is the answer to a challenge created with `create_authentication_challenge`,
+    /// exactly as for `verify_authentication`, but instead of creating a new session all the
+    /// active sessions of the user are removed from the `SessionStore`.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - A `Request<InvalidateSessionsRequest>` containing the authentication ID and the
+    ///               solution(s) to the challenge.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either a `Response<InvalidateSessionsResponse>` with the number of
+    /// invalidated sessions or a `Status` error if the authentication ID is not found or the
+    /// solution is incorrect.
+    async fn invalidate_sessions(
+        &self, request: Request<InvalidateSessionsRequest>,
+    ) -> Result<Response<InvalidateSessionsResponse>, Status> {
+        let request = request.into_inner();
+
+        let user_name = self
+            .check_answer(&request.auth_id, &request.s, request.answers)
+            .await?;
+
+        let invalidated = self.sessions.lock().unwrap().remove_user(&user_name);
+        println!("✅ Invalidated {} session(s) username: {:?}", invalidated, user_name);
+
+        Ok(Response::new(InvalidateSessionsResponse {
+            invalidated: invalidated as u32,
+        }))
+    }
+
+    /// Sends the group parameters of the server, for clients bootstrapping their own from them.
+    ///
+    /// # Arguments
+    ///
+    /// * `_request` - An empty `Request<GetParametersRequest>`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Response<GetParametersResponse>` with `p`, `q`, `alpha` and
+    /// `beta`, big-endian, and the byte order of the other integers the server exchanges.
+    async fn get_parameters(
+        &self, _request: Request<GetParametersRequest>,
+    ) -> Result<Response<GetParametersResponse>, Status> {
+        Ok(Response::new(GetParametersResponse {
+            p: self.zkp.p.to_bytes_be(),
+            q: self.zkp.q.to_bytes_be(),
+            alpha: self.zkp.alpha.to_bytes_be(),
+            beta: self.zkp.beta.to_bytes_be(),
+            little_endian: self.endianness == Endianness::Little,
+        }))
+    }
+
+    /// The stream of challenges sent back on a keep-alive stream.
+    type KeepAliveStream =
+        Pin<Box<dyn Stream<Item = Result<KeepAliveChallenge, Status>> + Send + 'static>>;
+
+    /// Keeps a session alive with periodic proofs of knowledge of the secret.
+    ///
+    /// The first message of the stream names the session and carries a commitment. The server
+    /// then sends a challenge for the last commitment every `keep_alive_interval`, and every
+    /// message that follows must carry the solution to that challenge together with the next
+    /// commitment. Each correct solution extends the session; a wrong one removes the session and
+    /// ends the stream with an error.
+    ///
+    /// Only the primary credential is proven on a keep-alive stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - A `Request<Streaming<KeepAliveRequest>>` with the messages of the client.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either a `Response` with the stream of `KeepAliveChallenge` or a
+    /// `Status` error if the session is not found or the first commitment is invalid.
+    async fn keep_alive(
+        &self, request: Request<Streaming<KeepAliveRequest>>,
+    ) -> Result<Response<Self::KeepAliveStream>, Status> {
+        self.check_ready()?;
+        let mut inbound = request.into_inner();
+
+        let first = inbound.message().await?.ok_or_else(|| {
+            Status::new(Code::InvalidArgument, "Keep-alive stream closed before naming a session")
+        })?;
+        let session_id = first.session_id.clone();
+        println!("Processing Keep Alive session_id: {:?}", session_id);
+
+        let user_name = match self.sessions.lock().unwrap().user(&session_id) {
+            Some(user) => user.to_string(),
+            None => {
+                return Err(Status::new(
+                    Code::NotFound,
+                    format!("Session: {} not found in database", session_id),
+                ))
+            }
+        };
+        let mut commitment = self.keep_alive_commitment(&first, &user_name)?;
+
+        let auth = self.clone();
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            loop {
+                let expires_at = auth.sessions.lock().unwrap().expires_at(&session_id);
+                let Some(expires_at) = expires_at else {
+                    let status = Status::new(Code::NotFound, "Session expired");
+                    let _ = tx.send(Err(status)).await;
+                    break;
+                };
+
+                let c = auth.issue_challenge();
+                let challenge = KeepAliveChallenge {
+                    c: auth.endianness.encode(&c),
+                    expires_at: expires_at
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_secs()),
+                };
+                if tx.send(Ok(challenge)).await.is_err() {
+                    break;
+                }
+
+                let request = match inbound.message().await {
+                    Ok(Some(request)) => request,
+                    _ => break,
+                };
+                let slot = auth.verification_slot().await;
+                let round =
+                    auth.keep_alive_round(&session_id, &user_name, &commitment, &c, request);
+                drop(slot);
+                match round {
+                    Ok(next) => commitment = next,
+                    Err(status) => {
+                        let _ = tx.send(Err(status)).await;
+                        break;
+                    }
+                }
+
+                tokio::time::sleep(auth.keep_alive_interval).await;
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+impl AuthImpl {
+    /// Describes the deployed protocol: the group, the hashes and MACs, the challenge size, the
+    /// TTLs and the limits, for security audits.
+    ///
+    /// # Returns
+    ///
+    /// The `ProtocolDescriptor` of the server's settings.
+    pub fn describe(&self) -> ProtocolDescriptor {
+        let encode = |value: &BigUint| hex::encode(value.to_bytes_be());
+        let q_bits = self.zkp.q.bits();
+        let lockouts = self.lockouts.lock().unwrap();
+        let accepted = self.accepted_commitments.lock().unwrap();
+        let outstanding = self.auth_id_to_user.lock().unwrap();
+        ProtocolDescriptor {
+            protocol: "chaum-pedersen".to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: self.min_protocol_version,
+            group: GroupDescriptor {
+                p: encode(&self.zkp.p),
+                q: encode(&self.zkp.q),
+                alpha: encode(&self.zkp.alpha),
+                beta: encode(&self.zkp.beta),
+                p_bits: self.zkp.p.bits(),
+                q_bits,
+                known_group: self
+                    .zkp
+                    .identify_group()
+                    .map(|known| known.name().to_string()),
+            },
+            challenge_bits: self.challenge_bits.map_or(q_bits, |bits| bits.min(q_bits)),
+            endianness: value_name(self.endianness),
+            fiat_shamir_hash: "SHA-256".to_string(),
+            fiat_shamir_domain: String::from_utf8_lossy(FIAT_SHAMIR_DOMAIN).into_owned(),
+            possession_domain: String::from_utf8_lossy(POSSESSION_DOMAIN).into_owned(),
+            auth_id_mac: "HMAC-SHA256".to_string(),
+            session_tokens: self
+                .session_tokens
+                .as_ref()
+                .map(|_| "HMAC-SHA256".to_string()),
+            registration_tokens: self
+                .registration_tokens
+                .as_ref()
+                .map(|_| "HMAC-SHA256".to_string()),
+            default_kdf: "PBKDF2-HMAC-SHA256".to_string(),
+            pbkdf2_rounds: PBKDF2_ROUNDS,
+            session_ttl_secs: self.sessions.lock().unwrap().ttl.as_secs(),
+            registration_ttl_secs: self.registration_ttl.map(|ttl| ttl.as_secs()),
+            keep_alive_interval_secs: self.keep_alive_interval.as_secs(),
+            max_failed_attempts: lockouts.max_failures,
+            lockout_cooldown_secs: lockouts.cooldown.as_secs(),
+            replay_cache_size: accepted.capacity,
+            replay_cache_eviction: value_name(accepted.eviction),
+            max_outstanding_challenges: outstanding.capacity,
+            challenge_overflow: value_name(outstanding.overflow),
+            max_concurrent_verifications: self
+                .verification_limit
+                .as_ref()
+                .map(VerificationLimit::limit),
+            registration_disabled: self.registration_disabled,
+            normalize_user_names: self.user_ids.normalize,
+            hash_user_names: self.user_ids.hash,
+        }
+    }
+
+    /// Serializes the registered users, and the active sessions if `include_sessions`, for backup.
+    ///
+    /// Only the public values of the users are exported, pending challenges are not. The state
+    /// is restored with `import_state`.
+    ///
+    /// # Returns
+    ///
+    /// The versioned JSON encoding of the state.
+    pub fn export_state(&self, include_sessions: bool) -> Vec<u8> {
+        let encode = |value: &BigUint| hex::encode(value.to_bytes_be());
+        let users = self
+            .user_info
+            .lock()
+            .unwrap()
+            .values()
+            .map(|user_info| UserSnapshot {
+                user_name: user_info.user_name.clone(),
+                y1: encode(&user_info.y1),
+                y2: encode(&user_info.y2),
+                credentials: user_info
+                    .credentials
+                    .iter()
+                    .map(|(label, credential)| {
+                        (label.clone(), (encode(&credential.y1), encode(&credential.y2)))
+                    })
+                    .collect(),
+            })
+            .collect();
+        let sessions = if include_sessions {
+            self.sessions
+                .lock()
+                .unwrap()
+                .active()
+                .map(|(session_id, user_name, expires_at)| SessionSnapshot {
+                    session_id: session_id.to_string(),
+                    user_name: user_name.to_string(),
+                    expires_at: expires_at
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let snapshot = StateSnapshot {
+            version: STATE_VERSION,
+            p: encode(&self.zkp.p),
+            users,
+            sessions,
+            redeemed_registration_tokens: self
+                .redeemed_registration_tokens
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(nonce, exp)| (hex::encode(nonce), *exp))
+                .collect(),
+        };
+        serde_json::to_vec(&snapshot).expect("the state serializes to JSON")
+    }
+
+    /// Restores a state exported with `export_state`.
+    ///
+    /// The users and sessions of the state are added to those of the server, replacing the
+    /// users with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The exported state.
+    ///
+    /// # Returns
+    ///
+    /// An error if `state` is malformed, of another version, or exported by a server using
+    /// another group. Nothing is restored then.
+    pub fn import_state(&self, state: &[u8]) -> Result<()> {
+        let snapshot: StateSnapshot = serde_json::from_slice(state)?;
+        if snapshot.version != STATE_VERSION {
+            return Err(anyhow!("unsupported state version {}", snapshot.version));
+        }
+        if hex::decode(&snapshot.p)? != self.zkp.p.to_bytes_be() {
+            return Err(anyhow!("the state was exported with another group"));
+        }
+        let decode = |value: &str| -> Result<BigUint> {
+            let value = BigUint::from_bytes_be(&hex::decode(value)?);
+            if value <= BigUint::from(1u32) || !self.zkp.is_in_subgroup(&value) {
+                return Err(anyhow!("public value out of range or not in the subgroup"));
+            }
+            Ok(value)
+        };
+
+        let mut redeemed = Vec::with_capacity(snapshot.redeemed_registration_tokens.len());
+        for (nonce, exp) in snapshot.redeemed_registration_tokens {
+            let nonce: [u8; 16] = hex::decode(&nonce)?
+                .try_into()
+                .map_err(|_| anyhow!("registration token nonce of the wrong length"))?;
+            redeemed.push((nonce, exp));
+        }
+
+        let mut users = Vec::with_capacity(snapshot.users.len());
+        for user in snapshot.users {
+            let mut credentials = BTreeMap::new();
+            for (label, (y1, y2)) in user.credentials {
+                let credential = CredentialInfo {
+                    y1: decode(&y1)?,
+                    y2: decode(&y2)?,
+                    ..Default::default()
+                };
+                credentials.insert(label, credential);
+            }
+            users.push(UserInfo {
+                y1: decode(&user.y1)?,
+                y2: decode(&user.y2)?,
+                user_name: user.user_name,
+                credentials,
+                ..Default::default()
+            });
+        }
+
+        let mut user_info = self.user_info.lock().unwrap();
+        for user in users {
+            user_info.insert(user.user_name.clone(), user);
+        }
+        let mut sessions = self.sessions.lock().unwrap();
+        for session in snapshot.sessions {
+            let expires_at = UNIX_EPOCH + Duration::from_secs(session.expires_at);
+            sessions.insert_until(&session.session_id, &session.user_name, expires_at);
+        }
+        self.redeemed_registration_tokens
+            .lock()
+            .unwrap()
+            .extend(redeemed);
+        Ok(())
+    }
+
+    /// Registers users whose public values are known out of band, without a `register` call.
+    ///
+    /// Each line of `keys` is a user name and its `VerifierKey` encoding, separated by a space;
+    /// blank lines and lines starting with `#` are skipped. User names go through `user_ids`
+    /// like those of the requests, and preloaded users replace registered ones of the same name.
+    ///
+    /// # Returns
+    ///
+    /// The number of users preloaded, or an error naming the first malformed line, or the first
+    /// public value out of range or not in the subgroup. Nothing is preloaded then.
+    pub fn preload_keys(&self, keys: &str) -> Result<usize> {
+        let mut users = Vec::new();
+        for (number, line) in keys
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+        {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (user, key) = line
+                .rsplit_once(' ')
+                .ok_or_else(|| anyhow!("line {}: expected `<user> <verifier key>`", number))?;
+            let key = VerifierKey::decode(key).map_err(|e| anyhow!("line {}: {}", number, e))?;
+            for value in [&key.y1, &key.y2] {
+                if *value <= BigUint::from(1u32) || !self.zkp.is_in_subgroup(value) {
+                    return Err(anyhow!(
+                        "line {}: public value out of range or not in the subgroup",
+                        number
+                    ));
+                }
+            }
+            let user_name = self.user_ids.id(user.trim());
+            users.push(UserInfo {
+                user_name,
+                y1: key.y1,
+                y2: key.y2,
+                ..Default::default()
+            });
+        }
+
+        let count = users.len();
+        let mut user_info = self.user_info.lock().unwrap();
+        for user in users {
+            user_info.insert(user.user_name.clone(), user);
+        }
+        Ok(count)
+    }
+
+    /// Records a message in the capture file, if capturing is enabled.
+    ///
+    /// A failed write is reported but does not fail the request.
+    fn capture(&self, direction: &str, name: &str, message: &impl Message) {
+        if let Some(capture) = &self.capture {
+            if let Err(e) = capture.record(direction, name, message) {
+                println!("⚠️ Could not capture {}: {:?}", name, e);
+            }
+        }
+    }
+
+    /// Decodes a group element sent by the client, see `ZKP::decode_fixed_with`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The fixed-width encoding of the element.
+    /// * `name` - The name of the element, for the error message.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the decoded element or an `InvalidArgument` status if the
+    /// encoding does not have the byte length of `p`.
+    fn decode_element(&self, bytes: &[u8], name: &str) -> Result<BigUint, Status> {
+        self.zkp
+            .decode_fixed_with(bytes, self.endianness)
+            .ok_or_else(|| {
+                Status::new(
+                    Code::InvalidArgument,
+                    format!(
+                        "{} must be encoded with the byte length of p, got {} bytes",
+                        name,
+                        bytes.len()
+                    ),
+                )
+            })
+    }
+
+    /// Decodes a public value sent at registration, see `decode_element`.
+    ///
+    /// The value must also be in the subgroup of order `q`, see `ZKP::is_in_subgroup`, which
+    /// costs one exponentiation per value.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the decoded value or an `InvalidArgument` status if it is
+    /// malformed or not in the subgroup.
+    fn decode_public_value(&self, bytes: &[u8], name: &str) -> Result<BigUint, Status> {
+        let value = self.decode_element(bytes, name)?;
+        if !self.zkp.is_in_subgroup(&value) {
+            return Err(Status::invalid_argument(format!(
+                "{} is not in the subgroup of order q",
+                name
+            )));
+        }
+        Ok(value)
+    }
+
+    /// Refuses a commitment that was already accepted (replay) or seen (nonce reuse), without
+    /// recording it, see `record_seen`.
+    fn check_fresh(&self, commitment: &(BigUint, BigUint), user_name: &str) -> Result<(), Status> {
+        if self
+            .accepted_commitments
+            .lock()
+            .unwrap()
+            .contains(user_name, commitment)
+        {
+            println!("⚠️ Replayed commitment username: {:?}", user_name);
+            return Err(Status::already_exists(format!(
+                "User: {} commitment was already accepted",
+                user_name
+            )));
+        }
+
+        if self.seen_commitments.lock().unwrap().contains(commitment) {
+            return Err(self.nonce_reuse(user_name));
+        }
+        Ok(())
+    }
+
+    /// Records commitments checked by `check_fresh`, so that they are refused from now on.
+    fn record_seen(&self, commitments: Vec<(BigUint, BigUint)>) {
+        let seen_commitments = &mut self.seen_commitments.lock().unwrap();
+        for commitment in commitments {
+            seen_commitments.insert(commitment);
+        }
+    }
+
+    /// The error refusing a commitment of `user_name` that reuses its nonce.
+    fn nonce_reuse(&self, user_name: &str) -> Status {
+        println!("⚠️ Nonce reuse detected username: {:?}", user_name);
+        Status::new(
+            Code::InvalidArgument,
+            format!("User: {} reused a commitment, every challenge needs a fresh k", user_name),
+        )
+    }
+
+    /// Decodes the commitment of a keep-alive message and checks that it is fresh.
+    fn keep_alive_commitment(
+        &self, request: &KeepAliveRequest, user_name: &str,
+    ) -> Result<(BigUint, BigUint), Status> {
+        let commitment =
+            (self.decode_element(&request.r1, "r1")?, self.decode_element(&request.r2, "r2")?);
+        self.check_fresh(&commitment, user_name)?;
+        self.record_seen(vec![commitment.clone()]);
+        Ok(commitment)
+    }
+
+    /// Checks one answer on a keep-alive stream.
+    ///
+    /// On a correct solution the session is extended and the next commitment, carried by the same
+    /// message, is returned. On a wrong one the session is removed.
+    fn keep_alive_round(
+        &self, session_id: &str, user_name: &str, commitment: &(BigUint, BigUint), c: &BigUint,
+        request: KeepAliveRequest,
+    ) -> Result<(BigUint, BigUint), Status> {
+        let s = self.endianness.decode(&request.s);
+        self.check_structure((&commitment.0, &commitment.1), c, &s)?;
+        self.expire_registrations();
+
+        let (y1, y2) = match self.user_info.lock().unwrap().get(user_name) {
+            Some(user_info) => (user_info.y1.clone(), user_info.y2.clone()),
+            None => {
+                return Err(Status::new(
+                    Code::NotFound,
+                    format!("User: {} not found in database", user_name),
+                ))
+            }
+        };
+
+        if !self.verify_logged(
+            "primary credential",
+            (&commitment.0, &commitment.1),
+            (&y1, &y2),
+            c,
+            &s,
+        ) {
+            println!("❌ Wrong Keep Alive Solution username: {:?}", user_name);
+            self.sessions.lock().unwrap().remove(session_id);
+            return Err(Status::new(
+                Code::PermissionDenied,
+                format!("Session: {} bad solution to the challenge", session_id),
+            ));
+        }
+
+        if self.sessions.lock().unwrap().extend(session_id).is_none() {
+            return Err(Status::new(
+                Code::NotFound,
+                format!("Session: {} not found in database", session_id),
+            ));
+        }
+        println!("✅ Correct Keep Alive Solution username: {:?}", user_name);
+        self.accepted_commitments
+            .lock()
+            .unwrap()
+            .insert(user_name, commitment.clone());
+
+        self.keep_alive_commitment(&request, user_name)
+    }
+
+    /// Issues an authentication ID for the challenge `c` that no outstanding challenge has.
+    ///
+    /// An ID colliding with an outstanding one would replace its challenge, so a new one is drawn
+    /// instead, up to `AUTH_ID_ATTEMPTS` times before failing with `Internal`.
+    fn issue_auth_id(
+        &self, outstanding: &OutstandingChallenges, c: &BigUint,
+    ) -> Result<String, Status> {
+        let auth_id_keys = self.auth_id_keys.lock().unwrap();
+        for _ in 0..AUTH_ID_ATTEMPTS {
+            let auth_id = auth_id_keys.issue(c);
+            if !outstanding.contains(&auth_id) {
+                return Ok(auth_id);
+            }
+            println!("⚠️ Authentication ID collision, drawing another one");
+        }
+        Err(Status::internal("Could not issue a unique authentication ID"))
+    }
+
+    /// Waits for a slot of `verification_limit`, if set, to hold while verifying.
+    async fn verification_slot(&self) -> Option<tokio::sync::SemaphorePermit<'_>> {
+        match &self.verification_limit {
+            Some(limit) => Some(limit.acquire().await),
+            None => None,
+        }
+    }
+
+    /// Verifies one solution, logging why it failed if it did.
+    ///
+    /// Only the `VerificationFailure` is logged, never the values of the proof. The duration of
+    /// the verification is recorded in the `VERIFICATION_SECONDS` histogram.
+    fn verify_logged(
+        &self, credential: &str, (r1, r2): (&BigUint, &BigUint), (y1, y2): (&BigUint, &BigUint),
+        c: &BigUint, s: &BigUint,
+    ) -> bool {
+        let start = Instant::now();
+        let result = self.zkp.verify_with_reason(r1, r2, y1, y2, c, s);
+        histogram!(VERIFICATION_SECONDS).record(start.elapsed());
+
+        match result {
+            Ok(()) => true,
+            Err(reason) => {
+                println!("❌ Verification of {} failed: {}", credential, reason);
+                false
+            }
+        }
+    }
+
+    /// Refuses with `PermissionDenied` a registration of `user_info` whose `possession_proof`
+    /// does not prove knowledge of its secret, see `ZKP::prove_possession`.
+    fn check_possession(
+        &self, user: &str, user_info: &UserInfo, possession_proof: &[u8],
+    ) -> Result<(), Status> {
+        let valid = Proof::from_bytes(possession_proof).is_ok_and(|proof| {
+            self.zkp
+                .verify_possession(&user_info.y1, &user_info.y2, user, &proof)
+        });
+        if valid {
+            return Ok(());
+        }
+        println!("❌ Registration without a proof of possession username: {:?}", user);
+        Err(Status::permission_denied(
+            "Registration must prove knowledge of the secret of y1 and y2",
+        ))
+    }
+
+    /// Refuses with `PermissionDenied` a registration of `user_info` whose credential `label`
+    /// comes without a `possession_proof` of its secret, see `ZKP::prove_credential_possession`.
+    fn check_credential_possession(
+        &self, user: &str, user_info: &UserInfo, label: &str, possession_proof: &[u8],
+    ) -> Result<(), Status> {
+        let credential = &user_info.credentials[label];
+        let valid = Proof::from_bytes(possession_proof).is_ok_and(|proof| {
+            self.zkp.verify_credential_possession(
+                &credential.y1,
+                &credential.y2,
+                user,
+                label,
+                &proof,
+            )
+        });
+        if valid {
+            return Ok(());
+        }
+        println!(
+            "❌ Registration without a proof of possession of credential {:?} username: {:?}",
+            label, user
+        );
+        Err(Status::permission_denied(format!(
+            "Registration must prove knowledge of the secret of credential {:?}",
+            label
+        )))
+    }
+
+    /// Refuses with `PermissionDenied` a registration of `user_name` without a valid registration
+    /// token for it, when `registration_tokens` is set: missing, malformed, expired, signed with
+    /// another key, issued for another user, or already used.
+    ///
+    /// # Returns
+    ///
+    /// The claims of the token, to redeem with `redeem_registration_token` once the registration
+    /// is accepted, or `None` if registrations need no token.
+    fn check_registration_token(
+        &self, user_name: &str, token: &str,
+    ) -> Result<Option<RegistrationClaims>, Status> {
+        let Some(key) = &self.registration_tokens else {
+            return Ok(None);
+        };
+        let refuse = |reason: String| {
+            println!("❌ Registration refused, {} username: {:?}", reason, user_name);
+            Status::permission_denied(format!("Registration token {}", reason))
+        };
+        if token.is_empty() {
+            return Err(refuse("missing".to_string()));
+        }
+        let claims = key.verify_registration(token).map_err(|e| match e {
+            ZkpError::Expired => refuse("expired".to_string()),
+            e => refuse(format!("invalid: {}", e)),
+        })?;
+        if self.user_ids.id(&claims.user) != user_name {
+            return Err(refuse("issued for another user".to_string()));
+        }
+        if self
+            .redeemed_registration_tokens
+            .lock()
+            .unwrap()
+            .contains_key(&claims.nonce)
+        {
+            return Err(refuse("already used".to_string()));
+        }
+        Ok(Some(claims))
+    }
+
+    /// Marks the registration token of `claims` as used, forgetting the used tokens that expired.
+    ///
+    /// The check and the insertion are done under one lock, so of concurrent registrations with
+    /// the same token, only one is accepted.
+    fn redeem_registration_token(&self, claims: &RegistrationClaims) -> Result<(), Status> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut redeemed = self.redeemed_registration_tokens.lock().unwrap();
+        redeemed.retain(|_, exp| *exp > now);
+        if redeemed.insert(claims.nonce, claims.exp).is_some() {
+            return Err(Status::permission_denied("Registration token already used"));
+        }
+        Ok(())
+    }
+
+    /// Rejects a malformed proof, see `Proof::validate_structure`, before any verification
+    /// arithmetic is done.
+    fn check_structure(
+        &self, (r1, r2): (&BigUint, &BigUint), c: &BigUint, s: &BigUint,
+    ) -> Result<(), Status> {
+        let proof = Proof {
+            r1: r1.clone(),
+            r2: r2.clone(),
+            c: c.clone(),
+            s: s.clone(),
+        };
+        proof
+            .validate_structure(&self.zkp)
+            .map_err(|e| Status::new(Code::InvalidArgument, format!("Malformed proof: {}", e)))
+    }
+
+    /// Rotates the key of the authentication IDs, still accepting the IDs issued with the
+    /// replaced key for `window`, see `AuthIdKeys`.
+    pub fn rotate_auth_id_key(&self, window: Duration) {
+        self.auth_id_keys.lock().unwrap().rotate(window);
+    }
+
+    /// Tells whether the store is initialized, see `ready`.
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+
+    /// Refuses a request with `Code::Unavailable` while the store is being initialized.
+    fn check_ready(&self) -> Result<(), Status> {
+        if self.is_ready() {
+            return Ok(());
+        }
+        Err(Status::unavailable("The server is still initializing, retry shortly"))
+    }
+
+    /// Issues a challenge of `challenge_bits` bits if set, of the full `[0, q)` otherwise.
+    fn issue_challenge(&self) -> BigUint {
+        match self.challenge_bits {
+            Some(bits) => self.zkp.issue_challenge_bits(bits),
+            None => self.zkp.issue_challenge(),
+        }
+    }
+
+    /// Refuses with `FailedPrecondition` a client whose protocol version is below
+    /// `min_protocol_version`, or newer than the server's `PROTOCOL_VERSION`.
+    fn check_protocol_version(&self, version: u32) -> Result<(), Status> {
+        if (self.min_protocol_version..=PROTOCOL_VERSION).contains(&version) {
+            return Ok(());
+        }
+        Err(Status::failed_precondition(format!(
+            "Protocol version {} is not supported, the server accepts versions {} to {}, \
+             upgrade the client",
+            version, self.min_protocol_version, PROTOCOL_VERSION
+        )))
+    }
+
+    /// Forgets the registrations past their `expires_at`, and the sessions of their users.
+    fn expire_registrations(&self) {
+        if self.registration_ttl.is_none() {
+            return;
+        }
+        let now = SystemTime::now();
+        let mut expired = Vec::new();
+        self.user_info
+            .lock()
+            .unwrap()
+            .retain(|user_name, user_info| {
+                let keep = user_info
+                    .expires_at
+                    .is_none_or(|expires_at| expires_at > now);
+                if !keep {
+                    expired.push(user_name.clone());
+                }
+                keep
+            });
+        let mut sessions = self.sessions.lock().unwrap();
+        for user_name in expired {
+            println!("Registration expired username: {:?}", user_name);
+            sessions.remove_user(&user_name);
+        }
+    }
+
+    /// Refuses with `ResourceExhausted` if `user_name` is locked out, see `Lockouts`.
+    fn check_not_locked(&self, user_name: &str) -> Result<(), Status> {
+        match self.lockouts.lock().unwrap().locked_for(user_name) {
+            Some(remaining) => Err(Status::resource_exhausted(format!(
+                "User: {} is locked out for {} more seconds after too many failed authentications",
+                user_name,
+                remaining.as_secs() + 1
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the solution(s) to the challenge identified by `auth_id`.
+    ///
+    /// The values to check are copied out of the maps, and verified with none of their locks
+    /// held, in a slot of `verification_limit`. The challenge is then removed, right or wrong, so
+    /// it cannot be answered again.
+    ///
+    /// # Arguments
+    ///
+    /// * `auth_id` - The authentication ID returned by `create_authentication_challenge`.
+    /// * `s` - The solution for the primary credential.
+    /// * `answers` - One labeled solution for each additional credential of the user.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the name of the user who proved knowledge of the secret(s)
+    /// or a `Status` error if the authentication ID is not found or the solution is incorrect.
+    async fn check_answer(
+        &self, auth_id: &str, s: &[u8], answers: Vec<Answer>,
+    ) -> Result<String, Status> {
+        let answer = self.pending_answer(auth_id, s, answers)?;
+
+        let slot = self.verification_slot().await;
+        let (verification, malformed) = self.verify_answer(&answer);
+        drop(slot);
+
+        // answered, right or wrong, so the challenge cannot be answered again; an answer racing
+        // another one to the same challenge loses here
+        if self
+            .auth_id_to_user
+            .lock()
+            .unwrap()
+            .remove(auth_id)
+            .is_none()
+        {
+            return Err(Status::new(
+                Code::NotFound,
+                format!("AuthId: {} not found in database", auth_id),
+            ));
+        }
+
+        if let Some(transcripts) = &self.transcripts {
+            transcripts.record(&Transcript {
+                user: answer.user_name.clone(),
+                y1: answer.primary.y1.clone(),
+                y2: answer.primary.y2.clone(),
+                r1: answer.primary.r1.clone(),
+                r2: answer.primary.r2.clone(),
+                c: answer.c.clone(),
+                s: answer.primary.s.clone(),
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                outcome: if verification {
+                    Outcome::Accepted
+                } else {
+                    Outcome::Rejected
+                },
+            });
+        }
+
+        self.settle_answer(auth_id, answer, verification, malformed)
+    }
+
+    /// Looks up the challenge identified by `auth_id` and copies out the values its answer is
+    /// checked against, storing the solutions in the user's state.
+    ///
+    /// The maps are locked one after the other, `auth_id_to_user` released before `user_info` is
+    /// taken, so this never holds them in the order opposite to `create_authentication_challenge`.
+    fn pending_answer(
+        &self, auth_id: &str, s: &[u8], answers: Vec<Answer>,
+    ) -> Result<PendingAnswer, Status> {
+        println!("Processing Challenge Solution auth_id: {:?}", auth_id);
+        self.check_ready()?;
+        self.expire_registrations();
+
+        let user_name = self.auth_id_to_user.lock().unwrap().get(auth_id).cloned();
+        let Some(user_name) = user_name else {
+            return Err(Status::new(
+                Code::NotFound,
+                format!("AuthId: {} not found in database", auth_id),
+            ));
+        };
+        let user_info_hashmap = &mut self.user_info.lock().unwrap();
+        let user_info = user_info_hashmap.get_mut(&user_name).ok_or_else(|| {
+            Status::new(Code::NotFound, format!("AuthId {} not found in database", auth_id))
+        })?;
+
+        self.check_not_locked(&user_name)?;
+        if !self
+            .auth_id_keys
+            .lock()
+            .unwrap()
+            .was_issued(auth_id, &user_info.c)
+        {
+            println!("⚠️ AuthId not issued for the challenge auth_id: {:?}", auth_id);
+            return Err(Status::new(
+                Code::PermissionDenied,
+                format!("AuthId: {} was not issued for this challenge", auth_id),
+            ));
+        }
+
+        user_info.s = self.endianness.decode(s);
+        let answers: HashMap<_, _> = answers.into_iter().map(|x| (x.label, x.s)).collect();
+        let mut credentials = Vec::new();
+        for (label, credential) in user_info.credentials.iter_mut() {
+            let answered = answers.get(label).map(|s| {
+                credential.s = self.endianness.decode(s);
+                credential.clone()
+            });
+            credentials.push((label.clone(), answered));
+        }
+
+        Ok(PendingAnswer {
+            user_name,
+            c: user_info.c.clone(),
+            primary: CredentialInfo {
+                y1: user_info.y1.clone(),
+                y2: user_info.y2.clone(),
+                r1: user_info.r1.clone(),
+                r2: user_info.r2.clone(),
+                s: user_info.s.clone(),
+            },
+            credentials,
+        })
+    }
+
+    /// Verifies every solution of `answer`, see `verify_logged`.
+    ///
+    /// # Returns
+    ///
+    /// Whether all of them are right, and the error of the first malformed one, if any, which
+    /// is not verified.
+    fn verify_answer(&self, answer: &PendingAnswer) -> (bool, Option<Status>) {
+        let primary = &answer.primary;
+        let mut malformed = self
+            .check_structure((&primary.r1, &primary.r2), &answer.c, &primary.s)
+            .err();
+        let mut verification = malformed.is_none()
+            && self.verify_logged(
+                "primary credential",
+                (&primary.r1, &primary.r2),
+                (&primary.y1, &primary.y2),
+                &answer.c,
+                &primary.s,
+            );
+
+        for (label, credential) in &answer.credentials {
+            let Some(credential) = credential else {
+                println!("❌ Missing solution for credential {:?}", label);
+                verification = false;
+                continue;
+            };
+            if let Err(status) =
+                self.check_structure((&credential.r1, &credential.r2), &answer.c, &credential.s)
+            {
+                malformed.get_or_insert(status);
+                verification = false;
+                continue;
+            }
+            verification &= self.verify_logged(
+                label,
+                (&credential.r1, &credential.r2),
+                (&credential.y1, &credential.y2),
+                &answer.c,
+                &credential.s,
+            );
+        }
+        (verification, malformed)
+    }
+
+    /// Records the outcome of a checked answer: accepted commitments on success, a failure
+    /// towards the lockout otherwise, a malformed answer included.
+    fn settle_answer(
+        &self, auth_id: &str, answer: PendingAnswer, verification: bool, malformed: Option<Status>,
+    ) -> Result<String, Status> {
+        let user_name = answer.user_name;
+        if verification {
+            println!("✅ Correct Challenge Solution username: {:?}", user_name);
+            self.lockouts.lock().unwrap().record_success(&user_name);
+
+            let accepted_commitments = &mut self.accepted_commitments.lock().unwrap();
+            accepted_commitments.insert(&user_name, (answer.primary.r1, answer.primary.r2));
+            for (_, credential) in answer.credentials {
+                if let Some(credential) = credential {
+                    accepted_commitments.insert(&user_name, (credential.r1, credential.r2));
+                }
+            }
+
+            Ok(user_name)
+        } else {
+            println!("❌ Wrong Challenge Solution username: {:?}", user_name);
+            if self.lockouts.lock().unwrap().record_failure(&user_name) {
+                println!("⚠️ Too many failed authentications, locked username: {:?}", user_name);
+            }
+
+            Err(malformed.unwrap_or_else(|| {
+                Status::new(
+                    Code::PermissionDenied,
+                    format!("AuthId: {} bad solution to the challenge", auth_id),
+                )
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zkp_chaum_pedersen::pb::{Commitment, Credential};
+    use zkp_chaum_pedersen::retry::{retry_unavailable, RetryPolicy};
+    use zkp_chaum_pedersen::transcript::MemorySink;
+
+    /// Registers `user` with the secret `x` and no additional credentials.
+    async fn register(auth: &AuthImpl, zkp: &ZKP, user: &str, x: &BigUint) {
+        let (y1, y2) = zkp.compute_pair(x);
+        let request = RegisterRequest {
+            user: user.to_string(),
+            y1: zkp.encode_fixed(&y1),
+            y2: zkp.encode_fixed(&y2),
+            possession_proof: possession_proof(zkp, user, x),
+            ..Default::default()
+        };
+        auth.register(Request::new(request)).await.unwrap();
+    }
+
+    /// The `possession_proof` of a registration of `user` with the secret `x`.
+    fn possession_proof(zkp: &ZKP, user: &str, x: &BigUint) -> Vec<u8> {
+        let (y1, y2) = zkp.compute_pair(x);
+        zkp.prove_possession(x, &y1, &y2, user).to_bytes().unwrap()
+    }
+
+    /// Requests a challenge for `user` and answers it, proving the primary credential with `x`
+    /// and the labeled credentials with the secrets in `proven`. Every labeled credential in
+    /// `committed` gets a commitment, but only those in `proven` get an answer.
+    async fn answer_challenge(
+        auth: &AuthImpl, zkp: &ZKP, user: &str, x: &BigUint, committed: &[&str],
+        proven: &[(&str, &BigUint)],
+    ) -> Result<AuthenticationAnswerRequest, Status> {
+        let k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let mut nonces = HashMap::new();
+        let mut commitments = Vec::new();
+        for label in committed {
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            nonces.insert(label.to_string(), k);
+            commitments.push(Commitment {
+                label: label.to_string(),
+                r1: zkp.encode_fixed(&r1),
+                r2: zkp.encode_fixed(&r2),
+            });
+        }
+
+        let request = AuthenticationChallengeRequest {
+            user: user.to_string(),
+            r1: zkp.encode_fixed(&r1),
+            r2: zkp.encode_fixed(&r2),
+            commitments,
+            protocol_version: PROTOCOL_VERSION,
+        };
+        let response = auth
+            .create_authentication_challenge(Request::new(request))
+            .await?;
+        let response = response.into_inner();
+        let c = BigUint::from_bytes_be(&response.c);
+
+        let answers = proven
+            .iter()
+            .map(|(label, x)| Answer {
+                label: label.to_string(),
+                s: zkp.solve(&nonces[*label], &c, x).to_bytes_be(),
+            })
+            .collect();
+        Ok(AuthenticationAnswerRequest {
+            auth_id: response.auth_id,
+            s: zkp.solve(&k, &c, x).to_bytes_be(),
+            answers,
+        })
+    }
+
+    /// Runs one full authentication round, see `answer_challenge`.
+    async fn authenticate(
+        auth: &AuthImpl, zkp: &ZKP, user: &str, x: &BigUint, committed: &[&str],
+        proven: &[(&str, &BigUint)],
+    ) -> Result<Response<AuthenticationAnswerResponse>, Status> {
+        let request = answer_challenge(auth, zkp, user, x, committed, proven).await?;
+        auth.verify_authentication(Request::new(request)).await
+    }
+
+    /// Proves knowledge of `x` for `user` and invalidates all of the user's sessions.
+    async fn invalidate_sessions(
+        auth: &AuthImpl, zkp: &ZKP, user: &str, x: &BigUint,
+    ) -> Result<Response<InvalidateSessionsResponse>, Status> {
+        let answer = answer_challenge(auth, zkp, user, x, &[], &[]).await?;
+        let request = InvalidateSessionsRequest {
+            auth_id: answer.auth_id,
+            s: answer.s,
+            answers: answer.answers,
+        };
+        auth.invalidate_sessions(Request::new(request)).await
+    }
+
+    async fn validate_session(auth: &AuthImpl, session_id: &str) -> Result<String, Status> {
+        let request = ValidateSessionRequest {
+            session_id: session_id.to_string(),
+        };
+        let response = auth.validate_session(Request::new(request)).await?;
+        Ok(response.into_inner().user)
+    }
+
+    #[tokio::test]
+    async fn test_grpc_path_matches_library_verifier() {
+        let (alpha, beta, p, q) = ZKP::get_constants();
+        let zkp = ZKP::new(&p, &q, &alpha, &beta);
+        let auth = AuthImpl::default();
+
+        let x = ZKP::generate_random_number_below(&q);
+        let (y1, y2) = zkp.compute_pair(&x);
+        let request = RegisterRequest {
+            user: "alice".to_string(),
+            y1: zkp.encode_fixed(&y1),
+            y2: zkp.encode_fixed(&y2),
+            possession_proof: possession_proof(&zkp, "alice", &x),
+            ..Default::default()
+        };
+        auth.register(Request::new(request)).await.unwrap();
+
+        for secret in [x.clone(), &x + 1u32] {
+            let k = ZKP::generate_random_number_below(&q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let request = AuthenticationChallengeRequest {
+                user: "alice".to_string(),
+                r1: zkp.encode_fixed(&r1),
+                r2: zkp.encode_fixed(&r2),
+                ..Default::default()
+            };
+            let response = auth
+                .create_authentication_challenge(Request::new(request))
+                .await
+                .unwrap();
+            let response = response.into_inner();
+
+            let c = BigUint::from_bytes_be(&response.c);
+            let s = zkp.solve(&k, &c, &secret);
+            let request = AuthenticationAnswerRequest {
+                auth_id: response.auth_id,
+                s: s.to_bytes_be(),
+                ..Default::default()
+            };
+            let grpc_result = auth
+                .verify_authentication(Request::new(request))
+                .await
+                .is_ok();
+
+            assert_eq!(grpc_result, zkp.finish(&r1, &r2, &y1, &y2, &c, &s));
+            assert_eq!(grpc_result, secret == x);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_all_labeled_credentials_must_be_proven() {
+        let (alpha, beta, p, q) = ZKP::get_constants();
+        let zkp = ZKP::new(&p, &q, &alpha, &beta);
+        let auth = AuthImpl::default();
+
+        let password = ZKP::generate_random_number_below(&q);
+        let token = ZKP::generate_random_number_below(&q);
+        let (y1, y2) = zkp.compute_pair(&password);
+        let (token_y1, token_y2) = zkp.compute_pair(&token);
+        let request = |label: &str, token_proof: Proof| RegisterRequest {
+            user: "alice".to_string(),
+            y1: zkp.encode_fixed(&y1),
+            y2: zkp.encode_fixed(&y2),
+            credentials: vec![Credential {
+                label: label.to_string(),
+                y1: zkp.encode_fixed(&token_y1),
+                y2: zkp.encode_fixed(&token_y2),
+                possession_proof: token_proof.to_bytes().unwrap(),
+            }],
+            protocol_version: PROTOCOL_VERSION,
+            possession_proof: possession_proof(&zkp, "alice", &password),
+            ..Default::default()
+        };
+
+        // the token must come with a proof of possession of its own, for its label
+        let proof = zkp.prove_credential_possession(&token, &token_y1, &token_y2, "alice", "token");
+        let (other, _, _) = zkp.generate_credential();
+        let forged =
+            zkp.prove_credential_possession(&other, &token_y1, &token_y2, "alice", "token");
+        for request in [
+            request("token", forged),
+            request("yubikey", proof.clone()),
+            request("token", zkp.prove_possession(&token, &token_y1, &token_y2, "alice")),
+        ] {
+            let result = auth.register(Request::new(request)).await;
+            assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+        }
+        assert!(auth.user_info.lock().unwrap().is_empty());
+        auth.register(Request::new(request("token", proof)))
+            .await
+            .unwrap();
+
+        // only the password is proven
+        let result = authenticate(&auth, &zkp, "alice", &password, &["token"], &[]).await;
+        assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+
+        // the token is not even committed to
+        let result = authenticate(&auth, &zkp, "alice", &password, &[], &[]).await;
+        assert_eq!(result.unwrap_err().code(), Code::InvalidArgument);
+
+        // the token is proven with the wrong secret
+        let proven = [("token", &password)];
+        let result = authenticate(&auth, &zkp, "alice", &password, &["token"], &proven).await;
+        assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+
+        // both are proven
+        let proven = [("token", &token)];
+        let result = authenticate(&auth, &zkp, "alice", &password, &["token"], &proven).await;
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_pb_types_accessible() {
+        use prost::Message;
+
+        let response = AuthenticationChallengeResponse {
+            auth_id: "auth".to_string(),
+            c: vec![1, 2, 3],
+        };
+        let bytes = response.encode_to_vec();
+        assert_eq!(AuthenticationChallengeResponse::decode(&bytes[..]).unwrap(), response);
+        let _ = AuthServer::new(AuthImpl::default());
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_all_sessions() {
+        let (alpha, beta, p, q) = ZKP::get_constants();
+        let zkp = ZKP::new(&p, &q, &alpha, &beta);
+        let auth = AuthImpl::default();
+
+        let x = ZKP::generate_random_number_below(&q);
+        register(&auth, &zkp, "alice", &x).await;
+        register(&auth, &zkp, "bob", &x).await;
+
+        let mut sessions = Vec::new();
+        for user in ["alice", "alice", "bob"] {
+            let response = authenticate(&auth, &zkp, user, &x, &[], &[]).await.unwrap();
+            sessions.push(response.into_inner().session_id);
+        }
+        for (session_id, user) in sessions.iter().zip(["alice", "alice", "bob"]) {
+            assert_eq!(validate_session(&auth, session_id).await.unwrap(), user);
+        }
+
+        // a wrong proof does not invalidate anything
+        let result = invalidate_sessions(&auth, &zkp, "alice", &(&x + 1u32)).await;
+        assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+        assert!(validate_session(&auth, &sessions[0]).await.is_ok());
+
+        let response = invalidate_sessions(&auth, &zkp, "alice", &x).await.unwrap();
+        assert_eq!(response.into_inner().invalidated, 2);
+
+        for session_id in &sessions[..2] {
+            let result = validate_session(&auth, session_id).await;
+            assert_eq!(result.unwrap_err().code(), Code::NotFound);
+        }
+        assert_eq!(validate_session(&auth, &sessions[2]).await.unwrap(), "bob");
+    }
+
+    #[tokio::test]
+    async fn test_nonce_reuse_is_refused() {
+        let (alpha, beta, p, q) = ZKP::get_constants();
+        let zkp = ZKP::new(&p, &q, &alpha, &beta);
+        let auth = AuthImpl::default();
+
+        let x = ZKP::generate_random_number_below(&q);
+        register(&auth, &zkp, "alice", &x).await;
+
+        let k = ZKP::generate_random_number_below(&q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let request = AuthenticationChallengeRequest {
+            user: "alice".to_string(),
+            r1: zkp.encode_fixed(&r1),
+            r2: zkp.encode_fixed(&r2),
+            ..Default::default()
+        };
+
+        let response = auth
+            .create_authentication_challenge(Request::new(request.clone()))
+            .await;
+        assert!(response.is_ok());
+
+        let response = auth
+            .create_authentication_challenge(Request::new(request))
+            .await;
+        assert_eq!(response.unwrap_err().code(), Code::InvalidArgument);
+
+        // a request refused for an unknown credential does not burn its commitment
+        let k = ZKP::generate_random_number_below(&q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let request = AuthenticationChallengeRequest {
+            user: "alice".to_string(),
+            r1: zkp.encode_fixed(&r1),
+            r2: zkp.encode_fixed(&r2),
+            ..Default::default()
+        };
+        let unknown = AuthenticationChallengeRequest {
+            commitments: vec![Commitment {
+                label: "token".to_string(),
+                r1: zkp.encode_fixed(&zkp.alpha),
+                r2: zkp.encode_fixed(&zkp.beta),
+            }],
+            ..request.clone()
+        };
+        let response = auth
+            .create_authentication_challenge(Request::new(unknown))
+            .await;
+        assert_eq!(response.unwrap_err().code(), Code::InvalidArgument);
+        let response = auth
+            .create_authentication_challenge(Request::new(request.clone()))
+            .await;
+        assert!(response.is_ok());
+
+        // nor does one refused for repeating a commitment within the request
+        auth.user_info
+            .lock()
+            .unwrap()
+            .get_mut("alice")
+            .unwrap()
+            .credentials
+            .insert("token".to_string(), Default::default());
+        let k = ZKP::generate_random_number_below(&q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let repeated = AuthenticationChallengeRequest {
+            r1: zkp.encode_fixed(&r1),
+            r2: zkp.encode_fixed(&r2),
+            commitments: vec![Commitment {
+                label: "token".to_string(),
+                r1: zkp.encode_fixed(&r1),
+                r2: zkp.encode_fixed(&r2),
+            }],
+            ..request
+        };
+        let response = auth
+            .create_authentication_challenge(Request::new(repeated))
+            .await;
+        assert_eq!(response.unwrap_err().code(), Code::InvalidArgument);
+        assert!(!auth.seen_commitments.lock().unwrap().contains(&(r1, r2)));
+        auth.user_info
+            .lock()
+            .unwrap()
+            .get_mut("alice")
+            .unwrap()
+            .credentials
+            .clear();
+
+        // a fresh nonce is still fine
+        assert!(authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn test_seen_commitments_capacity() {
+        let commitment = |i: u32| (BigUint::from(i), BigUint::from(i));
+        let mut seen_commitments = SeenCommitments::with_capacity(2);
+
+        assert!(seen_commitments.insert(commitment(1)));
+        assert!(seen_commitments.insert(commitment(2)));
+        assert!(!seen_commitments.insert(commitment(1)));
+
+        // the oldest commitment is forgotten once full
+        assert!(seen_commitments.insert(commitment(3)));
+        assert!(seen_commitments.insert(commitment(1)));
+        assert!(!seen_commitments.insert(commitment(3)));
+    }
+
+    #[tokio::test]
+    async fn test_replayed_commitment_is_rejected() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+
+        let k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let request = AuthenticationChallengeRequest {
+            user: "alice".to_string(),
+            r1: zkp.encode_fixed(&r1),
+            r2: zkp.encode_fixed(&r2),
+            ..Default::default()
+        };
+        let response = auth
+            .create_authentication_challenge(Request::new(request.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        let answer = AuthenticationAnswerRequest {
+            auth_id: response.auth_id,
+            s: zkp
+                .solve(&k, &BigUint::from_bytes_be(&response.c), &x)
+                .to_bytes_be(),
+            ..Default::default()
+        };
+        auth.verify_authentication(Request::new(answer))
+            .await
+            .unwrap();
+
+        // the captured commitment is refused, even once forgotten by the nonce reuse check
+        *auth.seen_commitments.lock().unwrap() = SeenCommitments::default();
+        let response = auth
+            .create_authentication_challenge(Request::new(request))
+            .await;
+        assert_eq!(response.unwrap_err().code(), Code::AlreadyExists);
+
+        // a fresh one succeeds
+        assert!(authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn test_accepted_commitments_eviction() {
+        let commitment = |i: u32| (BigUint::from(i), BigUint::from(i));
+
+        let mut fifo = AcceptedCommitments::new(2, Eviction::Fifo);
+        let mut lru = AcceptedCommitments::new(2, Eviction::Lru);
+        for cache in [&mut fifo, &mut lru] {
+            cache.insert("alice", commitment(1));
+            cache.insert("alice", commitment(2));
+            assert!(cache.contains("alice", &commitment(1)));
+            assert!(!cache.contains("bob", &commitment(1)));
+            cache.insert("alice", commitment(3));
+        }
+
+        // FIFO forgot the oldest entry, LRU the one not used since
+        assert!(!fifo.contains("alice", &commitment(1)));
+        assert!(fifo.contains("alice", &commitment(2)));
+        assert!(lru.contains("alice", &commitment(1)));
+        assert!(!lru.contains("alice", &commitment(2)));
+        assert!(fifo.contains("alice", &commitment(3)) && lru.contains("alice", &commitment(3)));
+
+        // repeated uses keep the order bounded, and the least recently used entry is still the
+        // one forgotten
+        for _ in 0..100 {
+            assert!(lru.contains("alice", &commitment(1)));
+        }
+        assert!(lru.order.len() < 4);
+        lru.insert("alice", commitment(4));
+        assert!(lru.contains("alice", &commitment(1)));
+        assert!(!lru.contains("alice", &commitment(3)));
+        assert!(lru.contains("alice", &commitment(4)));
+    }
+
+    #[tokio::test]
+    async fn test_fabricated_auth_id_is_rejected() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+        let answer = answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        assert!(auth
+            .auth_id_keys
+            .lock()
+            .unwrap()
+            .was_issued(&answer.auth_id, &auth.user_info.lock().unwrap()["alice"].c));
+
+        // an entry planted in the store, for an auth_id the server never issued
+        for fabricated in ["0123456789ab", "0123456789ab-00112233445566778899aabbccddeeff"] {
+            auth.auth_id_to_user
+                .lock()
+                .unwrap()
+                .insert(fabricated.to_string(), "alice".to_string());
+            let request = AuthenticationAnswerRequest {
+                auth_id: fabricated.to_string(),
+                ..answer.clone()
+            };
+            let result = auth.verify_authentication(Request::new(request)).await;
+            assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+        }
+
+        // an auth_id issued with another key
+        let other_key = AuthIdKey::generate();
+        let c = auth.user_info.lock().unwrap()["alice"].c.clone();
+        let forged = other_key.issue(&c);
+        auth.auth_id_to_user
+            .lock()
+            .unwrap()
+            .insert(forged.clone(), "alice".to_string());
+        let request = AuthenticationAnswerRequest {
+            auth_id: forged,
+            ..answer.clone()
+        };
+        let result = auth.verify_authentication(Request::new(request)).await;
+        assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+
+        // the issued one still works, once planted back since a user has one challenge at most
+        auth.auth_id_to_user
+            .lock()
+            .unwrap()
+            .insert(answer.auth_id.clone(), "alice".to_string());
+        assert!(auth
+            .verify_authentication(Request::new(answer))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_auth_id_key_rotation() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        for user in ["alice", "bob", "carol"] {
+            register(&auth, &zkp, user, &x).await;
+        }
+
+        // issued before the rotation, answered within the window
+        let alice = answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        let bob = answer_challenge(&auth, &zkp, "bob", &x, &[], &[])
+            .await
+            .unwrap();
+        auth.rotate_auth_id_key(Duration::from_millis(200));
+        let carol = answer_challenge(&auth, &zkp, "carol", &x, &[], &[])
+            .await
+            .unwrap();
+        assert!(auth
+            .verify_authentication(Request::new(alice))
+            .await
+            .is_ok());
+
+        // answered after the previous key was dropped
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let result = auth.verify_authentication(Request::new(bob)).await;
+        assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+        assert!(auth
+            .verify_authentication(Request::new(carol))
+            .await
+            .is_ok());
+
+        // dropped before the end of the window
+        let alice = answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        auth.rotate_auth_id_key(DEFAULT_AUTH_ID_KEY_ROTATION_WINDOW);
+        auth.auth_id_keys.lock().unwrap().drop_previous();
+        let result = auth.verify_authentication(Request::new(alice)).await;
+        assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+
+        let args = Args::try_parse_from(["server", "--auth-id-key-rotation", "3600"]).unwrap();
+        assert_eq!(args.auth_id_key_rotation, Some(3600));
+        assert_eq!(args.auth_id_key_rotation_window, DEFAULT_AUTH_ID_KEY_ROTATION_WINDOW.as_secs());
+        assert!(Args::try_parse_from(["server", "--auth-id-key-rotation", "0"]).is_err());
+        assert!(Args::try_parse_from(["server", "--auth-id-key-rotation-window", "60"]).is_err());
+
+        let rotation = auth_id_key_rotation_from_args(&args).unwrap();
+        assert_eq!(
+            rotation,
+            Some((Duration::from_secs(3600), DEFAULT_AUTH_ID_KEY_ROTATION_WINDOW))
+        );
+        let args = Args::try_parse_from(["server"]).unwrap();
+        assert_eq!(auth_id_key_rotation_from_args(&args).unwrap(), None);
+
+        // the default window outlasts a one-minute interval
+        let args = Args::try_parse_from(["server", "--auth-id-key-rotation", "60"]).unwrap();
+        assert!(auth_id_key_rotation_from_args(&args).is_err());
+        let args = Args::try_parse_from([
+            "server",
+            "--auth-id-key-rotation",
+            "60",
+            "--auth-id-key-rotation-window",
+            "60",
+        ])
+        .unwrap();
+        assert!(auth_id_key_rotation_from_args(&args).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_verification_limit() {
+        let zkp = ZKP::default();
+        let limit = VerificationLimit::new(1);
+        let auth = AuthImpl {
+            verification_limit: Some(limit.clone()),
+            ..Default::default()
+        };
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let mut answers = Vec::new();
+        for user in ["alice", "bob"] {
+            register(&auth, &zkp, user, &x).await;
+            answers.push(
+                answer_challenge(&auth, &zkp, user, &x, &[], &[])
+                    .await
+                    .unwrap(),
+            );
+        }
+        register(&auth, &zkp, "carol", &x).await;
+
+        // both verifications wait while the only slot is taken
+        let slot = limit.acquire().await;
+        let tasks: Vec<_> = answers
+            .into_iter()
+            .map(|answer| {
+                let auth = auth.clone();
+                tokio::spawn(async move { auth.verify_authentication(Request::new(answer)).await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(tasks.iter().all(|task| !task.is_finished()));
+
+        // without holding the maps, so other users still get challenges
+        assert!(auth.auth_id_to_user.try_lock().is_ok());
+        assert!(auth.user_info.try_lock().is_ok());
+        answer_challenge(&auth, &zkp, "carol", &x, &[], &[])
+            .await
+            .unwrap();
+
+        // then run one after the other, and both succeed
+        drop(slot);
+        for task in tasks {
+            assert!(task.await.unwrap().is_ok());
+        }
+        assert_eq!(limit.slots.available_permits(), 1);
+
+        let args = Args::try_parse_from(["server", "--max-concurrent-verifications", "2"]).unwrap();
+        assert_eq!(
+            auth_impl_from_args(&args, ZKP::default())
+                .verification_limit
+                .unwrap()
+                .limit(),
+            2
+        );
+        assert!(Args::try_parse_from(["server", "--max-concurrent-verifications", "0"]).is_err());
+        let args = Args::try_parse_from(["server"]).unwrap();
+        assert!(auth_impl_from_args(&args, ZKP::default())
+            .verification_limit
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_auth_id_collision() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // the first two nonces are the same, then each differs
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        fn colliding(len: usize) -> String {
+            let n = CALLS.fetch_add(1, Ordering::SeqCst).saturating_sub(1);
+            format!("{:0>len$}", n, len = len)
+        }
+        fn constant(len: usize) -> String {
+            "0".repeat(len)
+        }
+
+        // with 0-bit challenges, every challenge is 0, so equal nonces make equal IDs
+        let zkp = ZKP::default();
+        let auth = AuthImpl {
+            challenge_bits: Some(0),
+            ..Default::default()
+        };
+        auth.auth_id_keys.lock().unwrap().nonce_source = colliding;
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        for user in ["alice", "bob", "carol"] {
+            register(&auth, &zkp, user, &x).await;
+        }
+
+        let alice = answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        let bob = answer_challenge(&auth, &zkp, "bob", &x, &[], &[])
+            .await
+            .unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+        assert_ne!(alice.auth_id, bob.auth_id);
+        {
+            let outstanding = auth.auth_id_to_user.lock().unwrap();
+            assert_eq!(outstanding.get(&alice.auth_id).unwrap(), "alice");
+            assert_eq!(outstanding.get(&bob.auth_id).unwrap(), "bob");
+        }
+
+        // a generator that only ever collides gives up rather than clobbering
+        auth.auth_id_keys.lock().unwrap().nonce_source = constant;
+        let result = answer_challenge(&auth, &zkp, "carol", &x, &[], &[]).await;
+        assert_eq!(result.unwrap_err().code(), Code::Internal);
+        assert_eq!(
+            auth.auth_id_to_user
+                .lock()
+                .unwrap()
+                .get(&alice.auth_id)
+                .unwrap(),
+            "alice"
+        );
+
+        for answer in [alice, bob] {
+            assert!(auth
+                .verify_authentication(Request::new(answer))
+                .await
+                .is_ok());
+        }
+
+        for (flags, len) in [
+            (&[][..], DEFAULT_AUTH_ID_NONCE_LEN),
+            (&["--auth-id-length", "32"], 32),
+        ] {
+            let args = Args::try_parse_from(["server"].iter().chain(flags)).unwrap();
+            let auth = auth_impl_from_args(&args, ZKP::default());
+            let auth_id = auth.auth_id_keys.lock().unwrap().issue(&zkp.q);
+            assert_eq!(auth_id.split_once('-').unwrap().0.len(), len);
+        }
+        assert!(Args::try_parse_from(["server", "--auth-id-length", "4"]).is_err());
+    }
+
+    #[test]
+    fn test_verify_fixture_proofs() {
+        use zkp_chaum_pedersen::fixtures::{forged_proof, public_keys, toy_zkp, valid_proof};
+
+        let auth = AuthImpl {
+            zkp: toy_zkp(),
+            ..Default::default()
+        };
+        let (y1, y2) = public_keys();
+
+        let proof = valid_proof();
+        assert!(auth.verify_logged(
+            "fixture",
+            (&proof.r1, &proof.r2),
+            (&y1, &y2),
+            &proof.c,
+            &proof.s
+        ));
+        let proof = forged_proof();
+        assert!(!auth.verify_logged(
+            "fixture",
+            (&proof.r1, &proof.r2),
+            (&y1, &y2),
+            &proof.c,
+            &proof.s
+        ));
+    }
+
+    /// Posts `body` as JSON to `path` of the HTTP/JSON gateway, returning the status and the
+    /// JSON response.
+    async fn http_post(
+        auth: &AuthImpl, path: &str, body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::post(path)
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        let response = http_gateway(auth.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_http_gateway() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+
+        let register = serde_json::json!({
+            "user": "alice",
+            "y1": hex::encode(zkp.encode_fixed(&y1)),
+            "y2": hex::encode(zkp.encode_fixed(&y2)),
+            "possession_proof": hex::encode(possession_proof(&zkp, "alice", &x)),
+        });
+        let (status, _) = http_post(&auth, "/register", register).await;
+        assert_eq!(status, StatusCode::OK);
+
+        for s_offset in [1u32, 0] {
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let challenge = serde_json::json!({
+                "user": "alice",
+                "r1": hex::encode(zkp.encode_fixed(&r1)),
+                "r2": hex::encode(zkp.encode_fixed(&r2)),
+            });
+            let (status, response) = http_post(&auth, "/challenge", challenge).await;
+            assert_eq!(status, StatusCode::OK);
+            let response: HttpChallengeResponse = serde_json::from_value(response).unwrap();
+            let c = BigUint::from_bytes_be(&hex::decode(response.c).unwrap());
+
+            let s = (zkp.solve(&k, &c, &x) + s_offset) % &zkp.q;
+            let answer = serde_json::json!({
+                "auth_id": response.auth_id,
+                "s": hex::encode(s.to_bytes_be()),
+            });
+            let (status, response) = http_post(&auth, "/answer", answer).await;
+            if s_offset == 0 {
+                assert_eq!(status, StatusCode::OK);
+                let session_id = response["session_id"].as_str().unwrap();
+                assert_eq!(validate_session(&auth, session_id).await.unwrap(), "alice");
+            } else {
+                assert_eq!(status, StatusCode::FORBIDDEN, "{response}");
+                assert!(response["error"].is_string());
+            }
+        }
+
+        let unknown = serde_json::json!({ "user": "bob", "r1": "02", "r2": "03" });
+        let (status, _) = http_post(&auth, "/challenge", unknown).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let malformed =
+            serde_json::json!({ "user": "bob", "y1": "zz", "y2": "03", "possession_proof": "" });
+        let (status, _) = http_post(&auth, "/register", malformed).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_http_gateway_is_big_endian() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl {
+            endianness: Endianness::Little,
+            ..Default::default()
+        };
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+
+        let register = serde_json::json!({
+            "user": "alice",
+            "y1": hex::encode(zkp.encode_fixed(&y1)),
+            "y2": hex::encode(zkp.encode_fixed(&y2)),
+            "possession_proof": hex::encode(possession_proof(&zkp, "alice", &x)),
+        });
+        let (status, response) = http_post(&auth, "/register", register).await;
+        assert_eq!(status, StatusCode::OK, "{response}");
+        assert_eq!(auth.user_info.lock().unwrap()["alice"].y1, y1);
+
+        let k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let challenge = serde_json::json!({
+            "user": "alice",
+            "r1": hex::encode(zkp.encode_fixed(&r1)),
+            "r2": hex::encode(zkp.encode_fixed(&r2)),
+        });
+        let (status, response) = http_post(&auth, "/challenge", challenge).await;
+        assert_eq!(status, StatusCode::OK, "{response}");
+        let response: HttpChallengeResponse = serde_json::from_value(response).unwrap();
+        let c = BigUint::from_bytes_be(&hex::decode(response.c).unwrap());
+
+        let answer = serde_json::json!({
+            "auth_id": response.auth_id,
+            "s": hex::encode(zkp.solve(&k, &c, &x).to_bytes_be()),
+        });
+        let (status, response) = http_post(&auth, "/answer", answer).await;
+        assert_eq!(status, StatusCode::OK, "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_signed_session_tokens() {
+        let zkp = ZKP::default();
+        let key = TokenKey::generate();
+        let auth = AuthImpl {
+            session_tokens: Some(key.clone()),
+            ..Default::default()
+        };
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+
+        let token = authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap()
+            .into_inner()
+            .session_id;
+        let claims = zkp_chaum_pedersen::token::verify_token(&key, &token).unwrap();
+        assert_eq!(claims.user, "alice");
+        let ttl = DEFAULT_SESSION_TTL.as_secs();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(claims.exp > now + ttl - 60 && claims.exp <= now + ttl);
+
+        // the token is also a regular session ID for the server
+        assert_eq!(validate_session(&auth, &token).await.unwrap(), "alice");
+        assert!(zkp_chaum_pedersen::token::verify_token(&TokenKey::generate(), &token).is_err());
+    }
+
+    #[test]
+    fn test_read_token_key() {
+        let path = std::env::temp_dir().join(format!("zkp-token-key-{}", std::process::id()));
+        std::fs::write(&path, format!("{}\n", "ab".repeat(32))).unwrap();
+        let key = read_token_key(&path).unwrap();
+        let claims = SessionClaims::new("alice", u64::MAX);
+        let same = TokenKey::from_bytes([0xab; 32]);
+        assert!(same.verify(&key.issue(&claims)).is_ok());
+
+        std::fs::write(&path, "abcd").unwrap();
+        assert!(read_token_key(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_registration_tokens() {
+        let zkp = ZKP::default();
+        let key = TokenKey::generate();
+        let auth = AuthImpl {
+            registration_tokens: Some(key.clone()),
+            ..Default::default()
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let request = |user: &str, token: &str| {
+            let (y1, y2) = zkp.compute_pair(&x);
+            Request::new(RegisterRequest {
+                user: user.to_string(),
+                y1: zkp.encode_fixed(&y1),
+                y2: zkp.encode_fixed(&y2),
+                possession_proof: possession_proof(&zkp, user, &x),
+                registration_token: token.to_string(),
+                ..Default::default()
+            })
+        };
+        let denied = |result: Result<Response<RegisterResponse>, Status>| {
+            assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+        };
+
+        denied(auth.register(request("alice", "")).await);
+
+        let token = key.issue_registration(&RegistrationClaims::new("alice", now + 60));
+        denied(auth.register(request("bob", &token)).await);
+        // a refused registration does not use the token up
+        let mut bad_proof = request("alice", &token);
+        bad_proof.get_mut().possession_proof = possession_proof(&zkp, "bob", &x);
+        denied(auth.register(bad_proof).await);
+
+        auth.register(request("alice", &token)).await.unwrap();
+        assert!(authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .is_ok());
+
+        // reused, even after a restart from the state file
+        denied(auth.register(request("alice", &token)).await);
+        let restored = AuthImpl {
+            registration_tokens: Some(key.clone()),
+            ..Default::default()
+        };
+        restored.import_state(&auth.export_state(false)).unwrap();
+        denied(restored.register(request("alice", &token)).await);
+
+        let expired = key.issue_registration(&RegistrationClaims::new("alice", now - 1));
+        denied(auth.register(request("alice", &expired)).await);
+        let other_key = TokenKey::generate();
+        let forged = other_key.issue_registration(&RegistrationClaims::new("alice", now + 60));
+        denied(auth.register(request("alice", &forged)).await);
+
+        // without a registration token key, tokens are ignored
+        AuthImpl::default()
+            .register(request("alice", ""))
+            .await
+            .unwrap();
+
+        let args = Args::try_parse_from(["server", "--issue-registration-token", "alice"]);
+        assert!(args.is_err());
+        let args = Args::try_parse_from([
+            "server",
+            "--registration-token-key",
+            "key",
+            "--issue-registration-token",
+            "alice",
+        ])
+        .unwrap();
+        assert_eq!(args.issue_registration_token.as_deref(), Some("alice"));
+        assert_eq!(args.registration_token_ttl, DEFAULT_REGISTRATION_TOKEN_TTL.as_secs());
+    }
+
+    #[tokio::test]
+    async fn test_export_import_state() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+        let session_id = authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap()
+            .into_inner()
+            .session_id;
+
+        let restored = AuthImpl::default();
+        restored.import_state(&auth.export_state(false)).unwrap();
+        assert!(validate_session(&restored, &session_id).await.is_err());
+        authenticate(&restored, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        let x_fake = &x + 1u32;
+        assert!(authenticate(&restored, &zkp, "alice", &x_fake, &[], &[])
+            .await
+            .is_err());
+
+        let restored = AuthImpl::default();
+        restored.import_state(&auth.export_state(true)).unwrap();
+        assert_eq!(validate_session(&restored, &session_id).await.unwrap(), "alice");
+
+        let other_group = AuthImpl {
+            zkp: ZKP::from_group(Group::Rfc2048),
+            ..Default::default()
+        };
+        assert!(other_group.import_state(&auth.export_state(true)).is_err());
+        assert!(restored.import_state(b"{}").is_err());
+    }
+
+    #[test]
+    fn test_insecure_params_guard() {
+        let args = Args::try_parse_from(["server", "--group", "toy"]).unwrap();
+        assert!(zkp_from_args(&args).is_err());
+
+        let args =
+            Args::try_parse_from(["server", "--group", "toy", "--allow-insecure-params"]).unwrap();
+        assert_eq!(zkp_from_args(&args).unwrap().p, BigUint::from(23u32));
+
+        let args = Args::try_parse_from(["server"]).unwrap();
+        assert!(zkp_from_args(&args).is_ok());
+
+        let args = Args::try_parse_from(["server", "--min-p-bits", "2048"]).unwrap();
+        assert!(zkp_from_args(&args).is_err());
+        let args =
+            Args::try_parse_from(["server", "-g", "rfc2048", "--min-p-bits", "2048"]).unwrap();
+        assert!(zkp_from_args(&args).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_mode() {
+        // nothing is ever restored from or saved to a state file
+        assert!(
+            Args::try_parse_from(["server", "--ephemeral", "--state-file", "state.json"]).is_err()
+        );
+        assert!(Args::try_parse_from(["server", "--ephemeral-ttl", "60"]).is_err());
+
+        let args =
+            Args::try_parse_from(["server", "--ephemeral", "--ephemeral-ttl", "60"]).unwrap();
+        assert_eq!(args.state_file, None);
+        let mut auth = auth_impl_from_args(&args, ZKP::default());
+        assert_eq!(auth.registration_ttl, Some(Duration::from_secs(60)));
+        assert_eq!(auth.sessions.lock().unwrap().ttl(), Duration::from_secs(60));
+
+        let args = Args::try_parse_from(["server"]).unwrap();
+        assert_eq!(auth_impl_from_args(&args, ZKP::default()).registration_ttl, None);
+
+        auth.registration_ttl = Some(Duration::from_millis(200));
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+        let session_id = authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap()
+            .into_inner()
+            .session_id;
+        assert_eq!(validate_session(&auth, &session_id).await.unwrap(), "alice");
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let err = authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        assert!(auth.user_info.lock().unwrap().is_empty());
+        assert_eq!(
+            validate_session(&auth, &session_id)
+                .await
+                .unwrap_err()
+                .code(),
+            Code::NotFound
+        );
+    }
+
+    #[test]
+    fn test_user_ids() {
+        let raw = UserIds::default();
+        assert_eq!(raw.id("Alice"), "Alice");
+        assert_ne!(raw.id("Alice"), raw.id("alice"));
+
+        let normalized = UserIds {
+            normalize: true,
+            hash: false,
+        };
+        assert_eq!(normalized.id(" Alice "), "alice");
+
+        let hashed = UserIds {
+            normalize: false,
+            hash: true,
+        };
+        assert_eq!(hashed.id("alice").len(), 64);
+        assert_ne!(hashed.id("Alice"), hashed.id("alice"));
+
+        let both = UserIds {
+            normalize: true,
+            hash: true,
+        };
+        assert_eq!(both.id("Alice"), both.id(" alice"));
+        assert_eq!(both.id("alice"), hashed.id("alice"));
+    }
+
+    #[tokio::test]
+    async fn test_case_variant_user_names() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl {
+            user_ids: UserIds {
+                normalize: true,
+                hash: true,
+            },
+            ..Default::default()
+        };
+
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "Alice", &x).await;
+        assert!(!auth.user_info.lock().unwrap().contains_key("Alice"));
+
+        let session_id = authenticate(&auth, &zkp, "alice ", &x, &[], &[])
+            .await
+            .unwrap()
+            .into_inner()
+            .session_id;
+        assert_eq!(validate_session(&auth, &session_id).await.unwrap(), auth.user_ids.id("ALICE"));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_answer_uses_up_the_challenge() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+
+        let request = answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        let malformed = AuthenticationAnswerRequest {
+            s: zkp.q.to_bytes_be(),
+            ..request.clone()
+        };
+        let result = auth.verify_authentication(Request::new(malformed)).await;
+        assert_eq!(result.unwrap_err().code(), Code::InvalidArgument);
+        assert_eq!(auth.lockouts.lock().unwrap().users["alice"].failures, 1);
+
+        // the challenge is gone, even for the right answer
+        let result = auth.verify_authentication(Request::new(request)).await;
+        assert_eq!(result.unwrap_err().code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_lockout_after_failed_authentications() {
+        let zkp = ZKP::default();
+        let cooldown = Duration::from_millis(300);
+        let auth = AuthImpl {
+            lockouts: Arc::new(Mutex::new(Lockouts::new(3, cooldown))),
+            ..Default::default()
+        };
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let x_fake = &x + 1u32;
+        register(&auth, &zkp, "alice", &x).await;
+        register(&auth, &zkp, "bob", &x).await;
+
+        // a success clears the failures before the limit
+        for _ in 0..2 {
+            let status = authenticate(&auth, &zkp, "alice", &x_fake, &[], &[])
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), Code::PermissionDenied);
+        }
+        authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+
+        for _ in 0..3 {
+            let status = authenticate(&auth, &zkp, "alice", &x_fake, &[], &[])
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), Code::PermissionDenied);
+        }
+
+        // locked, even with the right secret, while other users are not
+        let status = authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        authenticate(&auth, &zkp, "bob", &x, &[], &[])
+            .await
+            .unwrap();
+
+        // a challenge obtained before the lockout cannot be answered during it
+        register(&auth, &zkp, "carol", &x).await;
+        let request = answer_challenge(&auth, &zkp, "carol", &x, &[], &[])
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            auth.lockouts.lock().unwrap().record_failure("carol");
+        }
+        let status = auth
+            .verify_authentication(Request::new(request))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        tokio::time::sleep(cooldown).await;
+        authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        assert_eq!(auth.lockouts.lock().unwrap().locked_for("alice"), None);
+    }
+
+    #[tokio::test]
+    async fn test_non_subgroup_registration_is_rejected() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+
+        // p - 1 has order 2, and y1 * (p - 1) is outside the subgroup as well
+        let order_two = &zkp.p - 1u32;
+        let confined = (&y1 * &order_two) % &zkp.p;
+        for (y1, y2) in [(&order_two, &y2), (&y1, &confined)] {
+            let request = RegisterRequest {
+                user: "alice".to_string(),
+                y1: zkp.encode_fixed(y1),
+                y2: zkp.encode_fixed(y2),
+                ..Default::default()
+            };
+            let status = auth.register(Request::new(request)).await.unwrap_err();
+            assert_eq!(status.code(), Code::InvalidArgument);
+            assert!(status.message().contains("subgroup"), "{}", status.message());
+        }
+
+        let request = RegisterRequest {
+            user: "alice".to_string(),
+            y1: zkp.encode_fixed(&y1),
+            y2: zkp.encode_fixed(&y2),
+            credentials: vec![Credential {
+                label: "token".to_string(),
+                y1: zkp.encode_fixed(&y1),
+                y2: zkp.encode_fixed(&confined),
+                ..Default::default()
+            }],
+            protocol_version: PROTOCOL_VERSION,
+            possession_proof: possession_proof(&zkp, "alice", &x),
+            ..Default::default()
+        };
+        let status = auth.register(Request::new(request)).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        register(&auth, &zkp, "alice", &x).await;
+        authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_registration_proves_possession() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+        let (x, y1, y2) = zkp.generate_credential();
+        let request = |user: &str, possession_proof| RegisterRequest {
+            user: user.to_string(),
+            y1: zkp.encode_fixed(&y1),
+            y2: zkp.encode_fixed(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            possession_proof,
+            ..Default::default()
+        };
+
+        // alice's values, registered for another user or without knowing x
+        let (other, _, _) = zkp.generate_credential();
+        let forged = zkp
+            .prove_possession(&other, &y1, &y2, "mallory")
+            .to_bytes()
+            .unwrap();
+        for request in [
+            request("mallory", possession_proof(&zkp, "alice", &x)),
+            request("mallory", forged),
+            request("mallory", Vec::new()),
+        ] {
+            let result = auth.register(Request::new(request)).await;
+            assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+        }
+        assert!(auth.user_info.lock().unwrap().is_empty());
+
+        let request = request("alice", possession_proof(&zkp, "alice", &x));
+        auth.register(Request::new(request)).await.unwrap();
+        authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_registered_user_cannot_be_taken_over() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+
+        // mallory proves possession of her own keys, not of alice's
+        let (mallory, y1, y2) = zkp.generate_credential();
+        let request = RegisterRequest {
+            user: "alice".to_string(),
+            y1: zkp.encode_fixed(&y1),
+            y2: zkp.encode_fixed(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            possession_proof: possession_proof(&zkp, "alice", &mallory),
+            ..Default::default()
+        };
+        let result = auth.register(Request::new(request)).await;
+        assert_eq!(result.unwrap_err().code(), Code::AlreadyExists);
+        authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+
+        // alice may register her own keys again
+        register(&auth, &zkp, "alice", &x).await;
+    }
+
+    #[tokio::test]
+    async fn test_challenge_bits() {
+        let zkp = ZKP::default();
+        let args = Args::try_parse_from(["server", "--challenge-bits", "80"]).unwrap();
+        let auth = auth_impl_from_args(&args, zkp.clone());
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+
+        let bound = BigUint::from(1u32) << 80;
+        for _ in 0..20 {
+            let answer = answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+                .await
+                .unwrap();
+            assert!(auth.user_info.lock().unwrap()["alice"].c < bound);
+            auth.verify_authentication(Request::new(answer))
+                .await
+                .unwrap();
+        }
+        assert!(Args::try_parse_from(["server", "--challenge-bits", "0"]).is_err());
+        assert!(Args::try_parse_from(["server", "--challenge-bits", "8193"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_variable_width_elements_are_rejected() {
+        let auth = AuthImpl::default();
+
+        let request = RegisterRequest {
+            user: "alice".to_string(),
+            y1: BigUint::from(2u32).to_bytes_be(),
+            y2: BigUint::from(3u32).to_bytes_be(),
+            ..Default::default()
+        };
+        let result = auth.register(Request::new(request)).await;
+        assert_eq!(result.unwrap_err().code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_authentications_through_client_pool() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
+        use zkp_chaum_pedersen::pool::AuthClientPool;
+
+        const USERS: usize = 32;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let incoming = TcpListenerStream::new(listener).map({
+            let connections = connections.clone();
+            move |stream| {
+                connections.fetch_add(1, Ordering::SeqCst);
+                stream
+            }
+        });
+        tokio::spawn(
+            Server::builder()
+                .add_service(AuthServer::new(AuthImpl::default()))
+                .serve_with_incoming(incoming),
+        );
+
+        let pool = Arc::new(AuthClientPool::connect(&endpoint, 4).await.unwrap());
+        let zkp = ZKP::default();
+
+        let mut handles = Vec::new();
+        for i in 0..USERS {
+            let (pool, zkp) = (pool.clone(), zkp.clone());
+            handles.push(tokio::spawn(async move {
+                let user = format!("user{}", i);
+                let x = ZKP::generate_random_number_below(&zkp.q);
+                let (y1, y2) = zkp.compute_pair(&x);
+                let request = RegisterRequest {
+                    user: user.clone(),
+                    y1: zkp.encode_fixed(&y1),
+                    y2: zkp.encode_fixed(&y2),
+                    possession_proof: possession_proof(&zkp, &user, &x),
+                    ..Default::default()
+                };
+                pool.get().register(request).await.unwrap();
+
+                let k = ZKP::generate_random_number_below(&zkp.q);
+                let (r1, r2) = zkp.compute_pair(&k);
+                let request = AuthenticationChallengeRequest {
+                    user,
+                    r1: zkp.encode_fixed(&r1),
+                    r2: zkp.encode_fixed(&r2),
+                    ..Default::default()
+                };
+                let response = pool.get().create_authentication_challenge(request).await;
+                let response = response.unwrap().into_inner();
+
+                let c = BigUint::from_bytes_be(&response.c);
+                let request = AuthenticationAnswerRequest {
+                    auth_id: response.auth_id,
+                    s: zkp.solve(&k, &c, &x).to_bytes_be(),
+                    ..Default::default()
+                };
+                pool.get().verify_authentication(request).await.is_ok()
+            }));
+        }
+        for handle in handles {
+            assert!(handle.await.unwrap());
+        }
+
+        assert_eq!(pool.size(), 4);
+        assert_eq!(connections.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_authentication() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+
+        let answer = answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        let request = CancelAuthenticationRequest {
+            auth_id: answer.auth_id.clone(),
+        };
+        auth.cancel_authentication(Request::new(request.clone()))
+            .await
+            .unwrap();
+
+        let result = auth.verify_authentication(Request::new(answer)).await;
+        assert_eq!(result.unwrap_err().code(), Code::NotFound);
+
+        let result = auth.cancel_authentication(Request::new(request)).await;
+        assert_eq!(result.unwrap_err().code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_outstanding_challenges_cap() {
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let users = ["alice", "bob", "carol"];
+
+        let auth = AuthImpl {
+            auth_id_to_user: Arc::new(Mutex::new(OutstandingChallenges::new(
+                2,
+                ChallengeOverflow::Reject,
+            ))),
+            ..Default::default()
+        };
+        for user in users {
+            register(&auth, &zkp, user, &x).await;
+        }
+        let first = answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        answer_challenge(&auth, &zkp, "bob", &x, &[], &[])
+            .await
+            .unwrap();
+        let result = answer_challenge(&auth, &zkp, "carol", &x, &[], &[]).await;
+        assert_eq!(result.unwrap_err().code(), Code::ResourceExhausted);
+
+        // an answered challenge frees its slot
+        auth.verify_authentication(Request::new(first))
+            .await
+            .unwrap();
+        assert_eq!(auth.auth_id_to_user.lock().unwrap().len(), 1);
+        let carol = answer_challenge(&auth, &zkp, "carol", &x, &[], &[])
+            .await
+            .unwrap();
+
+        // a full map refuses a new challenge without touching the outstanding one
+        let result = answer_challenge(&auth, &zkp, "alice", &x, &[], &[]).await;
+        assert_eq!(result.unwrap_err().code(), Code::ResourceExhausted);
+        auth.verify_authentication(Request::new(carol))
+            .await
+            .unwrap();
+
+        // a user reissuing challenges replaces its own instead of filling the map
+        for _ in 0..5 {
+            answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+                .await
+                .unwrap();
+        }
+        assert_eq!(auth.auth_id_to_user.lock().unwrap().len(), 2);
+        let last = answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        answer_challenge(&auth, &zkp, "bob", &x, &[], &[])
+            .await
+            .unwrap();
+        auth.verify_authentication(Request::new(last))
+            .await
+            .unwrap();
+
+        let auth = AuthImpl {
+            auth_id_to_user: Arc::new(Mutex::new(OutstandingChallenges::new(
+                2,
+                ChallengeOverflow::EvictOldest,
+            ))),
+            ..Default::default()
+        };
+        let mut answers = Vec::new();
+        for user in users {
+            register(&auth, &zkp, user, &x).await;
+            answers.push(
+                answer_challenge(&auth, &zkp, user, &x, &[], &[])
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert_eq!(auth.auth_id_to_user.lock().unwrap().len(), 2);
+
+        // the oldest challenge was evicted, the others can still be answered
+        let mut answers = answers.into_iter();
+        let result = auth
+            .verify_authentication(Request::new(answers.next().unwrap()))
+            .await;
+        assert_eq!(result.unwrap_err().code(), Code::NotFound);
+        for answer in answers {
+            auth.verify_authentication(Request::new(answer))
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protocol_version() {
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+        let args = Args::try_parse_from([
+            "server",
+            "--min-protocol-version",
+            &PROTOCOL_VERSION.to_string(),
+        ])
+        .unwrap();
+        let auth = auth_impl_from_args(&args, zkp.clone());
+        let register = |protocol_version| {
+            let request = RegisterRequest {
+                user: "alice".to_string(),
+                y1: zkp.encode_fixed(&y1),
+                y2: zkp.encode_fixed(&y2),
+                protocol_version,
+                possession_proof: possession_proof(&zkp, "alice", &x),
+                ..Default::default()
+            };
+            auth.register(Request::new(request))
+        };
+
+        // a client predating versioning, and one newer than the server
+        for version in [0, PROTOCOL_VERSION + 1] {
+            let status = register(version).await.unwrap_err();
+            assert_eq!(status.code(), Code::FailedPrecondition);
+            assert!(status.message().contains("upgrade the client"), "{}", status.message());
+        }
+        register(PROTOCOL_VERSION).await.unwrap();
+
+        let (r1, r2) = zkp.compute_pair(&ZKP::generate_random_number_below(&zkp.q));
+        let mut request = AuthenticationChallengeRequest {
+            user: "alice".to_string(),
+            r1: zkp.encode_fixed(&r1),
+            r2: zkp.encode_fixed(&r2),
+            ..Default::default()
+        };
+        let result = auth
+            .create_authentication_challenge(Request::new(request.clone()))
+            .await;
+        assert_eq!(result.unwrap_err().code(), Code::FailedPrecondition);
+        request.protocol_version = PROTOCOL_VERSION;
+        auth.create_authentication_challenge(Request::new(request))
+            .await
+            .unwrap();
+
+        // by default, clients predating versioning are accepted
+        let args = Args::try_parse_from(["server"]).unwrap();
+        assert_eq!(auth_impl_from_args(&args, zkp).min_protocol_version, 0);
+        let too_new = (PROTOCOL_VERSION + 1).to_string();
+        assert!(Args::try_parse_from(["server", "--min-protocol-version", &too_new]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_preloaded_keys() {
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+        let key = VerifierKey { y1, y2 };
+        let args = Args::try_parse_from(["server", "--disable-registration"]).unwrap();
+        let auth = auth_impl_from_args(&args, zkp.clone());
+
+        let keys = format!("# provisioned out of band\n\nalice {}\n", key.encode());
+        assert_eq!(auth.preload_keys(&keys).unwrap(), 1);
+
+        // the client goes straight to the challenge
+        let response = authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        let session_id = response.into_inner().session_id;
+        assert_eq!(validate_session(&auth, &session_id).await.unwrap(), "alice");
+
+        let request = RegisterRequest {
+            user: "bob".to_string(),
+            y1: zkp.encode_fixed(&key.y1),
+            y2: zkp.encode_fixed(&key.y2),
+            protocol_version: PROTOCOL_VERSION,
+            ..Default::default()
+        };
+        let result = auth.register(Request::new(request)).await;
+        assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+
+        // malformed files preload nothing
+        let outside = VerifierKey {
+            y1: key.y1.clone(),
+            y2: &zkp.p - 1u32,
+        };
+        for keys in [
+            format!("bob {}\nmallory", key.encode()),
+            format!("bob {}\nmallory zkvk1:2", key.encode()),
+            format!("bob {}\nmallory {}", key.encode(), outside.encode()),
+        ] {
+            let e = auth.preload_keys(&keys).unwrap_err();
+            assert!(e.to_string().starts_with("line 2:"), "{}", e);
+        }
+        assert!(!auth.user_info.lock().unwrap().contains_key("bob"));
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_until_ready() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+        auth.ready.store(false, Ordering::SeqCst);
+
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+        let request = RegisterRequest {
+            user: "alice".to_string(),
+            y1: zkp.encode_fixed(&y1),
+            y2: zkp.encode_fixed(&y2),
+            possession_proof: possession_proof(&zkp, "alice", &x),
+            ..Default::default()
+        };
+        let err = auth
+            .register(Request::new(request.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Unavailable);
+        assert!(auth.user_info.lock().unwrap().is_empty());
+
+        // the store becomes ready while the client backs off
+        let ready = auth.ready.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            ready.store(true, Ordering::SeqCst);
+        });
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(20),
+            max_backoff: Duration::from_millis(100),
+        };
+        retry_unavailable(&policy, || auth.register(Request::new(request.clone())), |_, _| {})
+            .await
+            .unwrap();
+        assert!(auth.user_info.lock().unwrap().contains_key("alice"));
+        authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+    }
+
+    /// A `TranscriptSink` checking that the maps of the server are unlocked while it records.
+    #[derive(Debug)]
+    struct UnlockedSink {
+        auth_id_to_user: Arc<Mutex<OutstandingChallenges>>,
+        user_info: Arc<Mutex<HashMap<String, UserInfo>>>,
+        recorded: std::sync::atomic::AtomicUsize,
+    }
+
+    impl TranscriptSink for UnlockedSink {
+        fn record(&self, _transcript: &Transcript) {
+            assert!(self.auth_id_to_user.try_lock().is_ok());
+            assert!(self.user_info.try_lock().is_ok());
+            self.recorded.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transcripts_are_recorded_unlocked() {
+        let zkp = ZKP::default();
+        let mut auth = AuthImpl::default();
+        let sink = Arc::new(UnlockedSink {
+            auth_id_to_user: auth.auth_id_to_user.clone(),
+            user_info: auth.user_info.clone(),
+            recorded: Default::default(),
+        });
+        auth.transcripts = Some(sink.clone());
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+        authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        assert_eq!(sink.recorded.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_transcripts() {
+        let zkp = ZKP::default();
+        let sink = Arc::new(MemorySink::default());
+        let auth = AuthImpl {
+            transcripts: Some(sink.clone()),
+            ..Default::default()
+        };
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+        authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        let wrong = (&x + 1u32) % &zkp.q;
+        authenticate(&auth, &zkp, "alice", &wrong, &[], &[])
+            .await
+            .unwrap_err();
+
+        let transcripts = sink.transcripts();
+        assert_eq!(transcripts.len(), 2);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let (y1, y2) = zkp.compute_pair(&x);
+        for (transcript, outcome) in transcripts
+            .iter()
+            .zip([Outcome::Accepted, Outcome::Rejected])
+        {
+            assert_eq!(transcript.user, "alice");
+            assert_eq!((&transcript.y1, &transcript.y2), (&y1, &y2));
+            assert_ne!(transcript.r1, BigUint::from(0u32));
+            assert_ne!(transcript.r2, BigUint::from(0u32));
+            assert_ne!(transcript.c, BigUint::from(0u32));
+            assert!(transcript.timestamp <= now && transcript.timestamp + 60 > now);
+            assert_eq!(transcript.outcome, outcome);
+            let Transcript { r1, r2, c, s, .. } = transcript;
+            assert_eq!(zkp.verify(r1, r2, &y1, &y2, c, s), outcome == Outcome::Accepted);
+        }
+
+        // the log written by `--transcript-log`
+        let path = std::env::temp_dir().join(format!(
+            "zkp-transcripts-{}-{}.jsonl",
+            std::process::id(),
+            ZKP::generate_random_string(8)
+        ));
+        let log = TranscriptLog::open(&path, TranscriptCompression::None).unwrap();
+        for transcript in &transcripts {
+            log.record(transcript);
+        }
+        let logged = std::fs::read_to_string(&path).unwrap();
+        let read = TranscriptLog::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let logged: Vec<Transcript> = logged
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(logged, transcripts);
+        assert_eq!(read, transcripts);
+    }
+
+    #[tokio::test]
+    async fn test_message_capture() {
+        let zkp = ZKP::default();
+        let path = std::env::temp_dir().join(format!(
+            "zkp-capture-{}-{}.txt",
+            std::process::id(),
+            ZKP::generate_random_string(8)
+        ));
+        let auth = AuthImpl {
+            capture: Some(Arc::new(MessageCapture::create(&path).unwrap())),
+            ..Default::default()
+        };
+
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+        authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+
+        let capture = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = capture.lines().collect();
+        let headers: Vec<&str> = lines
+            .iter()
+            .step_by(2)
+            .map(|header| header.rsplitn(3, ' ').nth(2).unwrap())
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                ">>> RegisterRequest",
+                "<<< RegisterResponse",
+                ">>> AuthenticationChallengeRequest",
+                "<<< AuthenticationChallengeResponse",
+                ">>> AuthenticationAnswerRequest",
+                "<<< AuthenticationAnswerResponse",
+            ]
+        );
+
+        // every dump decodes back to the message announced by its header
+        for pair in lines.chunks(2) {
+            let length: usize = pair[0].rsplit(' ').nth(1).unwrap().parse().unwrap();
+            assert_eq!(hex::decode(pair[1]).unwrap().len(), length);
+        }
+        let register_request =
+            RegisterRequest::decode(&hex::decode(lines[1]).unwrap()[..]).unwrap();
+        assert_eq!(register_request.user, "alice");
+    }
+
+    #[tokio::test]
+    async fn test_out_of_range_response_is_rejected() {
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+
+        let mut answer = answer_challenge(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap();
+        answer.s = (BigUint::from_bytes_be(&answer.s) + &zkp.q).to_bytes_be();
+
+        let result = auth.verify_authentication(Request::new(answer)).await;
+        assert_eq!(result.unwrap_err().code(), Code::InvalidArgument);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_domain_socket() {
+        use zkp_chaum_pedersen::channel;
+        use zkp_chaum_pedersen::pb::auth_client::AuthClient;
+
+        let path = std::env::temp_dir().join(format!("zkp-auth-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let router = Server::builder().add_service(AuthServer::new(AuthImpl::default()));
+        let server = tokio::spawn({
+            let path = path.clone();
+            async move {
+                serve_uds(router, &path, async {
+                    let _ = stopped.await;
+                })
+                .await
+            }
+        });
+        while !path.exists() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let endpoint = format!("unix://{}", path.display());
+        let mut client = AuthClient::new(channel::connect(&endpoint).await.unwrap());
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+        let request = RegisterRequest {
+            user: "alice".to_string(),
+            y1: zkp.encode_fixed(&y1),
+            y2: zkp.encode_fixed(&y2),
+            protocol_version: PROTOCOL_VERSION,
+            possession_proof: possession_proof(&zkp, "alice", &x),
+            ..Default::default()
+        };
+        client.register(request).await.unwrap();
+
+        let k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let request = AuthenticationChallengeRequest {
+            user: "alice".to_string(),
+            r1: zkp.encode_fixed(&r1),
+            r2: zkp.encode_fixed(&r2),
+            protocol_version: PROTOCOL_VERSION,
+            ..Default::default()
+        };
+        let response = client.create_authentication_challenge(request).await;
+        let response = response.unwrap().into_inner();
+        let c = BigUint::from_bytes_be(&response.c);
+        let request = AuthenticationAnswerRequest {
+            auth_id: response.auth_id,
+            s: zkp.solve(&k, &c, &x).to_bytes_be(),
+            ..Default::default()
+        };
+        let session_id = client.verify_authentication(request).await.unwrap();
+        let request = ValidateSessionRequest {
+            session_id: session_id.into_inner().session_id,
+        };
+        let user = client
+            .validate_session(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .user;
+        assert_eq!(user, "alice");
+
+        // the socket is removed on shutdown
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive() {
+        use tokio_stream::wrappers::TcpListenerStream;
+        use zkp_chaum_pedersen::pb::auth_client::AuthClient;
+
+        let zkp = ZKP::default();
+        let auth = AuthImpl {
+            keep_alive_interval: Duration::from_millis(10),
+            ..Default::default()
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            Server::builder()
+                .add_service(AuthServer::new(auth.clone()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let mut client = AuthClient::connect(endpoint).await.unwrap();
+
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        register(&auth, &zkp, "alice", &x).await;
+        let session_id = authenticate(&auth, &zkp, "alice", &x, &[], &[])
+            .await
+            .unwrap()
+            .into_inner()
+            .session_id;
+
+        let (tx, rx) = mpsc::channel(1);
+        let mut k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        tx.send(KeepAliveRequest {
+            session_id: session_id.clone(),
+            r1: zkp.encode_fixed(&r1),
+            r2: zkp.encode_fixed(&r2),
+            s: vec![],
+        })
+        .await
+        .unwrap();
+        let mut challenges = client
+            .keep_alive(ReceiverStream::new(rx))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let mut last_expires_at = 0;
+        for _ in 0..4 {
+            let challenge = challenges.message().await.unwrap().unwrap();
+            assert!(challenge.expires_at >= last_expires_at);
+            last_expires_at = challenge.expires_at;
+            assert_eq!(validate_session(&auth, &session_id).await.unwrap(), "alice");
+
+            let c = BigUint::from_bytes_be(&challenge.c);
+            let s = zkp.solve(&k, &c, &x);
+            k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            tx.send(KeepAliveRequest {
+                s: s.to_bytes_be(),
+                r1: zkp.encode_fixed(&r1),
+                r2: zkp.encode_fixed(&r2),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        }
+
+        // a wrong solution ends the stream and the session
+        let challenge = challenges.message().await.unwrap().unwrap();
+        let c = BigUint::from_bytes_be(&challenge.c);
+        let s = zkp.solve(&k, &c, &(&x + 1u32));
+        let (r1, r2) = zkp.compute_pair(&ZKP::generate_random_number_below(&zkp.q));
+        tx.send(KeepAliveRequest {
+            s: s.to_bytes_be(),
+            r1: zkp.encode_fixed(&r1),
+            r2: zkp.encode_fixed(&r2),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let status = challenges.message().await.unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert_eq!(
+            validate_session(&auth, &session_id)
+                .await
+                .unwrap_err()
+                .code(),
+            Code::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verification_latency_histogram() {
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        let zkp = ZKP::default();
+        let auth = AuthImpl::default();
+
+        // two right answers and a wrong one, which is timed as well
+        let mut answers = Vec::new();
+        for (user, wrong) in [("alice", false), ("bob", false), ("mallory", true)] {
+            let x = ZKP::generate_random_number_below(&zkp.q);
+            register(&auth, &zkp, user, &x).await;
+            let secret = if wrong { &x + 1u32 } else { x };
+            let answer = answer_challenge(&auth, &zkp, user, &secret, &[], &[])
+                .await
+                .unwrap();
+            let answer = auth.pending_answer(&answer.auth_id, &answer.s, vec![]);
+            answers.push((answer.unwrap(), wrong));
+        }
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            for (answer, wrong) in &answers {
+                let (verification, _) = auth.verify_answer(answer);
+                assert_eq!(verification, !*wrong);
+            }
+        });
+
+        let samples: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .filter(|(key, ..)| key.key().name() == VERIFICATION_SECONDS)
+            .map(|(.., value)| match value {
+                DebugValue::Histogram(samples) => samples.len(),
+                value => panic!("unexpected value {:?}", value),
+            })
+            .collect();
+        assert_eq!(samples, [3]);
+    }
+
+    #[tokio::test]
+    async fn test_client_bootstraps_parameters() {
+        use tokio_stream::wrappers::TcpListenerStream;
+        use zkp_chaum_pedersen::{params::fetch_parameters, pb::auth_client::AuthClient, Group};
+
+        let auth = AuthImpl {
+            zkp: ZKP::from_group(Group::Rfc2048),
+            ..Default::default()
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            Server::builder()
+                .add_service(AuthServer::new(auth))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let mut client = AuthClient::connect(endpoint).await.unwrap();
+
+        let zkp = fetch_parameters(&mut client).await.unwrap();
+        assert_eq!(zkp.p, ZKP::from_group(Group::Rfc2048).p);
+
+        let (x, y1, y2) = zkp.generate_credential();
+        let request = RegisterRequest {
+            user: "alice".to_string(),
+            y1: zkp.encode_fixed(&y1),
+            y2: zkp.encode_fixed(&y2),
+            possession_proof: possession_proof(&zkp, "alice", &x),
+            ..Default::default()
+        };
+        client.register(request).await.unwrap();
+
+        let k = ZKP::generate_random_number_below(&zkp.q);
+        let (r1, r2) = zkp.compute_pair(&k);
+        let request = AuthenticationChallengeRequest {
+            user: "alice".to_string(),
+            r1: zkp.encode_fixed(&r1),
+            r2: zkp.encode_fixed(&r2),
+            ..Default::default()
+        };
+        let response = client
+            .create_authentication_challenge(request)
+            .await
+            .unwrap()
+            .into_inner();
+        let c = BigUint::from_bytes_be(&response.c);
+        let request = AuthenticationAnswerRequest {
+            auth_id: response.auth_id,
+            s: zkp.solve(&k, &c, &x).to_bytes_be(),
+            ..Default::default()
+        };
+        let response = client.verify_authentication(request).await.unwrap();
+        assert!(!response.into_inner().session_id.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_loadtest() {
+        use tokio_stream::wrappers::TcpListenerStream;
+        use zkp_chaum_pedersen::loadtest;
+
+        let auth = AuthImpl::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            Server::builder()
+                .add_service(AuthServer::new(auth.clone()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let report = loadtest::run(&endpoint, &auth.zkp, Endianness::Big, 3, 4)
+            .await
+            .unwrap();
+        assert_eq!(report.errors, 0, "{report}");
+        // a registration and 4 challenges and answers per client
+        assert_eq!(report.requests, 3 * (1 + 2 * 4));
+        assert!(report.requests_per_sec() > 0.0);
+        assert!(report.percentile(50.0) > Duration::ZERO);
+        assert_eq!(auth.user_info.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_endianness_negotiation() {
+        use tokio_stream::wrappers::TcpListenerStream;
+        use zkp_chaum_pedersen::params::fetch_parameters_with_endianness;
+        use zkp_chaum_pedersen::{loadtest, pb::auth_client::AuthClient};
+
+        let auth = AuthImpl {
+            endianness: Endianness::Little,
+            ..Default::default()
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            Server::builder()
+                .add_service(AuthServer::new(auth.clone()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut client = AuthClient::connect(endpoint.clone()).await.unwrap();
+        let (zkp, endianness) = fetch_parameters_with_endianness(&mut client).await.unwrap();
+        assert_eq!(zkp.p, auth.zkp.p);
+        assert_eq!(endianness, Endianness::Little);
+
+        let report = loadtest::run(&endpoint, &zkp, endianness, 1, 2)
+            .await
+            .unwrap();
+        assert_eq!(report.errors, 0, "{report}");
+
+        // big-endian public values are not in the subgroup once read little-endian
+        let report = loadtest::run(&endpoint, &zkp, Endianness::Big, 1, 2)
+            .await
+            .unwrap();
+        assert_eq!(report.requests, 0, "{report}");
+
+        let args = Args::try_parse_from(["server", "--endianness", "little"]).unwrap();
+        let auth = auth_impl_from_args(&args, ZKP::default());
+        assert_eq!(auth.endianness, Endianness::Little);
+    }
+
+    #[test]
+    fn test_describe() {
+        let args = Args::try_parse_from([
+            "server",
+            "--group",
+            "rfc2048",
+            "--challenge-bits",
+            "128",
+            "--endianness",
+            "little",
+            "--min-protocol-version",
+            "1",
+            "--max-failed-attempts",
+            "3",
+            "--lockout-cooldown",
+            "60",
+            "--replay-cache-size",
+            "10",
+            "--challenge-overflow",
+            "evict-oldest",
+            "--keep-alive-interval",
+            "30",
+            "--ephemeral",
+            "--ephemeral-ttl",
+            "120",
+            "--disable-registration",
+            "--hash-user-names",
+        ])
+        .unwrap();
+        let zkp = ZKP::from_group(Group::Rfc2048);
+        let mut auth = auth_impl_from_args(&args, zkp.clone());
+        auth.session_tokens = Some(TokenKey::generate());
+
+        let descriptor = auth.describe();
+        assert_eq!(descriptor.group.p, hex::encode(zkp.p.to_bytes_be()));
+        assert_eq!(descriptor.group.beta, hex::encode(zkp.beta.to_bytes_be()));
+        assert_eq!((descriptor.group.p_bits, descriptor.group.q_bits), (2048, 224));
+        assert_eq!(descriptor.group.known_group.as_deref(), Some("rfc5114-2048"));
+        assert_eq!(descriptor.challenge_bits, 128);
+        assert_eq!(descriptor.endianness, "little");
+        assert_eq!(descriptor.min_protocol_version, 1);
+        assert_eq!(descriptor.max_failed_attempts, 3);
+        assert_eq!(descriptor.lockout_cooldown_secs, 60);
+        assert_eq!(descriptor.replay_cache_size, 10);
+        assert_eq!(descriptor.challenge_overflow, "evict-oldest");
+        assert_eq!(descriptor.keep_alive_interval_secs, 30);
+        assert_eq!(descriptor.session_ttl_secs, 120);
+        assert_eq!(descriptor.registration_ttl_secs, Some(120));
+        assert!(descriptor.registration_disabled);
+        assert!(descriptor.hash_user_names && !descriptor.normalize_user_names);
+        assert_eq!(descriptor.session_tokens.as_deref(), Some("HMAC-SHA256"));
+        assert_eq!(descriptor.registration_tokens, None);
+
+        let json = serde_json::to_string(&descriptor).unwrap();
+        assert_eq!(serde_json::from_str::<ProtocolDescriptor>(&json).unwrap(), descriptor);
+
+        // challenges are never longer than q
+        let args = Args::try_parse_from(["server", "--challenge-bits", "1000"]).unwrap();
+        let descriptor = auth_impl_from_args(&args, ZKP::default()).describe();
+        assert_eq!(descriptor.challenge_bits, 160);
+        assert_eq!(descriptor.endianness, "big");
+        assert_eq!(descriptor.session_tokens, None);
+        assert_eq!(descriptor.registration_ttl_secs, None);
+        let auth = AuthImpl {
+            registration_tokens: Some(TokenKey::generate()),
+            ..Default::default()
+        };
+        assert_eq!(auth.describe().registration_tokens.as_deref(), Some("HMAC-SHA256"));
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web() {
+        use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+        use tokio_stream::wrappers::TcpListenerStream;
+        use tonic_web::GrpcWebClientLayer;
+        use tower::ServiceBuilder;
+        use zkp_chaum_pedersen::pb::auth_client::AuthClient;
+
+        /// Serves `auth` on a fresh port, returning its URL.
+        async fn serve(auth: &AuthImpl, grpc_web: bool) -> String {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let endpoint = format!("http://{}", listener.local_addr().unwrap());
+            let router = auth_router(Server::builder(), auth.clone(), grpc_web);
+            tokio::spawn(router.serve_with_incoming(TcpListenerStream::new(listener)));
+            endpoint
+        }
+
+        /// A registration request of `user` with a random secret.
+        fn request(zkp: &ZKP, user: &str) -> RegisterRequest {
+            let x = ZKP::generate_random_number_below(&zkp.q);
+            let (y1, y2) = zkp.compute_pair(&x);
+            RegisterRequest {
+                user: user.to_string(),
+                y1: zkp.encode_fixed(&y1),
+                y2: zkp.encode_fixed(&y2),
+                possession_proof: possession_proof(zkp, user, &x),
+                ..Default::default()
+            }
+        }
+
+        let auth = AuthImpl::default();
+        let zkp = auth.zkp.clone();
+        let endpoint = serve(&auth, true).await;
+
+        let mut native = AuthClient::connect(endpoint.clone()).await.unwrap();
+        native.register(request(&zkp, "native")).await.unwrap();
+
+        // gRPC-web frames over HTTP/1.1
+        let web = |endpoint: &str| {
+            let http1 = Client::builder(TokioExecutor::new()).build_http();
+            let service = ServiceBuilder::new()
+                .layer(GrpcWebClientLayer::new())
+                .service(http1);
+            AuthClient::with_origin(service, endpoint.parse().unwrap())
+        };
+        web(&endpoint)
+            .register(request(&zkp, "browser"))
+            .await
+            .unwrap();
+
+        let registered = |user| auth.user_info.lock().unwrap().contains_key(user);
+        assert!(registered("native") && registered("browser"));
+
+        // without the flag, only native gRPC is served
+        let endpoint = serve(&AuthImpl::default(), false).await;
+        assert!(web(&endpoint)
+            .register(request(&zkp, "browser"))
+            .await
+            .is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_compressed_transcript_log() {
+        let zkp = ZKP::default();
+        let (y1, y2) = zkp.compute_pair(&ZKP::generate_random_number_below(&zkp.q));
+        let transcripts: Vec<_> = (0..20)
+            .map(|i| {
+                let (r1, r2) = zkp.compute_pair(&ZKP::generate_random_number_below(&zkp.q));
+                Transcript {
+                    user: "alice".to_string(),
+                    y1: y1.clone(),
+                    y2: y2.clone(),
+                    r1,
+                    r2,
+                    c: zkp.issue_challenge(),
+                    s: ZKP::generate_random_number_below(&zkp.q),
+                    timestamp: 1_700_000_000 + i,
+                    outcome: Outcome::Accepted,
+                }
+            })
+            .collect();
+
+        let mut sizes = Vec::new();
+        for compression in [TranscriptCompression::None, TranscriptCompression::Zstd] {
+            let path = std::env::temp_dir().join(format!(
+                "zkp-transcripts-{}-{}.log",
+                std::process::id(),
+                ZKP::generate_random_string(8)
+            ));
+            // records appended after reopening the log are read back too
+            for half in transcripts.chunks(10) {
+                let log = TranscriptLog::open(&path, compression).unwrap();
+                for transcript in half {
+                    log.record(transcript);
+                }
+            }
+            let read = TranscriptLog::read(&path);
+            let other = match compression {
+                TranscriptCompression::None => TranscriptCompression::Zstd,
+                TranscriptCompression::Zstd => TranscriptCompression::None,
+            };
+            let mixed = TranscriptLog::open(&path, other);
+            sizes.push(std::fs::metadata(&path).unwrap().len());
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(read.unwrap(), transcripts, "{compression:?}");
+            let error = mixed.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{compression:?}");
+        }
+        assert!(sizes[1] < sizes[0], "{sizes:?}");
     }
 }
@@ -0,0 +1,304 @@
+//! Signed session tokens, which services sharing the server's token key can validate without
+//! asking the server.
+//!
+//! A token is `TOKEN_VERSION`, a `.`, the unpadded base64url encoding of the claims, a `.`, and
+//! the unpadded base64url encoding of `HMAC-SHA256(key, version "." claims)`, like a JWT. The
+//! claims are the expiry `exp` (seconds since the Unix epoch, as a big-endian `u64`), a random
+//! 16-byte nonce, and the UTF-8 user name.
+//!
+//! Registration tokens, handed to a user out of band to let them register once, use the same
+//! format with the version `REGISTRATION_TOKEN_VERSION`, so neither kind of token passes for the
+//! other. Their claims are the same, the nonce identifying the token.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::rng::SecureRng;
+use crate::{Result, ZkpError};
+
+/// The version prefix of the current token format.
+pub const TOKEN_VERSION: &str = "zkt2";
+
+/// The version prefix of the current registration token format.
+pub const REGISTRATION_TOKEN_VERSION: &str = "zkr1";
+
+/// The claims carried by a session token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionClaims {
+    /// The user the session was created for.
+    pub user: String,
+    /// The expiry of the session, in seconds since the Unix epoch.
+    pub exp: u64,
+    /// A random value, so that two sessions of a user expiring the same second get distinct
+    /// tokens.
+    pub nonce: [u8; 16],
+}
+
+impl SessionClaims {
+    /// Creates the claims of a new session of `user` expiring at `exp`.
+    pub fn new(user: &str, exp: u64) -> Self {
+        Self {
+            user: user.to_string(),
+            exp,
+            nonce: SecureRng::default().gen(),
+        }
+    }
+}
+
+/// The claims carried by a registration token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistrationClaims {
+    /// The user allowed to register.
+    pub user: String,
+    /// The expiry of the token, in seconds since the Unix epoch.
+    pub exp: u64,
+    /// The random ID of the token, which the server remembers once the token is used.
+    pub nonce: [u8; 16],
+}
+
+impl RegistrationClaims {
+    /// Creates the claims of a new token allowing `user` to register until `exp`.
+    pub fn new(user: &str, exp: u64) -> Self {
+        Self {
+            user: user.to_string(),
+            exp,
+            nonce: SecureRng::default().gen(),
+        }
+    }
+}
+
+/// The key signing and validating session and registration tokens.
+#[derive(Clone)]
+pub struct TokenKey([u8; 32]);
+
+impl std::fmt::Debug for TokenKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TokenKey(..)")
+    }
+}
+
+impl TokenKey {
+    /// Generates a random key.
+    pub fn generate() -> Self {
+        Self(SecureRng::default().gen())
+    }
+
+    /// Builds a key from its bytes, e.g. read from a file shared with the validating services.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Issues a token carrying `claims`.
+    pub fn issue(&self, claims: &SessionClaims) -> String {
+        let mut payload = claims.exp.to_be_bytes().to_vec();
+        payload.extend_from_slice(&claims.nonce);
+        payload.extend_from_slice(claims.user.as_bytes());
+        self.sign(TOKEN_VERSION, &payload)
+    }
+
+    /// Validates a token issued by `issue` with this key, see `verify_token`.
+    pub fn verify(&self, token: &str) -> Result<SessionClaims> {
+        let (user, exp, nonce) = parse_claims(&self.open(TOKEN_VERSION, token)?)?;
+        check_expiry(exp)?;
+        Ok(SessionClaims { user, exp, nonce })
+    }
+
+    /// Issues a registration token carrying `claims`.
+    pub fn issue_registration(&self, claims: &RegistrationClaims) -> String {
+        let mut payload = claims.exp.to_be_bytes().to_vec();
+        payload.extend_from_slice(&claims.nonce);
+        payload.extend_from_slice(claims.user.as_bytes());
+        self.sign(REGISTRATION_TOKEN_VERSION, &payload)
+    }
+
+    /// Validates a registration token issued by `issue_registration` with this key.
+    ///
+    /// Only the signature and the expiry are checked: that the token was not used already is up
+    /// to the server, which remembers the nonces of the tokens it accepted.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the claims of the token, or the errors of `verify_token`.
+    pub fn verify_registration(&self, token: &str) -> Result<RegistrationClaims> {
+        let (user, exp, nonce) = parse_claims(&self.open(REGISTRATION_TOKEN_VERSION, token)?)?;
+        check_expiry(exp)?;
+        Ok(RegistrationClaims { user, exp, nonce })
+    }
+
+    /// Returns `version "." payload "." tag`, with the payload and the tag base64url encoded.
+    fn sign(&self, version: &str, payload: &[u8]) -> String {
+        let signed = format!("{}.{}", version, URL_SAFE_NO_PAD.encode(payload));
+        let tag = self.mac(&signed).finalize().into_bytes();
+        format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(tag))
+    }
+
+    /// Checks the version and the signature of `token`, and returns its decoded payload.
+    fn open(&self, expected_version: &str, token: &str) -> Result<Vec<u8>> {
+        let (signed, tag) = token
+            .rsplit_once('.')
+            .ok_or_else(|| ZkpError::InvalidEncoding("missing token signature".to_string()))?;
+        let (version, payload) = signed
+            .split_once('.')
+            .ok_or_else(|| ZkpError::InvalidEncoding("missing version prefix".to_string()))?;
+        if version != expected_version {
+            return Err(ZkpError::UnsupportedVersion(version.to_string()));
+        }
+
+        let tag = URL_SAFE_NO_PAD
+            .decode(tag)
+            .map_err(|e| ZkpError::InvalidEncoding(e.to_string()))?;
+        self.mac(signed)
+            .verify_slice(&tag)
+            .map_err(|_| ZkpError::InvalidSignature)?;
+
+        URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|e| ZkpError::InvalidEncoding(e.to_string()))
+    }
+
+    fn mac(&self, signed: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes keys of any size");
+        mac.update(signed.as_bytes());
+        mac
+    }
+}
+
+/// Splits the payload of a token into the user, the expiry and the nonce of its claims.
+fn parse_claims(payload: &[u8]) -> Result<(String, u64, [u8; 16])> {
+    let truncated = || ZkpError::InvalidEncoding("token claims truncated".to_string());
+    let (exp, rest) = payload.split_first_chunk::<8>().ok_or_else(truncated)?;
+    let (nonce, user) = rest.split_first_chunk::<16>().ok_or_else(truncated)?;
+    let user =
+        String::from_utf8(user.to_vec()).map_err(|e| ZkpError::InvalidEncoding(e.to_string()))?;
+    Ok((user, u64::from_be_bytes(*exp), *nonce))
+}
+
+/// Returns `ZkpError::Expired` if `exp` has passed.
+fn check_expiry(exp: u64) -> Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    if exp <= now {
+        return Err(ZkpError::Expired);
+    }
+    Ok(())
+}
+
+/// Validates a session token with the key it was issued with.
+///
+/// # Arguments
+///
+/// * `key` - The token key of the issuing server.
+/// * `token` - The token, as returned as the session ID.
+///
+/// # Returns
+///
+/// A `Result` containing either the claims of the token, `ZkpError::InvalidSignature` if it was
+/// not issued with `key` or was modified, `ZkpError::Expired` if its `exp` has passed, or
+/// `ZkpError::InvalidEncoding` / `ZkpError::UnsupportedVersion` if it is malformed.
+pub fn verify_token(key: &TokenKey, token: &str) -> Result<SessionClaims> {
+    key.verify(token)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn test_valid_token() {
+        let key = TokenKey::generate();
+        let claims = SessionClaims::new("alice", now() + 60);
+        let token = key.issue(&claims);
+        assert!(token.starts_with("zkt2."));
+        assert_eq!(verify_token(&key, &token), Ok(claims.clone()));
+
+        // another session of the same user, expiring the same second
+        let other = key.issue(&SessionClaims::new("alice", claims.exp));
+        assert_ne!(other, token);
+        assert!(verify_token(&key, &other).is_ok());
+    }
+
+    #[test]
+    fn test_expired_token() {
+        let key = TokenKey::generate();
+        let claims = SessionClaims::new("alice", now() - 1);
+        assert_eq!(verify_token(&key, &key.issue(&claims)), Err(ZkpError::Expired));
+    }
+
+    #[test]
+    fn test_tampered_token() {
+        let key = TokenKey::generate();
+        let claims = SessionClaims::new("alice", now() + 60);
+        let token = key.issue(&claims);
+
+        let other_key = TokenKey::generate();
+        assert_eq!(verify_token(&other_key, &token), Err(ZkpError::InvalidSignature));
+
+        // claims of another user, or a later expiry, with the original signature
+        let (_, tag) = token.rsplit_once('.').unwrap();
+        for forged in [
+            SessionClaims {
+                user: "mallory".to_string(),
+                ..claims.clone()
+            },
+            SessionClaims {
+                exp: claims.exp + 3600,
+                ..claims.clone()
+            },
+            SessionClaims {
+                nonce: [0; 16],
+                ..claims.clone()
+            },
+        ] {
+            let forged = key.issue(&forged);
+            let (signed, _) = forged.rsplit_once('.').unwrap();
+            let token = format!("{}.{}", signed, tag);
+            assert_eq!(verify_token(&key, &token), Err(ZkpError::InvalidSignature));
+        }
+
+        assert!(matches!(verify_token(&key, "zkt2.AAAA"), Err(ZkpError::InvalidEncoding(_))));
+        assert!(matches!(
+            verify_token(&key, &token.replacen("zkt2", "zkt1", 1)),
+            Err(ZkpError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_registration_token() {
+        let key = TokenKey::generate();
+        let claims = RegistrationClaims::new("alice", now() + 60);
+        let token = key.issue_registration(&claims);
+        assert!(token.starts_with("zkr1."));
+        assert_eq!(key.verify_registration(&token), Ok(claims.clone()));
+        assert_ne!(RegistrationClaims::new("alice", claims.exp).nonce, claims.nonce);
+
+        let expired = RegistrationClaims::new("alice", now() - 1);
+        assert_eq!(
+            key.verify_registration(&key.issue_registration(&expired)),
+            Err(ZkpError::Expired)
+        );
+        assert_eq!(
+            TokenKey::generate().verify_registration(&token),
+            Err(ZkpError::InvalidSignature)
+        );
+
+        // neither kind of token passes for the other
+        let session = key.issue(&SessionClaims::new("alice", now() + 60));
+        assert!(matches!(
+            key.verify_registration(&session),
+            Err(ZkpError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(key.verify(&token), Err(ZkpError::UnsupportedVersion(_))));
+    }
+}
@@ -0,0 +1,150 @@
+//! Transcripts of completed authentications, for compliance logging.
+//!
+//! A `Transcript` holds everything the verifier saw: the public values `(y1, y2)`, the
+//! commitment `(r1, r2)`, the challenge `c` and the response `s`, plus when and how the
+//! authentication ended. None of it is secret. `s = k - c * x mod q` would only give `x` away
+//! together with the nonce `k`, which never leaves the prover, and the protocol is zero
+//! knowledge precisely because such transcripts can be simulated without `x`. Once the
+//! authentication is over, `s` cannot be replayed either, since the verifier refuses reused
+//! commitments and issues a fresh challenge each time.
+//!
+//! A `SavedProof` is a transcript together with the group parameters it was made in, so it can
+//! be verified again offline, e.g. by `client verify-proof` on a file written with `--save-proof`.
+
+use num_bigint::BigUint;
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+use crate::ZKP;
+
+/// How an authentication ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    /// Every credential was proven and a session was created.
+    Accepted,
+    /// A proof did not verify.
+    Rejected,
+}
+
+/// The non-secret transcript of one authentication, see the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transcript {
+    /// The user, as keyed by the verifier.
+    pub user: String,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_biguint"))]
+    pub y1: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_biguint"))]
+    pub y2: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_biguint"))]
+    pub r1: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_biguint"))]
+    pub r2: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_biguint"))]
+    pub c: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_biguint"))]
+    pub s: BigUint,
+    /// When the response was verified, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub outcome: Outcome,
+}
+
+/// Where a verifier hands the transcripts of completed authentications.
+///
+/// `record` is called on the verifier's request path, so implementations should be quick, e.g.
+/// append to a file or a channel, and must not fail the authentication.
+pub trait TranscriptSink: Debug + Send + Sync {
+    /// Stores one transcript.
+    fn record(&self, transcript: &Transcript);
+}
+
+/// A `TranscriptSink` keeping the transcripts in memory, for tests.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    transcripts: Mutex<Vec<Transcript>>,
+}
+
+impl MemorySink {
+    /// Returns the transcripts recorded so far, oldest first.
+    pub fn transcripts(&self) -> Vec<Transcript> {
+        self.transcripts.lock().unwrap().clone()
+    }
+}
+
+impl TranscriptSink for MemorySink {
+    fn record(&self, transcript: &Transcript) {
+        self.transcripts.lock().unwrap().push(transcript.clone());
+    }
+}
+
+/// A transcript and the group parameters of its proof, for offline verification.
+///
+/// The `outcome` of the transcript is the verdict of the verifier at the time, which `verify`
+/// does not look at: the two differ when the verifier checked the proof against other public
+/// values than `y1` and `y2`, e.g. those registered with another password.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SavedProof {
+    pub params: ZKP,
+    pub transcript: Transcript,
+}
+
+impl SavedProof {
+    /// Verifies the proof of the transcript, `(r1, r2, c, s)` for `(y1, y2)`, with `params`.
+    pub fn verify(&self) -> bool {
+        let t = &self.transcript;
+        self.params.verify(&t.r1, &t.r2, &t.y1, &t.y2, &t.c, &t.s)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_serde_round_trip() {
+        let transcript = Transcript {
+            user: "alice".to_string(),
+            y1: BigUint::from(2u32),
+            y2: BigUint::from(3u32),
+            r1: BigUint::from(8u32),
+            r2: BigUint::from(4u32),
+            c: BigUint::from(4u32),
+            s: BigUint::from(5u32),
+            timestamp: 1_700_000_000,
+            outcome: Outcome::Accepted,
+        };
+        let json = serde_json::to_string(&transcript).unwrap();
+        assert!(json.contains(r#""outcome":"Accepted""#), "{json}");
+        assert_eq!(serde_json::from_str::<Transcript>(&json).unwrap(), transcript);
+    }
+
+    #[test]
+    fn test_saved_proof() {
+        let mut saved = SavedProof {
+            params: crate::fixtures::toy_zkp(),
+            transcript: Transcript {
+                user: "alice".to_string(),
+                y1: BigUint::from(2u32),
+                y2: BigUint::from(3u32),
+                r1: BigUint::from(8u32),
+                r2: BigUint::from(4u32),
+                c: BigUint::from(4u32),
+                s: BigUint::from(5u32),
+                timestamp: 1_700_000_000,
+                outcome: Outcome::Accepted,
+            },
+        };
+        assert!(saved.verify());
+
+        let json = serde_json::to_string(&saved).unwrap();
+        let restored: SavedProof = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.transcript, saved.transcript);
+        assert_eq!(restored.params.p, saved.params.p);
+        assert!(restored.verify());
+
+        saved.transcript.s = BigUint::from(6u32);
+        assert!(!saved.verify());
+    }
+}
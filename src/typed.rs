@@ -0,0 +1,200 @@
+//! Typed values of the protocol, so a secret cannot be passed where a challenge or a public value
+//! is expected.
+//!
+//! `ZKP::compute_pair`, `ZKP::solve` and `ZKP::verify` take bare `BigUint`s, and a swapped
+//! argument only shows up as a failed verification. The newtypes here wrap the same `BigUint`s,
+//! and the typed methods `ZKP::public_key`, `ZKP::commit`, `ZKP::respond` and
+//! `ZKP::verify_response` call the untyped ones with the values in the right places. Every type
+//! converts from and into its `BigUint`s (`From`, `into_inner`) to interoperate with the rest of
+//! the crate, whose API stays untyped: the typed methods are an opt-in layer beside it, not a
+//! replacement.
+//!
+//! The nonce `k` is a `Secret` too: it gives `x` away if it leaks. `respond` takes both, so it
+//! cannot tell them apart.
+//!
+//! ```
+//! use zkp_chaum_pedersen::typed::{Challenge, Secret};
+//! use zkp_chaum_pedersen::ZKP;
+//!
+//! let zkp = ZKP::default();
+//! let x = Secret::random(&zkp);
+//! let public_key = zkp.public_key(&x);
+//!
+//! let k = Secret::random(&zkp);
+//! let commitment = zkp.commit(&k);
+//! let c = Challenge::random(&zkp);
+//! let s = zkp.respond(&k, &c, &x);
+//! assert!(zkp.verify_response(&commitment, &public_key, &c, &s));
+//! ```
+
+use num_bigint::BigUint;
+
+use crate::ZKP;
+
+/// A secret: the prover's `x`, or the nonce `k` of a commitment.
+///
+/// Its `Debug` output does not show the value.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(BigUint);
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+impl Secret {
+    /// Samples a secret uniformly from `[0, q)`.
+    pub fn random(zkp: &ZKP) -> Self {
+        Self(ZKP::generate_random_number_below(&zkp.q))
+    }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> BigUint {
+        self.0
+    }
+}
+
+impl From<BigUint> for Secret {
+    fn from(value: BigUint) -> Self {
+        Self(value)
+    }
+}
+
+/// Defines a newtype wrapping one public `BigUint`, with its conversions.
+macro_rules! public_value {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $name(BigUint);
+
+        impl $name {
+            /// Returns the wrapped value.
+            pub fn into_inner(self) -> BigUint {
+                self.0
+            }
+        }
+
+        impl From<BigUint> for $name {
+            fn from(value: BigUint) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$name> for BigUint {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+    };
+}
+
+public_value!(
+    /// The verifier's challenge `c`.
+    Challenge
+);
+
+public_value!(
+    /// The prover's response `s` to a challenge.
+    Response
+);
+
+impl Challenge {
+    /// Samples a challenge uniformly from `[0, q)`, see `ZKP::random_challenge`.
+    pub fn random(zkp: &ZKP) -> Self {
+        Self(zkp.random_challenge())
+    }
+}
+
+/// Defines a newtype wrapping a public pair of `BigUint`s, with its conversions.
+macro_rules! public_pair {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $name(BigUint, BigUint);
+
+        impl $name {
+            /// Returns the wrapped pair.
+            pub fn into_inner(self) -> (BigUint, BigUint) {
+                (self.0, self.1)
+            }
+        }
+
+        impl From<(BigUint, BigUint)> for $name {
+            fn from((first, second): (BigUint, BigUint)) -> Self {
+                Self(first, second)
+            }
+        }
+
+        impl From<$name> for (BigUint, BigUint) {
+            fn from(value: $name) -> Self {
+                (value.0, value.1)
+            }
+        }
+    };
+}
+
+public_pair!(
+    /// The prover's public values `(y1, y2)`, registered with the verifier.
+    PublicKey
+);
+
+public_pair!(
+    /// The prover's commitment `(r1, r2)` to a nonce.
+    Commitment
+);
+
+impl ZKP {
+    /// Computes the public values of the secret `x`, see `compute_pair`.
+    pub fn public_key(&self, x: &Secret) -> PublicKey {
+        self.compute_pair(&x.0).into()
+    }
+
+    /// Computes the commitment to the nonce `k`, see `compute_pair`.
+    pub fn commit(&self, k: &Secret) -> Commitment {
+        self.compute_pair(&k.0).into()
+    }
+
+    /// Computes the response to the challenge `c` with the nonce `k` and the secret `x`, see
+    /// `solve`.
+    pub fn respond(&self, k: &Secret, c: &Challenge, x: &Secret) -> Response {
+        Response(self.solve(&k.0, &c.0, &x.0))
+    }
+
+    /// Verifies the response `s` to the challenge `c`, see `verify`.
+    pub fn verify_response(
+        &self, commitment: &Commitment, public_key: &PublicKey, c: &Challenge, s: &Response,
+    ) -> bool {
+        let Commitment(r1, r2) = commitment;
+        let PublicKey(y1, y2) = public_key;
+        self.verify(r1, r2, y1, y2, &c.0, &s.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::{self, toy_zkp, valid_proof};
+
+    #[test]
+    fn test_typed_matches_untyped() {
+        let zkp = toy_zkp();
+        let x = Secret::from(BigUint::from(fixtures::SECRET));
+        let k = Secret::from(BigUint::from(fixtures::NONCE));
+        let c = Challenge::from(BigUint::from(fixtures::CHALLENGE));
+        let proof = valid_proof();
+
+        let public_key = zkp.public_key(&x);
+        assert_eq!(public_key.clone().into_inner(), fixtures::public_keys());
+        let commitment = zkp.commit(&k);
+        assert_eq!(commitment.clone().into_inner(), (proof.r1, proof.r2));
+        let s = zkp.respond(&k, &c, &x);
+        assert_eq!(BigUint::from(s.clone()), proof.s);
+        assert!(zkp.verify_response(&commitment, &public_key, &c, &s));
+
+        let other = Challenge::from(BigUint::from(fixtures::CHALLENGE + 1));
+        assert!(!zkp.verify_response(&commitment, &public_key, &other, &s));
+        assert_eq!(format!("{:?}", x), "Secret(..)");
+        assert_eq!(x.into_inner(), BigUint::from(fixtures::SECRET));
+    }
+}
@@ -0,0 +1,104 @@
+//! A verifier loop fed through a channel, for embedding proof verification in event-driven
+//! services without going through gRPC.
+
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+
+use crate::{Proof, Registry, ZKP};
+
+/// A proof submitted to the verifier loop for `user`, with the channel its outcome is sent on.
+#[derive(Debug)]
+pub struct ProofRequest {
+    pub user: String,
+    pub proof: Proof,
+    pub respond_to: oneshot::Sender<Outcome>,
+}
+
+/// The outcome of verifying a `ProofRequest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Valid,
+    Invalid,
+    UnknownUser,
+}
+
+/// Spawns a task verifying the proofs received on `rx`.
+///
+/// Each proof is checked against the `(y1, y2)` registered for its user in `registry`, and the
+/// outcome is sent back on the request's `respond_to` channel. The task ends once every sender
+/// of `rx` has been dropped.
+///
+/// # Arguments
+///
+/// * `zkp` - The ZKP group parameters.
+/// * `registry` - The registered public values of the users.
+/// * `rx` - The receiving end of the channel the proofs are submitted on.
+///
+/// # Returns
+///
+/// The `JoinHandle` of the spawned task.
+pub fn run_verifier(
+    zkp: ZKP, registry: Registry, mut rx: mpsc::Receiver<ProofRequest>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(request) = rx.recv().await {
+            let proof = &request.proof;
+            let outcome = match registry.get(&request.user) {
+                Some((y1, y2)) if zkp.verify(&proof.r1, &proof.r2, y1, y2, &proof.c, &proof.s) => {
+                    Outcome::Valid
+                }
+                Some(_) => Outcome::Invalid,
+                None => Outcome::UnknownUser,
+            };
+            // the submitter may have stopped waiting for the outcome
+            let _ = request.respond_to.send(outcome);
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use num_bigint::BigUint;
+
+    async fn submit(tx: &mpsc::Sender<ProofRequest>, user: &str, proof: Proof) -> Outcome {
+        let (respond_to, response) = oneshot::channel();
+        let request = ProofRequest {
+            user: user.to_string(),
+            proof,
+            respond_to,
+        };
+        tx.send(request).await.unwrap();
+        response.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_run_verifier() {
+        let zkp = ZKP::default();
+        let x = ZKP::generate_random_number_below(&zkp.q);
+        let (y1, y2) = zkp.compute_pair(&x);
+
+        let mut registry = Registry::default();
+        registry.register("alice", y1, y2);
+
+        let (tx, rx) = mpsc::channel(4);
+        let handle = run_verifier(zkp.clone(), registry, rx);
+
+        let prove = |x: &BigUint| {
+            let k = ZKP::generate_random_number_below(&zkp.q);
+            let (r1, r2) = zkp.compute_pair(&k);
+            let c = zkp.issue_challenge();
+            let s = zkp.solve(&k, &c, x);
+            Proof { r1, r2, c, s }
+        };
+
+        assert_eq!(submit(&tx, "alice", prove(&x)).await, Outcome::Valid);
+        assert_eq!(submit(&tx, "alice", prove(&(&x + 1u32))).await, Outcome::Invalid);
+        assert_eq!(submit(&tx, "bob", prove(&x)).await, Outcome::UnknownUser);
+
+        drop(tx);
+        handle.await.unwrap();
+    }
+}
@@ -0,0 +1,12 @@
+//! Checks that the typestates of `prover::Session` reject out-of-order use, and the newtypes of
+//! `typed` swapped values, at compile time.
+
+#[test]
+fn test_prover_session_ordering() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
+
+#[test]
+fn test_typed_values() {
+    trybuild::TestCases::new().compile_fail("tests/ui/typed/*.rs");
+}
@@ -0,0 +1,122 @@
+//! Checks proofs generated by an independent implementation of the prover,
+//! `tests/vectors/generate.py`, against the verifier.
+
+use num_bigint::BigUint;
+use std::collections::HashMap;
+use zkp_chaum_pedersen::{Group, ZKP};
+
+/// One `name = hex` record of a vector file.
+type Record = HashMap<String, Vec<u8>>;
+
+/// Parses a vector file into its records, see `tests/vectors/generate.py`.
+fn records(vectors: &str) -> Vec<Record> {
+    vectors
+        .split("\n\n")
+        .map(|block| {
+            block
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .map(|line| {
+                    let (name, value) = line.split_once(" = ").expect("`name = hex` line");
+                    (name.to_string(), hex::decode(value).expect("hex value"))
+                })
+                .collect::<Record>()
+        })
+        .filter(|record| !record.is_empty())
+        .collect()
+}
+
+/// Changes one value of a vector, given the group.
+type Mutation = fn(&mut Vector, &ZKP);
+
+/// The values of a proof record, with group elements decoded as the server does.
+struct Vector {
+    y1: BigUint,
+    y2: BigUint,
+    r1: BigUint,
+    r2: BigUint,
+    c: BigUint,
+    s: BigUint,
+}
+
+impl Vector {
+    fn decode(zkp: &ZKP, record: &Record) -> Self {
+        let element = |name: &str| {
+            zkp.decode_fixed(&record[name])
+                .unwrap_or_else(|| panic!("{} is not {} bytes", name, zkp.p_byte_len()))
+        };
+        Self {
+            y1: element("y1"),
+            y2: element("y2"),
+            r1: element("r1"),
+            r2: element("r2"),
+            c: BigUint::from_bytes_be(&record["c"]),
+            s: BigUint::from_bytes_be(&record["s"]),
+        }
+    }
+
+    fn verify(&self, zkp: &ZKP) -> bool {
+        zkp.verify(&self.r1, &self.r2, &self.y1, &self.y2, &self.c, &self.s)
+    }
+}
+
+fn check_vectors(group: Group, vectors: &str) {
+    let zkp = ZKP::from_group(group);
+    let records = records(vectors);
+    let (params, proofs) = records.split_first().unwrap();
+
+    // the reference must use the same group, including the second generator
+    assert_eq!(BigUint::from_bytes_be(&params["p"]), zkp.p);
+    assert_eq!(BigUint::from_bytes_be(&params["q"]), zkp.q);
+    assert_eq!(BigUint::from_bytes_be(&params["alpha"]), zkp.alpha);
+    assert_eq!(BigUint::from_bytes_be(&params["beta"]), zkp.beta);
+    assert!(proofs.len() >= 4);
+
+    let mut leading_zeros = 0;
+    for (i, record) in proofs.iter().enumerate() {
+        let vector = Vector::decode(&zkp, record);
+        assert!(vector.verify(&zkp), "vector {} rejected", i);
+
+        // the fixed-width encoding round trips, leading zero bytes included
+        for (name, value) in [("y1", &vector.y1), ("y2", &vector.y2), ("r1", &vector.r1)] {
+            assert_eq!(zkp.encode_fixed(value), record[name], "vector {} {}", i, name);
+            leading_zeros += usize::from(record[name][0] == 0);
+        }
+        leading_zeros += usize::from(record["s"][0] == 0);
+
+        let mutations: [(&str, Mutation); 6] = [
+            ("y1", |v, _| v.y1 += 1u32),
+            ("y2", |v, _| v.y2 += 1u32),
+            ("r1", |v, _| v.r1 += 1u32),
+            ("r2", |v, _| v.r2 += 1u32),
+            ("c", |v, zkp| v.c = (&v.c + 1u32) % &zkp.q),
+            ("s", |v, zkp| v.s = (&v.s + 1u32) % &zkp.q),
+        ];
+        for (field, mutate) in mutations {
+            let mut mutated = Vector::decode(&zkp, record);
+            mutate(&mut mutated, &zkp);
+            assert!(!mutated.verify(&zkp), "vector {} accepted with {} mutated", i, field);
+        }
+
+        // a bit flipped in the encoding
+        let mut flipped = record.clone();
+        flipped.get_mut("r2").unwrap()[zkp.p_byte_len() / 2] ^= 0x10;
+        assert!(!Vector::decode(&zkp, &flipped).verify(&zkp), "vector {} accepted flipped", i);
+
+        // elements with their leading zero bytes stripped are not fixed-width encodings
+        let mut stripped = record["r1"].clone();
+        stripped.remove(0);
+        assert_eq!(zkp.decode_fixed(&stripped), None);
+    }
+    assert!(leading_zeros >= 3, "the vectors should cover leading zero bytes");
+}
+
+#[test]
+fn test_rfc5114_1024_vectors() {
+    check_vectors(Group::Rfc1024, include_str!("vectors/rfc5114_1024.txt"));
+}
+
+#[test]
+fn test_rfc5114_2048_vectors() {
+    check_vectors(Group::Rfc2048, include_str!("vectors/rfc5114_2048.txt"));
+}
@@ -0,0 +1,99 @@
+//! Checks that `client verify-proof` reports the verdict of the server on the proofs written
+//! with `--save-proof`.
+
+use num_bigint::BigUint;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use zkp_chaum_pedersen::fixtures;
+use zkp_chaum_pedersen::transcript::{Outcome, SavedProof, Transcript};
+use zkp_chaum_pedersen::ZKP;
+
+/// Writes `saved` to a file of its own and runs `client verify-proof` on it.
+fn verify_proof(name: &str, saved: &SavedProof) -> Output {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("zkp-saved-proof-{}-{}", name, std::process::id()));
+    std::fs::write(&path, serde_json::to_vec(saved).unwrap()).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_client"))
+        .arg("verify-proof")
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    output
+}
+
+/// A proof of `secret` in `zkp`, for the public values of `registered`.
+fn saved_proof(zkp: &ZKP, secret: &BigUint, registered: &BigUint) -> SavedProof {
+    let (y1, y2) = zkp.compute_pair(registered);
+    let k = ZKP::generate_random_number_below(&zkp.q);
+    let (r1, r2) = zkp.compute_pair(&k);
+    let c = zkp.issue_challenge();
+    let s = zkp.solve(&k, &c, secret);
+    // what the server, holding `registered`, answers
+    let outcome = if zkp.verify(&r1, &r2, &y1, &y2, &c, &s) {
+        Outcome::Accepted
+    } else {
+        Outcome::Rejected
+    };
+    SavedProof {
+        params: zkp.clone(),
+        transcript: Transcript {
+            user: "alice".to_string(),
+            y1,
+            y2,
+            r1,
+            r2,
+            c,
+            s,
+            timestamp: 1_700_000_000,
+            outcome,
+        },
+    }
+}
+
+#[test]
+fn test_verify_proof_reports_the_verdict_of_the_server() {
+    let zkp = ZKP::default();
+    let x = ZKP::generate_random_number_below(&zkp.q);
+    let other = &x + 1u32;
+
+    let accepted = saved_proof(&zkp, &x, &x);
+    assert_eq!(accepted.transcript.outcome, Outcome::Accepted);
+    let output = verify_proof("accepted", &accepted);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(
+        stdout.contains("is valid") && stdout.contains("the server accepted it"),
+        "{stdout}"
+    );
+
+    // answered with the secret of another password than the registered one
+    let rejected = saved_proof(&zkp, &other, &x);
+    assert_eq!(rejected.transcript.outcome, Outcome::Rejected);
+    let output = verify_proof("rejected", &rejected);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{stdout}");
+    assert!(
+        stdout.contains("is invalid") && stdout.contains("the server rejected it"),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("other public values"), "{stdout}");
+}
+
+#[test]
+fn test_verify_proof_tells_apart_other_public_values() {
+    // the toy proof is valid for its own public values, which the server did not hold
+    let zkp = fixtures::toy_zkp();
+    let mut saved = saved_proof(&zkp, &BigUint::from(fixtures::SECRET), &fixtures::SECRET.into());
+    saved.transcript.outcome = Outcome::Rejected;
+    let output = verify_proof("mismatch", &saved);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("other public values"), "{stdout}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_client"))
+        .args(["verify-proof", "/nonexistent/saved-proof.json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,8 @@
+use num_bigint::BigUint;
+use zkp_chaum_pedersen::{prover::Session, ZKP};
+
+fn main() {
+    let zkp = ZKP::default();
+    let session = Session::new(&zkp, BigUint::from(6u32));
+    session.respond(&BigUint::from(4u32));
+}
@@ -0,0 +1,10 @@
+use num_bigint::BigUint;
+use zkp_chaum_pedersen::{prover::Session, ZKP};
+
+fn main() {
+    let zkp = ZKP::default();
+    let session = Session::new(&zkp, BigUint::from(6u32)).commit();
+    let first = session.respond(&BigUint::from(4u32));
+    let second = session.respond(&BigUint::from(5u32));
+    assert_ne!(first.response(), second.response());
+}
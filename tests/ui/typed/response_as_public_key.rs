@@ -0,0 +1,12 @@
+use zkp_chaum_pedersen::typed::{Challenge, Secret};
+use zkp_chaum_pedersen::ZKP;
+
+fn main() {
+    let zkp = ZKP::default();
+    let x = Secret::random(&zkp);
+    let k = Secret::random(&zkp);
+    let commitment = zkp.commit(&k);
+    let c = Challenge::random(&zkp);
+    let s = zkp.respond(&k, &c, &x);
+    zkp.verify_response(&commitment, &s, &c, &s);
+}
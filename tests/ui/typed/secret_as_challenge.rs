@@ -0,0 +1,9 @@
+use zkp_chaum_pedersen::typed::Secret;
+use zkp_chaum_pedersen::ZKP;
+
+fn main() {
+    let zkp = ZKP::default();
+    let x = Secret::random(&zkp);
+    let k = Secret::random(&zkp);
+    zkp.respond(&k, &x, &x);
+}
@@ -0,0 +1,117 @@
+#!/usr/bin/env python3
+"""Generates the Chaum-Pedersen test vectors of tests/interop.rs.
+
+This is an independent implementation of the prover, using only the Python standard library,
+so that the vectors check the crate against someone else's reading of the protocol rather than
+against itself. Run it from this directory; the output is deterministic.
+
+Each file starts with a record of the group parameters, followed by one record per proof.
+Records are blocks of `name = hex` lines separated by blank lines. Group elements (`y1`, `y2`,
+`r1`, `r2`) are big-endian and left-padded to the byte length of `p`, `c` and `s` to the byte
+length of `q`, so some values start with zero bytes.
+"""
+
+import random
+
+GROUPS = {
+    "rfc5114_1024": {
+        "title": "RFC 5114 1024-bit MODP group with 160-bit prime order subgroup",
+        "p": int(
+            "B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B61"
+            "6073E28675A23D189838EF1E2EE652C013ECB4AEA906112324975C3CD49B83BF"
+            "ACCBDD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0"
+            "A151AF5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371",
+            16,
+        ),
+        "q": int("F518AA8781A8DF278ABA4E7D64B7CB9D49462353", 16),
+        "g": int(
+            "A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31"
+            "266FEA1E5C41564B777E690F5504F213160217B4B01B886A5E91547F9E2749F4"
+            "D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28A"
+            "D662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5",
+            16,
+        ),
+        # the second generator is g^i for this exponent, as in the crate
+        "beta_exp": int("266FEA1E5C41564B777E69", 16),
+    },
+    "rfc5114_2048": {
+        "title": "RFC 5114 2048-bit MODP group with 224-bit prime order subgroup",
+        "p": int(
+            "AD107E1E9123A9D0D660FAA79559C51FA20D64E5683B9FD1B54B1597B61D0A75"
+            "E6FA141DF95A56DBAF9A3C407BA1DF15EB3D688A309C180E1DE6B85A1274A0A6"
+            "6D3F8152AD6AC2129037C9EDEFDA4DF8D91E8FEF55B7394B7AD5B7D0B6C12207"
+            "C9F98D11ED34DBF6C6BA0B2C8BBC27BE6A00E0A0B9C49708B3BF8A3170918836"
+            "81286130BC8985DB1602E714415D9330278273C7DE31EFDC7310F7121FD5A074"
+            "15987D9ADC0A486DCDF93ACC44328387315D75E198C641A480CD86A1B9E587E8"
+            "BE60E69CC928B2B9C52172E413042E9B23F10B0E16E79763C9B53DCF4BA80A29"
+            "E3FB73C16B8E75B97EF363E2FFA31F71CF9DE5384E71B81C0AC4DFFE0C10E64F",
+            16,
+        ),
+        "q": int("801C0D34C58D93FE997177101F80535A4738CEBCBF389A99B36371EB", 16),
+        "g": int(
+            "AC4032EF4F2D9AE39DF30B5C8FFDAC506CDEBE7B89998CAF74866A08CFE4FFE3"
+            "A6824A4E10B9A6F0DD921F01A70C4AFAAB739D7700C29F52C57DB17C620A8652"
+            "BE5E9001A8D66AD7C17669101999024AF4D027275AC1348BB8A762D0521BC98A"
+            "E247150422EA1ED409939D54DA7460CDB5F6C6B250717CBEF180EB34118E98D1"
+            "19529A45D6F834566E3025E316A330EFBB77A86F0C1AB15B051AE3D428C8F8AC"
+            "B70A8137150B8EEB10E183EDD19963DDD9E263E4770589EF6AA21E7F5F2FF381"
+            "B539CCE3409D13CD566AFBB48D6C019181E1BCFE94B30269EDFE72FE9B6AA4BD"
+            "7B5A0F1C71CFFF4C19C418E1F6EC017981BC087F2A7065B384B890D3191F2BFA",
+            16,
+        ),
+        "beta_exp": int("1999024AF4D027275AC134", 16),
+    },
+}
+
+
+def fixed(value, width):
+    return value.to_bytes(width, "big").hex()
+
+
+def prove(group, rng, x):
+    p, q, alpha = group["p"], group["q"], group["g"]
+    beta = pow(alpha, group["beta_exp"], p)
+    k = rng.randrange(1, q)
+    c = rng.randrange(1, q)
+    return {
+        "y1": pow(alpha, x, p),
+        "y2": pow(beta, x, p),
+        "r1": pow(alpha, k, p),
+        "r2": pow(beta, k, p),
+        "c": c,
+        "s": (k - c * x) % q,
+    }
+
+
+def main():
+    for name, group in GROUPS.items():
+        p, q = group["p"], group["q"]
+        p_len, q_len = (p.bit_length() + 7) // 8, (q.bit_length() + 7) // 8
+        rng = random.Random(name)
+
+        proofs = [prove(group, rng, rng.randrange(1, q)) for _ in range(4)]
+        # proofs with a leading zero byte in an element and in the response
+        for field, width, modulus in [("r1", p_len, p), ("y2", p_len, p), ("s", q_len, q)]:
+            while True:
+                proof = prove(group, rng, rng.randrange(1, q))
+                if proof[field] < 1 << (8 * (width - 1)):
+                    proofs.append(proof)
+                    break
+
+        with open(name + ".txt", "w") as out:
+            out.write("# {}\n# generated by generate.py\n\n".format(group["title"]))
+            beta = pow(group["g"], group["beta_exp"], p)
+            out.write("p = {}\n".format(fixed(p, p_len)))
+            out.write("q = {}\n".format(fixed(q, q_len)))
+            out.write("alpha = {}\n".format(fixed(group["g"], p_len)))
+            out.write("beta = {}\n".format(fixed(beta, p_len)))
+            for proof in proofs:
+                out.write("\n")
+                for field in ["y1", "y2", "r1", "r2"]:
+                    out.write("{} = {}\n".format(field, fixed(proof[field], p_len)))
+                for field in ["c", "s"]:
+                    out.write("{} = {}\n".format(field, fixed(proof[field], q_len)))
+
+
+if __name__ == "__main__":
+    main()